@@ -0,0 +1,434 @@
+
+//==============================================================================
+
+// Abaqus .inp mesh import of *NODE, *ELEMENT, *NSET, and *ELSET.  Other
+// keywords, e.g. materials and steps, are skipped.  A deck with one *PART and
+// at most one *INSTANCE is read as one flat mesh, without the instance
+// transform.  Decks with more parts or instances, whose node and element IDs
+// are only unique per part, are rejected
+
+// Standard
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+//********
+
+// Third party
+use log::{info, warn};
+
+//********
+
+// This lib
+use crate::cells;
+use crate::fem::Mesh;
+use crate::vtk::VtkFile;
+
+//********
+
+pub const EXT: &str = ".inp";
+
+const COMMENT: &str = "**";
+
+// Abaqus element types without their suffix letters, e.g. C3D8 for C3D8R,
+// and their VTK types.  Abaqus node orders match VTK for these
+const TYPES: &[(&str, u8)] = &[
+	("T2D2" , cells::VTK_LINE), ("T3D2" , cells::VTK_LINE),
+	("B21"  , cells::VTK_LINE), ("B31"  , cells::VTK_LINE),
+	("T3D3" , cells::VTK_QUADRATIC_EDGE), ("B22"  , cells::VTK_QUADRATIC_EDGE),
+	("B32"  , cells::VTK_QUADRATIC_EDGE),
+	("S3"   , cells::VTK_TRIANGLE), ("M3D3" , cells::VTK_TRIANGLE),
+	("CPS3" , cells::VTK_TRIANGLE), ("CPE3" , cells::VTK_TRIANGLE),
+	("CAX3" , cells::VTK_TRIANGLE), ("R3D3" , cells::VTK_TRIANGLE),
+	("S4"   , cells::VTK_QUAD), ("M3D4" , cells::VTK_QUAD),
+	("CPS4" , cells::VTK_QUAD), ("CPE4" , cells::VTK_QUAD),
+	("CAX4" , cells::VTK_QUAD), ("R3D4" , cells::VTK_QUAD),
+	("S6"   , cells::VTK_QUADRATIC_TRIANGLE), ("STRI65", cells::VTK_QUADRATIC_TRIANGLE),
+	("CPS6" , cells::VTK_QUADRATIC_TRIANGLE), ("CPE6"  , cells::VTK_QUADRATIC_TRIANGLE),
+	("CAX6" , cells::VTK_QUADRATIC_TRIANGLE),
+	("S8"   , cells::VTK_QUADRATIC_QUAD), ("CPS8" , cells::VTK_QUADRATIC_QUAD),
+	("CPE8" , cells::VTK_QUADRATIC_QUAD), ("CAX8" , cells::VTK_QUADRATIC_QUAD),
+	("C3D4" , cells::VTK_TETRA),
+	("C3D5" , cells::VTK_PYRAMID),
+	("C3D6" , cells::VTK_WEDGE),
+	("C3D8" , cells::VTK_HEXAHEDRON),
+	("C3D10", cells::VTK_QUADRATIC_TETRA),
+	("C3D15", cells::VTK_QUADRATIC_WEDGE),
+	("C3D20", cells::VTK_QUADRATIC_HEXAHEDRON),
+];
+
+//==============================================================================
+
+fn vtk_type(abaqus_type: &str) -> Option<u8>
+{
+	// Suffixes are e.g. R for reduced integration or H for hybrid
+	let t = abaqus_type.to_uppercase();
+	let base = t.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+	return TYPES.iter().find(|(a, _)| *a == base || *a == t).map(|&(_, v)| v);
+}
+
+//********
+
+// Keyword line, e.g. "*ELEMENT, TYPE=C3D8, ELSET=EALL"
+struct Keyword
+{
+	name  : String,
+	params: HashMap<String, String>,
+}
+
+fn parse_keyword(line: &str) -> Keyword
+{
+	// Keywords and parameter names are case-insensitive, and parameters may
+	// have no value, e.g. GENERATE
+	let mut parts = line[1 ..].split(',');
+	let name = parts.next().unwrap_or("").trim().to_uppercase();
+	let params = parts.filter(|p| !p.trim().is_empty()).map(|p|
+	{
+		let mut kv = p.splitn(2, '=');
+		let k = kv.next().unwrap_or("").trim().to_uppercase();
+		let v = kv.next().unwrap_or("").trim().to_string();
+		(k, v)
+	}).collect();
+	return Keyword { name, params };
+}
+
+//********
+
+// Sets by upper-case name, keeping the first spelling for the array name
+#[derive(Default)]
+struct Sets
+{
+	sets : Vec<(String, Vec<i64>)>,
+	index: HashMap<String, usize>,
+}
+
+impl Sets
+{
+	fn get(&mut self, name: &str) -> &mut Vec<i64>
+	{
+		let key = name.to_uppercase();
+		let i = match self.index.get(&key)
+		{
+			Some(&i) => i,
+			None =>
+			{
+				self.sets.push((name.to_string(), Vec::new()));
+				self.index.insert(key, self.sets.len() - 1);
+				self.sets.len() - 1
+			},
+		};
+		return &mut self.sets[i].1;
+	}
+
+	fn add(&mut self, keyword: &Keyword, param: &str, data: &[String], errstr: &str)
+	{
+		// Members are IDs, other sets by name, or start, end, and step with
+		// GENERATE
+		let name = match keyword.params.get(param)
+		{
+			Some(n) => n.clone(),
+			None => panic!("{}:  *{} without {}", errstr, keyword.name, param),
+		};
+
+		let mut members = Vec::new();
+		if keyword.params.contains_key("GENERATE")
+		{
+			let r: Vec<i64> = data.iter().map(|w| parse(w, errstr)).collect();
+			for g in r.chunks(3)
+			{
+				let step = *g.get(2).unwrap_or(&1);
+				if g.len() < 2 || step <= 0
+				{
+					panic!("{}:  bad GENERATE range {:?}", errstr, g);
+				}
+				members.extend((g[0] ..= g[1]).step_by(step as usize));
+			}
+		}
+		else
+		{
+			for w in data
+			{
+				match w.parse::<i64>()
+				{
+					Ok(id) => members.push(id),
+					Err(_) if self.index.contains_key(&w.to_uppercase()) =>
+						members.extend(self.get(w).clone()),
+					Err(_) => warn!("skipping unknown set \"{}\" in set \"{}\"",
+						w, name),
+				}
+			}
+		}
+		self.get(&name).extend(members);
+	}
+}
+
+//********
+
+fn parse<T: std::str::FromStr>(word: &str, errstr: &str) -> T
+{
+	return word.trim().parse().unwrap_or_else(|_| panic!("{}:  bad number \
+		\"{}\"", errstr, word));
+}
+
+//********
+
+pub fn read<R: BufRead>(reader: R, name: &str) -> VtkFile
+{
+	let errstr = format!("Cannot read Abaqus file \"{}\"", name);
+
+	let mut mesh = Mesh::default();
+	let mut nsets = Sets::default();
+	let mut elsets = Sets::default();
+
+	// Data lines of each keyword, where lines ending with a comma continue
+	let mut blocks: Vec<(Keyword, Vec<Vec<String>>)> = Vec::new();
+	let mut continued = false;
+	for line in reader.lines()
+	{
+		let line = line.expect(&errstr);
+		let line = line.trim();
+		if line.is_empty() || line.starts_with(COMMENT)
+		{
+			continue;
+		}
+		if line.starts_with('*')
+		{
+			blocks.push((parse_keyword(line), Vec::new()));
+			continued = false;
+			continue;
+		}
+
+		let words = line.split(',').map(|w| w.trim().to_string())
+			.filter(|w| !w.is_empty());
+		if let Some((_, rows)) = blocks.last_mut()
+		{
+			match rows.last_mut()
+			{
+				Some(row) if continued => row.extend(words),
+				_ => rows.push(words.collect()),
+			}
+		}
+		continued = line.ends_with(',');
+	}
+
+	// Several parts would need their IDs kept apart, and several instances
+	// would need copies of their part
+	let count = |k| blocks.iter().filter(|(keyword, _)| keyword.name == k).count();
+	let (nparts, ninstances) = (count("PART"), count("INSTANCE"));
+	if nparts > 1 || ninstances > 1
+	{
+		panic!("{}:  {} parts and {} instances found.  Only decks with at most \
+			one part and instance are supported", errstr, nparts, ninstances);
+	}
+
+	let mut nskipped = 0;
+	let mut ignored = Vec::new();
+	for (keyword, rows) in &blocks
+	{
+		match keyword.name.as_str()
+		{
+			"NODE" =>
+			{
+				for row in rows
+				{
+					if row.len() < 2
+					{
+						panic!("{}:  bad node {:?}", errstr, row);
+					}
+					let mut x = [0.0; 3];
+					for (d, w) in row[1 ..].iter().take(3).enumerate()
+					{
+						x[d] = parse(w, &errstr);
+					}
+					mesh.nodes.push((parse(&row[0], &errstr), x));
+				}
+				if let Some(set) = keyword.params.get("NSET")
+				{
+					let ids: Vec<i64> = rows.iter().map(|r| parse(&r[0], &errstr))
+						.collect();
+					nsets.get(set).extend(ids);
+				}
+			},
+			"ELEMENT" =>
+			{
+				let t = keyword.params.get("TYPE").map(String::as_str).unwrap_or("");
+				let ctype = match vtk_type(t)
+				{
+					Some(c) => c,
+					None =>
+					{
+						warn!("skipping {} elements of unsupported type \"{}\"",
+							rows.len(), t);
+						nskipped += rows.len();
+						continue;
+					},
+				};
+				let mut ids = Vec::new();
+				for row in rows
+				{
+					let ids_row: Vec<i64> = row.iter().map(|w| parse(w, &errstr))
+						.collect();
+					if ids_row.len() < 2
+					{
+						panic!("{}:  bad element {:?}", errstr, row);
+					}
+					ids.push(ids_row[0]);
+					mesh.elements.push((ids_row[0], ctype, ids_row[1 ..].to_vec()));
+				}
+				if let Some(set) = keyword.params.get("ELSET")
+				{
+					elsets.get(set).extend(ids);
+				}
+			},
+			"NSET" =>
+			{
+				let data: Vec<String> = rows.iter().flatten().cloned().collect();
+				nsets.add(keyword, "NSET", &data, &errstr);
+			},
+			"ELSET" =>
+			{
+				let data: Vec<String> = rows.iter().flatten().cloned().collect();
+				elsets.add(keyword, "ELSET", &data, &errstr);
+			},
+			// Structure of single-part files
+			"HEADING" | "PART" | "END PART" | "ASSEMBLY" | "END ASSEMBLY"
+				| "INSTANCE" | "END INSTANCE" => (),
+			k =>
+			{
+				if k == "INCLUDE"
+				{
+					warn!("skipping *INCLUDE of \"{}\"", keyword.params
+						.get("INPUT").map(String::as_str).unwrap_or(""));
+				}
+				if !ignored.contains(&k)
+				{
+					ignored.push(k);
+				}
+			},
+		}
+	}
+
+	if !ignored.is_empty()
+	{
+		info!("Skipped Abaqus keywords:  *{}", ignored.join(", *"));
+	}
+	if nskipped > 0
+	{
+		warn!("skipped {} elements in total", nskipped);
+	}
+
+	mesh.node_sets = nsets.sets;
+	mesh.element_sets = elsets.sets;
+	return mesh.to_vtk(&errstr);
+}
+
+//********
+
+pub fn load(file: &str) -> VtkFile
+{
+	info!("Loading Abaqus file \"{}\"", file);
+	let errstr = format!("Cannot load Abaqus file \"{}\"", file);
+	let f = File::open(file).expect(&errstr);
+	return read(BufReader::new(f), file);
+}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fem;
+	use crate::vtk::Data;
+
+	const DECK: &str = "\
+*HEADING
+Two hexahedra and a shell
+** Nodes 1 to 12 on a 3 x 2 x 2 grid
+*NODE, NSET=NALL
+ 1, 0., 0., 0.
+ 2, 1., 0., 0.
+ 3, 2., 0., 0.
+ 4, 0., 1., 0.
+ 5, 1., 1., 0.
+ 6, 2., 1., 0.
+ 7, 0., 0., 1.
+ 8, 1., 0., 1.
+ 9, 2., 0., 1.
+10, 0., 1., 1.
+11, 1., 1., 1.
+12, 2., 1., 1.
+*ELEMENT, TYPE=C3D8R, ELSET=Solid
+101, 1, 2, 5, 4,
+     7, 8, 11, 10
+102, 2, 3, 6, 5, 8, 9, 12, 11
+*Element, type=S4R, elset=Skin
+201, 7, 8, 11, 10
+*NSET, NSET=Fixed, GENERATE
+1, 7, 3
+*ELSET, ELSET=Both
+Solid, 201
+*MATERIAL, NAME=Steel
+*ELASTIC
+210000., 0.3
+";
+
+	#[test]
+	fn test_abaqus()
+	{
+		let v = read(DECK.as_bytes(), "deck");
+		assert_eq!(v.npoints, 12);
+		assert_eq!(v.types, vec![cells::VTK_HEXAHEDRON, cells::VTK_HEXAHEDRON,
+			cells::VTK_QUAD]);
+		assert_eq!(v.cell_points(0), &[0, 1, 4, 3, 6, 7, 10, 9]);
+		assert_eq!(v.cell_array(fem::ELEMENT_IDS).unwrap().data,
+			Data::I64(vec![101, 102, 201]));
+
+		let nset = |n| v.point_array(n).unwrap().data.to_i64();
+		assert_eq!(nset("NALL"), vec![1; 12]);
+		assert_eq!(nset("Fixed"), vec![1, 0, 0, 1, 0, 0, 1, 0, 0, 0, 0, 0]);
+
+		let elset = |n| v.cell_array(n).unwrap().data.to_i64();
+		assert_eq!(elset("Solid"), vec![1, 1, 0]);
+		assert_eq!(elset("Skin"), vec![0, 0, 1]);
+		assert_eq!(elset("Both"), vec![1, 1, 1]);
+	}
+
+	#[test]
+	#[should_panic(expected = "element 101 has 7 nodes")]
+	fn test_abaqus_short_element()
+	{
+		let deck = DECK.replace("101, 1, 2, 5, 4,\n     7, 8, 11, 10",
+			"101, 1, 2, 5, 4, 7, 8, 11");
+		read(deck.as_bytes(), "deck");
+	}
+
+	#[test]
+	fn test_abaqus_part()
+	{
+		// A single part reads like a flat deck
+		let deck = format!("*PART, NAME=P\n{}*END PART\n*ASSEMBLY, NAME=A\n\
+			*INSTANCE, NAME=P-1, PART=P\n*END INSTANCE\n*END ASSEMBLY\n", DECK);
+		let v = read(deck.as_bytes(), "deck");
+		assert_eq!(v.npoints, 12);
+		assert_eq!(v.ncells, 3);
+	}
+
+	#[test]
+	#[should_panic(expected = "2 parts and 2 instances found")]
+	fn test_abaqus_two_parts()
+	{
+		// Both parts number their nodes from 1
+		let part = "*NODE\n1, 0., 0., 0.\n2, 1., 0., 0.\n\
+			*ELEMENT, TYPE=T3D2\n1, 1, 2\n";
+		let deck = format!("*PART, NAME=A\n{}*END PART\n*PART, NAME=B\n{}*END PART\n\
+			*ASSEMBLY, NAME=Asm\n\
+			*INSTANCE, NAME=A-1, PART=A\n*END INSTANCE\n\
+			*INSTANCE, NAME=B-1, PART=B\n*END INSTANCE\n\
+			*END ASSEMBLY\n", part, part);
+		read(deck.as_bytes(), "deck");
+	}
+}
+
+//==============================================================================
+
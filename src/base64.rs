@@ -1,15 +1,26 @@
 
 //==============================================================================
 
+use std::io::{Read, Write};
 use std::sync::Once;
 
 //********
 
+// Third party
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+
+//********
+
 pub const LITTLE_ENDIAN: u8 = 0;
 pub const BIG_ENDIAN: u8 = 1;
 
 const PAD: char = '=';
 
+// Uncompressed size of each zlib block, as written by VTK
+const ZLIB_BLOCK: usize = 32768;
+
 //********
 
 static INIT: Once = Once::new();
@@ -47,12 +58,10 @@ fn init()
 			// bytes and need to be split ahead of time
 			let chars: Vec<char> = BASE64_STR.chars().collect();
 
-			let mut i: u8 = 0;
-			for c in chars
+			for (i, c) in chars.into_iter().enumerate()
 			{
-				LUT_ENC[i as usize] = c;
-				LUT_DEC[c as usize] = i;
-				i += 1;
+				LUT_ENC[i] = c;
+				LUT_DEC[c as usize] = i as u8;
 			}
 
 			// Padding.  Same value as "A" so it can't be included in loop above
@@ -67,13 +76,13 @@ fn init()
 
 //==============================================================================
 
-pub fn encode_u8_raw(v: &Vec<u8>) -> String
+pub fn encode_u8_raw(v: &[u8]) -> String
 {
 	//println!("starting base64::encode_u8_raw()");
 	//println!("v = {:?}", v);
 
 	// Divide and round up
-	let len = (v.len() + 2) / 3;
+	let len = v.len().div_ceil(3);
 
 	let mut s = String::with_capacity(4 * len);
 
@@ -85,7 +94,7 @@ pub fn encode_u8_raw(v: &Vec<u8>) -> String
 	{ unsafe {
 		//println!("i = {}", i);
 
-		let i0 = 3*i + 0;
+		let i0 = 3*i;
 		let i1 = 3*i + 1;
 		let i2 = 3*i + 2;
 
@@ -151,7 +160,7 @@ pub fn decode_u8_raw(s: &str) -> Vec<u8>
 		//println!("{}", &s[4*i .. 4*i+4]);
 
 		// Step bytes by 3 and base64 chars by 4
-		v[3*i+0] = ((LUT_DEC[chars[4*i+0] as usize] & 0b111111) << 2)
+		v[3*i  ] = ((LUT_DEC[chars[4*i  ] as usize] & 0b111111) << 2)
 		         | ((LUT_DEC[chars[4*i+1] as usize] & 0b110000) >> 4);
 		v[3*i+1] = ((LUT_DEC[chars[4*i+1] as usize] & 0b001111) << 4)
 		         | ((LUT_DEC[chars[4*i+2] as usize] & 0b111100) >> 2);
@@ -180,25 +189,19 @@ fn encode_u64_len(endianness: u8, blen: u64) -> Vec<u8>
 		blen.to_le_bytes()
 	};
 
-	for i in 0 .. b.len()
-	{
-		bytes[i] = b[i];
-	}
+	bytes[.. b.len()].copy_from_slice(&b);
 
 	return bytes;
 }
 
 //********
 
-fn decode_u64_len(bytes: &Vec<u8>, endianness: u8) -> u64
+fn decode_u64_len(bytes: &[u8], endianness: u8) -> u64
 {
 	// By VTK convention, get the length from the beginning of a byte vec
 
 	let mut b: [u8; 8] = [0; 8];
-	for i in 0 .. 8
-	{
-		b[i] = bytes[i];
-	}
+	b.copy_from_slice(&bytes[.. 8]);
 
 	return if endianness == BIG_ENDIAN
 	{
@@ -212,7 +215,7 @@ fn decode_u64_len(bytes: &Vec<u8>, endianness: u8) -> u64
 
 //==============================================================================
 
-pub fn encode_f32(v: &Vec<f32>, endianness: u8) -> String
+pub fn encode_f32(v: &[f32], endianness: u8) -> String
 {
 	// Encode a VTK-encoded base64 string from an f32 Vec
 
@@ -284,7 +287,7 @@ pub fn decode_f32(string: &str, endianness: u8) -> Vec<f32>
 // Cannot have the same name as above fn, even though they take/return different
 // types
 
-pub fn encode_i64(v: &Vec<i64>, endianness: u8) -> String
+pub fn encode_i64(v: &[i64], endianness: u8) -> String
 {
 	// Encode a VTK-encoded base64 string from an i64 Vec
 
@@ -358,7 +361,7 @@ pub fn decode_i64(string: &str, endianness: u8) -> Vec<i64>
 
 //==============================================================================
 
-pub fn encode_u8(v: &Vec<u8>, endianness: u8) -> String
+pub fn encode_u8(v: &[u8], endianness: u8) -> String
 {
 	// Encode a VTK-encoded base64 string from a u8 Vec
 
@@ -400,17 +403,244 @@ pub fn decode_u8(string: &str, endianness: u8) -> Vec<u8>
 	let vlen = decode_u64_len(&bytes, endianness) as usize;
 	//println!("vlen (u8) = {}", vlen);
 
-	let mut v: Vec<u8> = vec![0; vlen];
+	// Endianness doesn't matter for a single byte
+	return bytes[8 .. vlen+8].to_vec();
+}
+
+//==============================================================================
 
-	for i in 8 .. vlen+8
+// Generic encoding/decoding for any VTK scalar type.  The typed fns above are
+// kept for the hot paths (points, connectivity, types) and for the tests
+
+pub trait Scalar: Copy + Default
+{
+	// Number of bytes per value
+	const NBYTES: usize;
+
+	fn to_bytes(self, endianness: u8) -> Vec<u8>;
+	fn from_bytes(b: &[u8], endianness: u8) -> Self;
+}
+
+macro_rules! impl_scalar
+{
+	($t:ty, $n:expr) =>
 	{
-		// Endianness doesn't matter for a single byte
-		v[i-8] = bytes[i];
+		impl Scalar for $t
+		{
+			const NBYTES: usize = $n;
+
+			fn to_bytes(self, endianness: u8) -> Vec<u8>
+			{
+				return if endianness == BIG_ENDIAN
+				{
+					self.to_be_bytes().to_vec()
+				}
+				else
+				{
+					self.to_le_bytes().to_vec()
+				};
+			}
+
+			fn from_bytes(b: &[u8], endianness: u8) -> Self
+			{
+				let mut a = [0; $n];
+				a.copy_from_slice(&b[0 .. $n]);
+
+				return if endianness == BIG_ENDIAN
+				{
+					<$t>::from_be_bytes(a)
+				}
+				else
+				{
+					<$t>::from_le_bytes(a)
+				};
+			}
+		}
+	};
+}
+
+impl_scalar!(i8 , 1);
+impl_scalar!(u8 , 1);
+impl_scalar!(i16, 2);
+impl_scalar!(u16, 2);
+impl_scalar!(i32, 4);
+impl_scalar!(u32, 4);
+impl_scalar!(i64, 8);
+impl_scalar!(u64, 8);
+impl_scalar!(f32, 4);
+impl_scalar!(f64, 8);
+
+//********
+
+pub fn encode<T: Scalar>(v: &[T], endianness: u8) -> String
+{
+	// Encode a VTK-encoded base64 string from a Vec of any scalar type
+	return encode_with_header(v, endianness, 8);
+}
+
+pub fn encode_with_header<T: Scalar>(v: &[T], endianness: u8,
+	header_bytes: usize) -> String
+{
+	// Like encode(), with a UInt32 (4 bytes) or UInt64 (8 bytes) length header
+
+	let blen = v.len() * T::NBYTES;
+
+	let mut bytes = if header_bytes == 4
+	{
+		(blen as u32).to_bytes(endianness)
+	}
+	else
+	{
+		(blen as u64).to_bytes(endianness)
+	};
+
+	for x in v
+	{
+		bytes.extend(x.to_bytes(endianness));
+	}
+
+	return encode_u8_raw(&bytes);
+}
+
+//********
+
+pub fn decode<T: Scalar>(string: &str, endianness: u8) -> Vec<T>
+{
+	// Decode a Vec of any scalar type from a VTK-encoded base64 string
+	return decode_with_header(string, endianness, 8);
+}
+
+pub fn decode_with_header<T: Scalar>(string: &str, endianness: u8,
+	header_bytes: usize) -> Vec<T>
+{
+	// Like decode(), with a UInt32 (4 bytes) or UInt64 (8 bytes) length header
+
+	let bytes = decode_u8_raw(string);
+
+	let blen = if header_bytes == 4
+	{
+		u32::from_bytes(&bytes, endianness) as usize
+	}
+	else
+	{
+		u64::from_bytes(&bytes, endianness) as usize
+	};
+	let vlen = blen / T::NBYTES;
+
+	let mut v: Vec<T> = Vec::with_capacity(vlen);
+	for i in 0 .. vlen
+	{
+		let i0 = header_bytes + T::NBYTES * i;
+		v.push(T::from_bytes(&bytes[i0 .. i0 + T::NBYTES], endianness));
 	}
 
 	return v;
 }
 
+//********
+
+fn header_int(bytes: &[u8], i: usize, endianness: u8, header_bytes: usize)
+	-> usize
+{
+	// Integer i of a UInt32 or UInt64 block header
+	let b = &bytes[header_bytes * i ..];
+	return if header_bytes == 4
+	{
+		u32::from_bytes(b, endianness) as usize
+	}
+	else
+	{
+		u64::from_bytes(b, endianness) as usize
+	};
+}
+
+//********
+
+fn header_to_bytes(n: usize, endianness: u8, header_bytes: usize) -> Vec<u8>
+{
+	if header_bytes == 4
+	{
+		(n as u32).to_bytes(endianness)
+	}
+	else
+	{
+		(n as u64).to_bytes(endianness)
+	}
+}
+
+//********
+
+fn encoded_len(nbytes: usize) -> usize
+{
+	// Number of base64 chars for nbytes, including padding
+	4 * nbytes.div_ceil(3)
+}
+
+//********
+
+pub fn encode_zlib<T: Scalar>(v: &[T], endianness: u8, header_bytes: usize)
+	-> String
+{
+	// zlib-compressed data for compressor="vtkZLibDataCompressor".  The header
+	// is [nblocks, block size, last block size, compressed sizes...], and it is
+	// base64-encoded separately from the concatenated compressed blocks
+
+	let mut bytes = Vec::with_capacity(v.len() * T::NBYTES);
+	for x in v
+	{
+		bytes.extend(x.to_bytes(endianness));
+	}
+
+	let mut header = Vec::new();
+	let mut data = Vec::new();
+	let nblocks = bytes.len().div_ceil(ZLIB_BLOCK);
+	let last = bytes.len() - ZLIB_BLOCK * nblocks.saturating_sub(1);
+
+	header.extend(header_to_bytes(nblocks, endianness, header_bytes));
+	header.extend(header_to_bytes(ZLIB_BLOCK, endianness, header_bytes));
+	header.extend(header_to_bytes(last, endianness, header_bytes));
+
+	for block in bytes.chunks(ZLIB_BLOCK)
+	{
+		let mut z = ZlibEncoder::new(Vec::new(), Compression::default());
+		z.write_all(block).unwrap();
+		let c = z.finish().unwrap();
+		header.extend(header_to_bytes(c.len(), endianness, header_bytes));
+		data.extend(c);
+	}
+
+	return encode_u8_raw(&header) + &encode_u8_raw(&data);
+}
+
+//********
+
+pub fn decode_zlib<T: Scalar>(string: &str, endianness: u8, header_bytes: usize)
+	-> Vec<T>
+{
+	// Inverse of encode_zlib()
+
+	// The number of blocks gives the length of the rest of the header
+	let first = decode_u8_raw(&string[.. encoded_len(header_bytes)]);
+	let nblocks = header_int(&first, 0, endianness, header_bytes);
+
+	let hlen = encoded_len(header_bytes * (3 + nblocks));
+	let header = decode_u8_raw(&string[.. hlen]);
+	let data = decode_u8_raw(&string[hlen ..]);
+
+	let mut bytes = Vec::new();
+	let mut begin = 0;
+	for i in 0 .. nblocks
+	{
+		let end = begin + header_int(&header, 3 + i, endianness, header_bytes);
+		let mut z = ZlibDecoder::new(&data[begin .. end]);
+		z.read_to_end(&mut bytes).expect("Cannot decompress zlib block");
+		begin = end;
+	}
+
+	return bytes.chunks_exact(T::NBYTES)
+		.map(|b| T::from_bytes(b, endianness)).collect();
+}
+
 //==============================================================================
 
 #[cfg(test)]
@@ -419,6 +649,23 @@ mod tests
 	// Import names from outer scope
 	use super::*;
 
+	#[test]
+	fn test_zlib()
+	{
+		// Compressed types array from "./data/icosahedron-zlib.vtu"
+		let s = "AQAAAAAAAAAAgAAAAAAAABQAAAAAAAAACwAAAAAAAAA=eJxjZcUEAAQuAGU=";
+		let types: Vec<u8> = decode_zlib(s, LITTLE_ENDIAN, 8);
+		assert_eq!(types, vec![5; 20]);
+
+		// Several blocks
+		let v: Vec<f64> = (0 .. 10000).map(|i| i as f64 / 3.0).collect();
+		for &h in &[4, 8]
+		{
+			let s = encode_zlib(&v, BIG_ENDIAN, h);
+			assert_eq!(decode_zlib::<f64>(&s, BIG_ENDIAN, h), v);
+		}
+	}
+
 	// Test strings from "./data/icosahedron-binary.vtu"
 
 	// Points (Float32)
@@ -487,7 +734,7 @@ mod tests
 	#[test]
 	fn test_encode_u8_raw()
 	{
-		let s = encode_u8_raw(&EXPECTED_F32_BYTES.to_vec());
+		let s = encode_u8_raw(&EXPECTED_F32_BYTES);
 		//println!("s = {}", s);
 		assert_eq!(s, STR_F32_RAW);
 	}
@@ -508,7 +755,7 @@ mod tests
 	#[test]
 	fn test_encode_f32()
 	{
-		let s = encode_f32(&EXPECTED_F32.to_vec(), LITTLE_ENDIAN);
+		let s = encode_f32(&EXPECTED_F32, LITTLE_ENDIAN);
 		//println!("s = {}", s);
 		assert_eq!(s, STR_F32);
 	}
@@ -544,7 +791,7 @@ mod tests
 	fn test_le_f32()
 	{
 		let endianness = LITTLE_ENDIAN;
-		let s = encode_f32(&EXPECTED_F32.to_vec(), endianness);
+		let s = encode_f32(&EXPECTED_F32, endianness);
 		//println!("s = {}", s);
 		let data = decode_f32(&s, endianness);
 		assert_eq!(data, EXPECTED_F32);
@@ -554,7 +801,7 @@ mod tests
 	fn test_be_f32()
 	{
 		let endianness = BIG_ENDIAN;
-		let s = encode_f32(&EXPECTED_F32.to_vec(), endianness);
+		let s = encode_f32(&EXPECTED_F32, endianness);
 		//println!("s = {}", s);
 		let data = decode_f32(&s, endianness);
 		assert_eq!(data, EXPECTED_F32);
@@ -564,7 +811,7 @@ mod tests
 	fn test_le_i64()
 	{
 		let endianness = LITTLE_ENDIAN;
-		let s = encode_i64(&EXPECTED_I64.to_vec(), endianness);
+		let s = encode_i64(&EXPECTED_I64, endianness);
 		//println!("s = {}", s);
 		let data = decode_i64(&s, endianness);
 		assert_eq!(data, EXPECTED_I64);
@@ -574,7 +821,7 @@ mod tests
 	fn test_be_i64()
 	{
 		let endianness = BIG_ENDIAN;
-		let s = encode_i64(&EXPECTED_I64.to_vec(), endianness);
+		let s = encode_i64(&EXPECTED_I64, endianness);
 		//println!("s = {}", s);
 		let data = decode_i64(&s, endianness);
 		assert_eq!(data, EXPECTED_I64);
@@ -584,17 +831,46 @@ mod tests
 	fn test_le_u8()
 	{
 		let endianness = LITTLE_ENDIAN;
-		let s = encode_u8(&EXPECTED_U8.to_vec(), endianness);
+		let s = encode_u8(&EXPECTED_U8, endianness);
 		//println!("s = {}", s);
 		let data = decode_u8(&s, endianness);
 		assert_eq!(data, EXPECTED_U8);
 	}
 
+	#[test]
+	fn test_decode_generic()
+	{
+		assert_eq!(decode::<f32>(STR_F32, LITTLE_ENDIAN), EXPECTED_F32);
+		assert_eq!(decode::<i64>(STR_I64, LITTLE_ENDIAN), EXPECTED_I64);
+		assert_eq!(decode::<u8 >(STR_U8 , LITTLE_ENDIAN), EXPECTED_U8 );
+	}
+
+	#[test]
+	fn test_u32_header()
+	{
+		let endianness = LITTLE_ENDIAN;
+		let s = encode_with_header(&EXPECTED_I64, endianness, 4);
+		assert_eq!(decode_u8_raw(&s)[0 .. 4], [224, 1, 0, 0]);
+
+		let data: Vec<i64> = decode_with_header(&s, endianness, 4);
+		assert_eq!(data, EXPECTED_I64);
+	}
+
+	#[test]
+	fn test_be_f64()
+	{
+		let endianness = BIG_ENDIAN;
+		let expected: Vec<f64> = EXPECTED_F32.iter().map(|&x| x as f64).collect();
+		let s = encode(&expected, endianness);
+		let data: Vec<f64> = decode(&s, endianness);
+		assert_eq!(data, expected);
+	}
+
 	#[test]
 	fn test_be_u8()
 	{
 		let endianness = BIG_ENDIAN;
-		let s = encode_u8(&EXPECTED_U8.to_vec(), endianness);
+		let s = encode_u8(&EXPECTED_U8, endianness);
 		//println!("s = {}", s);
 		let data = decode_u8(&s, endianness);
 		assert_eq!(data, EXPECTED_U8);
@@ -0,0 +1,713 @@
+
+//==============================================================================
+
+// Batch conversion jobs described by a JSON or TOML settings file, e.g.
+//
+//     inputs = ["results/*.vtu"]
+//     output_dir = "converted"
+//     output_name = "{stem}_ascii{ext}"
+//     encoding = "ascii"
+//     drop_arrays = ["vtkGhostType"]
+//     downcast = { Float64 = "Float32" }
+//
+// Relative paths are relative to the settings file
+
+// Standard
+use std::collections::BTreeMap;
+use std::panic;
+use std::path::{Component, Path, PathBuf};
+
+//********
+
+// Third party
+use ansi_term::Colour;
+use log::{debug, warn};
+use rayon::prelude::*;
+use serde_json::Value;
+
+//********
+
+// This lib
+use crate::utils;
+use crate::vtk;
+
+//********
+
+const JSON_EXT: &str = ".json";
+const TOML_EXT: &str = ".toml";
+
+// Output name placeholders
+const STEM : &str = "{stem}";
+const EXT  : &str = "{ext}";
+const INDEX: &str = "{index}";
+
+const DEFAULT_NAME: &str = "{stem}{ext}";
+
+const WILDCARDS: &[char] = &['*', '?', '['];
+
+//==============================================================================
+
+#[derive(Debug, Clone, Default)]
+pub struct Job
+{
+	// Input files, directories, or glob patterns.  A directory stands for all
+	// loadable files below it
+	pub inputs: Vec<String>,
+
+	pub output_dir: String,
+
+	// Pattern with {stem} (input file name without extension), {ext} (of the
+	// output format), and {index} (of the sorted inputs).  Default "{stem}{ext}".
+	// Outputs keep the subdirectories of their input directory or pattern
+	pub output_name: String,
+
+	// One of vtk::FORMATS.  Default vtu
+	pub format: Option<String>,
+
+	// "ascii" or "binary"
+	pub encoding: Option<String>,
+
+	// "zlib" or "none"
+	pub compression: Option<String>,
+
+	// "LittleEndian" or "BigEndian"
+	pub byte_order: Option<String>,
+
+	pub vtk_version: Option<String>,
+
+	pub linearize: bool,
+	pub strip_ghosts: bool,
+
+	pub keep_arrays: Vec<String>,
+	pub drop_arrays: Vec<String>,
+
+	// Array name or type to type, e.g. Float64 = "Float32"
+	pub downcast: BTreeMap<String, String>,
+
+	// Worker threads, or 0 for one per CPU
+	pub threads: usize,
+
+	// Convert even if an output is newer than its input
+	pub force: bool,
+}
+
+//********
+
+#[derive(Debug, Clone, Default)]
+pub struct Summary
+{
+	// (input, output) of each converted file
+	pub converted: Vec<(String, String)>,
+
+	// (input, output) of each file with an up-to-date output
+	pub skipped: Vec<(String, String)>,
+
+	// (input, error message) of each failed file
+	pub failed: Vec<(String, String)>,
+}
+
+//==============================================================================
+
+fn choice(key: &str, val: &Option<String>, choices: &[&str]) -> Option<String>
+{
+	// Check an optional string setting against its allowed values
+	if let Some(v) = val
+	{
+		if !choices.contains(&v.as_str())
+		{
+			panic!("Unknown {} \"{}\" in settings file.  Expected one of {}",
+				key, v, choices.join(", "));
+		}
+	}
+	return val.clone();
+}
+
+//********
+
+fn relative_to(dir: &Path, path: &str) -> String
+{
+	let p = Path::new(path);
+	return if p.is_absolute() { path.to_string() }
+		else { dir.join(p).to_string_lossy().into_owned() };
+}
+
+//********
+
+pub fn load(file: &str) -> vtk::Settings
+{
+	// Read a settings file into batch settings, with the inputs expanded
+
+	let errstr = format!("Cannot read settings file \"{}\"", file);
+	let text = std::fs::read_to_string(file).expect(&errstr);
+
+	// TOML goes through the same JSON value, so both formats are checked alike
+	let lower = file.to_lowercase();
+	let value: Value = if lower.ends_with(JSON_EXT)
+	{
+		serde_json::from_str(&text).unwrap_or_else(|e| panic!("{}:  {}", errstr, e))
+	}
+	else if lower.ends_with(TOML_EXT)
+	{
+		let table: toml::Table = toml::from_str(&text)
+			.unwrap_or_else(|e| panic!("{}:  {}", errstr, e));
+		serde_json::to_value(table).unwrap_or_else(|e| panic!("{}:  {}", errstr, e))
+	}
+	else
+	{
+		panic!("{}.  Expected a {} or {} extension", errstr, JSON_EXT, TOML_EXT);
+	};
+
+	let job = Job::from_value(&value).unwrap_or_else(|e| panic!("{}:  {}", errstr, e));
+
+	let dir = Path::new(file).parent().unwrap_or_else(|| Path::new(""));
+	return job.settings(dir);
+}
+
+//********
+
+fn string(key: &str, val: &Value) -> Result<String, String>
+{
+	return val.as_str().map(str::to_string)
+		.ok_or_else(|| format!("{} must be a string", key));
+}
+
+fn strings(key: &str, val: &Value) -> Result<Vec<String>, String>
+{
+	return val.as_array().ok_or_else(|| format!("{} must be an array", key))?
+		.iter().map(|v| string(key, v)).collect();
+}
+
+fn boolean(key: &str, val: &Value) -> Result<bool, String>
+{
+	return val.as_bool().ok_or_else(|| format!("{} must be true or false", key));
+}
+
+//==============================================================================
+
+impl Job
+{
+pub fn from_value(value: &Value) -> Result<Job, String>
+{
+	// Job from a parsed settings file.  Missing keys keep their defaults, and
+	// unknown keys are errors
+
+	let table = value.as_object()
+		.ok_or_else(|| "settings must be a table".to_string())?;
+
+	let mut job = Job::default();
+	for (key, val) in table
+	{
+		let key = key.as_str();
+		match key
+		{
+			"inputs"       => job.inputs       = strings(key, val)?,
+			"output_dir"   => job.output_dir   = string (key, val)?,
+			"output_name"  => job.output_name  = string (key, val)?,
+			"format"       => job.format       = Some(string(key, val)?),
+			"encoding"     => job.encoding     = Some(string(key, val)?),
+			"compression"  => job.compression  = Some(string(key, val)?),
+			"byte_order"   => job.byte_order   = Some(string(key, val)?),
+			"vtk_version"  => job.vtk_version  = Some(string(key, val)?),
+			"linearize"    => job.linearize    = boolean(key, val)?,
+			"strip_ghosts" => job.strip_ghosts = boolean(key, val)?,
+			"keep_arrays"  => job.keep_arrays  = strings(key, val)?,
+			"drop_arrays"  => job.drop_arrays  = strings(key, val)?,
+			"force"        => job.force        = boolean(key, val)?,
+			"threads" =>
+			{
+				job.threads = val.as_u64().ok_or_else(||
+					format!("{} must be a non-negative integer", key))? as usize;
+			},
+			"downcast" =>
+			{
+				let types = val.as_object().ok_or_else(||
+					format!("{} must be a table", key))?;
+				for (from, to) in types
+				{
+					job.downcast.insert(from.clone(), string(key, to)?);
+				}
+			},
+			_ => return Err(format!("unknown setting \"{}\"", key)),
+		}
+	}
+	return Ok(job);
+}
+
+//********
+
+pub fn settings(&self, dir: &Path) -> vtk::Settings
+{
+	// Batch settings, with relative paths resolved against dir
+
+	let mut s = new_settings();
+
+	let formats: Vec<&str> = vtk::FORMATS.iter().map(|f| f.0).collect();
+	if let Some(f) = choice("format", &self.format, &formats)
+	{
+		s.output_format = f;
+	}
+
+	match choice("encoding", &self.encoding, &["ascii", "binary"]).as_deref()
+	{
+		Some("ascii" ) => s.ascii  = true,
+		Some("binary") => s.binary = true,
+		_ => (),
+	}
+	match choice("compression", &self.compression, &["zlib", "none"]).as_deref()
+	{
+		Some("zlib") => s.zlib         = true,
+		Some("none") => s.uncompressed = true,
+		_ => (),
+	}
+	match choice("byte_order", &self.byte_order, &["LittleEndian", "BigEndian"])
+		.as_deref()
+	{
+		Some("LittleEndian") => s.le = true,
+		Some("BigEndian"   ) => s.be = true,
+		_ => (),
+	}
+
+	for (from, to) in &self.downcast
+	{
+		choice("downcast type", &Some(to.clone()), vtk::VTK_TYPES);
+		s.downcasts.push((from.clone(), to.clone()));
+	}
+
+	s.vtk_version  = self.vtk_version.clone();
+	s.linearize    = self.linearize;
+	s.strip_ghosts = self.strip_ghosts;
+	s.keep_arrays  = self.keep_arrays.clone();
+	s.drop_arrays  = self.drop_arrays.clone();
+	s.threads      = self.threads;
+	s.force        = self.force;
+
+	if !self.output_dir.is_empty()
+	{
+		s.output = relative_to(dir, &self.output_dir);
+	}
+	if !self.output_name.is_empty()
+	{
+		s.output_name = self.output_name.clone();
+	}
+
+	for pattern in &self.inputs
+	{
+		add_input(&mut s, &relative_to(dir, pattern));
+	}
+
+	return s;
+}}
+
+//==============================================================================
+
+pub fn new_settings() -> vtk::Settings
+{
+	// Batch settings without inputs, with the default output
+	let mut s = vtk::Settings::new(utils::BATCH);
+	s.output        = ".".to_string();
+	s.output_name   = DEFAULT_NAME.to_string();
+	s.output_format = vtk::FMT_VTU.to_string();
+	return s;
+}
+
+//********
+
+pub fn is_settings_file(file: &str) -> bool
+{
+	let lower = file.to_lowercase();
+	return lower.ends_with(JSON_EXT) || lower.ends_with(TOML_EXT);
+}
+
+//********
+
+fn glob_root(pattern: &str) -> String
+{
+	// Leading directories of a pattern, before the first wildcard
+	let mut root = PathBuf::new();
+	for c in Path::new(pattern).parent().into_iter().flat_map(|p| p.components())
+	{
+		if c.as_os_str().to_string_lossy().contains(WILDCARDS)
+		{
+			break;
+		}
+		root.push(c);
+	}
+	return root.to_string_lossy().into_owned();
+}
+
+//********
+
+pub fn add_input(settings: &mut vtk::Settings, input: &str)
+{
+	// Add a file, a glob pattern, or every loadable file under a directory.
+	// Subdirectories below a directory or the wildcards of a pattern are kept
+	// in the output tree
+
+	if !input.contains(WILDCARDS)
+	{
+		if !Path::new(input).is_dir()
+		{
+			// Missing files are kept, and fail in the summary
+			settings.inputs.push(input.to_string());
+			settings.input_roots.push("".to_string());
+			return;
+		}
+
+		let pattern = Path::new(input).join("**").join("*");
+		glob_inputs(settings, &pattern.to_string_lossy(), input, true);
+		return;
+	}
+
+	glob_inputs(settings, input, &glob_root(input), false);
+}
+
+//********
+
+fn glob_inputs(settings: &mut vtk::Settings, pattern: &str, root: &str,
+		loadable_only: bool)
+{
+	// With loadable_only, skip files of formats that vtk::load cannot read
+	let mut paths: Vec<PathBuf> = glob::glob(pattern)
+		.unwrap_or_else(|e| panic!("Bad input pattern \"{}\":  {}", pattern, e))
+		.filter_map(Result::ok).filter(|p| p.is_file())
+		.filter(|p| !loadable_only || vtk::is_loadable(&p.to_string_lossy()))
+		.collect();
+	if paths.is_empty()
+	{
+		warn!("no inputs match \"{}\"", pattern);
+	}
+	paths.sort();
+	for p in paths
+	{
+		settings.inputs.push(p.to_string_lossy().into_owned());
+		settings.input_roots.push(root.to_string());
+	}
+}
+
+//==============================================================================
+
+pub fn output_path(settings: &vtk::Settings, input: &str, root: &str,
+		index: usize) -> String
+{
+	// Fill the output name pattern for one input, below its subdirectory
+	// relative to root
+
+	let path = Path::new(input);
+	let stem = path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+
+	let ext = vtk::FORMATS.iter().find(|f| f.0 == settings.output_format)
+		.map(|f| f.1[0]).unwrap_or("");
+
+	let name = settings.output_name.replace(STEM, &stem).replace(EXT, ext)
+		.replace(INDEX, &index.to_string());
+
+	// Compare without "." components, which glob may drop
+	let parts = |p: &Path| -> Vec<PathBuf> { p.components()
+		.filter(|c| *c != Component::CurDir).map(|c| PathBuf::from(c.as_os_str()))
+		.collect() };
+
+	let mut out = PathBuf::from(&settings.output);
+	if !root.is_empty()
+	{
+		let dirs = parts(path.parent().unwrap_or_else(|| Path::new("")));
+		let root = parts(Path::new(root));
+		if dirs.starts_with(&root)
+		{
+			out.extend(&dirs[root.len() ..]);
+		}
+	}
+	out.push(name);
+	return out.to_string_lossy().into_owned();
+}
+
+//********
+
+pub fn jobs(settings: &vtk::Settings) -> Vec<vtk::Settings>
+{
+	// Settings of each file, for the convert steps
+	return settings.inputs.iter().enumerate().map(|(i, input)|
+	{
+		let root = settings.input_roots.get(i).map_or("", |r| r.as_str());
+		let mut s = settings.clone();
+		s.command = utils::CONVERT.to_string();
+		s.input   = input.clone();
+		s.output  = output_path(settings, input, root, i);
+		s.inputs.clear();
+		s.input_roots.clear();
+		s
+	}).collect();
+}
+
+//********
+
+fn up_to_date(input: &str, output: &str) -> bool
+{
+	// Output is at least as new as the input
+	let modified = |f: &str| std::fs::metadata(f).and_then(|m| m.modified()).ok();
+	return match (modified(input), modified(output))
+	{
+		(Some(i), Some(o)) => o >= i,
+		_ => false,
+	};
+}
+
+//********
+
+fn same_file(a: &str, b: &str) -> bool
+{
+	return match (Path::new(a).canonicalize(), Path::new(b).canonicalize())
+	{
+		(Ok(a), Ok(b)) => a == b,
+		_ => false,
+	};
+}
+
+//********
+
+pub fn convert_file(s: &vtk::Settings) -> Result<(), String>
+{
+	// Load, convert, and export one file.  Panics become errors, so one bad
+	// file does not stop the batch
+
+	if same_file(&s.input, &s.output)
+	{
+		return Err("the output would overwrite the input".to_string());
+	}
+
+	debug!("Converting \"{}\" to \"{}\"", s.input, s.output);
+
+	let result = panic::catch_unwind(|| {
+		if let Some(dir) = Path::new(&s.output).parent()
+		{
+			if let Err(e) = std::fs::create_dir_all(dir)
+			{
+				panic!("Cannot create output directory \"{}\":  {}",
+					dir.display(), e);
+			}
+		}
+
+		let mut v = vtk::load(&s.input);
+		v.convert(s);
+		v.export_as(&s.output, &s.output_format);
+	});
+
+	return result.map_err(|e|
+	{
+		if let Some(m) = e.downcast_ref::<String>()
+		{
+			m.clone()
+		}
+		else if let Some(m) = e.downcast_ref::<&str>()
+		{
+			m.to_string()
+		}
+		else
+		{
+			"unknown error".to_string()
+		}
+	});
+}
+
+//********
+
+pub fn run(settings: &vtk::Settings) -> Summary
+{
+	// Convert every input concurrently on a pool of settings.threads workers.
+	// Up-to-date outputs are skipped unless forced.  Errors are collected in
+	// the summary, but the panic hook still runs, so callers that print the
+	// summary may want to silence it
+
+	let pool = rayon::ThreadPoolBuilder::new().num_threads(settings.threads)
+		.build().unwrap_or_else(|e| panic!("Cannot start batch threads:  {}", e));
+
+	let jobs = jobs(settings);
+
+	// Inputs with the same output would overwrite each other, so all of them
+	// fail
+	let mut counts: BTreeMap<PathBuf, usize> = BTreeMap::new();
+	for s in &jobs
+	{
+		*counts.entry(PathBuf::from(&s.output)).or_default() += 1;
+	}
+
+	let results: Vec<Option<Result<(), String>>> = pool.install(||
+	{
+		jobs.par_iter().map(|s|
+		{
+			if counts[&PathBuf::from(&s.output)] > 1
+			{
+				Some(Err(format!("the output \"{}\" is also the output of \
+					another input", s.output)))
+			}
+			else if !settings.force && up_to_date(&s.input, &s.output)
+			{
+				debug!("Skipping \"{}\", which is up to date", s.output);
+				None
+			}
+			else
+			{
+				Some(convert_file(s))
+			}
+		}).collect()
+	});
+
+	let mut summary = Summary::default();
+	for (s, r) in jobs.into_iter().zip(results)
+	{
+		match r
+		{
+			None         => summary.skipped  .push((s.input, s.output)),
+			Some(Ok(())) => summary.converted.push((s.input, s.output)),
+			Some(Err(e)) => summary.failed   .push((s.input, e)),
+		}
+	}
+	return summary;
+}
+
+//==============================================================================
+
+impl Summary
+{
+	pub fn to_text(&self) -> String
+	{
+		let mut s = String::new();
+		for (input, e) in &self.failed
+		{
+			s += &format!("{} converting \"{}\":  {}\n",
+				Colour::Red.bold().paint("error"), input, e);
+		}
+		s += &format!("{} converted, {} up to date, {} failed\n",
+			self.converted.len(), self.skipped.len(), self.failed.len());
+		return s;
+	}
+}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	#[test]
+	fn test_batch_toml()
+	{
+		let dir = "./scratch/tmp.Qe7ZbN2xVd";
+		let _ = std::fs::remove_dir_all(dir);
+		std::fs::create_dir_all(dir).unwrap();
+
+		let file = format!("{}/job.toml", dir);
+		std::fs::write(&file, "\
+			inputs = [\"../../data/icosahedron-binary*.vtu\", \"missing.vtu\"]\n\
+			output_dir = \"out\"\n\
+			output_name = \"{index}_{stem}{ext}\"\n\
+			format = \"vtk\"\n\
+			encoding = \"ascii\"\n\
+			threads = 2\n\
+			drop_arrays = [\"Resultz\"]\n").unwrap();
+
+		let s = load(&file);
+		assert_eq!(s.inputs.len(), 4);
+		assert!(s.ascii);
+
+		// A file that fails does not stop the others
+		let summary = run(&s);
+		assert_eq!(summary.converted.len(), 3);
+		assert_eq!(summary.failed.len(), 1);
+		assert!(summary.failed[0].0.ends_with("missing.vtu"));
+
+		let out = &summary.converted[2].1;
+		assert!(out.ends_with("2_icosahedron-binary.vtk"));
+		let text = std::fs::read_to_string(out).unwrap();
+		assert!(text.contains("ASCII"));
+		assert!(!text.contains("Resultz"));
+
+		// Outputs are now up to date
+		let summary = run(&s);
+		assert_eq!(summary.skipped.len(), 3);
+		assert_eq!(summary.converted.len(), 0);
+	}
+
+	#[test]
+	fn test_batch_tree()
+	{
+		let dir = "./scratch/tmp.u4RkT9wLcs";
+		let _ = std::fs::remove_dir_all(dir);
+		std::fs::create_dir_all(format!("{}/in/a/b", dir)).unwrap();
+		std::fs::copy("./data/icosahedron-binary.vtu",
+			format!("{}/in/a/b/ico.vtu", dir)).unwrap();
+		std::fs::copy("./data/icosahedron-binary.vtu",
+			format!("{}/in/top.vtu", dir)).unwrap();
+		vtk::load("./data/icosahedron-binary.vtu")
+			.export_as(&format!("{}/in/a/cube.stl", dir), vtk::FMT_STL);
+		std::fs::write(format!("{}/in/notes.txt", dir), "not a mesh").unwrap();
+
+		let mut s = new_settings();
+		s.output = format!("{}/out", dir);
+		add_input(&mut s, &format!("{}/in", dir));
+		assert_eq!(s.inputs.len(), 3);
+
+		let summary = run(&s);
+		assert_eq!(summary.converted.len(), 3);
+		assert!(Path::new(&format!("{}/out/a/b/ico.vtu", dir)).exists());
+		assert!(Path::new(&format!("{}/out/a/cube.vtu", dir)).exists());
+		assert!(Path::new(&format!("{}/out/top.vtu", dir)).exists());
+
+		// Inputs that share an output all fail, without writing it
+		let mut d = new_settings();
+		d.output = format!("{}/flat", dir);
+		d.output_name = "mesh{ext}".to_string();
+		d.force = true;
+		add_input(&mut d, &format!("{}/in/top.vtu", dir));
+		add_input(&mut d, &format!("{}/in/a/b/ico.vtu", dir));
+		let summary = run(&d);
+		assert_eq!(summary.failed.len(), 2);
+		assert!(summary.failed[0].1.contains("also the output"));
+		assert!(!Path::new(&format!("{}/flat/mesh.vtu", dir)).exists());
+
+		// Glob patterns keep the subdirectories matched by wildcards
+		assert_eq!(glob_root("in/*/b/*.vtu"), "in");
+
+		// Never overwrite an input
+		s.output = format!("{}/in", dir);
+		s.force = true;
+		let summary = run(&s);
+		assert_eq!(summary.failed.len(), 2);
+		assert_eq!(summary.converted.len(), 1);
+	}
+
+	#[test]
+	fn test_batch_json()
+	{
+		let job = Job::from_value(&serde_json::from_str("{\"inputs\": [], \
+			\"downcast\": {\"Float64\": \"Float32\"}, \"compression\": \"zlib\"}")
+			.unwrap()).unwrap();
+		let s = job.settings(Path::new("."));
+		assert!(s.zlib);
+		assert_eq!(s.downcasts, vec![("Float64".to_string(), "Float32".to_string())]);
+
+		let mut v = vtk::load("./data/icosahedron-binary-pdata.vtu");
+		v.convert(&s);
+		assert_eq!(v.pdata[0].data.vtk_type(), "Float32");
+
+		let bad = Job::from_value(&serde_json::json!({"encodings": "ascii"}));
+		assert!(bad.unwrap_err().contains("unknown setting"));
+		let bad = Job::from_value(&serde_json::json!({"threads": "2"}));
+		assert!(bad.is_err());
+
+		// Conflicting encodings are caught like the CLI flags
+		let job = Job::from_value(&serde_json::json!({"encoding": "ascii",
+			"compression": "zlib"})).unwrap();
+		let e = utils::encoding_conflict(&job.settings(Path::new(".")));
+		assert!(e.unwrap().contains("'--ascii' cannot be used with '--zlib'"));
+
+		let job = Job::from_value(&serde_json::json!({"format": "stl",
+			"byte_order": "BigEndian"})).unwrap();
+		let e = utils::encoding_conflict(&job.settings(Path::new(".")));
+		assert!(e.unwrap().contains("'--be' cannot be used with stl output"));
+		assert!(utils::encoding_conflict(&s).is_none());
+	}
+}
+
+//==============================================================================
+
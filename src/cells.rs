@@ -0,0 +1,1292 @@
+
+//==============================================================================
+
+// VTK cell types, node counts, and higher-order (Lagrange and Bézier) cells
+
+//********
+
+// This lib
+use crate::vtk::{VtkFile, DataArray};
+
+//********
+
+// VTK cell type identifiers, from vtkCellType.h
+pub const VTK_EMPTY_CELL      : u8 =  0;
+pub const VTK_VERTEX          : u8 =  1;
+pub const VTK_POLY_VERTEX     : u8 =  2;
+pub const VTK_LINE            : u8 =  3;
+pub const VTK_POLY_LINE       : u8 =  4;
+pub const VTK_TRIANGLE        : u8 =  5;
+pub const VTK_TRIANGLE_STRIP  : u8 =  6;
+pub const VTK_POLYGON         : u8 =  7;
+pub const VTK_PIXEL           : u8 =  8;
+pub const VTK_QUAD            : u8 =  9;
+pub const VTK_TETRA           : u8 = 10;
+pub const VTK_VOXEL           : u8 = 11;
+pub const VTK_HEXAHEDRON      : u8 = 12;
+pub const VTK_WEDGE           : u8 = 13;
+pub const VTK_PYRAMID         : u8 = 14;
+pub const VTK_PENTAGONAL_PRISM: u8 = 15;
+pub const VTK_HEXAGONAL_PRISM : u8 = 16;
+
+pub const VTK_QUADRATIC_EDGE                   : u8 = 21;
+pub const VTK_QUADRATIC_TRIANGLE               : u8 = 22;
+pub const VTK_QUADRATIC_QUAD                   : u8 = 23;
+pub const VTK_QUADRATIC_TETRA                  : u8 = 24;
+pub const VTK_QUADRATIC_HEXAHEDRON             : u8 = 25;
+pub const VTK_QUADRATIC_WEDGE                  : u8 = 26;
+pub const VTK_QUADRATIC_PYRAMID                : u8 = 27;
+pub const VTK_BIQUADRATIC_QUAD                 : u8 = 28;
+pub const VTK_TRIQUADRATIC_HEXAHEDRON          : u8 = 29;
+pub const VTK_QUADRATIC_LINEAR_QUAD            : u8 = 30;
+pub const VTK_QUADRATIC_LINEAR_WEDGE           : u8 = 31;
+pub const VTK_BIQUADRATIC_QUADRATIC_WEDGE      : u8 = 32;
+pub const VTK_BIQUADRATIC_QUADRATIC_HEXAHEDRON : u8 = 33;
+pub const VTK_BIQUADRATIC_TRIANGLE             : u8 = 34;
+pub const VTK_CUBIC_LINE                       : u8 = 35;
+pub const VTK_QUADRATIC_POLYGON                : u8 = 36;
+pub const VTK_TRIQUADRATIC_PYRAMID             : u8 = 37;
+
+pub const VTK_CONVEX_POINT_SET: u8 = 41;
+pub const VTK_POLYHEDRON      : u8 = 42;
+
+pub const VTK_PARAMETRIC_CURVE        : u8 = 51;
+pub const VTK_PARAMETRIC_SURFACE      : u8 = 52;
+pub const VTK_PARAMETRIC_TRI_SURFACE  : u8 = 53;
+pub const VTK_PARAMETRIC_QUAD_SURFACE : u8 = 54;
+pub const VTK_PARAMETRIC_TETRA_REGION : u8 = 55;
+pub const VTK_PARAMETRIC_HEX_REGION   : u8 = 56;
+
+pub const VTK_HIGHER_ORDER_EDGE        : u8 = 60;
+pub const VTK_HIGHER_ORDER_TRIANGLE    : u8 = 61;
+pub const VTK_HIGHER_ORDER_QUAD        : u8 = 62;
+pub const VTK_HIGHER_ORDER_POLYGON     : u8 = 63;
+pub const VTK_HIGHER_ORDER_TETRAHEDRON : u8 = 64;
+pub const VTK_HIGHER_ORDER_WEDGE       : u8 = 65;
+pub const VTK_HIGHER_ORDER_PYRAMID     : u8 = 66;
+pub const VTK_HIGHER_ORDER_HEXAHEDRON  : u8 = 67;
+
+pub const VTK_LAGRANGE_CURVE         : u8 = 68;
+pub const VTK_LAGRANGE_TRIANGLE      : u8 = 69;
+pub const VTK_LAGRANGE_QUADRILATERAL : u8 = 70;
+pub const VTK_LAGRANGE_TETRAHEDRON   : u8 = 71;
+pub const VTK_LAGRANGE_HEXAHEDRON    : u8 = 72;
+pub const VTK_LAGRANGE_WEDGE         : u8 = 73;
+pub const VTK_LAGRANGE_PYRAMID       : u8 = 74;
+
+pub const VTK_BEZIER_CURVE           : u8 = 75;
+pub const VTK_BEZIER_TRIANGLE        : u8 = 76;
+pub const VTK_BEZIER_QUADRILATERAL   : u8 = 77;
+pub const VTK_BEZIER_TETRAHEDRON     : u8 = 78;
+pub const VTK_BEZIER_HEXAHEDRON      : u8 = 79;
+pub const VTK_BEZIER_WEDGE           : u8 = 80;
+pub const VTK_BEZIER_PYRAMID         : u8 = 81;
+
+// Name of the optional CellData array with per-cell degrees [p, q, r]
+pub const HO_DEGREES: &str = "HigherOrderDegrees";
+
+//==============================================================================
+
+pub fn is_higher_order(ctype: u8) -> bool
+{
+	(VTK_LAGRANGE_CURVE ..= VTK_BEZIER_PYRAMID).contains(&ctype)
+}
+
+//********
+
+pub fn linear_type(ctype: u8) -> u8
+{
+	// Linear counterpart of a higher-order cell type.  Linear types map to
+	// themselves
+
+	match ctype
+	{
+		VTK_LAGRANGE_CURVE         | VTK_BEZIER_CURVE         => VTK_LINE,
+		VTK_LAGRANGE_TRIANGLE      | VTK_BEZIER_TRIANGLE      => VTK_TRIANGLE,
+		VTK_LAGRANGE_QUADRILATERAL | VTK_BEZIER_QUADRILATERAL => VTK_QUAD,
+		VTK_LAGRANGE_TETRAHEDRON   | VTK_BEZIER_TETRAHEDRON   => VTK_TETRA,
+		VTK_LAGRANGE_HEXAHEDRON    | VTK_BEZIER_HEXAHEDRON    => VTK_HEXAHEDRON,
+		VTK_LAGRANGE_WEDGE         | VTK_BEZIER_WEDGE         => VTK_WEDGE,
+		VTK_LAGRANGE_PYRAMID       | VTK_BEZIER_PYRAMID       => VTK_PYRAMID,
+		_ => ctype,
+	}
+}
+
+//********
+
+pub fn corner_type(ctype: u8) -> u8
+{
+	// Linear cell spanned by the corners of a quadratic or higher-order cell,
+	// which are its first nodes.  Linear types map to themselves
+
+	match ctype
+	{
+		VTK_QUADRATIC_EDGE | VTK_CUBIC_LINE => VTK_LINE,
+		VTK_QUADRATIC_TRIANGLE | VTK_BIQUADRATIC_TRIANGLE => VTK_TRIANGLE,
+		VTK_QUADRATIC_QUAD | VTK_BIQUADRATIC_QUAD
+			| VTK_QUADRATIC_LINEAR_QUAD => VTK_QUAD,
+		VTK_QUADRATIC_TETRA => VTK_TETRA,
+		VTK_QUADRATIC_HEXAHEDRON | VTK_TRIQUADRATIC_HEXAHEDRON
+			| VTK_BIQUADRATIC_QUADRATIC_HEXAHEDRON => VTK_HEXAHEDRON,
+		VTK_QUADRATIC_WEDGE | VTK_QUADRATIC_LINEAR_WEDGE
+			| VTK_BIQUADRATIC_QUADRATIC_WEDGE => VTK_WEDGE,
+		VTK_QUADRATIC_PYRAMID | VTK_TRIQUADRATIC_PYRAMID => VTK_PYRAMID,
+		VTK_QUADRATIC_POLYGON => VTK_POLYGON,
+		_ => linear_type(ctype),
+	}
+}
+
+//********
+
+pub fn ncorners(ctype: u8, npts: usize) -> usize
+{
+	// Number of corner nodes of a cell with npts nodes
+	return match corner_type(ctype)
+	{
+		// Quadratic polygons have a mid-edge node per corner
+		VTK_POLYGON if ctype != VTK_POLYGON => npts / 2,
+		c => npoints_fixed(c).unwrap_or(npts),
+	};
+}
+
+//********
+
+pub fn dimension(ctype: u8) -> Option<usize>
+{
+	// Topological dimension of a cell type, or None for empty and unknown
+	// types
+	match corner_type(ctype)
+	{
+		VTK_VERTEX | VTK_POLY_VERTEX => Some(0),
+		VTK_LINE   | VTK_POLY_LINE   => Some(1),
+		VTK_TRIANGLE ..= VTK_QUAD    => Some(2),
+		VTK_TETRA ..= VTK_HEXAGONAL_PRISM
+			| VTK_CONVEX_POINT_SET | VTK_POLYHEDRON => Some(3),
+		_ => None,
+	}
+}
+
+//********
+
+pub fn faces(ctype: u8) -> &'static [&'static [usize]]
+{
+	// Faces of a 3D cell as local corner IDs, ordered counter-clockwise seen
+	// from outside, like vtkCell::GetFace.  Empty for other types and for 3D
+	// types without fixed faces, e.g. polyhedra
+
+	match corner_type(ctype)
+	{
+		VTK_TETRA => &[&[0, 1, 3], &[1, 2, 3], &[2, 0, 3], &[0, 2, 1]],
+		VTK_VOXEL => &[&[0, 4, 6, 2], &[1, 3, 7, 5], &[0, 1, 5, 4],
+			&[2, 6, 7, 3], &[0, 2, 3, 1], &[4, 5, 7, 6]],
+		VTK_HEXAHEDRON => &[&[0, 4, 7, 3], &[1, 2, 6, 5], &[0, 1, 5, 4],
+			&[3, 7, 6, 2], &[0, 3, 2, 1], &[4, 5, 6, 7]],
+		VTK_WEDGE => &[&[0, 1, 2], &[3, 5, 4], &[0, 3, 4, 1], &[1, 4, 5, 2],
+			&[2, 5, 3, 0]],
+		VTK_PYRAMID => &[&[0, 3, 2, 1], &[0, 1, 4], &[1, 2, 4], &[2, 3, 4],
+			&[3, 0, 4]],
+		_ => &[],
+	}
+}
+
+//********
+
+pub fn is_known_type(ctype: u8) -> bool
+{
+	// Any cell type defined in vtkCellType.h
+	matches!(ctype, VTK_EMPTY_CELL ..= VTK_HEXAGONAL_PRISM
+		| VTK_QUADRATIC_EDGE ..= VTK_TRIQUADRATIC_PYRAMID
+		| VTK_CONVEX_POINT_SET | VTK_POLYHEDRON
+		| VTK_PARAMETRIC_CURVE ..= VTK_PARAMETRIC_HEX_REGION
+		| VTK_HIGHER_ORDER_EDGE ..= VTK_BEZIER_PYRAMID)
+}
+
+//********
+
+pub fn type_name(ctype: u8) -> &'static str
+{
+	// Name of a cell type without the VTK_ prefix, e.g. for info tables
+	match ctype
+	{
+		VTK_EMPTY_CELL                       => "EMPTY_CELL",
+		VTK_VERTEX                           => "VERTEX",
+		VTK_POLY_VERTEX                      => "POLY_VERTEX",
+		VTK_LINE                             => "LINE",
+		VTK_POLY_LINE                        => "POLY_LINE",
+		VTK_TRIANGLE                         => "TRIANGLE",
+		VTK_TRIANGLE_STRIP                   => "TRIANGLE_STRIP",
+		VTK_POLYGON                          => "POLYGON",
+		VTK_PIXEL                            => "PIXEL",
+		VTK_QUAD                             => "QUAD",
+		VTK_TETRA                            => "TETRA",
+		VTK_VOXEL                            => "VOXEL",
+		VTK_HEXAHEDRON                       => "HEXAHEDRON",
+		VTK_WEDGE                            => "WEDGE",
+		VTK_PYRAMID                          => "PYRAMID",
+		VTK_PENTAGONAL_PRISM                 => "PENTAGONAL_PRISM",
+		VTK_HEXAGONAL_PRISM                  => "HEXAGONAL_PRISM",
+		VTK_QUADRATIC_EDGE                   => "QUADRATIC_EDGE",
+		VTK_QUADRATIC_TRIANGLE               => "QUADRATIC_TRIANGLE",
+		VTK_QUADRATIC_QUAD                   => "QUADRATIC_QUAD",
+		VTK_QUADRATIC_TETRA                  => "QUADRATIC_TETRA",
+		VTK_QUADRATIC_HEXAHEDRON             => "QUADRATIC_HEXAHEDRON",
+		VTK_QUADRATIC_WEDGE                  => "QUADRATIC_WEDGE",
+		VTK_QUADRATIC_PYRAMID                => "QUADRATIC_PYRAMID",
+		VTK_BIQUADRATIC_QUAD                 => "BIQUADRATIC_QUAD",
+		VTK_TRIQUADRATIC_HEXAHEDRON          => "TRIQUADRATIC_HEXAHEDRON",
+		VTK_QUADRATIC_LINEAR_QUAD            => "QUADRATIC_LINEAR_QUAD",
+		VTK_QUADRATIC_LINEAR_WEDGE           => "QUADRATIC_LINEAR_WEDGE",
+		VTK_BIQUADRATIC_QUADRATIC_WEDGE      => "BIQUADRATIC_QUADRATIC_WEDGE",
+		VTK_BIQUADRATIC_QUADRATIC_HEXAHEDRON => "BIQUADRATIC_QUADRATIC_HEXAHEDRON",
+		VTK_BIQUADRATIC_TRIANGLE             => "BIQUADRATIC_TRIANGLE",
+		VTK_CUBIC_LINE                       => "CUBIC_LINE",
+		VTK_QUADRATIC_POLYGON                => "QUADRATIC_POLYGON",
+		VTK_TRIQUADRATIC_PYRAMID             => "TRIQUADRATIC_PYRAMID",
+		VTK_CONVEX_POINT_SET                 => "CONVEX_POINT_SET",
+		VTK_POLYHEDRON                       => "POLYHEDRON",
+		VTK_PARAMETRIC_CURVE ..= VTK_PARAMETRIC_HEX_REGION => "PARAMETRIC",
+		VTK_HIGHER_ORDER_EDGE ..= VTK_HIGHER_ORDER_HEXAHEDRON => "HIGHER_ORDER",
+		VTK_LAGRANGE_CURVE         => "LAGRANGE_CURVE",
+		VTK_LAGRANGE_TRIANGLE      => "LAGRANGE_TRIANGLE",
+		VTK_LAGRANGE_QUADRILATERAL => "LAGRANGE_QUADRILATERAL",
+		VTK_LAGRANGE_TETRAHEDRON   => "LAGRANGE_TETRAHEDRON",
+		VTK_LAGRANGE_HEXAHEDRON    => "LAGRANGE_HEXAHEDRON",
+		VTK_LAGRANGE_WEDGE         => "LAGRANGE_WEDGE",
+		VTK_LAGRANGE_PYRAMID       => "LAGRANGE_PYRAMID",
+		VTK_BEZIER_CURVE           => "BEZIER_CURVE",
+		VTK_BEZIER_TRIANGLE        => "BEZIER_TRIANGLE",
+		VTK_BEZIER_QUADRILATERAL   => "BEZIER_QUADRILATERAL",
+		VTK_BEZIER_TETRAHEDRON     => "BEZIER_TETRAHEDRON",
+		VTK_BEZIER_HEXAHEDRON      => "BEZIER_HEXAHEDRON",
+		VTK_BEZIER_WEDGE           => "BEZIER_WEDGE",
+		VTK_BEZIER_PYRAMID         => "BEZIER_PYRAMID",
+		_ => "UNKNOWN",
+	}
+}
+
+//********
+
+pub fn npoints_fixed(ctype: u8) -> Option<usize>
+{
+	// Number of nodes of cell types with a fixed node count, including the
+	// quadratic types.  None for variable-size and higher-order types
+
+	match ctype
+	{
+		VTK_EMPTY_CELL => Some(0),
+		VTK_VERTEX     => Some(1),
+		VTK_LINE       => Some(2),
+		VTK_TRIANGLE   => Some(3),
+		VTK_PIXEL | VTK_QUAD | VTK_TETRA => Some(4),
+		VTK_VOXEL | VTK_HEXAHEDRON => Some(8),
+		VTK_WEDGE      => Some(6),
+		VTK_PYRAMID    => Some(5),
+		VTK_PENTAGONAL_PRISM                 => Some(10),
+		VTK_HEXAGONAL_PRISM                  => Some(12),
+		VTK_QUADRATIC_EDGE                   => Some( 3),
+		VTK_QUADRATIC_TRIANGLE               => Some( 6),
+		VTK_QUADRATIC_QUAD                   => Some( 8),
+		VTK_QUADRATIC_TETRA                  => Some(10),
+		VTK_QUADRATIC_HEXAHEDRON             => Some(20),
+		VTK_QUADRATIC_WEDGE                  => Some(15),
+		VTK_QUADRATIC_PYRAMID                => Some(13),
+		VTK_BIQUADRATIC_QUAD                 => Some( 9),
+		VTK_TRIQUADRATIC_HEXAHEDRON          => Some(27),
+		VTK_QUADRATIC_LINEAR_QUAD            => Some( 6),
+		VTK_QUADRATIC_LINEAR_WEDGE           => Some(12),
+		VTK_BIQUADRATIC_QUADRATIC_WEDGE      => Some(18),
+		VTK_BIQUADRATIC_QUADRATIC_HEXAHEDRON => Some(24),
+		VTK_BIQUADRATIC_TRIANGLE             => Some( 7),
+		VTK_CUBIC_LINE                       => Some( 4),
+		VTK_TRIQUADRATIC_PYRAMID             => Some(19),
+		_ => None,
+	}
+}
+
+//********
+
+pub fn npoints_higher_order(ctype: u8, degrees: [usize; 3]) -> usize
+{
+	// Number of nodes in a higher-order cell of the given degrees.  Simplices
+	// only use the first degree, wedges use the first and last
+
+	let [p, q, r] = degrees;
+	match linear_type(ctype)
+	{
+		VTK_LINE       => p + 1,
+		VTK_TRIANGLE   => (p + 1) * (p + 2) / 2,
+		VTK_QUAD       => (p + 1) * (q + 1),
+		VTK_TETRA      => (p + 1) * (p + 2) * (p + 3) / 6,
+		VTK_HEXAHEDRON => (p + 1) * (q + 1) * (r + 1),
+		VTK_WEDGE      => (p + 1) * (p + 2) / 2 * (r + 1),
+		VTK_PYRAMID    => (p + 1) * (p + 2) * (2*p + 3) / 6,
+		_ => panic!("Cell type {} is not higher order", ctype),
+	}
+}
+
+//********
+
+pub fn degree_from_npoints(ctype: u8, npts: usize) -> Option<usize>
+{
+	// Without a HigherOrderDegrees array, VTK infers a uniform degree from the
+	// number of nodes.  Returns None if no degree matches
+
+	let mut p = 1;
+	loop
+	{
+		let n = npoints_higher_order(ctype, [p, p, p]);
+		if n == npts
+		{
+			return Some(p);
+		}
+		else if n > npts
+		{
+			return None;
+		}
+		p += 1;
+	}
+}
+
+//==============================================================================
+
+fn cell_degrees(v: &VtkFile, degrees: &Option<Vec<f64>>, i: usize, npts: usize)
+	-> Result<[usize; 3], String>
+{
+	// Degrees of cell i, either from the HigherOrderDegrees array or inferred
+	// from its number of nodes
+
+	let ctype = v.types[i];
+	return match degrees
+	{
+		Some(d) =>
+		{
+			let dg = [d[3*i] as usize, d[3*i+1] as usize, d[3*i+2] as usize];
+			let expected = npoints_higher_order(ctype, dg);
+			if npts != expected
+			{
+				return Err(format!("Cell {} of type {} with degrees {:?} has {} \
+					points.  Expected {}", i, ctype, dg, npts, expected));
+			}
+			Ok(dg)
+		},
+		None =>
+		{
+			match degree_from_npoints(ctype, npts)
+			{
+				Some(p) => Ok([p, p, p]),
+				None => Err(format!("Cell {} of type {} has {} points, which \
+					does not match any degree", i, ctype, npts)),
+			}
+		},
+	};
+}
+
+//********
+
+fn ho_degrees(v: &VtkFile) -> Option<Vec<f64>>
+{
+	let a = v.cell_array(HO_DEGREES)?;
+	if a.ncomponents != 3
+	{
+		panic!("{} has {} components.  Expected 3", HO_DEGREES, a.ncomponents);
+	}
+	return Some(a.data.to_f64());
+}
+
+//********
+
+fn cell_range(v: &VtkFile, i: usize) -> (usize, usize)
+{
+	// Begin and end indices of cell i in the connectivity array
+	let begin = if i == 0 { 0 } else { v.offsets[i-1] as usize };
+	return (begin, v.offsets[i] as usize);
+}
+
+//********
+
+pub fn degree_errors(v: &VtkFile) -> Vec<(usize, String)>
+{
+	// Check the node counts of higher-order cells against their degrees.
+	// Returns the index of each bad cell with a message
+
+	let degrees = ho_degrees(v);
+
+	let mut errors = Vec::new();
+	for i in 0 .. v.ncells as usize
+	{
+		if is_higher_order(v.types[i])
+		{
+			let (begin, end) = cell_range(v, i);
+			if let Err(e) = cell_degrees(v, &degrees, i, end - begin)
+			{
+				errors.push((i, e));
+			}
+		}
+	}
+	return errors;
+}
+
+//********
+
+pub fn check_cells(v: &VtkFile)
+{
+	// Panics on the first bad higher-order cell
+	if let Some((_, e)) = degree_errors(v).first()
+	{
+		panic!("{}", e);
+	}
+}
+
+//==============================================================================
+
+// Node index functions following the ordering of VTK's higher-order cells:
+// corners first, then edge, face, and interior nodes
+
+fn curve_index(i: usize, p: usize) -> usize
+{
+	if i == 0 { 0 } else if i == p { 1 } else { i + 1 }
+}
+
+//********
+
+fn quad_index(i: usize, j: usize, o: [usize; 3]) -> usize
+{
+	let ibdy = i == 0 || i == o[0];
+	let jbdy = j == 0 || j == o[1];
+
+	if ibdy && jbdy
+	{
+		return if i != 0 { if j != 0 { 2 } else { 1 } }
+		             else { if j != 0 { 3 } else { 0 } };
+	}
+
+	let offset = 4;
+	if !ibdy && jbdy
+	{
+		return offset + (i - 1) + if j != 0 { o[0] - 1 + o[1] - 1 } else { 0 };
+	}
+	if ibdy && !jbdy
+	{
+		return offset + (j - 1) + if i != 0 { o[0] - 1 }
+		                          else { 2 * (o[0] - 1) + o[1] - 1 };
+	}
+
+	let offset = offset + 2 * (o[0] - 1 + o[1] - 1);
+	return offset + (i - 1) + (o[0] - 1) * (j - 1);
+}
+
+//********
+
+fn hex_index(i: usize, j: usize, k: usize, o: [usize; 3]) -> usize
+{
+	let ibdy = i == 0 || i == o[0];
+	let jbdy = j == 0 || j == o[1];
+	let kbdy = k == 0 || k == o[2];
+	let nbdy = ibdy as usize + jbdy as usize + kbdy as usize;
+
+	if nbdy == 3
+	{
+		// Corner
+		let c = if i != 0 { if j != 0 { 2 } else { 1 } }
+		             else { if j != 0 { 3 } else { 0 } };
+		return c + if k != 0 { 4 } else { 0 };
+	}
+
+	let mut offset = 8;
+	if nbdy == 2
+	{
+		// Edge
+		let kshift = if k != 0 { 2 * (o[0] + o[1] - 2) } else { 0 };
+		if !ibdy
+		{
+			return offset + (i - 1) + kshift
+				+ if j != 0 { o[0] + o[1] - 2 } else { 0 };
+		}
+		if !jbdy
+		{
+			return offset + (j - 1) + kshift
+				+ if i != 0 { o[0] - 1 } else { 2 * (o[0] - 1) + o[1] - 1 };
+		}
+		offset += 4 * (o[0] - 1) + 4 * (o[1] - 1);
+		let e = if i != 0 { if j != 0 { 3 } else { 1 } }
+		             else { if j != 0 { 2 } else { 0 } };
+		return offset + (k - 1) + (o[2] - 1) * e;
+	}
+
+	offset += 4 * (o[0] - 1 + o[1] - 1 + o[2] - 1);
+	if nbdy == 1
+	{
+		// Face
+		if ibdy
+		{
+			return offset + (j - 1) + (o[1] - 1) * (k - 1)
+				+ if i != 0 { (o[1] - 1) * (o[2] - 1) } else { 0 };
+		}
+		offset += 2 * (o[1] - 1) * (o[2] - 1);
+		if jbdy
+		{
+			return offset + (i - 1) + (o[0] - 1) * (k - 1)
+				+ if j != 0 { (o[2] - 1) * (o[0] - 1) } else { 0 };
+		}
+		offset += 2 * (o[2] - 1) * (o[0] - 1);
+		return offset + (i - 1) + (o[0] - 1) * (j - 1)
+			+ if k != 0 { (o[0] - 1) * (o[1] - 1) } else { 0 };
+	}
+
+	// Interior
+	offset += 2 * ((o[1] - 1) * (o[2] - 1) + (o[2] - 1) * (o[0] - 1)
+		+ (o[0] - 1) * (o[1] - 1));
+	return offset + (i - 1) + (o[0] - 1) * ((j - 1) + (o[1] - 1) * (k - 1));
+}
+
+//********
+
+fn tri_index(i: usize, j: usize, p: usize) -> usize
+{
+	// Triangles are numbered recursively:  corners, edges, then the interior
+	// as a triangle of degree p - 3
+
+	if p == 0
+	{
+		return 0;
+	}
+
+	let k = p - i - j;
+	if i == 0 && j == 0 { return 0; }
+	if i == p           { return 1; }
+	if j == p           { return 2; }
+
+	if j == 0 { return 3 + (i - 1); }
+	if k == 0 { return 3 + (p - 1) + (j - 1); }
+	if i == 0 { return 3 + 2 * (p - 1) + (p - j - 1); }
+
+	return 3 * p + tri_index(i - 1, j - 1, p - 3);
+}
+
+//********
+
+// Barycentric tetra numbering of VTK's higher-order tetra, with b = [i, j, k,
+// l] for the node at (i, j, k) / p and l = p - i - j - k.  Corner v is where
+// b[TET_CORNERS[v]] == p.  Edges hold 2 coordinates at 0 and count along a
+// third, and faces hold 1 coordinate at 0 and map 2 others onto the (i, j) of
+// a triangle
+const TET_CORNERS: [usize; 4] = [3, 0, 1, 2];
+const TET_EDGES: [([usize; 2], usize); 6] = [([1, 2], 0), ([2, 3], 1), ([0, 2], 3),
+	([0, 1], 2), ([1, 3], 2), ([0, 3], 2)];
+const TET_FACES: [(usize, [usize; 2]); 4] = [(1, [0, 2]), (3, [2, 0]),
+	(0, [2, 1]), (2, [1, 0])];
+
+fn tet_index(b: [usize; 4], p: usize) -> usize
+{
+	// Tetras are numbered recursively like triangles:  corners, edges, faces,
+	// then the interior as a tetra of degree p - 4
+
+	if let Some(v) = (0 .. 4).find(|&v| b[TET_CORNERS[v]] == p)
+	{
+		return v;
+	}
+
+	if b.iter().all(|&x| x > 0)
+	{
+		return 2 * (p * p + 1) + tet_index([b[0] - 1, b[1] - 1, b[2] - 1, b[3] - 1],
+			p - 4);
+	}
+
+	let mut offset = 4;
+	for &([c0, c1], count) in &TET_EDGES
+	{
+		if b[c0] == 0 && b[c1] == 0
+		{
+			return offset + b[count] - 1;
+		}
+		offset += p - 1;
+	}
+
+	for &(zero, [c0, c1]) in &TET_FACES
+	{
+		if b[zero] == 0
+		{
+			// Skip the corners and edges of the face triangle
+			return offset + tri_index(b[c0], b[c1], p) - 3 * p;
+		}
+		offset += (p - 1) * (p - 2) / 2;
+	}
+
+	panic!("Barycentric index {:?} is not in a tetra of degree {}", b, p);
+}
+
+//********
+
+fn wedge_index(i: usize, j: usize, k: usize, o: [usize; 3]) -> usize
+{
+	// Wedges are triangles of degree o[0] in (i, j) extruded over o[2] layers
+	// in k.  Triangular face and interior nodes are numbered row by row,
+	// unlike the recursive numbering of triangles
+
+	let (p, r) = (o[0], o[2]);
+	let ibdy  = i == 0;
+	let jbdy  = j == 0;
+	let ijbdy = i + j == p;
+	let kbdy  = k == 0 || k == r;
+	let nbdy = ibdy as usize + jbdy as usize + ijbdy as usize + kbdy as usize;
+
+	// Corner of the bottom triangle, or side of it, in the order 0-1, 1-2, 2-0
+	let side = if jbdy && !ibdy { 0 } else if ijbdy && !jbdy { 1 } else { 2 };
+	let corner = if ibdy && jbdy { 0 } else if jbdy { 1 } else { 2 };
+
+	if nbdy == 3
+	{
+		return corner + if k != 0 { 3 } else { 0 };
+	}
+
+	let mut offset = 6;
+	if nbdy == 2
+	{
+		if !kbdy
+		{
+			// Vertical edge
+			return offset + 6 * (p - 1) + corner * (r - 1) + (k - 1);
+		}
+
+		// Horizontal edge of the bottom or top triangle
+		offset += if k != 0 { 3 * (p - 1) } else { 0 };
+		return offset + side * (p - 1) + match side
+		{
+			0 => i - 1,
+			1 => j - 1,
+			_ => p - j - 1,
+		};
+	}
+
+	offset += 6 * (p - 1) + 3 * (r - 1);
+	let ntri  = (p - 1) * p.saturating_sub(2) / 2;
+	let nquad = (p - 1) * (r - 1);
+	let row = |i: usize, j: usize| i + (j - 1) * p - j * (j + 1) / 2;
+
+	if nbdy == 1
+	{
+		if kbdy
+		{
+			return offset + if k != 0 { ntri } else { 0 } + row(i, j);
+		}
+		offset += 2 * ntri;
+		let along = match side
+		{
+			0 => i - 1,
+			1 => j - 1,
+			_ => p - j - 1,
+		};
+		return offset + side * nquad + along + (p - 1) * (k - 1);
+	}
+
+	offset += 2 * ntri + 3 * nquad;
+	return offset + row(i, j) + ntri * (k - 1);
+}
+
+//********
+
+fn pyramid_index(i: usize, j: usize, k: usize, p: usize) -> usize
+{
+	// Pyramids are layers of (p - k + 1)^2 nodes from the base (k = 0) to the
+	// apex (k = p).  Corners come first, then the base edges like a quad, the
+	// lateral edges from the base to the apex, the base face like a quad, the
+	// triangular faces over each base edge row by row, and the interior layer
+	// by layer
+
+	let n = p - k;
+	let o = [p, p, 1];
+	if k == p
+	{
+		return 4;
+	}
+	if k == 0
+	{
+		let q = quad_index(i, j, o);
+		return if q < 4
+		{
+			q
+		}
+		else if q < 4 + 4 * (p - 1)
+		{
+			// Base edge
+			q + 1
+		}
+		else
+		{
+			// Base face
+			q + 1 + 4 * (p - 1)
+		};
+	}
+
+	let ibdy = i == 0 || i == n;
+	let jbdy = j == 0 || j == n;
+	let mut offset = 5 + 8 * (p - 1);
+	if ibdy && jbdy
+	{
+		// Lateral edge, from corners 0, 1, 2, and 3
+		let corner = if i != 0 { if j != 0 { 2 } else { 1 } }
+		                  else { if j != 0 { 3 } else { 0 } };
+		return offset - 4 * (p - 1) + corner * (p - 1) + (k - 1);
+	}
+
+	offset += (p - 1) * (p - 1);
+	if ibdy || jbdy
+	{
+		// Triangular face over base edge 0-1, 1-2, 3-2, or 0-3
+		let (face, along) = if j == 0 { (0, i) } else if i == n { (1, j) }
+		                    else if j == n { (2, i) } else { (3, j) };
+		let layer: usize = (1 .. k).map(|l| p - l - 1).sum();
+		return offset + face * (p - 1) * (p - 2) / 2 + layer + along - 1;
+	}
+
+	offset += 2 * (p - 1) * (p - 2);
+	let layer: usize = (1 .. k).map(|l| (p - l - 1) * (p - l - 1)).sum();
+	return offset + layer + (i - 1) + (n - 1) * (j - 1);
+}
+
+//********
+
+fn sub_cells(ctype: u8, o: [usize; 3]) -> Vec<Vec<usize>>
+{
+	// Local node indices of the linear sub-cells of one higher-order cell
+
+	let mut sub = Vec::new();
+	match linear_type(ctype)
+	{
+		VTK_LINE =>
+		{
+			for i in 0 .. o[0]
+			{
+				sub.push(vec![curve_index(i, o[0]), curve_index(i+1, o[0])]);
+			}
+		},
+
+		VTK_TRIANGLE =>
+		{
+			let p = o[0];
+			for j in 0 .. p
+			{
+				for i in 0 .. p - j
+				{
+					sub.push(vec![tri_index(i, j, p), tri_index(i+1, j, p),
+						tri_index(i, j+1, p)]);
+
+					if i + j + 1 < p
+					{
+						sub.push(vec![tri_index(i+1, j, p),
+							tri_index(i+1, j+1, p), tri_index(i, j+1, p)]);
+					}
+				}
+			}
+		},
+
+		VTK_QUAD =>
+		{
+			for j in 0 .. o[1]
+			{
+				for i in 0 .. o[0]
+				{
+					sub.push(vec![quad_index(i, j, o), quad_index(i+1, j, o),
+						quad_index(i+1, j+1, o), quad_index(i, j+1, o)]);
+				}
+			}
+		},
+
+		VTK_HEXAHEDRON =>
+		{
+			for k in 0 .. o[2]
+			{
+				for j in 0 .. o[1]
+				{
+					for i in 0 .. o[0]
+					{
+						sub.push(vec![
+							hex_index(i  , j  , k  , o),
+							hex_index(i+1, j  , k  , o),
+							hex_index(i+1, j+1, k  , o),
+							hex_index(i  , j+1, k  , o),
+							hex_index(i  , j  , k+1, o),
+							hex_index(i+1, j  , k+1, o),
+							hex_index(i+1, j+1, k+1, o),
+							hex_index(i  , j+1, k+1, o)]);
+					}
+				}
+			}
+		},
+
+		VTK_TETRA =>
+		{
+			// Freudenthal subdivision into p^3 tetras.  In u = (i, i+j, i+j+k)
+			// the tetra is 0 <= u0 <= u1 <= u2 <= p, which is split along with
+			// each unit cube, from a corner to the opposite one
+			let p = o[0];
+			let node = |u: [usize; 3]| tet_index([u[0], u[1] - u[0], u[2] - u[1],
+				p - u[2]], p);
+			let perms = [([0, 1, 2], true), ([1, 2, 0], true), ([2, 0, 1], true),
+				([0, 2, 1], false), ([2, 1, 0], false), ([1, 0, 2], false)];
+
+			for a in 0 .. p * p * p
+			{
+				let origin = [a % p, a / p % p, a / (p * p)];
+				for &(perm, even) in &perms
+				{
+					let mut u = origin;
+					let mut nodes = vec![u];
+					for &d in &perm
+					{
+						u[d] += 1;
+						nodes.push(u);
+					}
+					if nodes.iter().all(|u| u[0] <= u[1] && u[1] <= u[2] && u[2] <= p)
+					{
+						// Odd permutations are inside out
+						if !even
+						{
+							nodes.swap(2, 3);
+						}
+						sub.push(nodes.into_iter().map(node).collect());
+					}
+				}
+			}
+		},
+
+		VTK_WEDGE =>
+		{
+			let p = o[0];
+			for k in 0 .. o[2]
+			{
+				let prism = |a: [usize; 2], b: [usize; 2], c: [usize; 2]|
+				{
+					vec![
+						wedge_index(a[0], a[1], k  , o),
+						wedge_index(b[0], b[1], k  , o),
+						wedge_index(c[0], c[1], k  , o),
+						wedge_index(a[0], a[1], k+1, o),
+						wedge_index(b[0], b[1], k+1, o),
+						wedge_index(c[0], c[1], k+1, o)]
+				};
+				for j in 0 .. p
+				{
+					for i in 0 .. p - j
+					{
+						sub.push(prism([i, j], [i+1, j], [i, j+1]));
+						if i + j + 1 < p
+						{
+							sub.push(prism([i+1, j], [i+1, j+1], [i, j+1]));
+						}
+					}
+				}
+			}
+		},
+
+		VTK_PYRAMID =>
+		{
+			// Between each pair of layers:  upright pyramids on the lower
+			// layer, upside-down ones on the upper layer, and tetras in the
+			// gaps between them
+			let p = o[0];
+			for k in 0 .. p
+			{
+				let n = p - k;
+				let lo = |i: usize, j: usize| pyramid_index(i, j, k  , p);
+				let hi = |i: usize, j: usize| pyramid_index(i, j, k+1, p);
+				for j in 0 .. n
+				{
+					for i in 0 .. n
+					{
+						sub.push(vec![lo(i, j), lo(i+1, j), lo(i+1, j+1), lo(i, j+1),
+							hi(i, j)]);
+						if i + 1 < n && j + 1 < n
+						{
+							sub.push(vec![hi(i, j), hi(i, j+1), hi(i+1, j+1),
+								hi(i+1, j), lo(i+1, j+1)]);
+						}
+						if i + 1 < n
+						{
+							sub.push(vec![lo(i+1, j), lo(i+1, j+1), hi(i, j),
+								hi(i+1, j)]);
+						}
+						if j + 1 < n
+						{
+							sub.push(vec![lo(i, j+1), hi(i, j), lo(i+1, j+1),
+								hi(i, j+1)]);
+						}
+					}
+				}
+			}
+		},
+
+		_ => panic!("Cell type {} is not higher order", ctype),
+	}
+	return sub;
+}
+
+//==============================================================================
+
+impl VtkFile
+{
+pub fn linearize(&self) -> VtkFile
+{
+	// Split each higher-order cell into linear sub-cells of its linear
+	// counterpart, plus tetras between the sub-pyramids of pyramids.  Linear
+	// cells and points are copied as-is, and cell data is replicated onto the
+	// sub-cells.  Bézier cells use the same sub-division as Lagrange cells,
+	// which is exact only at the corners
+
+	let degrees = ho_degrees(self);
+
+	let mut v = self.clone();
+	v.connectivity.clear();
+	v.offsets.clear();
+	v.types.clear();
+
+	// Parent cell of each new cell, for replicating cell data
+	let mut parents = Vec::new();
+
+	for i in 0 .. self.ncells as usize
+	{
+		let (begin, end) = cell_range(self, i);
+		let conn = &self.connectivity[begin .. end];
+		let ctype = self.types[i];
+
+		let sub = if is_higher_order(ctype)
+		{
+			let o = cell_degrees(self, &degrees, i, end - begin)
+				.unwrap_or_else(|e| panic!("{}", e));
+			sub_cells(ctype, o)
+		}
+		else
+		{
+			vec![(0 .. conn.len()).collect()]
+		};
+
+		for s in sub
+		{
+			// Pyramids also split into tetras
+			let t = linear_type(ctype);
+			v.connectivity.extend(s.iter().map(|&j| conn[j]));
+			v.offsets.push(v.connectivity.len() as i64);
+			v.types.push(if t == VTK_PYRAMID && s.len() == 4 { VTK_TETRA } else { t });
+			parents.push(i);
+		}
+	}
+
+	v.ncells = v.types.len() as u64;
+	v.cdata = self.cdata.iter()
+		.filter(|a| a.name != HO_DEGREES)
+		.map(|a| DataArray
+		{
+			data: a.data.select(a.ncomponents as usize, &parents),
+			..a.clone()
+		})
+		.collect();
+
+	return v;
+}}
+
+//==============================================================================
+
+impl VtkFile
+{
+pub fn cell_points(&self, i: usize) -> &[i64]
+{
+	// Point IDs of cell i
+	let (begin, end) = cell_range(self, i);
+	return &self.connectivity[begin .. end];
+}
+
+//********
+
+pub fn extract_cells(&self, cells: &[usize]) -> (VtkFile, Vec<usize>)
+{
+	// Subset with only the given cells, in the given order, and the points
+	// that they use.  Point and cell data are carried along.  Also returns the
+	// original ID of each new point
+
+	let mut v = self.clone();
+	v.connectivity.clear();
+	v.offsets.clear();
+	v.types.clear();
+
+	// Old to new point IDs
+	let mut map: Vec<Option<usize>> = vec![None; self.npoints as usize];
+	let mut points = Vec::new();
+
+	for &i in cells
+	{
+		for &p in self.cell_points(i)
+		{
+			let p = p as usize;
+			let q = match map[p]
+			{
+				Some(q) => q,
+				None =>
+				{
+					map[p] = Some(points.len());
+					points.push(p);
+					points.len() - 1
+				},
+			};
+			v.connectivity.push(q as i64);
+		}
+		v.offsets.push(v.connectivity.len() as i64);
+		v.types.push(self.types[i]);
+	}
+
+	let nc = self.ncomponents as usize;
+	v.points = points.iter()
+		.flat_map(|&p| self.points[nc*p .. nc*(p+1)].iter().copied())
+		.collect();
+
+	v.npoints = points.len() as u64;
+	v.ncells  = cells.len() as u64;
+
+	for a in v.pdata.iter_mut()
+	{
+		a.data = a.data.select(a.ncomponents as usize, &points);
+	}
+	for a in v.cdata.iter_mut()
+	{
+		a.data = a.data.select(a.ncomponents as usize, cells);
+	}
+
+	return (v, points);
+}}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::vtk::Data;
+
+	// One cell of the given type and degrees with nodes numbered 0 .. n
+	fn single_cell(ctype: u8, degrees: [usize; 3]) -> VtkFile
+	{
+		let n = npoints_higher_order(ctype, degrees);
+
+		let mut v = VtkFile::new();
+		v.vtype = "UnstructuredGrid".to_string();
+		v.npoints = n as u64;
+		v.ncells = 1;
+		v.points = vec![0.0; 3 * n];
+		v.connectivity = (0 .. n as i64).collect();
+		v.offsets = vec![n as i64];
+		v.types = vec![ctype];
+		v.cdata.push(DataArray::new(HO_DEGREES, 3,
+			Data::F64(degrees.iter().map(|&d| d as f64).collect())));
+		return v;
+	}
+
+	#[test]
+	fn test_npoints()
+	{
+		assert_eq!(npoints_higher_order(VTK_LAGRANGE_HEXAHEDRON, [2, 2, 2]), 27);
+		assert_eq!(npoints_higher_order(VTK_LAGRANGE_TETRAHEDRON, [2, 2, 2]), 10);
+		assert_eq!(npoints_higher_order(VTK_BEZIER_WEDGE, [2, 2, 1]), 12);
+		assert_eq!(degree_from_npoints(VTK_LAGRANGE_TRIANGLE, 10), Some(3));
+		assert_eq!(degree_from_npoints(VTK_LAGRANGE_QUADRILATERAL, 8), None);
+	}
+
+	#[test]
+	fn test_linearize_quad()
+	{
+		let v = single_cell(VTK_LAGRANGE_QUADRILATERAL, [2, 1, 1]).linearize();
+
+		// Corners 0-3, bottom edge 4, top edge 5
+		assert_eq!(v.types, [VTK_QUAD, VTK_QUAD]);
+		assert_eq!(v.connectivity, [0, 4, 5, 3, 4, 1, 2, 5]);
+		assert_eq!(v.offsets, [4, 8]);
+		assert!(v.cdata.is_empty());
+	}
+
+	#[test]
+	fn test_linearize_hex()
+	{
+		let v = single_cell(VTK_LAGRANGE_HEXAHEDRON, [2, 2, 2]).linearize();
+		assert_eq!(v.ncells, 8);
+		assert_eq!(v.types, [VTK_HEXAHEDRON; 8]);
+
+		// Every node is used, the center (26) by every sub-cell
+		let mut used = v.connectivity.clone();
+		used.sort();
+		used.dedup();
+		assert_eq!(used, (0 .. 27).collect::<Vec<i64>>());
+		assert_eq!(v.connectivity.iter().filter(|&&i| i == 26).count(), 8);
+	}
+
+	#[test]
+	fn test_linearize_triangle()
+	{
+		let v = single_cell(VTK_LAGRANGE_TRIANGLE, [3, 3, 3]).linearize();
+		assert_eq!(v.ncells, 9);
+		assert_eq!(&v.connectivity[0 .. 3], [0, 3, 8]);
+	}
+
+	// Signed volume of cell i by the divergence theorem over its faces, which
+	// are counter-clockwise seen from outside
+	fn volume(v: &VtkFile, i: usize) -> f64
+	{
+		let c = v.cell_points(i);
+		let x = |a: usize| -> [f64; 3]
+		{
+			let p = c[a] as usize;
+			[v.points[3*p] as f64, v.points[3*p+1] as f64, v.points[3*p+2] as f64]
+		};
+		let mut vol = 0.0;
+		for f in faces(v.types[i])
+		{
+			for t in 1 .. f.len() - 1
+			{
+				let (a, b, c) = (x(f[0]), x(f[t]), x(f[t+1]));
+				vol += a[0] * (b[1]*c[2] - b[2]*c[1]) - a[1] * (b[0]*c[2] - b[2]*c[0])
+					+ a[2] * (b[0]*c[1] - b[1]*c[0]);
+			}
+		}
+		return vol / 6.0;
+	}
+
+	// Check that the sub-cells of a cell with nodes at the given positions
+	// use every node, are right side out, and fill the cell
+	fn check_sub_cells(mut v: VtkFile, positions: Vec<(usize, [f64; 3])>, vol: f64)
+		-> VtkFile
+	{
+		let n = v.npoints as usize;
+		let mut ids: Vec<usize> = positions.iter().map(|x| x.0).collect();
+		ids.sort();
+		assert_eq!(ids, (0 .. n).collect::<Vec<usize>>());
+		for (id, x) in positions
+		{
+			for d in 0 .. 3
+			{
+				v.points[3*id + d] = x[d] as f32;
+			}
+		}
+
+		let w = v.linearize();
+		let vols: Vec<f64> = (0 .. w.ncells as usize).map(|i| volume(&w, i)).collect();
+		assert!(vols.iter().all(|&x| x > 0.0), "{:?}", vols);
+		assert!((vols.iter().sum::<f64>() - vol).abs() < 1e-5);
+
+		let mut used = w.connectivity.clone();
+		used.sort();
+		used.dedup();
+		assert_eq!(used.len(), n);
+		return w;
+	}
+
+	#[test]
+	fn test_linearize_tetra()
+	{
+		for p in 1 ..= 5
+		{
+			let mut x = Vec::new();
+			for k in 0 ..= p
+			{
+				for j in 0 ..= p - k
+				{
+					for i in 0 ..= p - j - k
+					{
+						let id = tet_index([i, j, k, p - i - j - k], p);
+						let d = p as f64;
+						x.push((id, [i as f64 / d, j as f64 / d, k as f64 / d]));
+					}
+				}
+			}
+			let v = single_cell(VTK_LAGRANGE_TETRAHEDRON, [p, p, p]);
+			let w = check_sub_cells(v, x, 1.0 / 6.0);
+			assert_eq!(w.ncells as usize, p * p * p);
+			assert_eq!(w.types[0], VTK_TETRA);
+		}
+
+		// Quadratic nodes match VTK_QUADRATIC_TETRA, e.g. the edge 0-3 node
+		assert_eq!(tet_index([0, 0, 1, 1], 2), 7);
+		assert_eq!(tet_index([1, 1, 0, 0], 2), 5);
+
+		// One node per face at degree 3, then one interior node at degree 4
+		assert_eq!(tet_index([1, 0, 1, 1], 3), 16);
+		assert_eq!(tet_index([1, 1, 1, 1], 4), 34);
+	}
+
+	#[test]
+	fn test_linearize_wedge()
+	{
+		for &(p, r) in &[(1, 1), (2, 1), (2, 2), (3, 2), (4, 3)]
+		{
+			// Corner 1 on y and corner 2 on x, like a linear VTK wedge
+			let mut x = Vec::new();
+			for k in 0 ..= r
+			{
+				for j in 0 ..= p
+				{
+					for i in 0 ..= p - j
+					{
+						let id = wedge_index(i, j, k, [p, p, r]);
+						x.push((id, [j as f64 / p as f64, i as f64 / p as f64,
+							k as f64 / r as f64]));
+					}
+				}
+			}
+			let v = single_cell(VTK_LAGRANGE_WEDGE, [p, p, r]);
+			let w = check_sub_cells(v, x, 0.5);
+			assert_eq!(w.ncells as usize, p * p * r);
+			assert_eq!(w.types[0], VTK_WEDGE);
+		}
+
+		// Quadratic nodes match VTK_QUADRATIC_WEDGE, e.g. the top edge 5-3
+		// and the vertical edge 2-5
+		assert_eq!(wedge_index(0, 1, 2, [2, 2, 2]), 11);
+		assert_eq!(wedge_index(0, 2, 1, [2, 2, 2]), 14);
+	}
+
+	#[test]
+	fn test_linearize_pyramid()
+	{
+		for p in 1 ..= 4
+		{
+			let mut x = Vec::new();
+			for k in 0 ..= p
+			{
+				for j in 0 ..= p - k
+				{
+					for i in 0 ..= p - k
+					{
+						let d = p as f64;
+						let h = k as f64 / 2.0;
+						x.push((pyramid_index(i, j, k, p), [(i as f64 + h) / d,
+							(j as f64 + h) / d, k as f64 / d]));
+					}
+				}
+			}
+			let v = single_cell(VTK_LAGRANGE_PYRAMID, [p, p, p]);
+			let w = check_sub_cells(v, x, 1.0 / 3.0);
+			assert!(w.types.contains(&VTK_PYRAMID));
+			assert_eq!(w.types.contains(&VTK_TETRA), p > 1);
+		}
+	}
+
+	#[test]
+	#[should_panic]
+	fn test_bad_degrees()
+	{
+		let mut v = single_cell(VTK_LAGRANGE_HEXAHEDRON, [2, 2, 2]);
+		v.cdata[0].data = Data::F64(vec![2.0, 2.0, 3.0]);
+		check_cells(&v);
+	}
+
+	#[test]
+	fn test_ho_roundtrip()
+	{
+		let v = single_cell(VTK_LAGRANGE_HEXAHEDRON, [2, 2, 1]);
+		let temp = "./scratch/tmp.Hj3LqsZ8cP.vtu";
+		v.export(temp);
+
+		let w = crate::vtk::load(temp);
+		assert_eq!(w.cdata, v.cdata);
+		assert_eq!(w.linearize().ncells, 4);
+	}
+}
+
+//==============================================================================
+
@@ -0,0 +1,480 @@
+
+//==============================================================================
+
+// Comparison of two VTK files with tolerances, e.g. a new result against a
+// golden one.  Values are compared as f64, so the encodings and data types of
+// the files may differ
+
+// Standard
+use std::fmt::Write;
+
+//********
+
+// Third party
+use ansi_term::Colour;
+use serde_json::{json, Value};
+
+//********
+
+// This lib
+use crate::vtk::{Data, DataArray, VtkFile};
+
+//********
+
+// Max offending values kept for each array
+const MAX_WORST: usize = 5;
+
+//==============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance
+{
+	pub abs: f64,
+	pub rel: f64,
+}
+
+impl Tolerance
+{
+	pub fn new(abs: f64, rel: f64) -> Tolerance
+	{
+		Tolerance {abs, rel}
+	}
+
+	pub fn exact() -> Tolerance
+	{
+		Tolerance::new(0.0, 0.0)
+	}
+
+	// Values pass if either tolerance is met.  NaNs only match NaNs
+	pub fn passes(&self, a: f64, b: f64) -> bool
+	{
+		if a.is_nan() || b.is_nan()
+		{
+			return a.is_nan() && b.is_nan();
+		}
+		let d = (a - b).abs();
+		return d <= self.abs || d <= self.rel * a.abs().max(b.abs());
+	}
+}
+
+//********
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tolerances
+{
+	// For any array without its own tolerance
+	pub default: Tolerance,
+
+	// For the point coordinates
+	pub points: Tolerance,
+
+	// Per-array tolerances by name, for point or cell data
+	pub arrays: Vec<(String, Tolerance)>,
+}
+
+impl Tolerances
+{
+	pub fn new(default: Tolerance) -> Tolerances
+	{
+		Tolerances
+		{
+			default,
+			points: default,
+			arrays: Vec::new(),
+		}
+	}
+
+	pub fn get(&self, name: &str) -> Tolerance
+	{
+		self.arrays.iter().find(|(n, _)| n == name)
+			.map(|&(_, t)| t).unwrap_or(self.default)
+	}
+}
+
+//********
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Offender
+{
+	pub tuple    : usize,
+	pub component: usize,
+	pub a        : f64,
+	pub b        : f64,
+}
+
+//********
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayDiff
+{
+	// "Points", "PointData", or "CellData"
+	pub section: &'static str,
+	pub name   : String,
+
+	pub tolerance: Tolerance,
+
+	// Over all values, including those within tolerance
+	pub max_abs: f64,
+	pub max_rel: f64,
+
+	// Number of values out of tolerance
+	pub nfailed: usize,
+
+	// Largest differences out of tolerance, worst first
+	pub worst: Vec<Offender>,
+
+	// Structural mismatch, e.g. a missing array or different components
+	pub mismatch: Option<String>,
+}
+
+impl ArrayDiff
+{
+	fn new(section: &'static str, name: &str, tolerance: Tolerance) -> ArrayDiff
+	{
+		ArrayDiff
+		{
+			section,
+			name: name.to_string(),
+			tolerance,
+			max_abs: 0.0,
+			max_rel: 0.0,
+			nfailed: 0,
+			worst: Vec::new(),
+			mismatch: None,
+		}
+	}
+
+	pub fn is_equal(&self) -> bool
+	{
+		self.nfailed == 0 && self.mismatch.is_none()
+	}
+}
+
+//********
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison
+{
+	// Differences in counts, connectivity, offsets, or cell types
+	pub topology: Vec<String>,
+
+	// Points first, then point data and cell data
+	pub arrays: Vec<ArrayDiff>,
+}
+
+//==============================================================================
+
+fn compare_values(d: &mut ArrayDiff, a: &[f64], b: &[f64], ncomponents: usize)
+{
+	let nc = std::cmp::max(ncomponents, 1);
+	let mut offenders = Vec::new();
+
+	for i in 0 .. a.len()
+	{
+		let diff = (a[i] - b[i]).abs();
+		if diff.is_finite()
+		{
+			d.max_abs = d.max_abs.max(diff);
+			let scale = a[i].abs().max(b[i].abs());
+			if scale > 0.0
+			{
+				d.max_rel = d.max_rel.max(diff / scale);
+			}
+		}
+
+		if !d.tolerance.passes(a[i], b[i])
+		{
+			d.nfailed += 1;
+			offenders.push((if diff.is_nan() { f64::INFINITY } else { diff }, i));
+		}
+	}
+
+	offenders.sort_by(|x, y| y.0.partial_cmp(&x.0).unwrap());
+	d.worst = offenders.iter().take(MAX_WORST).map(|&(_, i)| Offender
+	{
+		tuple    : i / nc,
+		component: i % nc,
+		a        : a[i],
+		b        : b[i],
+	}).collect();
+}
+
+//********
+
+fn compare_arrays(diffs: &mut Vec<ArrayDiff>, section: &'static str,
+	a: &[DataArray], b: &[DataArray], same_size: bool, tol: &Tolerances)
+{
+	// Arrays are matched by name, in the order of the first file
+
+	for x in a
+	{
+		let mut d = ArrayDiff::new(section, &x.name, tol.get(&x.name));
+		match b.iter().find(|y| y.name == x.name)
+		{
+			None => d.mismatch = Some("missing from the second file".to_string()),
+			Some(y) if y.ncomponents != x.ncomponents =>
+			{
+				d.mismatch = Some(format!("{} components vs {}", x.ncomponents,
+					y.ncomponents));
+			},
+			Some(y) if !same_size || x.data.len() != y.data.len() =>
+			{
+				d.mismatch = Some(format!("{} values vs {}", x.data.len(),
+					y.data.len()));
+			},
+			Some(y) =>
+			{
+				compare_values(&mut d, &x.data.to_f64(), &y.data.to_f64(),
+					x.ncomponents as usize);
+			},
+		}
+		diffs.push(d);
+	}
+
+	for y in b
+	{
+		if !a.iter().any(|x| x.name == y.name)
+		{
+			let mut d = ArrayDiff::new(section, &y.name, tol.get(&y.name));
+			d.mismatch = Some("missing from the first file".to_string());
+			diffs.push(d);
+		}
+	}
+}
+
+//********
+
+fn first_difference<T: PartialEq>(a: &[T], b: &[T]) -> Option<usize>
+{
+	if a.len() != b.len()
+	{
+		return Some(std::cmp::min(a.len(), b.len()));
+	}
+	return a.iter().zip(b).position(|(x, y)| x != y);
+}
+
+//==============================================================================
+
+impl VtkFile
+{
+pub fn compare(&self, other: &VtkFile, tol: &Tolerances) -> Comparison
+{
+	// Topology must be identical.  Coordinates and arrays are compared within
+	// tolerances
+
+	let mut topology = Vec::new();
+	if self.npoints != other.npoints
+	{
+		topology.push(format!("{} points vs {}", self.npoints, other.npoints));
+	}
+	if self.ncells != other.ncells
+	{
+		topology.push(format!("{} cells vs {}", self.ncells, other.ncells));
+	}
+	if self.ncomponents != other.ncomponents
+	{
+		topology.push(format!("{} point components vs {}", self.ncomponents,
+			other.ncomponents));
+	}
+
+	let lists: [(&str, Option<usize>); 3] = [
+		("connectivity", first_difference(&self.connectivity, &other.connectivity)),
+		("offsets"     , first_difference(&self.offsets     , &other.offsets     )),
+		("types"       , first_difference(&self.types       , &other.types       )),
+	];
+	for (name, i) in &lists
+	{
+		if let Some(i) = i
+		{
+			topology.push(format!("{} differ at index {}", name, i));
+		}
+	}
+
+	let same_points = self.npoints == other.npoints;
+	let same_cells  = self.ncells  == other.ncells;
+
+	let mut arrays = Vec::new();
+
+	let points = [
+		DataArray::new("Points", self.ncomponents , Data::F32(self .points.clone())),
+		DataArray::new("Points", other.ncomponents, Data::F32(other.points.clone())),
+	];
+	let mut t = tol.clone();
+	t.arrays.retain(|(n, _)| n != "Points");
+	t.default = tol.points;
+	compare_arrays(&mut arrays, "Points", &points[0 .. 1], &points[1 ..],
+		same_points, &t);
+
+	compare_arrays(&mut arrays, "PointData", &self.pdata, &other.pdata,
+		same_points, tol);
+	compare_arrays(&mut arrays, "CellData" , &self.cdata, &other.cdata,
+		same_cells , tol);
+
+	return Comparison {topology, arrays};
+}}
+
+//==============================================================================
+
+impl Comparison
+{
+	pub fn is_equal(&self) -> bool
+	{
+		self.topology.is_empty() && self.arrays.iter().all(|d| d.is_equal())
+	}
+
+	//****************
+
+	pub fn to_text(&self, a: &str, b: &str) -> String
+	{
+		let mut s = String::new();
+		writeln!(s, "Comparing \"{}\" to \"{}\"\n", a, b).unwrap();
+
+		let pass = Colour::Green.bold().paint("same");
+		let fail = Colour::Red.bold().paint("different");
+
+		if self.topology.is_empty()
+		{
+			writeln!(s, "{}  topology", pass).unwrap();
+		}
+		for t in &self.topology
+		{
+			writeln!(s, "{}  topology:  {}", fail, t).unwrap();
+		}
+
+		for d in &self.arrays
+		{
+			let label = if d.section == d.name { d.name.clone() }
+				else { format!("{} {}", d.section, d.name) };
+			if let Some(m) = &d.mismatch
+			{
+				writeln!(s, "{}  {}:  {}", fail, label, m).unwrap();
+				continue;
+			}
+
+			writeln!(s, "{}  {}:  max abs diff {:e}, max rel diff {:e}, {} out of \
+				tolerance (abs {:e}, rel {:e})",
+				if d.is_equal() { &pass } else { &fail }, label, d.max_abs,
+				d.max_rel, d.nfailed, d.tolerance.abs, d.tolerance.rel).unwrap();
+
+			for o in &d.worst
+			{
+				writeln!(s, "      tuple {} component {}:  {} vs {}", o.tuple,
+					o.component, o.a, o.b).unwrap();
+			}
+		}
+
+		let ndiff = self.arrays.iter().filter(|d| !d.is_equal()).count();
+		writeln!(s, "\n{} topology differences, {} arrays differ",
+			self.topology.len(), ndiff).unwrap();
+
+		return s;
+	}
+
+	//****************
+
+	pub fn to_json(&self, a: &str, b: &str) -> String
+	{
+		let arrays: Vec<Value> = self.arrays.iter().map(|d|
+		{
+			let worst: Vec<Value> = d.worst.iter().map(|o| json!(
+			{
+				"tuple": o.tuple,
+				"component": o.component,
+				"a": o.a,
+				"b": o.b,
+			})).collect();
+			json!(
+			{
+				"section": d.section,
+				"name": d.name,
+				"equal": d.is_equal(),
+				"max_abs": d.max_abs,
+				"max_rel": d.max_rel,
+				"failed": d.nfailed,
+				"mismatch": d.mismatch,
+				"worst": worst,
+			})
+		}).collect();
+
+		let j = json!(
+		{
+			"a": a,
+			"b": b,
+			"equal": self.is_equal(),
+			"topology": self.topology,
+			"arrays": arrays,
+		});
+		return serde_json::to_string_pretty(&j).unwrap() + "\n";
+	}
+}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::vtk;
+
+	#[test]
+	fn test_diff_encodings()
+	{
+		// The same grid in different encodings
+		let a = vtk::load("./data/icosahedron-ascii.vtu");
+		let z = vtk::load("./data/icosahedron-zlib.vtu");
+		let b = vtk::load("./data/icosahedron-binary.vtu");
+
+		let exact = Tolerances::new(Tolerance::exact());
+		assert!(a.compare(&z, &exact).is_equal());
+		assert!(z.compare(&b, &exact).is_equal());
+
+		// Points have their own tolerance
+		let mut c = a.clone();
+		c.points[4] += 1e-6;
+		assert!(!a.compare(&c, &exact).is_equal());
+
+		let mut tol = exact.clone();
+		tol.points = Tolerance::new(0.0, 1e-5);
+		let d = a.compare(&c, &tol);
+		assert!(d.is_equal());
+		assert!(d.arrays[0].max_abs > 0.0);
+	}
+
+	#[test]
+	fn test_diff_arrays()
+	{
+		let a = vtk::load("./data/icosahedron-binary-pdata.vtu");
+		let mut b = a.clone();
+		if let Data::F64(v) = &mut b.pdata[0].data
+		{
+			v[3] += 0.5;
+			v[7] += 0.1;
+		}
+
+		let mut tol = Tolerances::new(Tolerance::exact());
+		let c = a.compare(&b, &tol);
+		assert!(!c.is_equal());
+
+		let d = &c.arrays[1];
+		assert_eq!(d.name, "Resultz");
+		assert_eq!(d.nfailed, 2);
+		assert_eq!(d.worst[0].tuple, 3);
+		assert_eq!(d.worst[1].tuple, 7);
+
+		let json: Value = serde_json::from_str(&c.to_json("a.vtu", "b.vtu")).unwrap();
+		assert_eq!(json["equal"], false);
+		assert_eq!(json["arrays"][1]["name"], "Resultz");
+		assert_eq!(json["arrays"][1]["worst"][0]["tuple"], 3);
+
+		// Per-array tolerance
+		tol.arrays.push(("Resultz".to_string(), Tolerance::new(0.6, 0.0)));
+		assert!(a.compare(&b, &tol).is_equal());
+
+		b.types[0] = 9;
+		assert_eq!(a.compare(&b, &tol).topology,
+			vec!["types differ at index 0".to_string()]);
+	}
+}
+
+//==============================================================================
+
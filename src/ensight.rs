@@ -0,0 +1,622 @@
+
+//==============================================================================
+
+// EnSight Gold export, in C binary or ASCII:  a .case file that references a
+// geometry file and a file per point and cell array.  Each cell type gets its
+// own part with its own coordinates.  Time series write a geometry and
+// variable file per step, numbered through wildcards in the .case file
+
+// Standard
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+//********
+
+// Third party
+use log::{info, warn};
+
+//********
+
+// This lib
+use crate::base64::{self, Scalar};
+use crate::cells;
+use crate::vtk::{DataArray, VtkFile};
+
+//********
+
+pub const EXT: &str = ".case";
+
+const GEO_EXT: &str = "geo";
+
+const ASCII: &str = "ascii";
+
+// Binary files start with this, and strings are padded to 80 bytes
+const C_BINARY: &str = "C Binary";
+const LINE_LEN: usize = 80;
+
+const TITLE: &str = "vtk-io-rs";
+
+// Element types with the VTK type they hold, and the VTK node of each EnSight
+// node where the orderings differ
+const ELEMENTS: &[(&str, u8, &[usize])] = &[
+	("point"    , cells::VTK_VERTEX              , &[]),
+	("bar2"     , cells::VTK_LINE                , &[]),
+	("bar3"     , cells::VTK_QUADRATIC_EDGE      , &[]),
+	("tria3"    , cells::VTK_TRIANGLE            , &[]),
+	("tria6"    , cells::VTK_QUADRATIC_TRIANGLE  , &[]),
+	("quad4"    , cells::VTK_QUAD                , &[]),
+	("quad4"    , cells::VTK_PIXEL               , &[0, 1, 3, 2]),
+	("quad8"    , cells::VTK_QUADRATIC_QUAD      , &[]),
+	("tetra4"   , cells::VTK_TETRA               , &[]),
+	("tetra10"  , cells::VTK_QUADRATIC_TETRA     , &[]),
+	("pyramid5" , cells::VTK_PYRAMID             , &[]),
+	("pyramid13", cells::VTK_QUADRATIC_PYRAMID   , &[]),
+	("penta6"   , cells::VTK_WEDGE               , &[0, 2, 1, 3, 5, 4]),
+	("penta15"  , cells::VTK_QUADRATIC_WEDGE     ,
+		&[0, 2, 1, 3, 5, 4, 8, 7, 6, 11, 10, 9, 12, 14, 13]),
+	("hexa8"    , cells::VTK_HEXAHEDRON          , &[]),
+	("hexa8"    , cells::VTK_VOXEL               , &[0, 1, 3, 2, 4, 5, 7, 6]),
+	("hexa20"   , cells::VTK_QUADRATIC_HEXAHEDRON, &[]),
+];
+
+// Polygons, with a node count per element
+const NSIDED: &str = "nsided";
+
+//==============================================================================
+
+// Cells of one element type, and the points they use in increasing order.
+// Cells refer to their points by position in the part
+struct Part
+{
+	etype : &'static str,
+	cells : Vec<usize>,
+	points: Vec<usize>,
+}
+
+fn parts(v: &VtkFile) -> Vec<Part>
+{
+	let mut parts: Vec<Part> = Vec::new();
+	let mut nskipped = 0;
+	for i in 0 .. v.ncells as usize
+	{
+		let etype = if v.types[i] == cells::VTK_POLYGON
+		{
+			NSIDED
+		}
+		else
+		{
+			match ELEMENTS.iter().find(|e| e.1 == v.types[i])
+			{
+				Some(e) => e.0,
+				None =>
+				{
+					nskipped += 1;
+					continue;
+				},
+			}
+		};
+
+		match parts.iter_mut().find(|p| p.etype == etype)
+		{
+			Some(p) => p.cells.push(i),
+			None => parts.push(Part { etype, cells: vec![i], points: Vec::new() }),
+		}
+	}
+	if nskipped > 0
+	{
+		warn!("skipping {} poly vertices, poly lines, strips, and polyhedra, \
+			which EnSight parts cannot hold", nskipped);
+	}
+
+	// Parts in the order of ELEMENTS, with polygons last
+	let order = |etype| ELEMENTS.iter().position(|e| e.0 == etype)
+		.unwrap_or(ELEMENTS.len());
+	parts.sort_by_key(|p| order(p.etype));
+
+	for p in &mut parts
+	{
+		let mut used = vec![false; v.npoints as usize];
+		for &i in &p.cells
+		{
+			for &q in v.cell_points(i)
+			{
+				used[q as usize] = true;
+			}
+		}
+		p.points = (0 .. used.len()).filter(|&q| used[q]).collect();
+	}
+	return parts;
+}
+
+//********
+
+// Writer of 80-character strings, ints, and floats, which are each on their
+// own line in ASCII.  Binary is little endian
+struct Out<'a, W: Write>
+{
+	f: &'a mut W,
+	binary: bool,
+}
+
+impl<W: Write> Out<'_, W>
+{
+	fn string(&mut self, s: &str) -> Result<(), io::Error>
+	{
+		if !self.binary
+		{
+			return writeln!(self.f, "{}", s);
+		}
+		let mut bytes = s.as_bytes().to_vec();
+		bytes.resize(LINE_LEN, 0);
+		self.f.write_all(&bytes)
+	}
+
+	fn int(&mut self, x: usize) -> Result<(), io::Error>
+	{
+		self.ints(&[x as i64])
+	}
+
+	fn ints(&mut self, row: &[i64]) -> Result<(), io::Error>
+	{
+		// A row of connectivity goes on one line
+		if !self.binary
+		{
+			let s: Vec<String> = row.iter().map(|x| format!("{:10}", x)).collect();
+			return writeln!(self.f, "{}", s.join(""));
+		}
+		for x in row
+		{
+			self.f.write_all(&(*x as i32).to_bytes(base64::LITTLE_ENDIAN))?;
+		}
+		Ok(())
+	}
+
+	fn reals(&mut self, xs: &[f32]) -> Result<(), io::Error>
+	{
+		for x in xs
+		{
+			if self.binary
+			{
+				self.f.write_all(&x.to_bytes(base64::LITTLE_ENDIAN))?;
+			}
+			else
+			{
+				writeln!(self.f, "{}", real(*x))?;
+			}
+		}
+		Ok(())
+	}
+}
+
+//********
+
+fn real(x: f32) -> String
+{
+	// Like C's %12.5e, with a signed exponent of at least 2 digits
+	let s = format!("{:.5e}", x);
+	let s = match s.split_once('e')
+	{
+		Some((m, e)) =>
+		{
+			let e: i32 = e.parse().unwrap_or(0);
+			format!("{}e{}{:02}", m, if e < 0 { '-' } else { '+' }, e.abs())
+		},
+		None => s,
+	};
+	return format!("{:>12}", s);
+}
+
+//********
+
+fn variable_type(a: &DataArray) -> Option<&'static str>
+{
+	return match a.ncomponents
+	{
+		1 => Some("scalar"),
+		3 => Some("vector"),
+		6 => Some("tensor symm"),
+		9 => Some("tensor asym"),
+		_ => None,
+	};
+}
+
+//********
+
+fn components(ncomponents: usize) -> Vec<usize>
+{
+	// VTK symmetric tensors are XX, YY, ZZ, XY, YZ, XZ, and EnSight ones are
+	// 11, 22, 33, 12, 13, 23
+	if ncomponents == 6
+	{
+		return vec![0, 1, 2, 3, 5, 4];
+	}
+	return (0 .. ncomponents).collect();
+}
+
+//********
+
+fn description(name: &str) -> String
+{
+	// Variable names in the .case file cannot have spaces, and they double as
+	// file names here
+	return name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_'
+		|| c == '-' { c } else { '_' }).collect();
+}
+
+//==============================================================================
+
+fn write_geometry<W: Write>(v: &VtkFile, parts: &[Part], f: &mut W, binary: bool)
+	-> Result<(), io::Error>
+{
+	let mut out = Out { f, binary };
+	if binary
+	{
+		out.string(C_BINARY)?;
+	}
+	out.string(TITLE)?;
+	out.string("geometry")?;
+	out.string("node id off")?;
+	out.string("element id off")?;
+
+	let nc = v.ncomponents as usize;
+	let mut local = vec![0; v.npoints as usize];
+	for (k, p) in parts.iter().enumerate()
+	{
+		out.string("part")?;
+		out.int(k + 1)?;
+		out.string(p.etype)?;
+
+		out.string("coordinates")?;
+		out.int(p.points.len())?;
+		for d in 0 .. 3
+		{
+			let x: Vec<f32> = p.points.iter()
+				.map(|&q| if d < nc { v.points[nc * q + d] } else { 0.0 }).collect();
+			out.reals(&x)?;
+		}
+
+		for (j, &q) in p.points.iter().enumerate()
+		{
+			local[q] = j as i64 + 1;
+		}
+
+		out.string(p.etype)?;
+		out.int(p.cells.len())?;
+		if p.etype == NSIDED
+		{
+			for &i in &p.cells
+			{
+				out.int(v.cell_points(i).len())?;
+			}
+		}
+		for &i in &p.cells
+		{
+			let conn = v.cell_points(i);
+			let order = ELEMENTS.iter().find(|e| e.1 == v.types[i]).map(|e| e.2)
+				.unwrap_or(&[]);
+			let row: Vec<i64> = if order.is_empty()
+			{
+				conn.iter().map(|&q| local[q as usize]).collect()
+			}
+			else
+			{
+				order.iter().map(|&j| local[conn[j] as usize]).collect()
+			};
+			out.ints(&row)?;
+		}
+	}
+	return Ok(());
+}
+
+//********
+
+fn write_variable<W: Write>(a: &DataArray, per_node: bool, parts: &[Part],
+	f: &mut W, binary: bool) -> Result<(), io::Error>
+{
+	// Values of each component in turn, for the points or cells of each part
+
+	let mut out = Out { f, binary };
+	out.string(&a.name)?;
+
+	let nc = a.ncomponents as usize;
+	let values = a.data.to_f64();
+	for (k, p) in parts.iter().enumerate()
+	{
+		out.string("part")?;
+		out.int(k + 1)?;
+		let ids = if per_node
+		{
+			out.string("coordinates")?;
+			&p.points
+		}
+		else
+		{
+			out.string(p.etype)?;
+			&p.cells
+		};
+		for c in components(nc)
+		{
+			let x: Vec<f32> = ids.iter().map(|&i| values[nc * i + c] as f32).collect();
+			out.reals(&x)?;
+		}
+	}
+	return Ok(());
+}
+
+//********
+
+fn write_case(steps: &[&VtkFile], times: Option<&[f64]>, file: &str)
+	-> Result<(), io::Error>
+{
+	// Arrays of the first step are exported, and each step must have them
+
+	let binary = steps[0].format != ASCII;
+
+	let path = Path::new(file);
+	let dir = path.parent().unwrap_or_else(|| Path::new(""));
+	let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+
+	// Steps are numbered by wildcards, e.g. "out.0001.geo"
+	let ndigits = std::cmp::max(4, (steps.len() - 1).to_string().len());
+	let name = |ext: &str, step: Option<usize>| -> String
+	{
+		match (times, step)
+		{
+			(None, _) => format!("{}.{}", stem, ext),
+			(Some(_), None) => format!("{}.{}.{}", stem, "*".repeat(ndigits), ext),
+			(Some(_), Some(s)) => format!("{}.{:0w$}.{}", stem, s, ext, w = ndigits),
+		}
+	};
+
+	// Descriptions double as file names, so they must be unique, e.g. for a
+	// point and a cell array with the same name, or names that clean to the
+	// same description.  Clashes get a _n or _e suffix, and then a counter
+	// Arrays are found in each step by name, component count, and which one
+	// of those they are
+	let mut variables: Vec<(String, u64, bool, &str, String, usize)> = Vec::new();
+	for (j, (a, per_node)) in steps[0].pdata.iter().map(|a| (a, true))
+		.chain(steps[0].cdata.iter().map(|a| (a, false))).enumerate()
+	{
+		let arrays = if per_node { &steps[0].pdata[.. j] }
+			else { &steps[0].cdata[.. j - steps[0].pdata.len()] };
+		let nth = arrays.iter().filter(|b| b.name == a.name
+			&& b.ncomponents == a.ncomponents).count();
+
+		let used = |d: &str| d == GEO_EXT || variables.iter().any(|v| v.4 == d);
+		let mut desc = description(&a.name);
+		if used(&desc)
+		{
+			let base = format!("{}_{}", desc, if per_node { "n" } else { "e" });
+			desc = base.clone();
+			let mut k = 2;
+			while used(&desc)
+			{
+				desc = format!("{}{}", base, k);
+				k += 1;
+			}
+		}
+
+		match variable_type(a)
+		{
+			Some(t) => variables.push((a.name.clone(), a.ncomponents, per_node, t,
+				desc, nth)),
+			None => warn!("skipping array \"{}\" of {} components, which is not a \
+				scalar, vector, or tensor", a.name, a.ncomponents),
+		}
+	}
+
+	for (s, &v) in steps.iter().enumerate()
+	{
+		let linear;
+		let v = if v.types.iter().any(|&t| cells::is_higher_order(t))
+		{
+			linear = v.linearize();
+			&linear
+		}
+		else
+		{
+			v
+		};
+
+		let parts = parts(v);
+		let step = times.map(|_| s);
+
+		let mut f = BufWriter::new(File::create(dir.join(name(GEO_EXT, step)))?);
+		write_geometry(v, &parts, &mut f, binary)?;
+		f.flush()?;
+
+		for (aname, nc, per_node, _, desc, nth) in &variables
+		{
+			let arrays = if *per_node { &v.pdata } else { &v.cdata };
+			let a = match arrays.iter()
+				.filter(|a| &a.name == aname && a.ncomponents == *nc).nth(*nth)
+			{
+				Some(a) => a,
+				None => return Err(io::Error::new(io::ErrorKind::InvalidData,
+					format!("step {} has no {}-component array \"{}\"", s, nc, aname))),
+			};
+			let mut f = BufWriter::new(File::create(dir.join(name(desc, step)))?);
+			write_variable(a, *per_node, &parts, &mut f, binary)?;
+			f.flush()?;
+		}
+	}
+
+	let mut f = BufWriter::new(File::create(file)?);
+	let set = if times.is_some() { "1 " } else { "" };
+	writeln!(f, "FORMAT")?;
+	writeln!(f, "type: ensight gold")?;
+	writeln!(f)?;
+	writeln!(f, "GEOMETRY")?;
+	writeln!(f, "model: {}{}", set, name(GEO_EXT, None))?;
+	if !variables.is_empty()
+	{
+		writeln!(f)?;
+		writeln!(f, "VARIABLE")?;
+		for (_, _, per_node, vtype, desc, _) in &variables
+		{
+			writeln!(f, "{} per {}: {}{} {}", vtype, if *per_node { "node" }
+				else { "element" }, set, desc, name(desc, None))?;
+		}
+	}
+	if let Some(times) = times
+	{
+		writeln!(f)?;
+		writeln!(f, "TIME")?;
+		writeln!(f, "time set: 1")?;
+		writeln!(f, "number of steps: {}", times.len())?;
+		writeln!(f, "filename start number: 0")?;
+		writeln!(f, "filename increment: 1")?;
+		writeln!(f, "time values:")?;
+		for t in times
+		{
+			writeln!(f, "{}", t)?;
+		}
+	}
+	return f.flush();
+}
+
+//********
+
+impl VtkFile
+{
+pub fn export_ensight(&self, file: &str)
+{
+	info!("Exporting EnSight file \"{}\"", file);
+	if let Err(e) = write_case(&[self], None, file)
+	{
+		panic!("Cannot export EnSight file \"{}\":  {}", file, e);
+	}
+}}
+
+//********
+
+pub fn export_series(steps: &[(f64, VtkFile)], file: &str)
+{
+	// Time steps, e.g. of a .pvd collection, in one .case file
+
+	info!("Exporting EnSight file \"{}\" with {} time steps", file, steps.len());
+	if steps.is_empty()
+	{
+		panic!("Cannot export EnSight file \"{}\":  no time steps", file);
+	}
+
+	let times: Vec<f64> = steps.iter().map(|s| s.0).collect();
+	let vs: Vec<&VtkFile> = steps.iter().map(|s| &s.1).collect();
+	if let Err(e) = write_case(&vs, Some(&times), file)
+	{
+		panic!("Cannot export EnSight file \"{}\":  {}", file, e);
+	}
+}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::vtk::Data;
+
+	fn grid() -> VtkFile
+	{
+		// A pixel and a triangle sharing an edge, and a tet apart from them
+		let mut v = VtkFile::new();
+		v.vtype = "UnstructuredGrid".to_string();
+		v.ncomponents = 3;
+		v.npoints = 9;
+		v.points = vec![0.0, 0.0, 0.0,  1.0, 0.0, 0.0,  0.0, 1.0, 0.0,
+			1.0, 1.0, 0.0,  2.0, 0.0, 0.0,
+			5.0, 0.0, 0.0,  6.0, 0.0, 0.0,  5.0, 1.0, 0.0,  5.0, 0.0, 1.0];
+		v.ncells = 3;
+		v.connectivity = vec![1, 4, 3,  5, 6, 7, 8,  0, 1, 2, 3];
+		v.offsets = vec![3, 7, 11];
+		v.types = vec![cells::VTK_TRIANGLE, cells::VTK_TETRA, cells::VTK_PIXEL];
+		v.pdata.push(DataArray::new("T", 1, Data::F64((0 .. 9).map(|i| i as f64)
+			.collect())));
+		v.cdata.push(DataArray::new("cell id", 1, Data::I32(vec![10, 11, 12])));
+		v.format = ASCII.to_string();
+		return v;
+	}
+
+	#[test]
+	fn test_ensight()
+	{
+		let v = grid();
+		let file = "./scratch/tmp.Ens7gQ2x.case";
+		v.export_ensight(file);
+
+		let case = std::fs::read_to_string(file).unwrap();
+		assert!(case.contains("model: tmp.Ens7gQ2x.geo\n"));
+		assert!(case.contains("scalar per node: T tmp.Ens7gQ2x.T\n"));
+		assert!(case.contains("scalar per element: cell_id tmp.Ens7gQ2x.cell_id\n"));
+
+		// Parts of tria3, quad4, and tetra4, with the pixel as a quad
+		let geo = std::fs::read_to_string("./scratch/tmp.Ens7gQ2x.geo").unwrap();
+		let lines: Vec<&str> = geo.lines().collect();
+		assert_eq!(&lines[4 .. 8], &["part", "         1", "tria3", "coordinates"]);
+		let quad = lines.iter().position(|&l| l == "quad4").unwrap();
+		assert_eq!(&lines[quad - 2 .. quad], &["part", "         2"]);
+		assert_eq!(&lines[quad + 15 ..= quad + 17], &["quad4", "         1",
+			"         1         2         4         3"]);
+		assert!(geo.contains("tetra4\n         1\n         1         2         3         4\n"));
+
+		let t = std::fs::read_to_string("./scratch/tmp.Ens7gQ2x.T").unwrap();
+		assert!(t.starts_with("T\npart\n         1\ncoordinates\n 1.00000e+00\n"));
+		assert_eq!(real(-1234.5), "-1.23450e+03");
+	}
+
+	#[test]
+	fn test_ensight_same_names()
+	{
+		let mut v = grid();
+		v.cdata.push(DataArray::new("T", 1, Data::F64(vec![1.0, 2.0, 3.0])));
+		v.cdata.push(DataArray::new("cell_id", 1, Data::I32(vec![0, 1, 2])));
+		v.cdata.push(DataArray::new("cell_id", 1, Data::I32(vec![3, 4, 5])));
+		let file = "./scratch/tmp.Ens3Hq8v.case";
+		v.export_ensight(file);
+
+		let case = std::fs::read_to_string(file).unwrap();
+		assert!(case.contains("scalar per node: T tmp.Ens3Hq8v.T\n"));
+		assert!(case.contains("scalar per element: T_e tmp.Ens3Hq8v.T_e\n"));
+		assert!(case.contains("scalar per element: cell_id tmp.Ens3Hq8v.cell_id\n"));
+		assert!(case.contains("scalar per element: cell_id_e tmp.Ens3Hq8v.cell_id_e\n"));
+		assert!(case.contains("scalar per element: cell_id_e2 tmp.Ens3Hq8v.cell_id_e2\n"));
+
+		// Each array keeps its own file
+		let t = std::fs::read_to_string("./scratch/tmp.Ens3Hq8v.T").unwrap();
+		assert!(t.contains("coordinates"));
+		let t = std::fs::read_to_string("./scratch/tmp.Ens3Hq8v.T_e").unwrap();
+		assert!(t.contains("tria3"));
+		let c = std::fs::read_to_string("./scratch/tmp.Ens3Hq8v.cell_id_e2").unwrap();
+		assert!(c.contains(" 3.00000e+00"));
+	}
+
+	#[test]
+	fn test_ensight_series()
+	{
+		let mut v = grid();
+		v.format = "binary".to_string();
+		let mut w = v.clone();
+		w.points[3] = -1.0;
+
+		let file = "./scratch/tmp.Ens9Tw4k.case";
+		export_series(&[(0.0, v), (0.5, w)], file);
+
+		let case = std::fs::read_to_string(file).unwrap();
+		assert!(case.contains("model: 1 tmp.Ens9Tw4k.****.geo\n"));
+		assert!(case.contains("time values:\n0\n0.5\n"));
+
+		let geo = std::fs::read("./scratch/tmp.Ens9Tw4k.0001.geo").unwrap();
+		assert_eq!(&geo[.. 8], C_BINARY.as_bytes());
+
+		// 5 header strings, 3 part strings, the part number, and the point
+		// count come before the first x
+		let x0 = 8 * LINE_LEN + 8;
+		assert_eq!(f32::from_bytes(&geo[x0 ..], base64::LITTLE_ENDIAN), -1.0);
+		assert!(Path::new("./scratch/tmp.Ens9Tw4k.0000.cell_id").exists());
+	}
+}
+
+//==============================================================================
+
@@ -0,0 +1,516 @@
+
+//==============================================================================
+
+// Extraction of single arrays as columns:  CSV of the point coordinates and
+// point arrays, of cell arrays, or of field arrays, e.g. TimeValue, and NumPy
+// .npy files of one array each.  The coordinates can be extracted like an
+// array by the name "Points".  .npy files can also be read back and attached
+// as point, cell or field arrays
+
+// Standard
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+//********
+
+// Third party
+use log::info;
+
+//********
+
+// This lib
+use crate::base64;
+use crate::vtk::{Data, DataArray, VtkFile};
+
+//********
+
+pub const CSV_EXT: &str = ".csv";
+pub const NPY_EXT: &str = ".npy";
+
+const POINTS: &str = "Points";
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+// Data of .npy files starts on a multiple of this
+const NPY_ALIGN: usize = 64;
+
+// NumPy type codes of the VTK types, without the byte order character
+const NPY_TYPES: &[(&str, &str)] = &[
+	("Int8"   , "i1"),
+	("UInt8"  , "u1"),
+	("Int16"  , "i2"),
+	("UInt16" , "u2"),
+	("Int32"  , "i4"),
+	("UInt32" , "u4"),
+	("Int64"  , "i8"),
+	("UInt64" , "u8"),
+	("Float32", "f4"),
+	("Float64", "f8"),
+];
+
+// Section of the grid that arrays are extracted from or attached to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Section
+{
+	Point,
+	Cell,
+	Field,
+}
+
+impl Section
+{
+fn name(&self) -> &'static str
+{
+	return match self
+	{
+		Section::Point => "point",
+		Section::Cell  => "cell",
+		Section::Field => "field",
+	};
+}}
+
+//==============================================================================
+
+fn column_names(a: &DataArray) -> Vec<String>
+{
+	// ParaView's spreadsheet names, e.g. "Points:0", quoted for CSV
+	let nc = a.ncomponents as usize;
+	let names: Vec<String> = if nc == 1
+	{
+		vec![a.name.clone()]
+	}
+	else
+	{
+		(0 .. nc).map(|c| format!("{}:{}", a.name, c)).collect()
+	};
+	return names.iter().map(|n| format!("\"{}\"", n.replace('"', "\"\""))).collect();
+}
+
+//********
+
+pub fn write_npy<W: Write>(a: &DataArray, f: &mut W, endianness: u8)
+	-> Result<(), io::Error>
+{
+	// Format version 1.0:  magic, version, header length, and a header dict
+	// padded with spaces, then the data in C order
+
+	let vtk_type = a.data.vtk_type();
+	let code = NPY_TYPES.iter().find(|t| t.0 == vtk_type).map_or("f8", |t| t.1);
+	let order = if code.ends_with('1') {
+		'|'
+	} else if endianness == base64::BIG_ENDIAN {
+		'>'
+	} else {
+		'<'
+	};
+
+	let nc = a.ncomponents.max(1) as usize;
+	let n = a.data.len() / nc;
+	let shape = if nc == 1 { format!("({},)", n) } else { format!("({}, {})", n, nc) };
+
+	let mut header = format!("{{'descr': '{}{}', 'fortran_order': False, \
+		'shape': {}, }}", order, code, shape);
+	let len = NPY_MAGIC.len() + 4 + header.len() + 1;
+	header.push_str(&" ".repeat((NPY_ALIGN - len % NPY_ALIGN) % NPY_ALIGN));
+	header.push('\n');
+
+	f.write_all(NPY_MAGIC)?;
+	f.write_all(&[1, 0])?;
+	f.write_all(&(header.len() as u16).to_le_bytes())?;
+	f.write_all(header.as_bytes())?;
+	return f.write_all(&a.data.to_bytes(endianness));
+}
+
+//********
+
+fn dict_value<'a>(header: &'a str, key: &str) -> Option<&'a str>
+{
+	// Value of a key of the header dict, e.g. "'<f4'" or "(3, 2)"
+	let pattern = format!("'{}':", key);
+	let rest = header[header.find(&pattern)? + pattern.len() ..].trim_start();
+	let end = if rest.starts_with('(')
+	{
+		rest.find(')')? + 1
+	}
+	else if let Some(quoted) = rest.strip_prefix('\'')
+	{
+		quoted.find('\'')? + 2
+	}
+	else
+	{
+		rest.find([',', '}'])?
+	};
+	return Some(&rest[.. end]);
+}
+
+//********
+
+pub fn read_npy<R: Read>(mut reader: R, name: &str) -> DataArray
+{
+	// 1D or C or Fortran ordered arrays of any number of dimensions.  The
+	// first dimension is the number of tuples, and the others are components
+
+	let errstr = format!("Cannot read NumPy file \"{}\"", name);
+
+	let mut bytes = Vec::new();
+	reader.read_to_end(&mut bytes).expect(&errstr);
+	if !bytes.starts_with(NPY_MAGIC) || bytes.len() < NPY_MAGIC.len() + 4
+	{
+		panic!("{}:  not a .npy file", errstr);
+	}
+
+	// Versions 2 and 3 have a 4-byte header length
+	let major = bytes[NPY_MAGIC.len()];
+	let (hlen, begin) = if major == 1
+	{
+		(u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10)
+	}
+	else if bytes.len() >= 12
+	{
+		(u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize, 12)
+	}
+	else
+	{
+		panic!("{}:  truncated header", errstr);
+	};
+	if bytes.len() < begin + hlen
+	{
+		panic!("{}:  truncated header", errstr);
+	}
+	let header = String::from_utf8_lossy(&bytes[begin .. begin + hlen]).to_string();
+
+	let descr = dict_value(&header, "descr").unwrap_or("").trim_matches('\'');
+	let (order, code) = match descr.chars().next()
+	{
+		Some(c) if "<>|=".contains(c) => (c, &descr[1 ..]),
+		_ => ('=', descr),
+	};
+	let endianness = match order
+	{
+		'>' => base64::BIG_ENDIAN,
+		'<' => base64::LITTLE_ENDIAN,
+		_ if cfg!(target_endian = "big") => base64::BIG_ENDIAN,
+		_ => base64::LITTLE_ENDIAN,
+	};
+
+	// Booleans are bytes of 0 or 1
+	let code = if code == "b1" || code == "?" { "u1" } else { code };
+	let vtk_type = match NPY_TYPES.iter().find(|t| t.1 == code)
+	{
+		Some(t) => t.0,
+		None => panic!("{}:  unsupported type \"{}\"", errstr, descr),
+	};
+
+	let fortran = dict_value(&header, "fortran_order") == Some("True");
+	let shape: Vec<usize> = dict_value(&header, "shape").unwrap_or("()")
+		.trim_matches(['(', ')']).split(',')
+		.filter(|d| !d.trim().is_empty())
+		.map(|d| d.trim().parse().unwrap_or_else(|_| panic!("{}:  bad shape \
+			\"{}\"", errstr, d)))
+		.collect();
+	let n = shape.first().copied().unwrap_or(1);
+	let nc: usize = shape.iter().skip(1).product();
+
+	let data = Data::from_bytes(&bytes[begin + hlen ..], vtk_type, endianness);
+	if data.len() != n * nc
+	{
+		panic!("{}:  {} values instead of {} for shape {:?}", errstr, data.len(),
+			n * nc, shape);
+	}
+
+	// Fortran order keeps the components of a tuple apart.  The other
+	// dimensions of e.g. tensors stay transposed, like in memory
+	let data = if fortran && nc > 1
+	{
+		let indices: Vec<usize> = (0 .. n * nc).map(|j| (j % nc) * n + j / nc).collect();
+		data.select(1, &indices)
+	}
+	else
+	{
+		data
+	};
+
+	let stem = Path::new(name).file_stem().unwrap_or_default().to_string_lossy();
+	return DataArray::new(&stem, nc as u64, data);
+}
+
+//********
+
+pub fn load_npy(file: &str) -> DataArray
+{
+	info!("Loading NumPy file \"{}\"", file);
+	let errstr = format!("Cannot load NumPy file \"{}\"", file);
+	let f = File::open(file).expect(&errstr);
+	return read_npy(BufReader::new(f), file);
+}
+
+//==============================================================================
+
+impl VtkFile
+{
+fn section_arrays(&self, section: Section) -> &Vec<DataArray>
+{
+	return match section
+	{
+		Section::Point => &self.pdata,
+		Section::Cell  => &self.cdata,
+		Section::Field => &self.fdata,
+	};
+}
+
+//********
+
+fn extracted_arrays(&self, names: &[String], section: Section) -> Vec<DataArray>
+{
+	// Arrays by name, or all arrays if no names are given.  The coordinates
+	// come first among point arrays
+
+	let points = DataArray::new(POINTS, self.ncomponents, Data::F32(self.points.clone()));
+	let arrays = self.section_arrays(section);
+	if names.is_empty()
+	{
+		let first = if section == Section::Point { Some(points) } else { None };
+		return first.into_iter().chain(arrays.iter().cloned()).collect();
+	}
+
+	return names.iter().map(|name|
+	{
+		match arrays.iter().find(|a| &a.name == name)
+		{
+			Some(a) => a.clone(),
+			None if name == POINTS && section == Section::Point => points.clone(),
+			None => panic!("Cannot extract array \"{}\":  there is no {} array \
+				by that name", name, section.name()),
+		}
+	}).collect();
+}
+
+//********
+
+pub fn write_csv<W: Write>(&self, f: &mut W, names: &[String], section: Section)
+	-> Result<(), io::Error>
+{
+	// A row per point, with the coordinates first, or a row per cell.  Field
+	// arrays have a row per tuple of the longest array, and shorter arrays
+	// leave their columns empty
+
+	let mut arrays = self.extracted_arrays(names, section);
+	if section == Section::Point && !arrays.iter().any(|a| a.name == POINTS)
+	{
+		arrays.insert(0, DataArray::new(POINTS, self.ncomponents,
+			Data::F32(self.points.clone())));
+	}
+
+	let header: Vec<String> = arrays.iter().flat_map(column_names).collect();
+	writeln!(f, "{}", header.join(","))?;
+
+	let n = match section
+	{
+		Section::Point => self.npoints as usize,
+		Section::Cell  => self.ncells as usize,
+		Section::Field => arrays.iter()
+			.map(|a| a.data.len() / a.ncomponents.max(1) as usize).max().unwrap_or(0),
+	};
+	let mut row = Vec::new();
+	for i in 0 .. n
+	{
+		row.clear();
+		for a in &arrays
+		{
+			let nc = a.ncomponents.max(1) as usize;
+			row.extend((0 .. nc).map(|c| if nc * i + c < a.data.len()
+			{
+				a.data.value_string(nc * i + c)
+			}
+			else
+			{
+				"".to_string()
+			}));
+		}
+		writeln!(f, "{}", row.join(","))?;
+	}
+	return Ok(());
+}
+
+//********
+
+pub fn extract(&self, names: &[String], section: Section, file: &str)
+{
+	// CSV of all arrays, or a .npy file of each array.  Several arrays go to
+	// files named after the output and each array, e.g. "out.Velocity.npy"
+
+	let errstr = format!("Cannot extract to \"{}\"", file);
+	let lower = file.to_lowercase();
+	if lower.ends_with(CSV_EXT)
+	{
+		info!("Extracting CSV file \"{}\"", file);
+		let try_export = || -> Result<(), io::Error>
+		{
+			let mut f = BufWriter::new(File::create(file)?);
+			self.write_csv(&mut f, names, section)?;
+			f.flush()
+		};
+		if let Err(e) = try_export()
+		{
+			panic!("{}:  {}", errstr, e);
+		}
+		return;
+	}
+	if !lower.ends_with(NPY_EXT)
+	{
+		panic!("{}:  the extension must be {} or {}", errstr, CSV_EXT, NPY_EXT);
+	}
+
+	let arrays = self.extracted_arrays(names, section);
+	let stem = &file[.. file.len() - NPY_EXT.len()];
+	for a in &arrays
+	{
+		let out = if arrays.len() == 1
+		{
+			file.to_string()
+		}
+		else
+		{
+			format!("{}.{}{}", stem, a.name.replace(['/', '\\'], "_"), NPY_EXT)
+		};
+		info!("Extracting NumPy file \"{}\"", out);
+		let try_export = || -> Result<(), io::Error>
+		{
+			let mut f = BufWriter::new(File::create(&out)?);
+			write_npy(a, &mut f, self.endianness)?;
+			f.flush()
+		};
+		if let Err(e) = try_export()
+		{
+			panic!("Cannot extract to \"{}\":  {}", out, e);
+		}
+	}
+}
+
+//********
+
+pub fn attach_npy(&mut self, file: &str, name: &str, section: Section)
+{
+	// Add a .npy file as a point, cell or field array, replacing any array of
+	// the same name.  Field arrays can have any number of tuples
+
+	let mut a = load_npy(file);
+	if !name.is_empty()
+	{
+		a.name = name.to_string();
+	}
+
+	let n = match section
+	{
+		Section::Point => Some(self.npoints),
+		Section::Cell  => Some(self.ncells),
+		Section::Field => None,
+	};
+	let ntuples = a.data.len() as u64 / a.ncomponents.max(1);
+	if let Some(n) = n.filter(|&n| n != ntuples)
+	{
+		panic!("Cannot attach NumPy file \"{}\":  it has {} tuples, but there \
+			are {} {}s", file, ntuples, n, section.name());
+	}
+
+	let arrays = match section
+	{
+		Section::Point => &mut self.pdata,
+		Section::Cell  => &mut self.cdata,
+		Section::Field => &mut self.fdata,
+	};
+	arrays.retain(|b| b.name != a.name);
+	arrays.push(a);
+}}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	fn grid() -> VtkFile
+	{
+		// 3 points and a cell
+		let mut v = VtkFile::new();
+		v.ncomponents = 3;
+		v.npoints = 3;
+		v.points = vec![0.0, 0.0, 0.0,  1.0, 0.0, 0.0,  0.0, 1.5, 0.0];
+		v.ncells = 1;
+		v.connectivity = vec![0, 1, 2];
+		v.offsets = vec![3];
+		v.types = vec![5];
+		v.pdata.push(DataArray::new("T", 1, Data::I32(vec![7, 8, 9])));
+		v.cdata.push(DataArray::new("grad", 2, Data::F64(vec![0.5, -1.0])));
+		v.fdata.push(DataArray::new("TimeValue", 1, Data::F64(vec![0.25])));
+		v.fdata.push(DataArray::new("steps", 1, Data::I32(vec![1, 2])));
+		return v;
+	}
+
+	#[test]
+	fn test_csv()
+	{
+		let v = grid();
+		let mut buf = Vec::new();
+		v.write_csv(&mut buf, &["T".to_string()], Section::Point).unwrap();
+		assert_eq!(String::from_utf8(buf).unwrap(), "\"Points:0\",\"Points:1\",\
+			\"Points:2\",\"T\"\n0,0,0,7\n1,0,0,8\n0,1.5,0,9\n");
+
+		let mut buf = Vec::new();
+		v.write_csv(&mut buf, &[], Section::Cell).unwrap();
+		assert_eq!(String::from_utf8(buf).unwrap(), "\"grad:0\",\"grad:1\"\n0.5,-1\n");
+
+		let mut buf = Vec::new();
+		v.write_csv(&mut buf, &[], Section::Field).unwrap();
+		assert_eq!(String::from_utf8(buf).unwrap(), "\"TimeValue\",\"steps\"\n\
+			0.25,1\n,2\n");
+	}
+
+	#[test]
+	fn test_npy()
+	{
+		// Roundtrip in both byte orders, and the header padding
+		let v = grid();
+		for &e in &[base64::LITTLE_ENDIAN, base64::BIG_ENDIAN]
+		{
+			let a = DataArray::new(POINTS, 3, Data::F32(v.points.clone()));
+			let mut buf = Vec::new();
+			write_npy(&a, &mut buf, e).unwrap();
+			assert_eq!((buf.len() - 4 * 9) % NPY_ALIGN, 0);
+			let b = read_npy(buf.as_slice(), "x/Points.npy");
+			assert_eq!(b, a);
+		}
+
+		// Fortran order, as e.g. np.asfortranarray writes it
+		let mut buf = NPY_MAGIC.to_vec();
+		let header = "{'descr': '<i2', 'fortran_order': True, 'shape': (3, 2), }\n";
+		buf.extend([1, 0, header.len() as u8, 0]);
+		buf.extend(header.as_bytes());
+		for x in &[1_i16, 2, 3, 10, 20, 30]
+		{
+			buf.extend(x.to_le_bytes());
+		}
+		let a = read_npy(buf.as_slice(), "f.npy");
+		assert_eq!(a.ncomponents, 2);
+		assert_eq!(a.data, Data::I16(vec![1, 10, 2, 20, 3, 30]));
+
+		let mut w = grid();
+		let file = "./scratch/tmp.Npy4aTq9.npy";
+		v.extract(&["T".to_string()], Section::Point, file);
+		w.attach_npy(file, "T2", Section::Point);
+		assert_eq!(w.point_array("T2").unwrap().data, Data::I32(vec![7, 8, 9]));
+
+		// Field arrays of any length
+		v.extract(&["steps".to_string()], Section::Field, file);
+		w.attach_npy(file, "", Section::Field);
+		assert_eq!(w.fdata.len(), 3);
+		assert_eq!(w.fdata[2].name, "tmp.Npy4aTq9");
+		assert_eq!(w.fdata[2].data, Data::I32(vec![1, 2]));
+	}
+}
+
+//==============================================================================
+
@@ -0,0 +1,137 @@
+
+//==============================================================================
+
+// Meshes of finite element solver input decks, e.g. Abaqus and Nastran, which
+// number nodes and elements by ID and group them in sets.  IDs are kept as
+// global ID arrays, and sets become 0/1 marker arrays
+
+// Standard
+use std::collections::HashMap;
+
+//********
+
+// Third party
+use log::warn;
+
+//********
+
+// This lib
+use crate::cells;
+use crate::vtk::{Attribute, Data, DataArray, VtkFile};
+
+//********
+
+// Point and cell arrays of the original IDs
+pub const NODE_IDS   : &str = "NodeIds";
+pub const ELEMENT_IDS: &str = "ElementIds";
+
+//==============================================================================
+
+// Nodes and elements by ID in order of appearance, with node and element
+// sets by name.  Element nodes are IDs, which may be defined later
+#[derive(Default)]
+pub struct Mesh
+{
+	pub nodes   : Vec<(i64, [f64; 3])>,
+	pub elements: Vec<(i64, u8, Vec<i64>)>,
+
+	pub node_sets   : Vec<(String, Vec<i64>)>,
+	pub element_sets: Vec<(String, Vec<i64>)>,
+
+	// Other integer cell arrays, e.g. property IDs
+	pub cell_ints: Vec<(String, Vec<i64>)>,
+}
+
+impl Mesh
+{
+	pub fn to_vtk(&self, errstr: &str) -> VtkFile
+	{
+		let mut v = VtkFile::new();
+		v.vtype = "UnstructuredGrid".to_string();
+		v.ncomponents = 3;
+
+		let mut index = HashMap::new();
+		for (i, (id, x)) in self.nodes.iter().enumerate()
+		{
+			if index.insert(*id, i).is_some()
+			{
+				panic!("{}:  node {} is defined twice", errstr, id);
+			}
+			v.points.extend(x.iter().map(|&x| x as f32));
+		}
+
+		for (id, ctype, nodes) in &self.elements
+		{
+			if let Some(n) = cells::npoints_fixed(*ctype).filter(|&n| n != nodes.len())
+			{
+				panic!("{}:  element {} has {} nodes, but a {} has {}", errstr, id,
+					nodes.len(), cells::type_name(*ctype), n);
+			}
+			for n in nodes
+			{
+				match index.get(n)
+				{
+					Some(&i) => v.connectivity.push(i as i64),
+					None => panic!("{}:  element {} has unknown node {}", errstr,
+						id, n),
+				}
+			}
+			v.offsets.push(v.connectivity.len() as i64);
+			v.types.push(*ctype);
+		}
+		v.npoints = self.nodes.len() as u64;
+		v.ncells = self.elements.len() as u64;
+
+		let node_ids: Vec<i64> = self.nodes.iter().map(|n| n.0).collect();
+		let element_ids: Vec<i64> = self.elements.iter().map(|e| e.0).collect();
+		v.pdata.push(DataArray::new(NODE_IDS, 1, Data::I64(node_ids.clone())));
+		v.cdata.push(DataArray::new(ELEMENT_IDS, 1, Data::I64(element_ids.clone())));
+		v.set_point_attribute(Attribute::GlobalIds, Some(NODE_IDS));
+		v.set_cell_attribute(Attribute::GlobalIds, Some(ELEMENT_IDS));
+
+		for (name, values) in &self.cell_ints
+		{
+			v.cdata.push(DataArray::new(name, 1, Data::I64(values.clone())));
+		}
+
+		for (name, members) in &self.node_sets
+		{
+			v.pdata.push(DataArray::new(name, 1, markers(&node_ids, members)));
+		}
+		for (name, members) in &self.element_sets
+		{
+			v.cdata.push(DataArray::new(name, 1, markers(&element_ids, members)));
+		}
+
+		return v;
+	}
+}
+
+//********
+
+fn markers(ids: &[i64], members: &[i64]) -> Data
+{
+	// 1 for IDs in the set, else 0.  Unknown members are reported, since
+	// decks may list e.g. nodes of another part
+	let position: HashMap<i64, usize> = ids.iter().enumerate()
+		.map(|(i, &id)| (id, i)).collect();
+
+	let mut m = vec![0; ids.len()];
+	let mut nunknown = 0;
+	for id in members
+	{
+		match position.get(id)
+		{
+			Some(&i) => m[i] = 1,
+			None => nunknown += 1,
+		}
+	}
+	if nunknown > 0
+	{
+		warn!("skipping {} unknown set members", nunknown);
+	}
+	return Data::U8(m);
+}
+
+//==============================================================================
+
@@ -0,0 +1,510 @@
+
+//==============================================================================
+
+// Ghost cells and points, global IDs, and pedigree IDs for partitioned grids
+
+//********
+
+// Standard
+use std::collections::HashMap;
+
+//********
+
+// Third party
+use log::warn;
+
+//********
+
+// This lib
+use crate::vtk::{Attribute, Attributes, Data, DataArray, VtkFile};
+
+//********
+
+// Conventional array names
+pub const GHOST_TYPE     : &str = "vtkGhostType";
+pub const GLOBAL_NODE_IDS: &str = "GlobalNodeIds";
+pub const GLOBAL_CELL_IDS: &str = "GlobalCellIds";
+
+// vtkGhostType bits for points, from vtkDataSetAttributes.h
+pub const DUPLICATEPOINT: u8 = 1;
+pub const HIDDENPOINT   : u8 = 2;
+
+// vtkGhostType bits for cells
+pub const DUPLICATECELL       : u8 =  1;
+pub const HIGHCONNECTIVITYCELL: u8 =  2;
+pub const LOWCONNECTIVITYCELL : u8 =  4;
+pub const REFINEDCELL         : u8 =  8;
+pub const EXTERIORCELL        : u8 = 16;
+pub const HIDDENCELL          : u8 = 32;
+
+//==============================================================================
+
+fn ghost_values(a: Option<&DataArray>) -> Option<&[u8]>
+{
+	return match a.map(|a| &a.data)
+	{
+		None => None,
+		Some(Data::U8(g)) => Some(g),
+		Some(d) => panic!("{} must be of type UInt8.  Found type {}",
+			GHOST_TYPE, d.vtk_type()),
+	};
+}
+
+//********
+
+fn ids(a: Option<&DataArray>) -> Option<Vec<i64>>
+{
+	return a.map(|a| a.data.to_i64());
+}
+
+//==============================================================================
+
+impl VtkFile
+{
+
+// Typed accessors.  Ghost arrays are found by name.  Global IDs use the active
+// GlobalIds attribute if it is set, and otherwise fall back to the conventional
+// array names.  Pedigree IDs have no conventional name
+
+pub fn ghost_points(&self) -> Option<&[u8]>
+{
+	ghost_values(self.point_array(GHOST_TYPE))
+}
+
+pub fn ghost_cells(&self) -> Option<&[u8]>
+{
+	ghost_values(self.cell_array(GHOST_TYPE))
+}
+
+pub fn global_point_ids(&self) -> Option<Vec<i64>>
+{
+	ids(self.active_point_array(Attribute::GlobalIds)
+		.or_else(|| self.point_array(GLOBAL_NODE_IDS)))
+}
+
+pub fn global_cell_ids(&self) -> Option<Vec<i64>>
+{
+	ids(self.active_cell_array(Attribute::GlobalIds)
+		.or_else(|| self.cell_array(GLOBAL_CELL_IDS)))
+}
+
+pub fn pedigree_point_ids(&self) -> Option<Vec<i64>>
+{
+	ids(self.active_point_array(Attribute::PedigreeIds))
+}
+
+pub fn pedigree_cell_ids(&self) -> Option<Vec<i64>>
+{
+	ids(self.active_cell_array(Attribute::PedigreeIds))
+}
+
+//********
+
+pub fn strip_ghosts(&self) -> VtkFile
+{
+	// Remove duplicate (ghost) and hidden cells, and cells with a hidden point,
+	// then any points that are no longer used, e.g. before merging partitions.
+	// The ghost arrays themselves are removed too
+
+	let ghost_cells  = self.ghost_cells();
+	let ghost_points = self.ghost_points();
+	let cells: Vec<usize> = (0 .. self.ncells as usize).filter(|&i|
+	{
+		let hidden_cell = ghost_cells
+			.is_some_and(|g| g[i] & (DUPLICATECELL | HIDDENCELL) != 0);
+		let hidden_point = ghost_points.is_some_and(|g| self.cell_points(i)
+			.iter().any(|&p| g[p as usize] & HIDDENPOINT != 0));
+		!hidden_cell && !hidden_point
+	}).collect();
+
+	let (mut v, _) = self.extract_cells(&cells);
+	v.pdata.retain(|a| a.name != GHOST_TYPE);
+	v.cdata.retain(|a| a.name != GHOST_TYPE);
+	return v;
+}
+
+//********
+
+pub fn split(&self, npieces: usize, ghost_levels: usize) -> Vec<VtkFile>
+{
+	// Split into pieces with the given number of ghost cell layers around each
+	// piece.  Cells are sorted by centroid along the longest axis of the
+	// bounding box and divided evenly.  Each piece gets vtkGhostType arrays, and
+	// GlobalNodeIds and GlobalCellIds unless the grid already has global IDs
+
+	if npieces == 0
+	{
+		panic!("Cannot split into zero pieces");
+	}
+
+	let ncells = self.ncells as usize;
+	let npoints = self.npoints as usize;
+	let nc = self.ncomponents as usize;
+
+	// Centroids and bounding box
+	let mut centroids = vec![[0.0_f64; 3]; ncells];
+	let mut lo = [f64::MAX; 3];
+	let mut hi = [f64::MIN; 3];
+	for i in 0 .. ncells
+	{
+		let pts = self.cell_points(i);
+		for &p in pts
+		{
+			for d in 0 .. std::cmp::min(nc, 3)
+			{
+				let x = self.points[nc * p as usize + d] as f64;
+				centroids[i][d] += x / pts.len() as f64;
+				lo[d] = lo[d].min(x);
+				hi[d] = hi[d].max(x);
+			}
+		}
+	}
+
+	let mut axis = 0;
+	for d in 1 .. 3
+	{
+		if hi[d] - lo[d] > hi[axis] - lo[axis]
+		{
+			axis = d;
+		}
+	}
+
+	let mut order: Vec<usize> = (0 .. ncells).collect();
+	order.sort_by(|&a, &b| centroids[a][axis]
+		.partial_cmp(&centroids[b][axis]).unwrap());
+
+	// Cells of each point, for growing ghost layers
+	let mut point_cells = vec![Vec::new(); npoints];
+	for i in 0 .. ncells
+	{
+		for &p in self.cell_points(i)
+		{
+			point_cells[p as usize].push(i);
+		}
+	}
+
+	let global_points = self.global_point_ids();
+	let global_cells  = self.global_cell_ids();
+
+	let mut pieces = Vec::with_capacity(npieces);
+	for ip in 0 .. npieces
+	{
+		let owned: Vec<usize> = order[ip * ncells / npieces
+			.. (ip + 1) * ncells / npieces].to_vec();
+
+		let mut marked = vec![false; ncells];
+		for &i in &owned
+		{
+			marked[i] = true;
+		}
+
+		// Grow one layer of neighbors sharing a point per ghost level
+		let mut ghosts = Vec::new();
+		let mut frontier = owned.clone();
+		for _ in 0 .. ghost_levels
+		{
+			let mut next = Vec::new();
+			for &i in &frontier
+			{
+				for &p in self.cell_points(i)
+				{
+					for &j in &point_cells[p as usize]
+					{
+						if !marked[j]
+						{
+							marked[j] = true;
+							next.push(j);
+						}
+					}
+				}
+			}
+			next.sort();
+			ghosts.extend(next.iter().copied());
+			frontier = next;
+		}
+
+		let cells: Vec<usize> = owned.iter().chain(ghosts.iter()).copied().collect();
+		let (mut v, points) = self.extract_cells(&cells);
+
+		// Points that no owned cell uses are ghosts
+		let mut used = vec![false; npoints];
+		for &i in &owned
+		{
+			for &p in self.cell_points(i)
+			{
+				used[p as usize] = true;
+			}
+		}
+
+		let gp = points.iter()
+			.map(|&p| if used[p] { 0 } else { DUPLICATEPOINT }).collect();
+		let gc = (0 .. cells.len())
+			.map(|i| if i < owned.len() { 0 } else { DUPLICATECELL }).collect();
+
+		v.pdata.retain(|a| a.name != GHOST_TYPE);
+		v.cdata.retain(|a| a.name != GHOST_TYPE);
+		v.pdata.push(DataArray::new(GHOST_TYPE, 1, Data::U8(gp)));
+		v.cdata.push(DataArray::new(GHOST_TYPE, 1, Data::U8(gc)));
+
+		if global_points.is_none()
+		{
+			let ids = points.iter().map(|&p| p as i64).collect();
+			v.pdata.push(DataArray::new(GLOBAL_NODE_IDS, 1, Data::I64(ids)));
+			v.set_point_attribute(Attribute::GlobalIds, Some(GLOBAL_NODE_IDS));
+		}
+		if global_cells.is_none()
+		{
+			let ids = cells.iter().map(|&i| i as i64).collect();
+			v.cdata.push(DataArray::new(GLOBAL_CELL_IDS, 1, Data::I64(ids)));
+			v.set_cell_attribute(Attribute::GlobalIds, Some(GLOBAL_CELL_IDS));
+		}
+
+		pieces.push(v);
+	}
+
+	return pieces;
+}
+
+}
+
+//==============================================================================
+
+fn merge_arrays(pieces: &[VtkFile], arrays: fn(&VtkFile) -> &Vec<DataArray>,
+	sources: &[(usize, usize)]) -> Vec<DataArray>
+{
+	// Gather the tuples of each source (piece, index).  Arrays are kept if
+	// every piece has them with the same type and components
+
+	let mut merged = Vec::new();
+	for a in arrays(&pieces[0])
+	{
+		let all: Option<Vec<&DataArray>> = pieces.iter().map(|p| arrays(p).iter()
+			.find(|b| b.name == a.name && b.ncomponents == a.ncomponents
+				&& b.data.vtk_type() == a.data.vtk_type()))
+			.collect();
+
+		let all = match all
+		{
+			Some(all) => all,
+			None =>
+			{
+				warn!("dropping array \"{}\", which differs between pieces",
+					a.name);
+				continue;
+			},
+		};
+
+		let nc = a.ncomponents as usize;
+		let mut data = a.data.select(nc, &[]);
+		for (ip, b) in all.iter().enumerate()
+		{
+			let indices: Vec<usize> = sources.iter().filter(|s| s.0 == ip)
+				.map(|s| s.1).collect();
+			data.append(&b.data.select(nc, &indices));
+		}
+
+		let mut m = DataArray::new(&a.name, a.ncomponents, data);
+		m.component_names = a.component_names.clone();
+		merged.push(m);
+	}
+	return merged;
+}
+
+//********
+
+fn keep_attributes(attrs: &Attributes, arrays: &[DataArray]) -> Attributes
+{
+	// Designations of the arrays that survived a merge
+	let mut kept = attrs.clone();
+	for &a in Attribute::ALL.iter()
+	{
+		if let Some(name) = attrs.get(a)
+		{
+			if !arrays.iter().any(|b| b.name == name)
+			{
+				kept.set(a, None);
+			}
+		}
+	}
+	return kept;
+}
+
+//********
+
+pub fn merge(pieces: &[VtkFile]) -> VtkFile
+{
+	// Merge partitioned pieces, e.g. from split or a .pvtu, into one grid.
+	// Ghost cells are removed first.  Points shared between pieces are merged
+	// if every piece has global point IDs, and otherwise they are duplicated.
+	// Cells stay in piece order, and points are numbered by first appearance
+
+	if pieces.is_empty()
+	{
+		panic!("Cannot merge zero pieces");
+	}
+
+	let pieces: Vec<VtkFile> = pieces.iter().map(|p| p.strip_ghosts()).collect();
+	let nc = pieces[0].ncomponents;
+	if let Some(p) = pieces.iter().find(|p| p.ncomponents != nc)
+	{
+		panic!("Cannot merge pieces with {} and {} point components", nc,
+			p.ncomponents);
+	}
+
+	let globals: Option<Vec<Vec<i64>>> = pieces.iter()
+		.map(|p| p.global_point_ids()).collect();
+	if globals.is_none() && pieces.len() > 1
+	{
+		warn!("pieces have no global point IDs.  Shared points are duplicated");
+	}
+
+	let mut v = pieces[0].clone();
+	v.points.clear();
+	v.connectivity.clear();
+	v.offsets.clear();
+	v.types.clear();
+
+	// Source (piece, local index) of each merged point and cell
+	let mut psources = Vec::new();
+	let mut csources = Vec::new();
+
+	let mut ids = HashMap::new();
+	for (ip, p) in pieces.iter().enumerate()
+	{
+		// Merged index of each local point
+		let mut map = Vec::with_capacity(p.npoints as usize);
+		for i in 0 .. p.npoints as usize
+		{
+			let next = psources.len();
+			let j = match &globals
+			{
+				Some(g) => *ids.entry(g[ip][i]).or_insert(next),
+				None => next,
+			};
+			if j == next
+			{
+				psources.push((ip, i));
+				v.points.extend_from_slice(&p.points[nc as usize * i
+					.. nc as usize * (i+1)]);
+			}
+			map.push(j as i64);
+		}
+
+		for i in 0 .. p.ncells as usize
+		{
+			v.connectivity.extend(p.cell_points(i).iter().map(|&q| map[q as usize]));
+			v.offsets.push(v.connectivity.len() as i64);
+			v.types.push(p.types[i]);
+			csources.push((ip, i));
+		}
+	}
+
+	v.npoints = psources.len() as u64;
+	v.ncells  = csources.len() as u64;
+
+	v.pdata = merge_arrays(&pieces, |p| &p.pdata, &psources);
+	v.cdata = merge_arrays(&pieces, |p| &p.cdata, &csources);
+	v.pattributes = keep_attributes(&pieces[0].pattributes, &v.pdata);
+	v.cattributes = keep_attributes(&pieces[0].cattributes, &v.cdata);
+
+	return v;
+}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::vtk;
+
+	#[test]
+	fn test_split_strip()
+	{
+		let v = vtk::load("./data/icosahedron-binary.vtu");
+		let pieces = v.split(3, 1);
+		assert_eq!(pieces.len(), 3);
+
+		let mut cells = Vec::new();
+		for p in &pieces
+		{
+			let g = p.ghost_cells().unwrap();
+			assert!(g.contains(&DUPLICATECELL));
+			assert_eq!(p.ghost_points().unwrap().len(), p.npoints as usize);
+
+			let s = p.strip_ghosts();
+			assert!(s.ghost_cells().is_none());
+			assert_eq!(s.global_cell_ids().unwrap().len(), s.ncells as usize);
+			cells.extend(s.global_cell_ids().unwrap());
+		}
+
+		// Owned cells cover the grid exactly once
+		cells.sort();
+		assert_eq!(cells, (0 .. 20).collect::<Vec<i64>>());
+	}
+
+	#[test]
+	#[should_panic(expected = "Cannot split into zero pieces")]
+	fn test_split_zero()
+	{
+		vtk::load("./data/icosahedron-binary.vtu").split(0, 1);
+	}
+
+	#[test]
+	fn test_strip_hidden()
+	{
+		let mut v = vtk::load("./data/icosahedron-binary.vtu");
+		let mut gc = vec![0; v.ncells as usize];
+		gc[0] = HIDDENCELL;
+		gc[1] = DUPLICATECELL;
+		let mut gp = vec![0; v.npoints as usize];
+		let p = v.cell_points(19)[0] as usize;
+		gp[p] = HIDDENPOINT;
+		v.cdata.push(DataArray::new(GHOST_TYPE, 1, Data::U8(gc)));
+		v.pdata.push(DataArray::new(GHOST_TYPE, 1, Data::U8(gp)));
+
+		// Cells using the hidden point go too
+		let nhidden = (0 .. v.ncells as usize)
+			.filter(|&i| i > 1 && v.cell_points(i).contains(&(p as i64))).count();
+		let s = v.strip_ghosts();
+		assert_eq!(s.ncells as usize, 20 - 2 - nhidden);
+		assert!(s.ghost_points().is_none());
+	}
+
+	#[test]
+	fn test_merge()
+	{
+		let v = vtk::load("./data/icosahedron-binary-pdata.vtu");
+		let m = merge(&v.split(3, 1));
+
+		assert_eq!(m.npoints, v.npoints);
+		assert_eq!(m.ncells , v.ncells );
+		assert!(m.validate().is_valid());
+		assert!(m.ghost_cells().is_none());
+
+		// Values follow their points
+		let ids = m.global_point_ids().unwrap();
+		let r = m.point_array("Resultz").unwrap().data.to_f64();
+		let r0 = v.point_array("Resultz").unwrap().data.to_f64();
+		for i in 0 .. ids.len()
+		{
+			assert_eq!(r[i], r0[ids[i] as usize]);
+		}
+		assert_eq!(m.point_attribute(Attribute::Scalars), Some("Resultz"));
+	}
+
+	#[test]
+	fn test_no_ghosts()
+	{
+		let v = vtk::load("./data/icosahedron-binary.vtu");
+		assert!(v.ghost_cells().is_none());
+		assert!(v.global_point_ids().is_none());
+
+		let s = v.strip_ghosts();
+		assert_eq!(s.ncells, v.ncells);
+		assert_eq!(s.npoints, v.npoints);
+	}
+}
+
+//==============================================================================
+
@@ -0,0 +1,458 @@
+//==============================================================================
+
+// glTF 2.0 export of the boundary surface, as one triangle mesh with
+// positions, smooth normals, and indices.  A .glb file holds the JSON and the
+// buffer in binary chunks, and a .gltf file embeds the buffer as a base64 data
+// URI.  Vertices may be coloured from a point array through a colormap
+
+// Standard
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+
+//********
+
+// Third party
+use log::{info, warn};
+use serde_json::{json, Value};
+
+//********
+
+// This lib
+use crate::base64::{self, Scalar};
+use crate::cells;
+use crate::vtk::{self, Attribute, VtkFile};
+
+//********
+
+pub const GLTF_EXT: &str = ".gltf";
+pub const GLB_EXT : &str = ".glb";
+
+const GENERATOR: &str = "vtk-io-rs";
+
+// GLB header and chunk types, "glTF", "JSON", and "BIN\0" as little endian
+const GLB_MAGIC  : u32 = 0x46546c67;
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON : u32 = 0x4e4f534a;
+const CHUNK_BIN  : u32 = 0x004e4942;
+
+// Accessor component types and buffer view targets
+const FLOAT       : u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER        : u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+const TRIANGLES: u32 = 4;
+
+pub const VIRIDIS     : &str = "viridis";
+pub const COOL_TO_WARM: &str = "cool-to-warm";
+
+// sRGB control points, evenly spaced from the low end to the high end
+const VIRIDIS_RGB: &[[u8; 3]] = &[
+	[ 68,   1,  84], [ 72,  40, 120], [ 62,  74, 137], [ 49, 104, 142],
+	[ 38, 130, 142], [ 31, 158, 137], [ 53, 183, 121], [110, 206,  88],
+	[253, 231,  37]];
+
+// Moreland's diverging map, as ParaView's "Cool to Warm"
+const COOL_TO_WARM_RGB: &[[u8; 3]] = &[
+	[ 59,  76, 192], [ 98, 130, 234], [141, 176, 254], [184, 208, 249],
+	[221, 221, 221], [245, 196, 173], [244, 154, 123], [222,  96,  77],
+	[180,   4,  38]];
+
+pub const COLORMAPS: &[(&str, &[[u8; 3]])] = &[
+	(VIRIDIS     , VIRIDIS_RGB),
+	(COOL_TO_WARM, COOL_TO_WARM_RGB),
+];
+
+//==============================================================================
+
+// Vertex colours from a point array.  Vectors are coloured by magnitude, and
+// the range is the array's own range if None
+#[derive(Debug, Clone)]
+pub struct Coloring
+{
+	pub array: String,
+	pub colormap: String,
+	pub range: Option<(f64, f64)>,
+}
+
+//********
+
+fn linear(c: f64) -> f32
+{
+	// glTF vertex colours are linear, while colormaps are given in sRGB
+	let c = if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) };
+	return c as f32;
+}
+
+//********
+
+fn map_color(table: &[[u8; 3]], t: f64) -> [f32; 3]
+{
+	// Interpolate the control points at t in [0, 1], clamped
+
+	let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+	let x = t * (table.len() - 1) as f64;
+	let i = (x.floor() as usize).min(table.len() - 2);
+	let w = x - i as f64;
+
+	let mut rgb = [0.0; 3];
+	for c in 0 .. 3
+	{
+		let lo = table[i  ][c] as f64 / 255.0;
+		let hi = table[i+1][c] as f64 / 255.0;
+		rgb[c] = linear(lo + w * (hi - lo));
+	}
+	return rgb;
+}
+
+//********
+
+fn accessor(view: usize, count: usize, ctype: u32, atype: &str) -> Value
+{
+	return json!({
+		"bufferView": view,
+		"componentType": ctype,
+		"count": count,
+		"type": atype,
+	});
+}
+
+//==============================================================================
+
+impl VtkFile
+{
+fn gltf_buffer(&self, coloring: Option<&Coloring>)
+	-> Result<(Value, Vec<u8>), io::Error>
+{
+	// The glTF JSON, without the buffer URI, and the binary buffer.  Only the
+	// points of the surface triangles are kept
+
+	let s = self.surface();
+	let nother = s.types.iter().filter(|&&t| cells::dimension(t) != Some(2)).count();
+	if nother > 0
+	{
+		warn!("dropping {} vertices and lines, which glTF export skips", nother);
+	}
+	let t = s.triangulate();
+	if t.ncells == 0
+	{
+		return Err(io::Error::new(io::ErrorKind::InvalidData,
+			"there are no surface triangles"));
+	}
+
+	// Vertices in order of first use
+	let mut ids: Vec<usize> = Vec::new();
+	let mut index: HashMap<i64, u32> = HashMap::new();
+	let indices: Vec<u32> = t.connectivity.iter().map(|&p|
+	{
+		*index.entry(p).or_insert_with(||
+		{
+			ids.push(p as usize);
+			(ids.len() - 1) as u32
+		})
+	}).collect();
+
+	let nc = t.ncomponents as usize;
+	let positions: Vec<[f32; 3]> = ids.iter().map(|&p|
+	{
+		let mut x = [0.0; 3];
+		for d in 0 .. nc.min(3)
+		{
+			x[d] = t.points[nc * p + d];
+		}
+		x
+	}).collect();
+
+	// Active point normals if there are any, otherwise the average of the
+	// facet normals around each vertex
+	let mut normals: Vec<[f32; 3]> = match t.active_point_array(Attribute::Normals)
+	{
+		Some(a) if a.ncomponents == 3 =>
+		{
+			let n = a.data.to_f64();
+			ids.iter().map(|&p| [n[3*p] as f32, n[3*p+1] as f32, n[3*p+2] as f32])
+				.collect()
+		},
+		_ =>
+		{
+			let mut sums = vec![[0.0f32; 3]; ids.len()];
+			for (i, n) in t.facet_normals().iter().enumerate()
+			{
+				for &v in &indices[3*i .. 3*i+3]
+				{
+					for c in 0 .. 3
+					{
+						sums[v as usize][c] += n[c];
+					}
+				}
+			}
+			sums
+		},
+	};
+	for n in normals.iter_mut()
+	{
+		let len = (n[0]*n[0] + n[1]*n[1] + n[2]*n[2]).sqrt();
+		*n = if len > 0.0
+		{
+			[n[0] / len, n[1] / len, n[2] / len]
+		}
+		else
+		{
+			[0.0, 0.0, 1.0]
+		};
+	}
+
+	let colors: Option<Vec<[f32; 3]>> = coloring.map(|c|
+	{
+		let a = t.point_array(&c.array).unwrap_or_else(||
+			panic!("Cannot color by \"{}\".  There is no point array of that \
+			name", c.array));
+		let table = COLORMAPS.iter().find(|m| m.0 == c.colormap)
+			.unwrap_or_else(|| panic!("Cannot color with unknown colormap \"{}\"",
+			c.colormap)).1;
+		let (lo, hi) = c.range.or_else(|| a.range()).unwrap_or((0.0, 1.0));
+
+		let na = std::cmp::max(a.ncomponents as usize, 1);
+		let values = a.data.to_f64();
+		ids.iter().map(|&p|
+		{
+			let tuple = &values[na * p .. na * (p + 1)];
+			let x = if na == 1
+			{
+				tuple[0]
+			}
+			else
+			{
+				tuple.iter().map(|x| x * x).sum::<f64>().sqrt()
+			};
+			map_color(table, if hi > lo { (x - lo) / (hi - lo) } else { 0.5 })
+		}).collect()
+	});
+
+	// Buffer views in the order positions, normals, indices, and colours,
+	// with one accessor each.  All of them are 4-byte aligned
+	let mut bin = Vec::new();
+	let mut views = Vec::new();
+	let mut push = |bin: &mut Vec<u8>, bytes: Vec<u8>, target: u32|
+	{
+		views.push(json!({
+			"buffer": 0,
+			"byteOffset": bin.len(),
+			"byteLength": bytes.len(),
+			"target": target,
+		}));
+		bin.extend(bytes);
+	};
+	let floats = |v: &[[f32; 3]]| -> Vec<u8>
+	{
+		v.iter().flatten().flat_map(|x| x.to_bytes(base64::LITTLE_ENDIAN)).collect()
+	};
+
+	push(&mut bin, floats(&positions), ARRAY_BUFFER);
+	push(&mut bin, floats(&normals), ARRAY_BUFFER);
+	push(&mut bin, indices.iter().flat_map(|i| i.to_bytes(base64::LITTLE_ENDIAN))
+		.collect(), ELEMENT_ARRAY_BUFFER);
+	if let Some(colors) = &colors
+	{
+		push(&mut bin, floats(colors), ARRAY_BUFFER);
+	}
+
+	let mut min = [f32::INFINITY; 3];
+	let mut max = [f32::NEG_INFINITY; 3];
+	for x in &positions
+	{
+		for c in 0 .. 3
+		{
+			min[c] = min[c].min(x[c]);
+			max[c] = max[c].max(x[c]);
+		}
+	}
+
+	let mut position = accessor(0, positions.len(), FLOAT, "VEC3");
+	position["min"] = json!(min);
+	position["max"] = json!(max);
+	let mut accessors = vec![position,
+		accessor(1, normals.len(), FLOAT, "VEC3"),
+		accessor(2, indices.len(), UNSIGNED_INT, "SCALAR")];
+
+	let mut attributes = json!({"POSITION": 0, "NORMAL": 1});
+	if let Some(colors) = &colors
+	{
+		accessors.push(accessor(3, colors.len(), FLOAT, "VEC3"));
+		attributes["COLOR_0"] = json!(3);
+	}
+
+	// Surfaces of open meshes are seen from both sides
+	let gltf = json!({
+		"asset": {"version": "2.0", "generator": GENERATOR},
+		"scene": 0,
+		"scenes": [{"nodes": [0]}],
+		"nodes": [{"mesh": 0}],
+		"meshes": [{"primitives": [{
+			"attributes": attributes,
+			"indices": 2,
+			"material": 0,
+			"mode": TRIANGLES,
+		}]}],
+		"materials": [{
+			"pbrMetallicRoughness": {"metallicFactor": 0, "roughnessFactor": 1},
+			"doubleSided": true,
+		}],
+		"buffers": [{"byteLength": bin.len()}],
+		"bufferViews": views,
+		"accessors": accessors,
+	});
+
+	return Ok((gltf, bin));
+}
+
+//********
+
+pub fn write_gltf<W: Write>(&self, f: &mut W, binary: bool, coloring: Option<&Coloring>)
+	-> Result<(), io::Error>
+{
+	let (mut gltf, mut bin) = self.gltf_buffer(coloring)?;
+
+	if !binary
+	{
+		let uri = format!("data:application/octet-stream;base64,{}",
+			base64::encode_u8_raw(&bin));
+		gltf["buffers"][0]["uri"] = json!(uri);
+		serde_json::to_writer(&mut *f, &gltf)?;
+		writeln!(f)?;
+		return Ok(());
+	}
+
+	// Both chunks are padded to 4 bytes, JSON with spaces and BIN with zeros
+	let mut json = serde_json::to_vec(&gltf)?;
+	while json.len() % 4 != 0
+	{
+		json.push(b' ');
+	}
+	while bin.len() % 4 != 0
+	{
+		bin.push(0);
+	}
+
+	let len = 12 + 8 + json.len() + 8 + bin.len();
+	for x in &[GLB_MAGIC, GLB_VERSION, len as u32, json.len() as u32, CHUNK_JSON]
+	{
+		f.write_all(&x.to_bytes(base64::LITTLE_ENDIAN))?;
+	}
+	f.write_all(&json)?;
+	for x in &[bin.len() as u32, CHUNK_BIN]
+	{
+		f.write_all(&x.to_bytes(base64::LITTLE_ENDIAN))?;
+	}
+	f.write_all(&bin)?;
+	return Ok(());
+}
+
+//********
+
+pub fn export_gltf(&self, file: &str, binary: bool, coloring: Option<&Coloring>)
+{
+	info!("Exporting glTF file \"{}\"", file);
+
+	let try_export = || -> Result<(), io::Error>
+	{
+		if file == vtk::STDIO
+		{
+			let stdout = io::stdout();
+			let mut f = stdout.lock();
+			return self.write_gltf(&mut f, binary, coloring);
+		}
+		let mut f = BufWriter::new(File::create(file)?);
+		self.write_gltf(&mut f, binary, coloring)?;
+		f.flush()
+	};
+
+	if let Err(e) = try_export()
+	{
+		panic!("Cannot export glTF file \"{}\":  {}", file, e);
+	}
+}}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::vtk;
+
+	fn u32_at(b: &[u8], i: usize) -> u32
+	{
+		return u32::from_bytes(&b[i .. i+4], base64::LITTLE_ENDIAN);
+	}
+
+	#[test]
+	fn test_glb()
+	{
+		let v = vtk::load("./data/icosahedron-binary-pdata.vtu");
+		let coloring = Coloring
+		{
+			array: "Resultz".to_string(),
+			colormap: VIRIDIS.to_string(),
+			range: None,
+		};
+
+		let mut glb = Vec::new();
+		v.write_gltf(&mut glb, true, Some(&coloring)).unwrap();
+		assert_eq!(u32_at(&glb, 0), GLB_MAGIC);
+		assert_eq!(u32_at(&glb, 8) as usize, glb.len());
+		assert_eq!(u32_at(&glb, 16), CHUNK_JSON);
+
+		let njson = u32_at(&glb, 12) as usize;
+		let gltf: Value = serde_json::from_slice(&glb[20 .. 20 + njson]).unwrap();
+		let accessors = &gltf["accessors"];
+		assert_eq!(accessors[0]["count"], 12);
+		assert_eq!(accessors[2]["count"], 60);
+		assert_eq!(accessors[0]["max"][2], json!(1.0));
+		assert_eq!(gltf["meshes"][0]["primitives"][0]["attributes"]["COLOR_0"], 3);
+
+		let bin = &glb[20 + njson + 8 ..];
+		assert_eq!(u32_at(&glb, 20 + njson + 4), CHUNK_BIN);
+
+		// Normals of a sphere point away from the center
+		let normals = gltf["bufferViews"][1]["byteOffset"].as_u64().unwrap() as usize;
+		for i in 0 .. 12
+		{
+			let x = |b: usize, c: usize| f32::from_bytes(&bin[b + 12*i + 4*c ..],
+				base64::LITTLE_ENDIAN);
+			let dot: f32 = (0 .. 3).map(|c| x(0, c) * x(normals, c)).sum();
+			assert!(dot > 0.9);
+		}
+
+		// The lowest and highest values get the ends of the colormap
+		let colors = gltf["bufferViews"][3]["byteOffset"].as_u64().unwrap() as usize;
+		let rgb: Vec<[f32; 3]> = (0 .. 12).map(|i| [0, 1, 2].map(|c|
+			f32::from_bytes(&bin[colors + 12*i + 4*c ..], base64::LITTLE_ENDIAN)))
+			.collect();
+		assert!(rgb.contains(&map_color(VIRIDIS_RGB, 0.0)));
+		assert!(rgb.contains(&map_color(VIRIDIS_RGB, 1.0)));
+	}
+
+	#[test]
+	fn test_gltf()
+	{
+		let v = vtk::load("./data/icosahedron-binary-pdata.vtu");
+		let mut buf = Vec::new();
+		v.write_gltf(&mut buf, false, None).unwrap();
+		let gltf: Value = serde_json::from_slice(&buf).unwrap();
+		let uri = gltf["buffers"][0]["uri"].as_str().unwrap();
+		assert!(uri.starts_with("data:application/octet-stream;base64,"));
+		assert!(gltf["meshes"][0]["primitives"][0]["attributes"]["COLOR_0"].is_null());
+		assert_eq!(gltf["accessors"].as_array().unwrap().len(), 3);
+
+		// Cool to warm is gray in the middle
+		let mid = map_color(COOL_TO_WARM_RGB, 0.5);
+		assert_eq!(mid[0], mid[1]);
+		assert_eq!(mid[1], mid[2]);
+	}
+}
+
+//==============================================================================
+
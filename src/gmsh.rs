@@ -0,0 +1,1018 @@
+
+//==============================================================================
+
+// Gmsh .msh IO.  Format 2.2 and 4.1 are read in ASCII or binary, and 4.1 is
+// written.  Physical and elementary (geometrical) tags become cell arrays
+
+// Standard
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Read, Write};
+
+//********
+
+// Third party
+use log::{info, warn};
+
+//********
+
+// This lib
+use crate::base64::{self, Scalar};
+use crate::cells;
+use crate::vtk::{Data, DataArray, VtkFile};
+
+//********
+
+pub const EXT: &str = ".msh";
+
+// Cell arrays of the physical group and elementary entity of each element
+pub const PHYSICAL: &str = "gmsh:physical";
+pub const ENTITY  : &str = "gmsh:geometrical";
+
+const MESH_FORMAT: &str = "MeshFormat";
+const PHYS_NAMES : &str = "PhysicalNames";
+const ENTITIES   : &str = "Entities";
+const NODES      : &str = "Nodes";
+const ELEMENTS   : &str = "Elements";
+
+const ASCII: &str = "ascii";
+
+// Gmsh element types with their VTK cell types, and the Gmsh node of each VTK
+// node where the orderings differ
+const TYPES: &[(u32, u8, &[usize])] = &[
+	(15, cells::VTK_VERTEX    , &[]),
+	( 1, cells::VTK_LINE      , &[]),
+	( 2, cells::VTK_TRIANGLE  , &[]),
+	( 3, cells::VTK_QUAD      , &[]),
+	( 4, cells::VTK_TETRA     , &[]),
+	( 5, cells::VTK_HEXAHEDRON, &[]),
+	( 6, cells::VTK_WEDGE     , &[]),
+	( 7, cells::VTK_PYRAMID   , &[]),
+	( 8, cells::VTK_QUADRATIC_EDGE    , &[]),
+	( 9, cells::VTK_QUADRATIC_TRIANGLE, &[]),
+	(16, cells::VTK_QUADRATIC_QUAD    , &[]),
+	(10, cells::VTK_BIQUADRATIC_QUAD  , &[]),
+	(11, cells::VTK_QUADRATIC_TETRA   , &[0, 1, 2, 3, 4, 5, 6, 7, 9, 8]),
+	(17, cells::VTK_QUADRATIC_HEXAHEDRON,
+		&[0, 1, 2, 3, 4, 5, 6, 7, 8, 11, 13, 9, 16, 18, 19, 17, 10, 12, 14, 15]),
+	(12, cells::VTK_TRIQUADRATIC_HEXAHEDRON,
+		&[0, 1, 2, 3, 4, 5, 6, 7, 8, 11, 13, 9, 16, 18, 19, 17, 10, 12, 14, 15,
+		22, 23, 21, 24, 20, 25, 26]),
+	(18, cells::VTK_QUADRATIC_WEDGE,
+		&[0, 1, 2, 3, 4, 5, 6, 9, 7, 12, 14, 13, 8, 10, 11]),
+	(13, cells::VTK_BIQUADRATIC_QUADRATIC_WEDGE,
+		&[0, 1, 2, 3, 4, 5, 6, 9, 7, 12, 14, 13, 8, 10, 11, 15, 17, 16]),
+	(19, cells::VTK_QUADRATIC_PYRAMID,
+		&[0, 1, 2, 3, 4, 5, 8, 10, 6, 7, 9, 11, 12]),
+];
+
+//==============================================================================
+
+// Cursor over the sections of an ASCII or binary file.  Counts and tags are
+// ints or size_t (u64) in binary, which are all words in ASCII
+struct Cursor<'a>
+{
+	bytes : &'a [u8],
+	pos   : usize,
+	binary: bool,
+	endianness: u8,
+	errstr: &'a str,
+}
+
+impl Cursor<'_>
+{
+	fn line(&mut self) -> Option<String>
+	{
+		if self.pos >= self.bytes.len()
+		{
+			return None;
+		}
+		let end = self.bytes[self.pos ..].iter().position(|&b| b == b'\n')
+			.map_or(self.bytes.len(), |i| self.pos + i);
+		let line = String::from_utf8_lossy(&self.bytes[self.pos .. end]);
+		self.pos = end + 1;
+		return Some(line.trim().to_string());
+	}
+
+	fn word(&mut self) -> String
+	{
+		while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace()
+		{
+			self.pos += 1;
+		}
+		let begin = self.pos;
+		while self.pos < self.bytes.len() && !self.bytes[self.pos].is_ascii_whitespace()
+		{
+			self.pos += 1;
+		}
+		if begin == self.pos
+		{
+			panic!("{}:  unexpected end of file", self.errstr);
+		}
+		return String::from_utf8_lossy(&self.bytes[begin .. self.pos]).to_string();
+	}
+
+	fn parse<T: std::str::FromStr>(&mut self) -> T
+	{
+		let word = self.word();
+		return word.parse().unwrap_or_else(|_| panic!("{}:  bad number \"{}\"",
+			self.errstr, word));
+	}
+
+	fn binary<T: Scalar>(&mut self) -> T
+	{
+		if self.pos + T::NBYTES > self.bytes.len()
+		{
+			panic!("{}:  unexpected end of file", self.errstr);
+		}
+		let x = T::from_bytes(&self.bytes[self.pos ..], self.endianness);
+		self.pos += T::NBYTES;
+		return x;
+	}
+
+	fn int(&mut self) -> i64
+	{
+		if self.binary { self.binary::<i32>() as i64 } else { self.parse() }
+	}
+
+	fn size(&mut self) -> u64
+	{
+		if self.binary { self.binary::<u64>() } else { self.parse() }
+	}
+
+	fn float(&mut self) -> f64
+	{
+		if self.binary { self.binary::<f64>() } else { self.parse() }
+	}
+
+	fn skip_section(&mut self, name: &str)
+	{
+		// Search for the end, since binary data may contain line breaks
+		let end = format!("$End{}", name);
+		match self.bytes[self.pos ..].windows(end.len())
+			.position(|w| w == end.as_bytes())
+		{
+			Some(i) => self.pos += i,
+			None => panic!("{}:  missing {}", self.errstr, end),
+		}
+		self.line();
+	}
+
+	fn end_section(&mut self, name: &str)
+	{
+		let end = format!("$End{}", name);
+		while let Some(line) = self.line()
+		{
+			if line == end
+			{
+				return;
+			}
+			if !line.is_empty()
+			{
+				panic!("{}:  expected {}, found \"{}\"", self.errstr, end, line);
+			}
+		}
+		panic!("{}:  missing {}", self.errstr, end);
+	}
+}
+
+//==============================================================================
+
+// Mesh being read, with node tags and element data before conversion
+#[derive(Default)]
+struct Mesh
+{
+	node_ids: HashMap<u64, i64>,
+	points  : Vec<f32>,
+
+	connectivity: Vec<i64>,
+	offsets     : Vec<i64>,
+	types       : Vec<u8>,
+	physical    : Vec<i32>,
+	entity      : Vec<i32>,
+
+	nskipped: usize,
+}
+
+impl Mesh
+{
+	fn add_node(&mut self, tag: u64, x: [f64; 3])
+	{
+		let id = self.points.len() as i64 / 3;
+		self.node_ids.insert(tag, id);
+		self.points.extend(x.iter().map(|&x| x as f32));
+	}
+
+	fn add_element(&mut self, gtype: u32, nodes: &[u64], physical: i32,
+		entity: i32, errstr: &str)
+	{
+		let (_, ctype, perm) = match TYPES.iter().find(|t| t.0 == gtype)
+		{
+			Some(t) => t,
+			None =>
+			{
+				self.nskipped += 1;
+				return;
+			},
+		};
+
+		let id = |tag: u64| *self.node_ids.get(&tag).unwrap_or_else(||
+			panic!("{}:  unknown node {}", errstr, tag));
+		if perm.is_empty()
+		{
+			let conn: Vec<i64> = nodes.iter().map(|&t| id(t)).collect();
+			self.connectivity.extend(conn);
+		}
+		else
+		{
+			let conn: Vec<i64> = perm.iter().map(|&j| id(nodes[j])).collect();
+			self.connectivity.extend(conn);
+		}
+		self.offsets.push(self.connectivity.len() as i64);
+		self.types.push(*ctype);
+		self.physical.push(physical);
+		self.entity.push(entity);
+	}
+}
+
+//********
+
+fn npoints(gtype: u32, errstr: &str) -> usize
+{
+	return TYPES.iter().find(|t| t.0 == gtype)
+		.and_then(|t| cells::npoints_fixed(t.1))
+		.unwrap_or_else(|| panic!("{}:  unsupported element type {} in binary \
+			data", errstr, gtype));
+}
+
+//********
+
+fn read_nodes_v2(c: &mut Cursor, mesh: &mut Mesh)
+{
+	// The count is ASCII, even in binary files
+	let n: usize = c.parse();
+	c.line();
+	for _ in 0 .. n
+	{
+		let tag = c.int() as u64;
+		let x = [c.float(), c.float(), c.float()];
+		mesh.add_node(tag, x);
+	}
+}
+
+//********
+
+fn read_elements_v2(c: &mut Cursor, mesh: &mut Mesh)
+{
+	// Tags are the physical group, then the elementary entity, then others
+	let n: usize = c.parse();
+	c.line();
+
+	let mut read = 0;
+	while read < n
+	{
+		if c.binary
+		{
+			// Blocks of elements of one type with the same number of tags
+			let gtype = c.int() as u32;
+			let nblock = c.int() as usize;
+			let ntags = c.int() as usize;
+			let npts = npoints(gtype, c.errstr);
+			for _ in 0 .. nblock
+			{
+				c.int();
+				let tags: Vec<i64> = (0 .. ntags).map(|_| c.int()).collect();
+				let nodes: Vec<u64> = (0 .. npts).map(|_| c.int() as u64).collect();
+				mesh.add_element(gtype, &nodes, *tags.first().unwrap_or(&0) as i32,
+					*tags.get(1).unwrap_or(&0) as i32, c.errstr);
+			}
+			read += nblock;
+		}
+		else
+		{
+			let line = c.line().unwrap_or_else(|| panic!("{}:  missing \
+				elements", c.errstr));
+			let words: Vec<i64> = line.split_whitespace().map(|w| w.parse()
+				.unwrap_or_else(|_| panic!("{}:  bad number \"{}\"", c.errstr, w)))
+				.collect();
+			if words.is_empty()
+			{
+				continue;
+			}
+			if words.len() < 3 || words.len() < 3 + words[2] as usize
+			{
+				panic!("{}:  bad element \"{}\"", c.errstr, line);
+			}
+			let ntags = words[2] as usize;
+			let tags = &words[3 .. 3 + ntags];
+			let nodes: Vec<u64> = words[3 + ntags ..].iter().map(|&t| t as u64)
+				.collect();
+			mesh.add_element(words[1] as u32, &nodes, *tags.first().unwrap_or(&0)
+				as i32, *tags.get(1).unwrap_or(&0) as i32, c.errstr);
+			read += 1;
+		}
+	}
+}
+
+//********
+
+fn read_entities_v4(c: &mut Cursor) -> HashMap<(i64, i64), i32>
+{
+	// First physical tag of each entity by dimension and tag
+	let counts: Vec<u64> = (0 .. 4).map(|_| c.size()).collect();
+
+	let mut physical = HashMap::new();
+	for (dim, &n) in counts.iter().enumerate()
+	{
+		for _ in 0 .. n
+		{
+			let tag = c.int();
+
+			// Points have coordinates, and others a bounding box
+			let ncoords = if dim == 0 { 3 } else { 6 };
+			for _ in 0 .. ncoords
+			{
+				c.float();
+			}
+
+			let nphysical = c.size();
+			let tags: Vec<i64> = (0 .. nphysical).map(|_| c.int()).collect();
+			if let Some(&p) = tags.first()
+			{
+				physical.insert((dim as i64, tag), p as i32);
+			}
+
+			if dim > 0
+			{
+				let nbounding = c.size();
+				for _ in 0 .. nbounding
+				{
+					c.int();
+				}
+			}
+		}
+	}
+	return physical;
+}
+
+//********
+
+fn read_nodes_v4(c: &mut Cursor, mesh: &mut Mesh)
+{
+	// Blocks by entity, with all tags first and then all coordinates
+	let nblocks = c.size();
+	for _ in 0 .. 3
+	{
+		c.size();
+	}
+
+	for _ in 0 .. nblocks
+	{
+		let dim = c.int();
+		c.int();
+		let parametric = c.int() != 0;
+		let n = c.size() as usize;
+
+		let tags: Vec<u64> = (0 .. n).map(|_| c.size()).collect();
+		let nparams = if parametric { dim as usize } else { 0 };
+		for tag in tags
+		{
+			let x = [c.float(), c.float(), c.float()];
+			for _ in 0 .. nparams
+			{
+				c.float();
+			}
+			mesh.add_node(tag, x);
+		}
+	}
+}
+
+//********
+
+fn read_elements_v4(c: &mut Cursor, mesh: &mut Mesh,
+	physical: &HashMap<(i64, i64), i32>)
+{
+	let nblocks = c.size();
+	for _ in 0 .. 3
+	{
+		c.size();
+	}
+
+	for _ in 0 .. nblocks
+	{
+		let dim = c.int();
+		let entity = c.int();
+		let gtype = c.int() as u32;
+		let n = c.size() as usize;
+		let p = *physical.get(&(dim, entity)).unwrap_or(&0);
+
+		for _ in 0 .. n
+		{
+			let nodes: Vec<u64> = if c.binary
+			{
+				let npts = npoints(gtype, c.errstr);
+				c.size();
+				(0 .. npts).map(|_| c.size()).collect()
+			}
+			else
+			{
+				// Skip to the end of the block header or the previous element
+				let mut line = String::new();
+				while line.is_empty()
+				{
+					line = c.line().unwrap_or_else(|| panic!("{}:  missing \
+						elements", c.errstr));
+				}
+				line.split_whitespace().skip(1).map(|w| w.parse().unwrap_or_else(
+					|_| panic!("{}:  bad number \"{}\"", c.errstr, w))).collect()
+			};
+			mesh.add_element(gtype, &nodes, p, entity as i32, c.errstr);
+		}
+	}
+}
+
+//********
+
+fn read_physical_names(c: &mut Cursor)
+{
+	// Names are only reported, since arrays hold numbers
+	let n: usize = c.parse();
+	c.line();
+	for _ in 0 .. n
+	{
+		if let Some(line) = c.line()
+		{
+			info!("Gmsh physical group {}", line);
+		}
+	}
+}
+
+//********
+
+pub fn read<R: Read>(mut reader: R, name: &str) -> VtkFile
+{
+	let errstr = format!("Cannot read Gmsh file \"{}\"", name);
+	let mut bytes = Vec::new();
+	reader.read_to_end(&mut bytes).expect(&errstr);
+
+	let mut c = Cursor
+	{
+		bytes: &bytes,
+		pos: 0,
+		binary: false,
+		endianness: base64::LITTLE_ENDIAN,
+		errstr: &errstr,
+	};
+
+	let mut version = 0;
+	let mut mesh = Mesh::default();
+	let mut physical = HashMap::new();
+
+	while let Some(line) = c.line()
+	{
+		let section = match line.strip_prefix('$')
+		{
+			Some(s) => s.to_string(),
+			None if line.is_empty() => continue,
+			None => panic!("{}:  expected a section, found \"{}\"", errstr, line),
+		};
+
+		if section != MESH_FORMAT && version == 0
+		{
+			panic!("{}:  missing {}", errstr, MESH_FORMAT);
+		}
+
+		match section.as_str()
+		{
+			MESH_FORMAT =>
+			{
+				let v: f64 = c.parse();
+				let binary: i32 = c.parse();
+				let size: usize = c.parse();
+				c.line();
+
+				version = if (2.0 .. 3.0).contains(&v) { 2 }
+					else if (4.1 .. 5.0).contains(&v) { 4 }
+					else { panic!("{}:  unsupported version {}.  Use 2.2 or 4.1",
+						errstr, v) };
+				if binary != 0
+				{
+					if size != 8
+					{
+						panic!("{}:  unsupported data size {}", errstr, size);
+					}
+
+					// The int 1 tells the byte order
+					c.binary = true;
+					let one = &c.bytes[c.pos .. (c.pos + 4).min(c.bytes.len())];
+					if i32::from_bytes(one, base64::BIG_ENDIAN) == 1
+					{
+						c.endianness = base64::BIG_ENDIAN;
+					}
+					c.binary::<i32>();
+				}
+			},
+			PHYS_NAMES => read_physical_names(&mut c),
+			ENTITIES if version == 4 => physical = read_entities_v4(&mut c),
+			NODES if version == 2 => read_nodes_v2(&mut c, &mut mesh),
+			NODES => read_nodes_v4(&mut c, &mut mesh),
+			ELEMENTS if version == 2 => read_elements_v2(&mut c, &mut mesh),
+			ELEMENTS => read_elements_v4(&mut c, &mut mesh, &physical),
+			_ =>
+			{
+				c.skip_section(&section);
+				continue;
+			},
+		}
+		c.end_section(&section);
+	}
+
+	if mesh.nskipped > 0
+	{
+		warn!("skipping {} elements of unsupported types", mesh.nskipped);
+	}
+
+	let mut v = VtkFile::new();
+	v.vtype = "UnstructuredGrid".to_string();
+	v.ncomponents = 3;
+	v.endianness = c.endianness;
+	if !c.binary
+	{
+		v.format = ASCII.to_string();
+	}
+
+	v.npoints = mesh.points.len() as u64 / 3;
+	v.points = mesh.points;
+	v.ncells = mesh.types.len() as u64;
+	v.connectivity = mesh.connectivity;
+	v.offsets = mesh.offsets;
+	v.types = mesh.types;
+	v.cdata.push(DataArray::new(PHYSICAL, 1, Data::I32(mesh.physical)));
+	v.cdata.push(DataArray::new(ENTITY  , 1, Data::I32(mesh.entity  )));
+
+	return v;
+}
+
+//********
+
+pub fn load(file: &str) -> VtkFile
+{
+	info!("Loading Gmsh file \"{}\"", file);
+	let errstr = format!("Cannot load Gmsh file \"{}\"", file);
+	let f = File::open(file).expect(&errstr);
+	return read(io::BufReader::new(f), file);
+}
+
+//==============================================================================
+
+// Writer of numbers as words on ASCII lines, or as ints, size_t, and doubles
+// in binary
+struct Out<'a, W: Write>
+{
+	f: &'a mut W,
+	binary: bool,
+	endianness: u8,
+	first: bool,
+}
+
+impl<W: Write> Out<'_, W>
+{
+	fn word(&mut self, word: String, bytes: Vec<u8>) -> Result<(), io::Error>
+	{
+		if self.binary
+		{
+			return self.f.write_all(&bytes);
+		}
+		if !self.first
+		{
+			write!(self.f, " ")?;
+		}
+		self.first = false;
+		write!(self.f, "{}", word)
+	}
+
+	fn int(&mut self, x: i64) -> Result<(), io::Error>
+	{
+		let e = self.endianness;
+		self.word(x.to_string(), (x as i32).to_bytes(e))
+	}
+
+	fn size(&mut self, x: u64) -> Result<(), io::Error>
+	{
+		let e = self.endianness;
+		self.word(x.to_string(), x.to_bytes(e))
+	}
+
+	fn float(&mut self, x: f64) -> Result<(), io::Error>
+	{
+		let e = self.endianness;
+		self.word(x.to_string(), x.to_bytes(e))
+	}
+
+	fn end_line(&mut self) -> Result<(), io::Error>
+	{
+		self.first = true;
+		if self.binary { Ok(()) } else { writeln!(self.f) }
+	}
+
+	fn section(&mut self, name: &str) -> Result<(), io::Error>
+	{
+		writeln!(self.f, "${}", name)
+	}
+
+	fn end_section(&mut self, name: &str) -> Result<(), io::Error>
+	{
+		if self.binary
+		{
+			writeln!(self.f)?;
+		}
+		writeln!(self.f, "$End{}", name)
+	}
+}
+
+//********
+
+fn gmsh_type(ctype: u8) -> Option<(u32, Vec<usize>)>
+{
+	// Gmsh type and the VTK node of each Gmsh node.  Pixels and voxels are
+	// quads and hexahedra with swapped nodes
+	match ctype
+	{
+		cells::VTK_PIXEL => Some((3, vec![0, 1, 3, 2])),
+		cells::VTK_VOXEL => Some((5, vec![0, 1, 3, 2, 4, 5, 7, 6])),
+		_ =>
+		{
+			let (gtype, _, perm) = TYPES.iter().find(|t| t.1 == ctype)?;
+			let n = cells::npoints_fixed(ctype)?;
+			let mut inverse: Vec<usize> = (0 .. n).collect();
+			for (k, &j) in perm.iter().enumerate()
+			{
+				inverse[j] = k;
+			}
+			Some((*gtype, inverse))
+		},
+	}
+}
+
+//********
+
+impl VtkFile
+{
+pub fn write_gmsh<W: Write>(&self, f: &mut W, binary: bool) -> Result<(), io::Error>
+{
+	// Format 4.1, with an entity for each dimension and pair of physical and
+	// elementary tags of the cells.  All nodes go in one block
+
+	if self.types.iter().any(|&t| cells::is_higher_order(t))
+	{
+		return self.linearize().write_gmsh(f, binary);
+	}
+
+	let nc = self.ncomponents as usize;
+	let point = |p: usize| -> [f64; 3]
+	{
+		let mut x = [0.0; 3];
+		for d in 0 .. nc.min(3)
+		{
+			x[d] = self.points[nc * p + d] as f64;
+		}
+		x
+	};
+
+	let tags = |name: &str| self.cell_array(name).map(|a| a.data.to_i64())
+		.unwrap_or_else(|| vec![0; self.ncells as usize]);
+	let physical = tags(PHYSICAL);
+	let entity   = tags(ENTITY);
+
+	// Entities by dimension, physical, and elementary tag, with their element
+	// blocks by Gmsh type.  Entity tags are renumbered per dimension
+	let mut entities: BTreeMap<(usize, i64, i64), BTreeMap<u32, Vec<usize>>> =
+		BTreeMap::new();
+	let mut nskipped = 0;
+	for i in 0 .. self.ncells as usize
+	{
+		match (cells::dimension(self.types[i]), gmsh_type(self.types[i]))
+		{
+			(Some(dim), Some((gtype, _))) => entities
+				.entry((dim, physical[i], entity[i])).or_default()
+				.entry(gtype).or_default().push(i),
+			_ => nskipped += 1,
+		}
+	}
+	if nskipped > 0
+	{
+		warn!("skipping {} cells of types that Gmsh cannot hold, e.g. \
+			polygons", nskipped);
+	}
+
+	let mut tag_of = HashMap::new();
+	let mut counts = [0_u64; 4];
+	for &(dim, p, e) in entities.keys()
+	{
+		counts[dim] += 1;
+		tag_of.insert((dim, p, e), counts[dim] as i64);
+	}
+
+	let mut out = Out { f, binary, endianness: self.endianness, first: true };
+	let e = self.endianness;
+
+	out.section(MESH_FORMAT)?;
+	writeln!(out.f, "4.1 {} 8", if binary { 1 } else { 0 })?;
+	if binary
+	{
+		out.f.write_all(&1_i32.to_bytes(e))?;
+		writeln!(out.f)?;
+	}
+	writeln!(out.f, "$End{}", MESH_FORMAT)?;
+
+	// Entities with the bounding box of their cells
+	out.section(ENTITIES)?;
+	for &n in &counts
+	{
+		out.size(n)?;
+	}
+	out.end_line()?;
+	for (&(dim, p, en), blocks) in &entities
+	{
+		let mut lo = [f64::MAX; 3];
+		let mut hi = [f64::MIN; 3];
+		for &i in blocks.values().flatten()
+		{
+			for &q in self.cell_points(i)
+			{
+				let x = point(q as usize);
+				for d in 0 .. 3
+				{
+					lo[d] = lo[d].min(x[d]);
+					hi[d] = hi[d].max(x[d]);
+				}
+			}
+		}
+
+		out.int(tag_of[&(dim, p, en)])?;
+		let coords = if dim == 0 { lo.to_vec() } else { [lo, hi].concat() };
+		for x in coords
+		{
+			out.float(x)?;
+		}
+		if p != 0
+		{
+			out.size(1)?;
+			out.int(p)?;
+		}
+		else
+		{
+			out.size(0)?;
+		}
+		if dim > 0
+		{
+			out.size(0)?;
+		}
+		out.end_line()?;
+	}
+	out.end_section(ENTITIES)?;
+
+	// Nodes are numbered from 1, on the first entity of the highest dimension
+	let (top, _) = counts.iter().enumerate().rev().find(|(_, &n)| n > 0)
+		.unwrap_or((0, &0));
+	out.section(NODES)?;
+	let nblocks = if self.npoints > 0 { 1 } else { 0 };
+	for x in [nblocks, self.npoints, 1.min(self.npoints), self.npoints]
+	{
+		out.size(x)?;
+	}
+	out.end_line()?;
+	if self.npoints > 0
+	{
+		out.int(top as i64)?;
+		out.int(1)?;
+		out.int(0)?;
+		out.size(self.npoints)?;
+		out.end_line()?;
+		for p in 1 ..= self.npoints
+		{
+			out.size(p)?;
+			out.end_line()?;
+		}
+		for p in 0 .. self.npoints as usize
+		{
+			for x in point(p)
+			{
+				out.float(x)?;
+			}
+			out.end_line()?;
+		}
+	}
+	out.end_section(NODES)?;
+
+	// Elements are numbered from 1 in block order
+	let nblocks: usize = entities.values().map(|b| b.len()).sum();
+	let nelements: usize = entities.values().flat_map(|b| b.values())
+		.map(|c| c.len()).sum();
+	out.section(ELEMENTS)?;
+	for x in [nblocks, nelements, 1.min(nelements), nelements]
+	{
+		out.size(x as u64)?;
+	}
+	out.end_line()?;
+
+	let mut id = 0;
+	for (&(dim, p, en), blocks) in &entities
+	{
+		for (&gtype, block) in blocks
+		{
+			out.int(dim as i64)?;
+			out.int(tag_of[&(dim, p, en)])?;
+			out.int(gtype as i64)?;
+			out.size(block.len() as u64)?;
+			out.end_line()?;
+			for &i in block
+			{
+				id += 1;
+				out.size(id)?;
+				let (_, order) = gmsh_type(self.types[i]).unwrap_or_default();
+				let conn = self.cell_points(i);
+				for &k in &order
+				{
+					out.size(conn[k] as u64 + 1)?;
+				}
+				out.end_line()?;
+			}
+		}
+	}
+	out.end_section(ELEMENTS)?;
+
+	return Ok(());
+}
+
+//********
+
+pub fn export_gmsh(&self, file: &str)
+{
+	info!("Exporting Gmsh file \"{}\"", file);
+
+	let try_export = || -> Result<(), io::Error>
+	{
+		let mut f = BufWriter::new(File::create(file)?);
+		self.write_gmsh(&mut f, self.format != ASCII)?;
+		f.flush()
+	};
+
+	if let Err(e) = try_export()
+	{
+		panic!("Cannot export Gmsh file \"{}\":  {}", file, e);
+	}
+}}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	// Two tets and a boundary triangle, with a physical group for each
+	// dimension
+	const V2: &str = "\
+$MeshFormat
+2.2 0 8
+$EndMeshFormat
+$PhysicalNames
+2
+2 1 \"wall\"
+3 2 \"fluid\"
+$EndPhysicalNames
+$Nodes
+5
+1 0 0 0
+2 1 0 0
+3 0 1 0
+4 0 0 1
+5 1 1 1
+$EndNodes
+$Elements
+3
+1 2 2 1 10 1 2 3
+2 4 2 2 20 1 2 3 4
+3 4 2 2 20 2 3 4 5
+$EndElements
+";
+
+	const V4: &str = "\
+$MeshFormat
+4.1 0 8
+$EndMeshFormat
+$Entities
+0 0 1 1
+10 0 0 0 1 1 0 1 1 0
+20 0 0 0 1 1 1 1 2 0
+$EndEntities
+$Nodes
+2 5 1 5
+2 10 0 3
+1
+2
+3
+0 0 0
+1 0 0
+0 1 0
+3 20 0 2
+4
+5
+0 0 1
+1 1 1
+$EndNodes
+$Elements
+2 3 1 3
+2 10 2 1
+1 1 2 3
+3 20 4 2
+2 1 2 3 4
+3 2 3 4 5
+$EndElements
+";
+
+	fn check(v: &VtkFile)
+	{
+		assert_eq!(v.npoints, 5);
+		assert_eq!(v.types, vec![cells::VTK_TRIANGLE, cells::VTK_TETRA,
+			cells::VTK_TETRA]);
+		assert_eq!(v.connectivity, vec![0, 1, 2,  0, 1, 2, 3,  1, 2, 3, 4]);
+		assert_eq!(v.cell_array(PHYSICAL).unwrap().data, Data::I32(vec![1, 2, 2]));
+	}
+
+	#[test]
+	fn test_gmsh()
+	{
+		let v2 = read(V2.as_bytes(), "v2");
+		let v4 = read(V4.as_bytes(), "v4");
+		check(&v2);
+		check(&v4);
+		assert_eq!(v2.points, v4.points);
+		assert_eq!(v2.cell_array(ENTITY).unwrap().data, Data::I32(vec![10, 20,
+			20]));
+
+		// Entities are renumbered on export
+		for &binary in &[false, true]
+		{
+			let mut buf = Vec::new();
+			v2.write_gmsh(&mut buf, binary).unwrap();
+			let w = read(&buf[..], "buffer");
+			check(&w);
+			assert_eq!(w.points, v2.points);
+			assert_eq!(w.cell_array(ENTITY).unwrap().data, Data::I32(vec![1, 1,
+				1]));
+		}
+	}
+
+	#[test]
+	fn test_gmsh_v2_binary()
+	{
+		// The V2 mesh in big endian, with one block per element type
+		let e = base64::BIG_ENDIAN;
+		let ints = |x: &[i32]| -> Vec<u8> { x.iter().flat_map(|i| i.to_bytes(e))
+			.collect() };
+
+		let mut b = b"$MeshFormat\n2.2 1 8\n".to_vec();
+		b.extend(ints(&[1]));
+		b.extend(b"\n$EndMeshFormat\n$Nodes\n5\n");
+		let v2 = read(V2.as_bytes(), "v2");
+		for (p, x) in v2.points.chunks_exact(3).enumerate()
+		{
+			b.extend(ints(&[p as i32 + 1]));
+			b.extend(x.iter().flat_map(|&x| (x as f64).to_bytes(e)));
+		}
+		b.extend(b"\n$EndNodes\n$Elements\n3\n");
+		b.extend(ints(&[2, 1, 2,  1, 1, 10, 1, 2, 3]));
+		b.extend(ints(&[4, 2, 2,  2, 2, 20, 1, 2, 3, 4,  3, 2, 20, 2, 3, 4, 5]));
+		b.extend(b"\n$EndElements\n");
+
+		let v = read(&b[..], "binary");
+		check(&v);
+		assert_eq!(v.endianness, e);
+		assert_eq!(v.cdata, v2.cdata);
+	}
+
+	#[test]
+	fn test_gmsh_order()
+	{
+		// A 10-node tet swaps its last 2 nodes, both ways
+		let mut v = VtkFile::new();
+		v.npoints = 10;
+		v.points = vec![0.0; 30];
+		v.connectivity = (0 .. 10).collect();
+		v.offsets = vec![10];
+		v.types = vec![cells::VTK_QUADRATIC_TETRA];
+		v.ncells = 1;
+
+		let mut buf = Vec::new();
+		v.write_gmsh(&mut buf, false).unwrap();
+		let text = String::from_utf8(buf.clone()).unwrap();
+		assert!(text.contains("\n1 1 2 3 4 5 6 7 8 10 9\n"));
+		assert_eq!(read(&buf[..], "buffer").connectivity, v.connectivity);
+	}
+}
+
+//==============================================================================
+
@@ -0,0 +1,409 @@
+
+//==============================================================================
+
+// Summary of a VTK file for the info subcommand:  header attributes, counts,
+// cell types, bounds, and arrays
+
+// Standard
+use std::fmt::Write;
+
+//********
+
+// Third party
+use ansi_term::Colour;
+use serde_json::{json, Value};
+
+//********
+
+// This lib
+use crate::cells;
+use crate::probe;
+use crate::vtk::{self, Data, DataArray, VtkFile};
+
+//==============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArraySummary
+{
+	// "Points", "PointData", or "CellData"
+	pub section    : &'static str,
+
+	pub name       : String,
+	pub dtype      : String,
+	pub ncomponents: u64,
+
+	// Of the values, or of the magnitudes for multiple components
+	pub range      : Option<(f64, f64)>,
+
+	// Memory size in bytes
+	pub nbytes     : usize,
+}
+
+impl ArraySummary
+{
+	fn new(section: &'static str, a: &DataArray) -> ArraySummary
+	{
+		ArraySummary
+		{
+			section,
+			name       : a.name.clone(),
+			dtype      : a.data.vtk_type().to_string(),
+			ncomponents: a.ncomponents,
+			range      : a.range(),
+			nbytes     : a.data.nbytes(),
+		}
+	}
+
+	fn from_probe(section: &'static str, a: &probe::ArrayInfo, ntuples: u64)
+		-> ArraySummary
+	{
+		ArraySummary
+		{
+			section,
+			name       : a.name.clone(),
+			dtype      : a.dtype.clone(),
+			ncomponents: a.ncomponents,
+			range      : a.range,
+			nbytes     : (ntuples * a.ncomponents * probe::type_size(&a.dtype))
+				as usize,
+		}
+	}
+}
+
+//********
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Info
+{
+	pub file       : String,
+	pub vtype      : String,
+	pub version    : String,
+	pub byte_order : String,
+	pub header_type: String,
+
+	// Empty if the file is not compressed
+	pub compressor : String,
+
+	pub npoints: u64,
+	pub ncells : u64,
+
+	// Number of cells of each type, sorted by type
+	pub cell_types: Vec<(u8, usize)>,
+
+	// Min and max of x, y, and z.  None without points
+	pub bounds: Option<[(f64, f64); 3]>,
+
+	pub arrays: Vec<ArraySummary>,
+}
+
+//==============================================================================
+
+fn human_size(nbytes: usize) -> String
+{
+	// e.g. "1.5 KiB"
+	let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+	let mut x = nbytes as f64;
+	let mut u = 0;
+	while x >= 1024.0 && u < units.len() - 1
+	{
+		x /= 1024.0;
+		u += 1;
+	}
+	return if u == 0
+	{
+		format!("{} {}", nbytes, units[0])
+	}
+	else
+	{
+		format!("{:.1} {}", x, units[u])
+	};
+}
+
+//********
+
+fn range_string(range: Option<(f64, f64)>) -> String
+{
+	match range
+	{
+		Some((lo, hi)) => format!("[{}, {}]", lo, hi),
+		None => "-".to_string(),
+	}
+}
+
+//********
+
+fn label(s: &str) -> String
+{
+	Colour::Cyan.bold().paint(s).to_string()
+}
+
+//==============================================================================
+
+impl VtkFile
+{
+pub fn info(&self, file: &str) -> Info
+{
+	// Summarize a loaded file
+
+	let mut counts = vec![0; 256];
+	for &t in &self.types
+	{
+		counts[t as usize] += 1;
+	}
+	let cell_types = (0 .. 256).filter(|&t| counts[t] > 0)
+		.map(|t| (t as u8, counts[t])).collect();
+
+	let nc = std::cmp::max(self.ncomponents as usize, 1);
+	let mut bounds: Option<[(f64, f64); 3]> = None;
+	for p in self.points.chunks_exact(nc)
+	{
+		let b = bounds.get_or_insert([(f64::MAX, f64::MIN); 3]);
+		for d in 0 .. 3
+		{
+			let x = if d < nc { p[d] as f64 } else { 0.0 };
+			b[d] = (b[d].0.min(x), b[d].1.max(x));
+		}
+	}
+
+	let mut arrays = Vec::new();
+	let points = DataArray::new("Points", self.ncomponents,
+		Data::F32(self.points.clone()));
+	arrays.push(ArraySummary::new("Points", &points));
+	arrays.extend(self.pdata.iter().map(|a| ArraySummary::new("PointData", a)));
+	arrays.extend(self.cdata.iter().map(|a| ArraySummary::new("CellData" , a)));
+	arrays.extend(self.fdata.iter().map(|a| ArraySummary::new("FieldData", a)));
+
+	return Info
+	{
+		file       : file.to_string(),
+		vtype      : self.vtype.clone(),
+		version    : self.version.to_string(),
+		byte_order : self.byte_order().to_string(),
+		header_type: self.header_type.clone(),
+		compressor : self.compressor.clone(),
+		npoints    : self.npoints,
+		ncells     : self.ncells,
+		cell_types,
+		bounds,
+		arrays,
+	};
+}}
+
+//==============================================================================
+
+pub fn info(file: &str) -> Info
+{
+	// Files that load cannot read, e.g. legacy, .pvtu, and .pvd files, are
+	// summarized from their headers by probe, without cell types or bounds
+	if file != vtk::STDIO && !vtk::is_loadable(file)
+	{
+		return probe_info(&probe::probe(file));
+	}
+	return vtk::load(file).info(file);
+}
+
+//********
+
+fn probe_info(m: &probe::Metadata) -> Info
+{
+	let mut arrays: Vec<ArraySummary> = m.points.iter()
+		.map(|a| ArraySummary::from_probe("Points", a, m.npoints)).collect();
+	arrays.extend(m.pdata.iter()
+		.map(|a| ArraySummary::from_probe("PointData", a, m.npoints)));
+	arrays.extend(m.cdata.iter()
+		.map(|a| ArraySummary::from_probe("CellData" , a, m.ncells )));
+
+	return Info
+	{
+		file       : m.file.clone(),
+		vtype      : m.vtype.clone(),
+		version    : m.version.clone(),
+		byte_order : m.byte_order.clone(),
+		header_type: m.header_type.clone(),
+		compressor : m.compressor.clone(),
+		npoints    : m.npoints,
+		ncells     : m.ncells,
+		cell_types : Vec::new(),
+		bounds     : None,
+		arrays,
+	};
+}
+
+//==============================================================================
+
+impl Info
+{
+	pub fn to_text(&self) -> String
+	{
+		// Coloured table.  Columns are padded before painting, since escape
+		// codes have no width
+
+		let mut s = String::new();
+
+		let compressor = if self.compressor.is_empty() { "none" }
+			else { &self.compressor };
+
+		let rows = [
+			("File"       , self.file.clone()),
+			("Type"       , self.vtype.clone()),
+			("Version"    , self.version.clone()),
+			("Byte order" , self.byte_order.clone()),
+			("Header type", self.header_type.clone()),
+			("Compressor" , compressor.to_string()),
+			("Points"     , self.npoints.to_string()),
+			("Cells"      , self.ncells.to_string()),
+		];
+		for (k, v) in &rows
+		{
+			writeln!(s, "{} {}", label(&format!("{:<12}", k)), v).unwrap();
+		}
+
+		writeln!(s, "\n{}", label("Cell types")).unwrap();
+		if self.cell_types.is_empty() && self.ncells > 0
+		{
+			writeln!(s, "  not read from the file header").unwrap();
+		}
+		for (t, n) in &self.cell_types
+		{
+			writeln!(s, "  {:<34} {:>3} {:>10}", cells::type_name(*t), t, n).unwrap();
+		}
+
+		if let Some(b) = self.bounds
+		{
+			writeln!(s, "\n{}", label("Bounds")).unwrap();
+			for (d, x) in ["x", "y", "z"].iter().enumerate()
+			{
+				writeln!(s, "  {}  {:>14}  {:>14}", x, b[d].0, b[d].1).unwrap();
+			}
+		}
+
+		// Array table with columns sized to fit
+		let header = ["Section", "Name", "Type", "Components", "Range", "Size"];
+		let table: Vec<[String; 6]> = self.arrays.iter().map(|a| [
+			a.section.to_string(),
+			a.name.clone(),
+			a.dtype.clone(),
+			a.ncomponents.to_string(),
+			range_string(a.range),
+			human_size(a.nbytes),
+		]).collect();
+
+		let mut widths: Vec<usize> = header.iter().map(|h| h.len()).collect();
+		for row in &table
+		{
+			for (j, c) in row.iter().enumerate()
+			{
+				widths[j] = std::cmp::max(widths[j], c.chars().count());
+			}
+		}
+
+		writeln!(s, "\n{}", label("Arrays")).unwrap();
+		let line: Vec<String> = header.iter().enumerate()
+			.map(|(j, h)| format!("{:<w$}", h, w = widths[j])).collect();
+		writeln!(s, "  {}", Colour::White.bold().paint(line.join("  "))).unwrap();
+		for row in &table
+		{
+			let line: Vec<String> = row.iter().enumerate()
+				.map(|(j, c)| format!("{:<w$}", c, w = widths[j])).collect();
+			writeln!(s, "  {}", line.join("  ").trim_end()).unwrap();
+		}
+
+		return s;
+	}
+
+	//****************
+
+	pub fn to_json(&self) -> String
+	{
+		let types: Vec<Value> = self.cell_types.iter().map(|(t, n)| json!(
+		{
+			"type": t,
+			"name": cells::type_name(*t),
+			"count": n,
+		})).collect();
+
+		let arrays: Vec<Value> = self.arrays.iter().map(|a| json!(
+		{
+			"section": a.section,
+			"name": a.name,
+			"type": a.dtype,
+			"components": a.ncomponents,
+			"range": a.range.map(|(lo, hi)| [lo, hi]),
+			"bytes": a.nbytes,
+		})).collect();
+
+		let j = json!(
+		{
+			"file": self.file,
+			"type": self.vtype,
+			"version": self.version,
+			"byte_order": self.byte_order,
+			"header_type": self.header_type,
+			"compressor": self.compressor,
+			"npoints": self.npoints,
+			"ncells": self.ncells,
+			"cell_types": types,
+			"bounds": self.bounds.map(|b| b.map(|(lo, hi)| [lo, hi])),
+			"arrays": arrays,
+		});
+		return serde_json::to_string_pretty(&j).unwrap() + "\n";
+	}
+}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	#[test]
+	fn test_info()
+	{
+		let i = info("./data/icosahedron-binary-pdata.vtu");
+		assert_eq!(i.npoints, 12);
+		assert_eq!(i.ncells, 20);
+		assert_eq!(i.cell_types, vec![(cells::VTK_TRIANGLE, 20)]);
+		assert_eq!(i.compressor, "");
+
+		let points = &i.arrays[0];
+		assert_eq!(points.section, "Points");
+		assert_eq!(points.nbytes, 12 * 3 * 4);
+
+		let b = i.bounds.unwrap();
+		assert!(b[0].0 < 0.0 && b[0].1 > 0.0);
+
+		let json: Value = serde_json::from_str(&i.to_json()).unwrap();
+		assert_eq!(json["cell_types"], json!([{"type": 5, "name": "TRIANGLE",
+			"count": 20}]));
+		let resultz = &json["arrays"].as_array().unwrap().iter()
+			.find(|a| a["name"] == "Resultz").unwrap();
+		assert_eq!(resultz["type"], "Float64");
+		assert_eq!(json["bounds"].as_array().unwrap().len(), 3);
+	}
+
+	#[test]
+	fn test_info_legacy()
+	{
+		let temp = "./scratch/tmp.Rn6xWp3KcB.vtk";
+		vtk::load("./data/icosahedron-binary-pdata.vtu").export_as(temp, vtk::FMT_VTK);
+
+		let i = info(temp);
+		assert_eq!(i.vtype, "UNSTRUCTURED_GRID");
+		assert_eq!((i.npoints, i.ncells), (12, 20));
+		assert_eq!(i.arrays[0].section, "Points");
+		assert!(i.arrays.iter().any(|a| a.name == "Resultz"
+			&& a.section == "PointData"));
+		assert!(i.bounds.is_none());
+	}
+
+	#[test]
+	fn test_human_size()
+	{
+		assert_eq!(human_size(100), "100 B");
+		assert_eq!(human_size(1536), "1.5 KiB");
+	}
+}
+
+//==============================================================================
+
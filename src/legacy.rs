@@ -0,0 +1,315 @@
+
+//==============================================================================
+
+// Legacy (non-XML) VTK file export
+
+// Standard
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+
+//********
+
+// Third party
+use log::{info, warn};
+
+//********
+
+// This lib
+use crate::base64;
+use crate::vtk::{Attribute, Attributes, Data, DataArray, VtkFile};
+
+//********
+
+pub const EXT: &str = ".vtk";
+
+// Version 4.2 is the last one with the counted CELLS layout, which is readable
+// by every ParaView version
+const HEADER: &str = "# vtk DataFile Version 4.2";
+
+const ASCII : &str = "ascii";
+
+//==============================================================================
+
+fn fits_attribute(attr: Attribute, nc: u64) -> bool
+{
+	// Legacy readers only accept these component counts for each attribute
+	// section
+	match attr
+	{
+		Attribute::Scalars => (1 ..= 4).contains(&nc),
+		Attribute::Vectors | Attribute::Normals => nc == 3,
+		Attribute::Tensors => nc == 9 || nc == 6,
+		Attribute::TCoords => (1 ..= 3).contains(&nc),
+		Attribute::GlobalIds | Attribute::PedigreeIds => nc == 1,
+	}
+}
+
+//********
+
+fn legacy_type(data: &Data) -> &'static str
+{
+	match data
+	{
+		Data::I8 (_) => "char",
+		Data::U8 (_) => "unsigned_char",
+		Data::I16(_) => "short",
+		Data::U16(_) => "unsigned_short",
+		Data::I32(_) => "int",
+		Data::U32(_) => "unsigned_int",
+		Data::I64(_) => "vtktypeint64",
+		Data::U64(_) => "vtktypeuint64",
+		Data::F32(_) => "float",
+		Data::F64(_) => "double",
+	}
+}
+
+//********
+
+fn legacy_name(name: &str) -> String
+{
+	// Legacy names are whitespace-delimited, so spaces are escaped
+	name.replace(' ', "%20")
+}
+
+//********
+
+fn write_values<W: Write>(f: &mut W, data: &Data, ncomponents: usize,
+	binary: bool) -> Result<(), io::Error>
+{
+	// Write the values of a data section, one tuple per line for ASCII.
+	// Legacy binary data is always big endian
+
+	if binary
+	{
+		f.write_all(&data.to_bytes(base64::BIG_ENDIAN))?;
+		writeln!(f)?;
+		return Ok(());
+	}
+
+	let ncomponents = std::cmp::max(ncomponents, 1);
+	for i in 0 .. data.len()
+	{
+		write!(f, "{}", data.value_string(i))?;
+		if (i + 1) % ncomponents == 0
+		{
+			writeln!(f)?;
+		}
+		else
+		{
+			write!(f, " ")?;
+		}
+	}
+	return Ok(());
+}
+
+//********
+
+fn write_arrays<W: Write>(f: &mut W, arrays: &[DataArray], attrs: &Attributes,
+	binary: bool) -> Result<(), io::Error>
+{
+	// Write one POINT_DATA or CELL_DATA section.  Designated arrays become
+	// attribute sections and the rest become a FIELD, as do designated arrays
+	// with a component count that the attribute does not allow
+
+	let mut fields = Vec::new();
+	for a in arrays
+	{
+		let name = legacy_name(&a.name);
+		let dtype = legacy_type(&a.data);
+		let nc = a.ncomponents;
+
+		let attr = attrs.of(&a.name);
+		if let Some(attr) = attr.filter(|&attr| !fits_attribute(attr, nc))
+		{
+			warn!("writing {} array \"{}\" with {} components as a field",
+				attr.xml_name(), a.name, nc);
+			fields.push(a);
+			continue;
+		}
+
+		match attr
+		{
+			Some(Attribute::Scalars) =>
+			{
+				writeln!(f, "SCALARS {} {} {}", name, dtype, nc)?;
+				writeln!(f, "LOOKUP_TABLE default")?;
+			},
+			Some(Attribute::Vectors) => writeln!(f, "VECTORS {} {}", name, dtype)?,
+			Some(Attribute::Normals) => writeln!(f, "NORMALS {} {}", name, dtype)?,
+			Some(Attribute::Tensors) =>
+			{
+				// Symmetric tensors have their own keyword
+				let key = if nc == 6 { "TENSORS6" } else { "TENSORS" };
+				writeln!(f, "{} {} {}", key, name, dtype)?;
+			},
+			Some(Attribute::TCoords) =>
+			{
+				writeln!(f, "TEXTURE_COORDINATES {} {} {}", name, nc, dtype)?;
+			},
+			Some(Attribute::GlobalIds) =>
+			{
+				writeln!(f, "GLOBAL_IDS {} {}", name, dtype)?;
+			},
+			Some(Attribute::PedigreeIds) =>
+			{
+				writeln!(f, "PEDIGREE_IDS {} {}", name, dtype)?;
+			},
+			None =>
+			{
+				fields.push(a);
+				continue;
+			},
+		}
+		write_values(f, &a.data, nc as usize, binary)?;
+	}
+
+	if !fields.is_empty()
+	{
+		writeln!(f, "FIELD FieldData {}", fields.len())?;
+		for a in fields
+		{
+			writeln!(f, "{} {} {} {}", legacy_name(&a.name), a.ncomponents,
+				a.ntuples(), legacy_type(&a.data))?;
+			write_values(f, &a.data, a.ncomponents as usize, binary)?;
+		}
+	}
+
+	return Ok(());
+}
+
+//==============================================================================
+
+impl VtkFile
+{
+pub fn export_legacy(&self, file: &str)
+{
+	info!("Exporting legacy VTK file \"{}\"", file);
+
+	let try_export = || -> Result<(), io::Error>
+	{
+		let mut f = BufWriter::new(File::create(file)?);
+		self.write_legacy(&mut f)?;
+		f.flush()
+	};
+
+	if let Err(_err) = try_export()
+	{
+		panic!("Cannot export legacy VTK file \"{}\"", file);
+	}
+}
+
+pub fn write_legacy<W: Write>(&self, f: &mut W) -> Result<(), io::Error>
+{
+	let binary = self.format != ASCII;
+
+	writeln!(f, "{}", HEADER)?;
+	writeln!(f, "vtk output")?;
+	writeln!(f, "{}", if binary { "BINARY" } else { "ASCII" })?;
+	writeln!(f, "DATASET UNSTRUCTURED_GRID")?;
+
+	writeln!(f, "POINTS {} float", self.npoints)?;
+	write_values(f, &Data::F32(self.points.clone()),
+		self.ncomponents as usize, binary)?;
+
+	// Counted cells:  number of points followed by the point IDs
+	let mut cells = Vec::with_capacity(self.connectivity.len()
+		+ self.ncells as usize);
+	let mut begin = 0;
+	for &end in &self.offsets
+	{
+		cells.push((end - begin) as i32);
+		for &p in &self.connectivity[begin as usize .. end as usize]
+		{
+			cells.push(p as i32);
+		}
+		begin = end;
+	}
+
+	writeln!(f, "CELLS {} {}", self.ncells, cells.len())?;
+	if binary
+	{
+		write_values(f, &Data::I32(cells), 1, binary)?;
+	}
+	else
+	{
+		let mut i = 0;
+		while i < cells.len()
+		{
+			let n = cells[i] as usize;
+			let line: Vec<String> = cells[i .. i+n+1].iter()
+				.map(|c| c.to_string()).collect();
+			writeln!(f, "{}", line.join(" "))?;
+			i += n + 1;
+		}
+	}
+
+	writeln!(f, "CELL_TYPES {}", self.ncells)?;
+	let types = self.types.iter().map(|&t| t as i32).collect();
+	write_values(f, &Data::I32(types), 1, binary)?;
+
+	if !self.pdata.is_empty()
+	{
+		writeln!(f, "POINT_DATA {}", self.npoints)?;
+		write_arrays(f, &self.pdata, &self.pattributes, binary)?;
+	}
+
+	if !self.cdata.is_empty()
+	{
+		writeln!(f, "CELL_DATA {}", self.ncells)?;
+		write_arrays(f, &self.cdata, &self.cattributes, binary)?;
+	}
+
+	return Ok(());
+}}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::vtk;
+
+	#[test]
+	fn test_legacy_attributes()
+	{
+		let mut v = vtk::load("./data/icosahedron-binary-pdata.vtu");
+		v.format = ASCII.to_string();
+
+		let temp = "./scratch/tmp.W0nA4kqL1x.vtk";
+		v.export(temp);
+
+		let s = std::fs::read_to_string(temp).unwrap();
+		assert!(s.contains("CELLS 20 80\n3 0 1 10\n"));
+		assert!(s.contains("POINT_DATA 12\nSCALARS Resultz double 1\n\
+			LOOKUP_TABLE default\n"));
+
+		// Without a designation, the array is written as a field
+		v.set_point_attribute(Attribute::Scalars, None);
+		v.export(temp);
+		let s = std::fs::read_to_string(temp).unwrap();
+		assert!(s.contains("FIELD FieldData 1\nResultz 1 12 double\n"));
+
+		// Designations with the wrong number of components are written as
+		// fields too
+		let mut w = v.clone();
+		w.pdata.push(DataArray::new("V", 2, Data::F32(vec![1.0; 24])));
+		w.pdata.push(DataArray::new("T", 5, Data::F32(vec![1.0; 60])));
+		w.pdata.push(DataArray::new("N", 3, Data::F32(vec![1.0; 36])));
+		w.set_point_attribute(Attribute::Vectors, Some("V"));
+		w.set_point_attribute(Attribute::Tensors, Some("T"));
+		w.set_point_attribute(Attribute::Normals, Some("N"));
+		w.export(temp);
+		let s = std::fs::read_to_string(temp).unwrap();
+		assert!(!s.contains("VECTORS"));
+		assert!(!s.contains("TENSORS"));
+		assert!(s.contains("NORMALS N float\n"));
+		assert!(s.contains("FIELD FieldData 3\nResultz 1 12 double\n"));
+		assert!(s.contains("\nV 2 12 float\n"));
+		assert!(s.contains("\nT 5 12 float\n"));
+	}
+}
+
+//==============================================================================
+
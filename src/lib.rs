@@ -0,0 +1,37 @@
+
+//==============================================================================
+
+// The code base deliberately uses explicit returns and index loops for
+// readability, so these style lints are allowed crate-wide
+#![allow(clippy::needless_return)]
+#![allow(clippy::needless_range_loop)]
+
+pub mod abaqus;
+pub mod base64;
+pub mod batch;
+pub mod cells;
+pub mod diff;
+pub mod ensight;
+pub mod extract;
+pub mod fem;
+pub mod ghost;
+pub mod gltf;
+pub mod gmsh;
+pub mod info;
+pub mod legacy;
+pub mod logger;
+pub mod nastran;
+pub mod obj;
+pub mod ply;
+pub mod probe;
+pub mod stl;
+pub mod surface;
+pub mod tecplot;
+pub mod utils;
+pub mod validate;
+pub mod vtk;
+pub mod vtkjs;
+pub mod xdmf;
+
+//==============================================================================
+
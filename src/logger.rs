@@ -0,0 +1,113 @@
+
+//==============================================================================
+
+// Logger of the CLI.  The lib reports diagnostics through the log facade:
+// progress as info, problems that it can work around as warnings, and details
+// as debug.  Library users can install any other logger to capture them
+
+// Standard
+use std::io::Write;
+
+//********
+
+// Third party
+use ansi_term::Colour;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+//==============================================================================
+
+pub struct Logger
+{
+	// Turn warnings into errors
+	pub strict: bool,
+}
+
+impl Log for Logger
+{
+	fn enabled(&self, metadata: &Metadata) -> bool
+	{
+		return metadata.level() <= log::max_level();
+	}
+
+	fn log(&self, record: &Record)
+	{
+		if !self.enabled(record.metadata())
+		{
+			return;
+		}
+
+		if self.strict && record.level() == Level::Warn
+		{
+			panic!("{} (strict mode)", record.args());
+		}
+
+		// Everything goes to stderr, keeping stdout for exported files and
+		// reports.  Bold does not work on Windows
+		let _ = match record.level()
+		{
+			Level::Error => writeln!(std::io::stderr(), "{}: {}",
+				Colour::Red.bold().paint("error"), record.args()),
+			Level::Warn  => writeln!(std::io::stderr(), "{}: {}",
+				Colour::Yellow.bold().paint("warning"), record.args()),
+			Level::Info  => writeln!(std::io::stderr(), "{}", record.args()),
+			_            => writeln!(std::io::stderr(), "{}",
+				Colour::Fixed(8).paint(record.args().to_string())),
+		};
+	}
+
+	fn flush(&self) {}
+}
+
+//==============================================================================
+
+pub fn init(quiet: bool, verbose: bool, strict: bool)
+{
+	// Quiet shows errors only, and verbose adds debug details.  Strict mode
+	// needs warnings to reach the logger even if they are not shown
+
+	let mut level = if quiet
+	{
+		LevelFilter::Error
+	}
+	else if verbose
+	{
+		LevelFilter::Debug
+	}
+	else
+	{
+		LevelFilter::Info
+	};
+	if strict
+	{
+		level = std::cmp::max(level, LevelFilter::Warn);
+	}
+
+	// A logger may only be set once, e.g. not again in tests
+	if log::set_boxed_logger(Box::new(Logger { strict })).is_ok()
+	{
+		log::set_max_level(level);
+	}
+}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	#[test]
+	fn test_strict()
+	{
+		log::set_max_level(LevelFilter::Warn);
+		let warn = |strict| std::panic::catch_unwind(|| Logger { strict }.log(
+			&Record::builder().level(Level::Warn).args(format_args!("careful"))
+			.build()));
+
+		assert!(warn(false).is_ok());
+		assert!(warn(true ).is_err());
+	}
+}
+
+//==============================================================================
+
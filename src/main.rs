@@ -1,7 +1,155 @@
 
-mod base64;
-mod utils;
-mod vtk;
+// Same style lints as the lib
+#![allow(clippy::needless_return)]
+
+use log::info;
+
+use vtk_io_rs::{batch, diff, ensight, ghost, info, probe, utils, vtk, xdmf};
+
+//==============================================================================
+
+fn diff(settings: &vtk::Settings) -> i32
+{
+	// Print a comparison and return the exit code
+
+	let a = vtk::load(&settings.input);
+	let b = vtk::load(&settings.other);
+
+	let mut tol = diff::Tolerances::new(diff::Tolerance::new(settings.abs_tol,
+		settings.rel_tol));
+	for (name, abs, rel) in &settings.array_tols
+	{
+		let t = diff::Tolerance::new(*abs, *rel);
+		if name == "Points"
+		{
+			tol.points = t;
+		}
+		else
+		{
+			tol.arrays.push((name.clone(), t));
+		}
+	}
+
+	let c = a.compare(&b, &tol);
+	if settings.json
+	{
+		print!("{}", c.to_json(&settings.input, &settings.other));
+	}
+	else
+	{
+		print!("{}", c.to_text(&settings.input, &settings.other));
+	}
+
+	return if c.is_equal() { 0 } else { 1 };
+}
+
+//********
+
+fn info(settings: &vtk::Settings)
+{
+	let i = info::info(&settings.input);
+	if settings.json
+	{
+		print!("{}", i.to_json());
+	}
+	else
+	{
+		print!("{}", i.to_text());
+	}
+}
+
+//********
+
+fn validate(settings: &vtk::Settings) -> i32
+{
+	// Print a validation report and return the exit code
+
+	let v = vtk::load_unchecked(&settings.input);
+	let r = v.validate();
+
+	if settings.json
+	{
+		print!("{}", r.to_json(&settings.input));
+	}
+	else
+	{
+		print!("{}", r.to_text(&settings.input));
+	}
+
+	return if r.is_valid() { 0 } else { 1 };
+}
+
+//********
+
+fn merge(settings: &vtk::Settings) -> vtk::VtkFile
+{
+	// Load and merge all inputs.  A .pvtu input stands for its pieces
+
+	let mut pieces = Vec::new();
+	for input in &settings.inputs
+	{
+		let m = probe::probe(input);
+		if m.vtype == "PUnstructuredGrid"
+		{
+			pieces.extend(m.children.iter().map(|c| vtk::load(&c.file)));
+		}
+		else
+		{
+			pieces.push(vtk::load(input));
+		}
+	}
+	return ghost::merge(&pieces);
+}
+
+//********
+
+fn series(settings: &vtk::Settings)
+{
+	// Load and convert each time step of a .pvd collection or a temporal XDMF
+	// file.  A .pvtu step is merged from its pieces
+
+	let mut steps: Vec<(f64, vtk::VtkFile)> = if is_pvd(&settings.input)
+	{
+		let m = probe::probe(&settings.input);
+		m.children.iter().enumerate().map(|(i, c)|
+		{
+			let v = if c.vtype == "PUnstructuredGrid"
+			{
+				let pieces: Vec<vtk::VtkFile> = c.children.iter()
+					.map(|p| vtk::load(&p.file)).collect();
+				ghost::merge(&pieces)
+			}
+			else
+			{
+				vtk::load(&c.file)
+			};
+			(c.time.unwrap_or(i as f64), v)
+		}).collect()
+	}
+	else
+	{
+		xdmf::load_series(&settings.input)
+	};
+
+	for (_, v) in &mut steps
+	{
+		v.convert(settings);
+	}
+
+	match settings.output_format.as_str()
+	{
+		vtk::FMT_ENSIGHT => ensight::export_series(&steps, &settings.output),
+		vtk::FMT_XDMF    => xdmf::export_series(&steps, &settings.output),
+		f => panic!("Cannot export a time series as {} output", f),
+	}
+}
+
+//********
+
+fn is_pvd(file: &str) -> bool
+{
+	return file.to_lowercase().ends_with(probe::PVD_EXT);
+}
 
 //==============================================================================
 
@@ -9,17 +157,75 @@ fn main()
 {
 	// TODO:  don't unwrap
 	let this = utils::this().unwrap();
-	println!("\n{}:  starting\n", this);
 
 	// Get command line args or other configuration settings
 	let settings = utils::get_settings(&this);
 
-	let mut v = vtk::load(&settings.input);
+	if settings.command == utils::DIFF
+	{
+		std::process::exit(diff(&settings));
+	}
+	else if settings.command == utils::INFO
+	{
+		info(&settings);
+		return;
+	}
+	else if settings.command == utils::VALIDATE
+	{
+		std::process::exit(validate(&settings));
+	}
+	else if settings.command == utils::EXTRACT
+	{
+		let v = vtk::load(&settings.input);
+		v.extract(&settings.arrays, settings.section, &settings.output);
+		return;
+	}
+	else if settings.command == utils::BATCH
+	{
+		// Failed files are reported in the summary instead of by the default
+		// panic message
+		let hook = std::panic::take_hook();
+		std::panic::set_hook(Box::new(|_| {}));
+		let summary = batch::run(&settings);
+		std::panic::set_hook(hook);
+
+		print!("{}", summary.to_text());
+		std::process::exit(if summary.failed.is_empty() { 0 } else { 1 });
+	}
+
+	info!("{}:  starting", this);
+
+	// Time series keep all of their steps in series formats
+	let series_output = settings.output_format == vtk::FMT_ENSIGHT
+		|| settings.output_format == vtk::FMT_XDMF;
+	let xdmf_input = vtk::input_format_from_path(&settings.input)
+		== Some(vtk::FMT_XDMF);
+	if settings.command == utils::CONVERT
+		&& (is_pvd(&settings.input) || (xdmf_input && series_output))
+	{
+		series(&settings);
+		info!("{}:  done", this);
+		return;
+	}
+
+	let mut v = if settings.command == utils::MERGE
+	{
+		merge(&settings)
+	}
+	else
+	{
+		vtk::load(&settings.input)
+	};
 
 	v.convert(&settings);
-	v.export(&settings.output);
+	match &settings.coloring
+	{
+		Some(c) => v.export_gltf(&settings.output,
+			settings.output_format == vtk::FMT_GLB, Some(c)),
+		None => v.export_as(&settings.output, &settings.output_format),
+	}
 
-	println!("{}:  done", this);
+	info!("{}:  done", this);
 }
 
 //==============================================================================
@@ -0,0 +1,345 @@
+
+//==============================================================================
+
+// Nastran bulk data mesh import of GRID and element cards, in free, small
+// fixed, and large fixed field format.  Property IDs of the elements become a
+// cell array, since Nastran groups elements by property.  Grids must be in the
+// basic coordinate system, since CORD cards are not read
+
+// Standard
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+//********
+
+// Third party
+use log::info;
+
+//********
+
+// This lib
+use crate::cells;
+use crate::fem::Mesh;
+use crate::vtk::VtkFile;
+
+//********
+
+pub const EXTS: &[&str] = &[".bdf", ".nas"];
+
+// Cell array of element property IDs
+pub const PROPERTY_IDS: &str = "PropertyIds";
+
+const COMMENT   : char = '$';
+const BEGIN_BULK: &str = "BEGIN BULK";
+const ENDDATA   : &str = "ENDDATA";
+
+// Small fields are 8 characters, and large ones 16, with 8 for the name and
+// the continuation marker
+const SMALL: usize = 8;
+const LARGE: usize = 16;
+
+// Element cards with their linear and quadratic VTK types, if any, whether
+// they have a property ID, and the Nastran grid of each VTK node of quadratic
+// elements where the orderings differ
+type Element = (&'static str, u8, Option<u8>, bool, &'static [usize]);
+const ELEMENTS: &[Element] = &[
+	("CROD"  , cells::VTK_LINE       , None                                 , true , &[]),
+	("CONROD", cells::VTK_LINE       , None                                 , false, &[]),
+	("CBAR"  , cells::VTK_LINE       , None                                 , true , &[]),
+	("CBEAM" , cells::VTK_LINE       , None                                 , true , &[]),
+	("CTRIA3", cells::VTK_TRIANGLE   , None                                 , true , &[]),
+	("CTRIA6", cells::VTK_TRIANGLE   , Some(cells::VTK_QUADRATIC_TRIANGLE)  , true , &[]),
+	("CQUAD4", cells::VTK_QUAD       , None                                 , true , &[]),
+	("CQUAD8", cells::VTK_QUAD       , Some(cells::VTK_QUADRATIC_QUAD)      , true , &[]),
+	("CTETRA", cells::VTK_TETRA      , Some(cells::VTK_QUADRATIC_TETRA)     , true , &[]),
+	("CPYRAM", cells::VTK_PYRAMID    , Some(cells::VTK_QUADRATIC_PYRAMID)   , true , &[]),
+	("CPENTA", cells::VTK_WEDGE      , Some(cells::VTK_QUADRATIC_WEDGE)     , true ,
+		&[0, 1, 2, 3, 4, 5, 6, 7, 8, 12, 13, 14, 9, 10, 11]),
+	("CHEXA" , cells::VTK_HEXAHEDRON , Some(cells::VTK_QUADRATIC_HEXAHEDRON), true ,
+		&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 16, 17, 18, 19, 12, 13, 14, 15]),
+];
+
+//==============================================================================
+
+fn parse_real(field: &str, errstr: &str) -> f64
+{
+	// Reals may omit the E of the exponent, e.g. 1.5-3, or use D
+	let f = field.trim().to_uppercase().replace('D', "E");
+	if let Ok(x) = f.parse()
+	{
+		return x;
+	}
+	let split = f.char_indices().skip(1)
+		.filter(|&(i, c)| (c == '+' || c == '-') && !f[.. i].ends_with('E'))
+		.map(|(i, _)| i).last();
+	return split.and_then(|i| format!("{}E{}", &f[.. i], &f[i ..]).parse().ok())
+		.unwrap_or_else(|| panic!("{}:  bad real \"{}\"", errstr, field));
+}
+
+//********
+
+fn parse_int(field: &str, errstr: &str) -> i64
+{
+	return field.trim().parse().unwrap_or_else(|_| panic!("{}:  bad integer \
+		\"{}\"", errstr, field));
+}
+
+//********
+
+fn fixed_fields(line: &str, width: usize) -> Vec<String>
+{
+	// Fields 2 to 9 in small format, or 2 to 5 in large format
+	let chars: Vec<char> = line.chars().collect();
+	let field = |begin: usize, end: usize| -> String
+	{
+		chars[begin.min(chars.len()) .. end.min(chars.len())].iter().collect::<String>()
+			.trim().to_string()
+	};
+	let n = 8 * SMALL / width;
+	return (0 .. n).map(|j| field(SMALL + j * width, SMALL + (j + 1) * width))
+		.collect();
+}
+
+//********
+
+fn card_fields(line: &str) -> (String, Vec<String>)
+{
+	// Name, or the continuation marker, and the data fields of a line
+	if line.contains(',')
+	{
+		let mut fields: Vec<String> = line.split(',').map(|f| f.trim().to_string())
+			.collect();
+		let name = fields.remove(0);
+
+		// The last of 8 data fields, or 4 in large format, may be followed by a
+		// continuation marker.  Longer lines are all data
+		let n = if name.starts_with('*') || name.ends_with('*') { 4 } else { 8 };
+		if fields.len() == n + 1
+		{
+			fields.truncate(n);
+		}
+		return (name, fields);
+	}
+
+	let name: String = line.chars().take(SMALL).collect::<String>().trim().to_string();
+	let width = if name.ends_with('*') || name.starts_with('*') { LARGE } else { SMALL };
+	return (name, fixed_fields(line, width));
+}
+
+//********
+
+pub fn read<R: BufRead>(reader: R, name: &str) -> VtkFile
+{
+	// Panics on grids with a nonzero CP, whose coordinates are in a local
+	// coordinate system
+
+	let errstr = format!("Cannot read Nastran file \"{}\"", name);
+
+	// Bulk data starts after BEGIN BULK if there is one
+	let mut lines = Vec::new();
+	for line in reader.lines()
+	{
+		let line = line.expect(&errstr);
+		let line = line.split(COMMENT).next().unwrap_or("").trim_end().to_string();
+		if line.trim().to_uppercase().starts_with(BEGIN_BULK)
+		{
+			lines.clear();
+			continue;
+		}
+		if line.trim().to_uppercase() == ENDDATA
+		{
+			break;
+		}
+		if !line.trim().is_empty()
+		{
+			lines.push(line);
+		}
+	}
+
+	// Cards with their continuations, which start with + or *, or are blank
+	// in the name field
+	let mut cards: Vec<(String, Vec<String>)> = Vec::new();
+	for line in &lines
+	{
+		let (name, fields) = card_fields(line);
+		let continued = name.is_empty() || name.starts_with('+')
+			|| name.starts_with('*');
+		match cards.last_mut()
+		{
+			Some((_, data)) if continued => data.extend(fields),
+			_ => cards.push((name.trim_end_matches('*').to_uppercase(), fields)),
+		}
+	}
+
+	let mut mesh = Mesh::default();
+	let mut pids = Vec::new();
+	let mut ignored: Vec<String> = Vec::new();
+	for (card, fields) in &cards
+	{
+		let field = |j: usize| fields.get(j).map(String::as_str).unwrap_or("");
+
+		if card == "GRID"
+		{
+			let cp = if field(1).trim().is_empty() { 0 }
+				else { parse_int(field(1), &errstr) };
+			if cp != 0
+			{
+				panic!("{}:  GRID {} is in coordinate system {}.  Only the basic \
+					system 0 is supported", errstr, field(0).trim(), cp);
+			}
+			let x = [parse_real(field(2), &errstr), parse_real(field(3), &errstr),
+				parse_real(field(4), &errstr)];
+			mesh.nodes.push((parse_int(field(0), &errstr), x));
+			continue;
+		}
+
+		let (_, linear, quadratic, has_pid, order) = match ELEMENTS.iter()
+			.find(|e| e.0 == card)
+		{
+			Some(e) => e,
+			None =>
+			{
+				if !ignored.contains(card)
+				{
+					ignored.push(card.clone());
+				}
+				continue;
+			},
+		};
+
+		// Grids follow the ID and property ID.  Beams have orientation fields
+		// after 2 grids, and blank mid-side grids mean a linear element
+		let first = if *has_pid { 2 } else { 1 };
+		let nlinear = cells::npoints_fixed(*linear).unwrap_or(0);
+		let grids: Vec<&str> = fields.iter().skip(first).map(String::as_str)
+			.take(quadratic.and_then(cells::npoints_fixed).unwrap_or(nlinear))
+			.collect();
+		let quad = quadratic.filter(|_| grids.len() > nlinear
+			&& grids[nlinear ..].iter().any(|g| !g.trim().is_empty()));
+
+		let grids: Vec<i64> = if quad.is_some()
+		{
+			let g: Vec<i64> = grids.iter().map(|g| parse_int(g, &errstr)).collect();
+			if order.is_empty() { g } else { order.iter().map(|&j| g[j]).collect() }
+		}
+		else
+		{
+			grids[.. nlinear.min(grids.len())].iter()
+				.map(|g| parse_int(g, &errstr)).collect()
+		};
+
+		let id = parse_int(field(0), &errstr);
+		mesh.elements.push((id, quad.unwrap_or(*linear), grids));
+		pids.push(if *has_pid { parse_int(field(1), &errstr) } else { 0 });
+	}
+
+	if !ignored.is_empty()
+	{
+		info!("Skipped Nastran cards:  {}", ignored.join(", "));
+	}
+
+	mesh.cell_ints.push((PROPERTY_IDS.to_string(), pids));
+	return mesh.to_vtk(&errstr);
+}
+
+//********
+
+pub fn load(file: &str) -> VtkFile
+{
+	info!("Loading Nastran file \"{}\"", file);
+	let errstr = format!("Cannot load Nastran file \"{}\"", file);
+	let f = File::open(file).expect(&errstr);
+	return read(BufReader::new(f), file);
+}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fem;
+	use crate::vtk::Data;
+
+	// Grids in each field format, a tet, and a 10-node tet with a continuation
+	const DECK: &str = "\
+SOL 101
+CEND
+BEGIN BULK
+$ Free field
+GRID,1,,0.,0.,0.
+GRID,2,,1.,0.,0.
+$ Small field, with an exponent without E
+GRID           3              0.     1.0      0.
+GRID           4              0.      0.    1.-0
+$ Large field with a continuation
+GRID*                  5                              .5              .5
+*                     0.
+GRID,6,,.5,0.,0.
+GRID,7,,.5,.5,0.
+GRID,8,,0.,.5,0.
+GRID,9,,0.,0.,.5
+GRID,10,,.5,0.,.5
+GRID,11,,0.,.5,.5
+CTETRA,10,7,1,2,3,4
+CTETRA  11      8       1       2       3       4       6       7       +
++       8       9       10      11
+PSOLID,7,1
+ENDDATA
+";
+
+	#[test]
+	fn test_nastran()
+	{
+		let v = read(DECK.as_bytes(), "deck");
+		assert_eq!(v.npoints, 11);
+		assert_eq!(&v.points[6 .. 15], &[0.0, 1.0, 0.0,  0.0, 0.0, 1.0,  0.5, 0.5,
+			0.0]);
+		assert_eq!(v.types, vec![cells::VTK_TETRA, cells::VTK_QUADRATIC_TETRA]);
+		assert_eq!(v.cell_points(1), &[0, 1, 2, 3, 5, 6, 7, 8, 9, 10]);
+		assert_eq!(v.cell_array(PROPERTY_IDS).unwrap().data, Data::I64(vec![7, 8]));
+		assert_eq!(v.point_array(fem::NODE_IDS).unwrap().data.to_i64(),
+			(1 ..= 11).collect::<Vec<i64>>());
+	}
+
+	#[test]
+	fn test_nastran_lines()
+	{
+		// Rods and bars have 2 grids, and bars an orientation vector after them
+		let deck = "GRID,1,,0.,0.,0.\nGRID,2,,1.,0.,0.\nGRID,3,,1.,1.,0.\n\
+			CROD,5,1,1,2\nCONROD,6,2,3\nCBAR,7,1,1,3,0.,0.,1.\n";
+		let v = read(deck.as_bytes(), "deck");
+		assert_eq!(v.types, vec![cells::VTK_LINE; 3]);
+		assert_eq!(v.connectivity, vec![0, 1, 1, 2, 0, 2]);
+		assert_eq!(v.offsets, vec![2, 4, 6]);
+		assert_eq!(v.cell_array(PROPERTY_IDS).unwrap().data, Data::I64(vec![1, 0, 1]));
+	}
+
+	#[test]
+	#[should_panic(expected = "element 9 has 6 nodes")]
+	fn test_nastran_short_element()
+	{
+		let deck = "GRID,1,,0.,0.,0.\nCHEXA,9,1,1,1,1,1,1,1,1\n";
+		read(deck.as_bytes(), "deck");
+	}
+
+	#[test]
+	#[should_panic(expected = "GRID 2 is in coordinate system 5")]
+	fn test_nastran_local_grid()
+	{
+		let deck = "GRID,1,,0.,0.,0.\nGRID,2,5,1.,0.,0.\nCROD,3,1,1,2\n";
+		read(deck.as_bytes(), "deck");
+	}
+
+	#[test]
+	fn test_nastran_real()
+	{
+		assert_eq!(parse_real("1.5-3", ""), 1.5e-3);
+		assert_eq!(parse_real("-2.+2", ""), -200.0);
+		assert_eq!(parse_real("1.D1", ""), 10.0);
+		assert_eq!(parse_real("7.", ""), 7.0);
+	}
+}
+
+//==============================================================================
+
@@ -0,0 +1,480 @@
+
+//==============================================================================
+
+// Wavefront OBJ IO of points, lines, and faces, with vertex normals, texture
+// coordinates, and groups.  OBJ indexes normals and texture coordinates per
+// face corner, while VTK has one tuple per point, so points that are used with
+// different normals or texture coordinates are duplicated on import
+
+// Standard
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+//********
+
+// Third party
+use log::{info, warn};
+
+//********
+
+// This lib
+use crate::cells;
+use crate::vtk::{Attribute, Data, DataArray, VtkFile};
+
+//********
+
+pub const EXT: &str = ".obj";
+
+// Point arrays of vn and vt, and the cell array of g and o group indices
+pub const NORMALS: &str = "Normals";
+pub const TCOORDS: &str = "TextureCoordinates";
+pub const GROUPS : &str = "GroupIds";
+
+// Name of exported groups, followed by their index
+const GROUP_PREFIX: &str = "group";
+
+const ASCII: &str = "ascii";
+
+//==============================================================================
+
+impl VtkFile
+{
+pub fn write_obj<W: Write>(&self, f: &mut W) -> Result<(), io::Error>
+{
+	// Vertices, lines, and 2D cells of the boundary surface, with the active
+	// point normals and texture coordinates
+
+	let s = self.surface();
+	let nc = s.ncomponents as usize;
+
+	writeln!(f, "# {} points, {} cells", s.npoints, s.ncells)?;
+	for p in s.points.chunks_exact(nc.max(1))
+	{
+		let x = |d: usize| p.get(d).copied().unwrap_or(0.0);
+		writeln!(f, "v {} {} {}", x(0), x(1), x(2))?;
+	}
+
+	let normals = s.active_point_array(Attribute::Normals)
+		.filter(|a| a.ncomponents == 3);
+	if let Some(a) = normals
+	{
+		for n in a.data.to_f64().chunks_exact(3)
+		{
+			writeln!(f, "vn {} {} {}", n[0] as f32, n[1] as f32, n[2] as f32)?;
+		}
+	}
+
+	let tcoords = s.active_point_array(Attribute::TCoords)
+		.filter(|a| a.ncomponents == 2 || a.ncomponents == 3);
+	if let Some(a) = tcoords
+	{
+		for t in a.data.to_f64().chunks_exact(a.ncomponents as usize)
+		{
+			let t: Vec<String> = t.iter().map(|&x| (x as f32).to_string())
+				.collect();
+			writeln!(f, "vt {}", t.join(" "))?;
+		}
+	}
+
+	// Corners are 1-based and share their index with vn and vt
+	let corner = |p: i64| -> String
+	{
+		match (tcoords.is_some(), normals.is_some())
+		{
+			(false, false) => format!("{}", p + 1),
+			(true , false) => format!("{}/{}", p + 1, p + 1),
+			(false, true ) => format!("{}//{}", p + 1, p + 1),
+			(true , true ) => format!("{}/{}/{}", p + 1, p + 1, p + 1),
+		}
+	};
+
+	let groups = s.cell_array(GROUPS).map(|a| a.data.to_i64());
+	let mut group = None;
+
+	let mut nskipped = 0;
+	for i in 0 .. s.ncells as usize
+	{
+		if let Some(g) = groups.as_ref().map(|g| g[i])
+		{
+			if group != Some(g)
+			{
+				writeln!(f, "g {}{}", GROUP_PREFIX, g)?;
+				group = Some(g);
+			}
+		}
+
+		let mut conn = s.cell_points(i).to_vec();
+		let ctype = s.types[i];
+
+		// Faces are polygons, so strips are split and pixels reordered
+		let mut polys = Vec::new();
+		match ctype
+		{
+			cells::VTK_TRIANGLE_STRIP => for j in 2 .. conn.len()
+			{
+				polys.push(if j % 2 == 0 { vec![conn[j-2], conn[j-1], conn[j]] }
+					else { vec![conn[j-1], conn[j-2], conn[j]] });
+			},
+			cells::VTK_PIXEL if conn.len() == 4 =>
+			{
+				conn.swap(2, 3);
+				polys.push(conn);
+			},
+			_ => polys.push(conn),
+		}
+
+		let key = match cells::dimension(ctype)
+		{
+			Some(0) => "p",
+			Some(1) => "l",
+			Some(2) => "f",
+			_ =>
+			{
+				nskipped += 1;
+				continue;
+			},
+		};
+		for poly in polys
+		{
+			let corners: Vec<String> = if key == "f"
+			{
+				poly.iter().map(|&p| corner(p)).collect()
+			}
+			else
+			{
+				poly.iter().map(|&p| format!("{}", p + 1)).collect()
+			};
+			writeln!(f, "{} {}", key, corners.join(" "))?;
+		}
+	}
+
+	if nskipped > 0
+	{
+		warn!("skipping {} cells that OBJ cannot hold", nskipped);
+	}
+	return Ok(());
+}
+
+//********
+
+pub fn export_obj(&self, file: &str)
+{
+	info!("Exporting OBJ file \"{}\"", file);
+
+	let try_export = || -> Result<(), io::Error>
+	{
+		let mut f = BufWriter::new(File::create(file)?);
+		self.write_obj(&mut f)?;
+		f.flush()
+	};
+
+	if let Err(e) = try_export()
+	{
+		panic!("Cannot export OBJ file \"{}\":  {}", file, e);
+	}
+}}
+
+//==============================================================================
+
+fn index(word: &str, count: usize, errstr: &str) -> usize
+{
+	// 0-based index of a 1-based OBJ index, where negative indices count back
+	// from the last element so far
+	let i: i64 = word.parse().unwrap_or_else(|_| panic!("{}:  bad index \
+		\"{}\"", errstr, word));
+
+	let i = if i < 0 { count as i64 + i } else { i - 1 };
+	if i < 0 || i >= count as i64
+	{
+		panic!("{}:  index {} is out of range", errstr, word);
+	}
+	return i as usize;
+}
+
+//********
+
+pub fn read<R: BufRead>(reader: R, name: &str) -> VtkFile
+{
+	let errstr = format!("Cannot read OBJ file \"{}\"", name);
+
+	let mut v = VtkFile::new();
+	v.vtype = "UnstructuredGrid".to_string();
+	v.format = ASCII.to_string();
+	v.ncomponents = 3;
+
+	let mut normals: Vec<[f32; 3]> = Vec::new();
+	let mut tcoords: Vec<[f32; 3]> = Vec::new();
+	let mut ntcomponents = 2;
+
+	// Normal and texture coordinate index of each point, and the points that
+	// were duplicated for other ones
+	let mut corners: Vec<(Option<usize>, Option<usize>)> = Vec::new();
+	let mut copies: HashMap<(usize, Option<usize>, Option<usize>), usize> =
+		HashMap::new();
+
+	// Group indices by name in order of appearance, and the group of each cell
+	let mut group_ids: HashMap<String, i32> = HashMap::new();
+	let mut group = None;
+	let mut groups = Vec::new();
+
+	for line in reader.lines()
+	{
+		let line = line.expect(&errstr);
+		let line = line.split('#').next().unwrap_or("");
+		let mut words = line.split_whitespace();
+		let key = match words.next()
+		{
+			Some(k) => k,
+			None => continue,
+		};
+
+		let mut floats = || -> Vec<f32> { words.by_ref().map(|w| w.parse()
+			.unwrap_or_else(|_| panic!("{}:  bad number \"{}\"", errstr, w)))
+			.collect() };
+
+		match key
+		{
+			// Any w of homogeneous coordinates is dropped
+			"v" =>
+			{
+				let x = floats();
+				if x.len() < 3
+				{
+					panic!("{}:  vertices need 3 coordinates", errstr);
+				}
+				v.points.extend_from_slice(&x[.. 3]);
+				corners.push((None, None));
+			},
+			"vn" =>
+			{
+				let n = floats();
+				if n.len() != 3
+				{
+					panic!("{}:  normals need 3 components", errstr);
+				}
+				normals.push([n[0], n[1], n[2]]);
+			},
+			"vt" =>
+			{
+				let t = floats();
+				if t.is_empty() || t.len() > 3
+				{
+					panic!("{}:  texture coordinates need 1 to 3 components",
+						errstr);
+				}
+				if t.len() == 3
+				{
+					ntcomponents = 3;
+				}
+				tcoords.push([t[0], *t.get(1).unwrap_or(&0.0),
+					*t.get(2).unwrap_or(&0.0)]);
+			},
+			"g" | "o" =>
+			{
+				let name: Vec<&str> = words.collect();
+				let n = group_ids.len() as i32;
+				group = Some(*group_ids.entry(name.join(" ")).or_insert(n));
+			},
+			"f" | "l" | "p" =>
+			{
+				let npoints = corners.len();
+				let mut conn = Vec::new();
+				for word in words
+				{
+					let mut parts = word.split('/');
+					let p = index(parts.next().unwrap_or(""), npoints, &errstr);
+					let t = parts.next().filter(|t| !t.is_empty())
+						.map(|t| index(t, tcoords.len(), &errstr));
+					let n = parts.next().filter(|n| !n.is_empty())
+						.map(|n| index(n, normals.len(), &errstr));
+
+					// Attach the normal and texture coordinates to the point, or
+					// to a copy if it already has others.  Copies are numbered
+					// after all vertices, so they get negative IDs until then
+					let id = if (t, n) == (None, None) || corners[p] == (t, n)
+					{
+						p as i64
+					}
+					else if corners[p] == (None, None)
+					{
+						corners[p] = (t, n);
+						p as i64
+					}
+					else
+					{
+						let k = copies.len();
+						-1 - *copies.entry((p, t, n)).or_insert(k) as i64
+					};
+					conn.push(id);
+				}
+
+				let ctype = match (key, conn.len())
+				{
+					(_  , 0) => panic!("{}:  \"{}\" without points", errstr, key),
+					("f", 1) | ("f", 2) => panic!("{}:  faces need 3 or more \
+						points", errstr),
+					("f", 3) => cells::VTK_TRIANGLE,
+					("f", 4) => cells::VTK_QUAD,
+					("f", _) => cells::VTK_POLYGON,
+					("l", 1) => panic!("{}:  lines need 2 or more points", errstr),
+					("l", 2) => cells::VTK_LINE,
+					("l", _) => cells::VTK_POLY_LINE,
+					(_  , 1) => cells::VTK_VERTEX,
+					_        => cells::VTK_POLY_VERTEX,
+				};
+
+				v.connectivity.extend(conn);
+				v.offsets.push(v.connectivity.len() as i64);
+				v.types.push(ctype);
+				groups.push(group);
+			},
+			// Materials, smoothing groups, and free-form geometry
+			_ => (),
+		}
+	}
+
+	let nvertices = corners.len() as i64;
+	let mut copies: Vec<_> = copies.into_iter().collect();
+	copies.sort_by_key(|&(_, k)| k);
+	for ((p, t, n), _) in copies
+	{
+		let x = v.points[3 * p .. 3 * p + 3].to_vec();
+		v.points.extend(x);
+		corners.push((t, n));
+	}
+	for id in v.connectivity.iter_mut().filter(|id| **id < 0)
+	{
+		*id = nvertices - 1 - *id;
+	}
+
+	v.npoints = corners.len() as u64;
+	v.ncells  = v.types.len() as u64;
+
+	if !normals.is_empty()
+	{
+		let data = corners.iter().flat_map(|&(_, n)|
+			n.map(|n| normals[n]).unwrap_or([0.0; 3])).collect();
+		v.pdata.push(DataArray::new(NORMALS, 3, Data::F32(data)));
+		v.set_point_attribute(Attribute::Normals, Some(NORMALS));
+	}
+	if !tcoords.is_empty()
+	{
+		let data = corners.iter().flat_map(|&(t, _)|
+		{
+			let t = t.map(|t| tcoords[t]).unwrap_or([0.0; 3]);
+			t[.. ntcomponents].to_vec()
+		}).collect();
+		v.pdata.push(DataArray::new(TCOORDS, ntcomponents as u64, Data::F32(data)));
+		v.set_point_attribute(Attribute::TCoords, Some(TCOORDS));
+	}
+
+	// Cells before the first group get their own group
+	if !group_ids.is_empty()
+	{
+		let first = if groups.iter().any(|g| g.is_none())
+		{
+			group_ids.insert("".to_string(), group_ids.len() as i32);
+			group_ids.len() as i32 - 1
+		}
+		else
+		{
+			0
+		};
+		let data = groups.iter().map(|g| g.unwrap_or(first)).collect();
+		v.cdata.push(DataArray::new(GROUPS, 1, Data::I32(data)));
+
+		let mut names: Vec<(&String, &i32)> = group_ids.iter().collect();
+		names.sort_by_key(|&(_, &i)| i);
+		for (name, i) in names
+		{
+			info!("OBJ group {}:  \"{}\"", i, name);
+		}
+	}
+
+	return v;
+}
+
+//********
+
+pub fn load(file: &str) -> VtkFile
+{
+	info!("Loading OBJ file \"{}\"", file);
+	let errstr = format!("Cannot load OBJ file \"{}\"", file);
+	let f = File::open(file).expect(&errstr);
+	return read(BufReader::new(f), file);
+}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	const CUBE_SIDES: &str = "\
+# Two sides of a unit cube, which share an edge with different normals
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 1 0 1
+v 1 1 1
+vn 0 0 -1
+vn 1 0 0
+vt 0 0
+vt 1 1
+o bottom
+f 1//1 4//1 3//1 2//1
+g right side
+f -5//2 -2//2 -1//2 -4//2
+l 1 2 3
+v 0 0 1
+p -1
+";
+
+	#[test]
+	fn test_obj()
+	{
+		let v = read(CUBE_SIDES.as_bytes(), "cube");
+
+		// Points 2 and 3 are copied for their second normal, after the last
+		// vertex
+		assert_eq!(v.npoints, 9);
+		assert_eq!(v.types, vec![cells::VTK_QUAD, cells::VTK_QUAD,
+			cells::VTK_POLY_LINE, cells::VTK_VERTEX]);
+		assert_eq!(v.cell_points(1), &[7, 4, 5, 8]);
+		assert_eq!(v.cell_points(3), &[6]);
+		assert_eq!(v.cell_array(GROUPS).unwrap().data, Data::I32(vec![0, 1, 1,
+			1]));
+		assert_eq!(v.point_attribute(Attribute::Normals), Some(NORMALS));
+		assert_eq!(v.pdata[0].data.to_f64()[18 ..], [0.0, 0.0, 0.0, 1.0, 0.0,
+			0.0, 1.0, 0.0, 0.0]);
+
+		let mut buf = Vec::new();
+		v.write_obj(&mut buf).unwrap();
+		let w = read(&buf[..], "buffer");
+		assert_eq!(w.points, v.points);
+		assert_eq!(w.connectivity, v.connectivity);
+		assert_eq!(w.types, v.types);
+		assert_eq!(w.pdata, v.pdata);
+		assert_eq!(w.cdata, v.cdata);
+	}
+
+	#[test]
+	fn test_obj_polygon()
+	{
+		let v = read("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0 2 0\nvt 0.5 0.5\n\
+			f 1/1 2/1 3/1\nf 1 2 3 4 5\np 5\n".as_bytes(), "polygon");
+
+		assert_eq!(v.types, vec![cells::VTK_TRIANGLE, cells::VTK_POLYGON,
+			cells::VTK_VERTEX]);
+		assert!(v.cell_array(GROUPS).is_none());
+		assert_eq!(v.pdata[0].name, TCOORDS);
+		assert_eq!(v.pdata[0].ncomponents, 2);
+	}
+}
+
+//==============================================================================
+
@@ -0,0 +1,708 @@
+
+//==============================================================================
+
+// PLY (polygon file format) IO, in ASCII or binary of either byte order.
+// Vertex properties become point arrays, except for the coordinates, normals,
+// and colors, and face vertex lists become polygon cells with the other face
+// properties as cell arrays
+
+// Standard
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+//********
+
+// Third party
+use log::{info, warn};
+
+//********
+
+// This lib
+use crate::base64::{self, Scalar};
+use crate::cells;
+use crate::vtk::{Attribute, Data, DataArray, VtkFile};
+
+//********
+
+pub const EXT: &str = ".ply";
+
+// Point arrays of nx/ny/nz and red/green/blue/alpha
+pub const NORMALS: &str = "Normals";
+pub const COLORS : &str = "Colors";
+
+const MAGIC     : &str = "ply";
+const END_HEADER: &str = "end_header";
+
+const FMT_ASCII: &str = "ascii";
+const FMT_LE   : &str = "binary_little_endian";
+const FMT_BE   : &str = "binary_big_endian";
+
+const VERTEX: &str = "vertex";
+const FACE  : &str = "face";
+
+// Name of the coordinates among the vertex arrays
+const POINTS_NAME: &str = "Points";
+
+const XYZ    : [&str; 3] = ["x", "y", "z"];
+const NXYZ   : [&str; 3] = ["nx", "ny", "nz"];
+const RGBA   : [&str; 4] = ["red", "green", "blue", "alpha"];
+const INDICES: [&str; 2] = ["vertex_indices", "vertex_index"];
+
+const ASCII: &str = "ascii";
+
+//==============================================================================
+
+fn vtk_type(ply_type: &str) -> Option<&'static str>
+{
+	// PLY types have both their old and their sized names
+	match ply_type
+	{
+		"char"   | "int8"    => Some("Int8"),
+		"uchar"  | "uint8"   => Some("UInt8"),
+		"short"  | "int16"   => Some("Int16"),
+		"ushort" | "uint16"  => Some("UInt16"),
+		"int"    | "int32"   => Some("Int32"),
+		"uint"   | "uint32"  => Some("UInt32"),
+		"float"  | "float32" => Some("Float32"),
+		"double" | "float64" => Some("Float64"),
+		_ => None,
+	}
+}
+
+//********
+
+fn ply_type(data: &Data) -> &'static str
+{
+	// PLY has no 64-bit ints, so those are written as doubles
+	match data
+	{
+		Data::I8 (_) => "char",
+		Data::U8 (_) => "uchar",
+		Data::I16(_) => "short",
+		Data::U16(_) => "ushort",
+		Data::I32(_) => "int",
+		Data::U32(_) => "uint",
+		Data::F32(_) => "float",
+		Data::I64(_) | Data::U64(_) | Data::F64(_) => "double",
+	}
+}
+
+//********
+
+fn nbytes(vtk_type: &str) -> usize
+{
+	match vtk_type
+	{
+		"Int8"  | "UInt8"  => 1,
+		"Int16" | "UInt16" => 2,
+		"Float64"          => 8,
+		_                  => 4,
+	}
+}
+
+//==============================================================================
+
+// Single-component column of a PLY element, in the PLY type
+struct Column
+{
+	name: String,
+	data: Data,
+}
+
+fn ply_columns(arrays: &[DataArray], special: &[(&str, &[&str])]) -> Vec<Column>
+{
+	// Split arrays into their components.  Special arrays use the standard
+	// property names, and other arrays get a suffix if they have several
+	// components
+
+	let mut columns = Vec::new();
+	for a in arrays
+	{
+		let nc = a.ncomponents as usize;
+		let values = a.data.to_f64();
+		let vtk_type = vtk_type(ply_type(&a.data)).unwrap_or("Float64");
+		let name = a.name.split_whitespace().collect::<Vec<_>>().join("_");
+
+		let names: Vec<String> = match special.iter().find(|(s, _)| *s == a.name)
+		{
+			Some((_, names)) if nc <= names.len() =>
+				names[.. nc].iter().map(|n| n.to_string()).collect(),
+			_ if nc == 1 => vec![name],
+			_ => (0 .. nc).map(|c| format!("{}_{}", name, c)).collect(),
+		};
+
+		for (c, name) in names.into_iter().enumerate()
+		{
+			let component = values.iter().skip(c).step_by(nc).copied().collect();
+			columns.push(Column
+			{
+				name,
+				data: Data::F64(component).cast(vtk_type),
+			});
+		}
+	}
+	return columns;
+}
+
+//********
+
+impl VtkFile
+{
+pub fn write_ply<W: Write>(&self, f: &mut W, ascii: bool) -> Result<(), io::Error>
+{
+	// Points with their point data, and the polygons of the boundary surface
+	// with their cell data
+
+	let s = self.surface();
+	let nc = s.ncomponents as usize;
+	let e = s.endianness;
+
+	let mut polys: Vec<Vec<i64>> = Vec::new();
+	let mut parents = Vec::new();
+	let mut nskipped = 0;
+	for i in 0 .. s.ncells as usize
+	{
+		let mut conn = s.cell_points(i).to_vec();
+		match s.types[i]
+		{
+			t if cells::dimension(t) != Some(2) =>
+			{
+				nskipped += 1;
+				continue;
+			},
+			cells::VTK_TRIANGLE_STRIP => for j in 2 .. conn.len()
+			{
+				polys.push(if j % 2 == 0 { vec![conn[j-2], conn[j-1], conn[j]] }
+					else { vec![conn[j-1], conn[j-2], conn[j]] });
+				parents.push(i);
+			},
+			t =>
+			{
+				if t == cells::VTK_PIXEL && conn.len() == 4
+				{
+					conn.swap(2, 3);
+				}
+				polys.push(conn);
+				parents.push(i);
+			},
+		}
+	}
+	if nskipped > 0
+	{
+		warn!("skipping {} vertices and lines, which PLY cannot hold", nskipped);
+	}
+
+	// Coordinates first, then normals and colors by their standard names
+	let coords: Vec<f32> = s.points.chunks_exact(nc.max(1))
+		.flat_map(|p| (0 .. 3).map(move |d| p.get(d).copied().unwrap_or(0.0)))
+		.collect();
+	let mut pdata = vec![DataArray::new(POINTS_NAME, 3, Data::F32(coords))];
+	pdata.extend(s.pdata.iter().cloned());
+
+	let normals = s.point_attribute(Attribute::Normals).unwrap_or(NORMALS)
+		.to_string();
+	let special: [(&str, &[&str]); 3] = [(POINTS_NAME, &XYZ), (&normals, &NXYZ),
+		(COLORS, &RGBA)];
+	let vcolumns = ply_columns(&pdata, &special);
+
+	let cdata: Vec<DataArray> = s.cdata.iter().map(|a| DataArray
+	{
+		data: a.data.select(a.ncomponents as usize, &parents),
+		..a.clone()
+	}).collect();
+	let fcolumns = ply_columns(&cdata, &[]);
+
+	// Smallest list count type that fits
+	let (count_type, count_data) = if polys.iter().all(|p| p.len() <= u8::MAX as usize)
+	{
+		("uchar", Data::U8(polys.iter().map(|p| p.len() as u8).collect()))
+	}
+	else
+	{
+		("int", Data::I32(polys.iter().map(|p| p.len() as i32).collect()))
+	};
+
+	writeln!(f, "{}", MAGIC)?;
+	writeln!(f, "format {} 1.0", if ascii { FMT_ASCII }
+		else if e == base64::BIG_ENDIAN { FMT_BE } else { FMT_LE })?;
+	writeln!(f, "comment vtk-io-rs")?;
+	writeln!(f, "element {} {}", VERTEX, s.npoints)?;
+	for c in &vcolumns
+	{
+		writeln!(f, "property {} {}", ply_type(&c.data), c.name)?;
+	}
+	if !polys.is_empty()
+	{
+		writeln!(f, "element {} {}", FACE, polys.len())?;
+		writeln!(f, "property list {} int {}", count_type, INDICES[0])?;
+		for c in &fcolumns
+		{
+			writeln!(f, "property {} {}", ply_type(&c.data), c.name)?;
+		}
+	}
+	writeln!(f, "{}", END_HEADER)?;
+
+	if ascii
+	{
+		for i in 0 .. s.npoints as usize
+		{
+			let row: Vec<String> = vcolumns.iter().map(|c| c.data.value_string(i))
+				.collect();
+			writeln!(f, "{}", row.join(" "))?;
+		}
+		for (i, poly) in polys.iter().enumerate()
+		{
+			let row: Vec<String> = std::iter::once(poly.len().to_string())
+				.chain(poly.iter().map(|p| p.to_string()))
+				.chain(fcolumns.iter().map(|c| c.data.value_string(i)))
+				.collect();
+			writeln!(f, "{}", row.join(" "))?;
+		}
+		return Ok(());
+	}
+
+	// Binary rows interleave the bytes of each column
+	let write_rows = |f: &mut W, columns: &[&Data], n: usize,
+		list: Option<&[Vec<i64>]>| -> Result<(), io::Error>
+	{
+		let bytes: Vec<(Vec<u8>, usize)> = columns.iter()
+			.map(|d| (d.to_bytes(e), nbytes(d.vtk_type()))).collect();
+		let mut row = Vec::new();
+		for i in 0 .. n
+		{
+			row.clear();
+			for (j, (b, size)) in bytes.iter().enumerate()
+			{
+				row.extend_from_slice(&b[size * i .. size * (i + 1)]);
+				if let (0, Some(list)) = (j, list)
+				{
+					for &p in &list[i]
+					{
+						row.extend((p as i32).to_bytes(e));
+					}
+				}
+			}
+			f.write_all(&row)?;
+		}
+		Ok(())
+	};
+
+	let vcols: Vec<&Data> = vcolumns.iter().map(|c| &c.data).collect();
+	write_rows(f, &vcols, s.npoints as usize, None)?;
+
+	let fcols: Vec<&Data> = std::iter::once(&count_data)
+		.chain(fcolumns.iter().map(|c| &c.data)).collect();
+	write_rows(f, &fcols, polys.len(), Some(&polys))?;
+
+	return Ok(());
+}
+
+//********
+
+pub fn export_ply(&self, file: &str)
+{
+	info!("Exporting PLY file \"{}\"", file);
+
+	let try_export = || -> Result<(), io::Error>
+	{
+		let mut f = BufWriter::new(File::create(file)?);
+		self.write_ply(&mut f, self.format == ASCII)?;
+		f.flush()
+	};
+
+	if let Err(e) = try_export()
+	{
+		panic!("Cannot export PLY file \"{}\":  {}", file, e);
+	}
+}}
+
+//==============================================================================
+
+// Property of a PLY element, with the VTK types of its values and of the
+// counts of lists
+struct Property
+{
+	name : String,
+	dtype: &'static str,
+	count: Option<&'static str>,
+}
+
+struct Element
+{
+	name : String,
+	count: usize,
+	properties: Vec<Property>,
+}
+
+//********
+
+// Reader of values in ASCII or binary data
+struct Values<'a>
+{
+	bytes: &'a [u8],
+	pos  : usize,
+	ascii: bool,
+	endianness: u8,
+	errstr: &'a str,
+}
+
+impl Values<'_>
+{
+	fn next(&mut self, dtype: &str) -> f64
+	{
+		if self.ascii
+		{
+			while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace()
+			{
+				self.pos += 1;
+			}
+			let begin = self.pos;
+			while self.pos < self.bytes.len() && !self.bytes[self.pos].is_ascii_whitespace()
+			{
+				self.pos += 1;
+			}
+			let word = String::from_utf8_lossy(&self.bytes[begin .. self.pos]);
+			return word.parse().unwrap_or_else(|_| panic!("{}:  bad number \
+				\"{}\"", self.errstr, word));
+		}
+
+		let n = nbytes(dtype);
+		if self.pos + n > self.bytes.len()
+		{
+			panic!("{}:  unexpected end of data", self.errstr);
+		}
+		let b = &self.bytes[self.pos ..];
+		let e = self.endianness;
+		self.pos += n;
+		return match dtype
+		{
+			"Int8"    => i8 ::from_bytes(b, e) as f64,
+			"UInt8"   => u8 ::from_bytes(b, e) as f64,
+			"Int16"   => i16::from_bytes(b, e) as f64,
+			"UInt16"  => u16::from_bytes(b, e) as f64,
+			"Int32"   => i32::from_bytes(b, e) as f64,
+			"UInt32"  => u32::from_bytes(b, e) as f64,
+			"Float32" => f32::from_bytes(b, e) as f64,
+			_         => f64::from_bytes(b, e),
+		};
+	}
+}
+
+//********
+
+fn parse_header<R: BufRead>(reader: &mut R, errstr: &str) -> (String, Vec<Element>)
+{
+	let mut format = String::new();
+	let mut elements: Vec<Element> = Vec::new();
+
+	let mut first = true;
+	loop
+	{
+		let mut line = String::new();
+		if reader.read_line(&mut line).expect(errstr) == 0
+		{
+			panic!("{}:  missing {}", errstr, END_HEADER);
+		}
+		let words: Vec<&str> = line.split_whitespace().collect();
+		if first && words != [MAGIC]
+		{
+			panic!("{}:  not a PLY file", errstr);
+		}
+		first = false;
+
+		let dtype = |t: &str| vtk_type(t).unwrap_or_else(||
+			panic!("{}:  unknown type \"{}\"", errstr, t));
+
+		match words.as_slice()
+		{
+			[END_HEADER] => break,
+			["format", f, _] => format = f.to_string(),
+			["element", name, count] => elements.push(Element
+			{
+				name: name.to_string(),
+				count: count.parse().unwrap_or_else(|_| panic!("{}:  bad \
+					element count \"{}\"", errstr, count)),
+				properties: Vec::new(),
+			}),
+			["property", "list", count, item, name] => elements.last_mut()
+				.unwrap_or_else(|| panic!("{}:  property before element", errstr))
+				.properties.push(Property
+				{
+					name: name.to_string(),
+					dtype: dtype(item),
+					count: Some(dtype(count)),
+				}),
+			["property", t, name] => elements.last_mut()
+				.unwrap_or_else(|| panic!("{}:  property before element", errstr))
+				.properties.push(Property
+				{
+					name: name.to_string(),
+					dtype: dtype(t),
+					count: None,
+				}),
+			// Comments, obj_info, and the magic
+			_ => (),
+		}
+	}
+
+	if ![FMT_ASCII, FMT_LE, FMT_BE].contains(&format.as_str())
+	{
+		panic!("{}:  unknown format \"{}\"", errstr, format);
+	}
+	return (format, elements);
+}
+
+//********
+
+pub fn read<R: BufRead>(mut reader: R, name: &str) -> VtkFile
+{
+	let errstr = format!("Cannot read PLY file \"{}\"", name);
+	let (format, elements) = parse_header(&mut reader, &errstr);
+
+	let mut bytes = Vec::new();
+	reader.read_to_end(&mut bytes).expect(&errstr);
+
+	let mut v = VtkFile::new();
+	v.vtype = "UnstructuredGrid".to_string();
+	v.ncomponents = 3;
+	match format.as_str()
+	{
+		FMT_ASCII => v.format = ASCII.to_string(),
+		FMT_BE    => v.endianness = base64::BIG_ENDIAN,
+		_         => v.endianness = base64::LITTLE_ENDIAN,
+	}
+
+	let mut values = Values
+	{
+		bytes: &bytes,
+		pos: 0,
+		ascii: format == FMT_ASCII,
+		endianness: v.endianness,
+		errstr: &errstr,
+	};
+
+	for element in &elements
+	{
+		// Scalar properties by column, and the lists of the face indices
+		let mut columns = vec![Vec::with_capacity(element.count);
+			element.properties.len()];
+		let mut lists = Vec::new();
+
+		for _ in 0 .. element.count
+		{
+			for (j, p) in element.properties.iter().enumerate()
+			{
+				match p.count
+				{
+					None => columns[j].push(values.next(p.dtype)),
+					Some(count) =>
+					{
+						let n = values.next(count) as usize;
+						let list: Vec<i64> = (0 .. n)
+							.map(|_| values.next(p.dtype) as i64).collect();
+						if INDICES.contains(&p.name.as_str())
+						{
+							lists.push(list);
+						}
+					},
+				}
+			}
+		}
+
+		let scalars = element.properties.iter().zip(columns)
+			.filter(|(p, _)| p.count.is_none())
+			.map(|(p, c)| (p.name.as_str(), p.dtype, c)).collect();
+
+		match element.name.as_str()
+		{
+			VERTEX =>
+			{
+				v.npoints = element.count as u64;
+				let arrays = ply_arrays(scalars, &[(POINTS_NAME, &XYZ),
+					(NORMALS, &NXYZ), (COLORS, &RGBA)]);
+				for a in arrays
+				{
+					if a.name == POINTS_NAME
+					{
+						if a.ncomponents != 3
+						{
+							panic!("{}:  vertices need x, y, and z", errstr);
+						}
+						v.points = a.data.to_f64().iter().map(|&x| x as f32)
+							.collect();
+					}
+					else
+					{
+						let normals = a.name == NORMALS && a.ncomponents == 3;
+						v.pdata.push(a);
+						if normals
+						{
+							v.set_point_attribute(Attribute::Normals, Some(NORMALS));
+						}
+					}
+				}
+			},
+			FACE =>
+			{
+				for list in lists
+				{
+					v.types.push(match list.len()
+					{
+						3 => cells::VTK_TRIANGLE,
+						4 => cells::VTK_QUAD,
+						_ => cells::VTK_POLYGON,
+					});
+					v.connectivity.extend(list);
+					v.offsets.push(v.connectivity.len() as i64);
+				}
+				v.ncells = v.types.len() as u64;
+				v.cdata.extend(ply_arrays(scalars, &[]));
+			},
+			_ => warn!("skipping PLY element \"{}\"", element.name),
+		}
+	}
+
+	if v.points.len() != 3 * v.npoints as usize
+	{
+		panic!("{}:  missing {} element", errstr, VERTEX);
+	}
+	return v;
+}
+
+//********
+
+fn ply_arrays(scalars: Vec<(&str, &'static str, Vec<f64>)>,
+	special: &[(&str, &[&str])]) -> Vec<DataArray>
+{
+	// Group the properties with special names into multi-component arrays of
+	// the type of their first component, e.g. red/green/blue into colors, and
+	// make the others single-component arrays
+
+	let mut arrays = Vec::new();
+	let mut grouped = vec![false; scalars.len()];
+	for (name, components) in special
+	{
+		let found: Vec<usize> = components.iter().map_while(|c| scalars.iter()
+			.position(|s| s.0 == *c)).collect();
+		if found.len() < components.len().min(3)
+		{
+			continue;
+		}
+
+		let n = found.len();
+		let mut values = Vec::with_capacity(n * scalars[found[0]].2.len());
+		for i in 0 .. scalars[found[0]].2.len()
+		{
+			values.extend(found.iter().map(|&j| scalars[j].2[i]));
+		}
+		arrays.push(DataArray::new(name, n as u64,
+			Data::F64(values).cast(scalars[found[0]].1)));
+		for j in found
+		{
+			grouped[j] = true;
+		}
+	}
+
+	for (j, (name, dtype, values)) in scalars.into_iter().enumerate()
+	{
+		if !grouped[j]
+		{
+			arrays.push(DataArray::new(name, 1, Data::F64(values).cast(dtype)));
+		}
+	}
+	return arrays;
+}
+
+//********
+
+pub fn load(file: &str) -> VtkFile
+{
+	info!("Loading PLY file \"{}\"", file);
+	let errstr = format!("Cannot load PLY file \"{}\"", file);
+	let f = File::open(file).expect(&errstr);
+	return read(BufReader::new(f), file);
+}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::vtk;
+
+	const SCAN: &str = "\
+ply
+format ascii 1.0
+comment A quad and a triangle
+element vertex 5
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+property float confidence
+element face 2
+property list uchar int vertex_indices
+property int label
+end_header
+0 0 0 255 0 0 0.5
+1 0 0 0 255 0 0.25
+1 1 0 0 0 255 1
+0 1 0 9 9 9 0
+0 2 0 1 2 3 0.75
+4 0 1 2 3 7
+3 3 2 4 8
+";
+
+	#[test]
+	fn test_ply()
+	{
+		let v = read(SCAN.as_bytes(), "scan");
+		assert_eq!(v.npoints, 5);
+		assert_eq!(v.types, vec![cells::VTK_QUAD, cells::VTK_TRIANGLE]);
+		assert_eq!(v.point_array(COLORS).unwrap().data, Data::U8(vec![255, 0, 0,
+			0, 255, 0,  0, 0, 255,  9, 9, 9,  1, 2, 3]));
+		assert_eq!(v.point_array("confidence").unwrap().data, Data::F32(vec![0.5,
+			0.25, 1.0, 0.0, 0.75]));
+		assert_eq!(v.cell_array("label").unwrap().data, Data::I32(vec![7, 8]));
+
+		// Every encoding round trips
+		let mut v = v;
+		for &(ascii, e) in &[(true, base64::LITTLE_ENDIAN),
+			(false, base64::LITTLE_ENDIAN), (false, base64::BIG_ENDIAN)]
+		{
+			v.endianness = e;
+			let mut buf = Vec::new();
+			v.write_ply(&mut buf, ascii).unwrap();
+			let w = read(&buf[..], "buffer");
+			assert_eq!(w.endianness, e);
+			assert_eq!(w.points, v.points);
+			assert_eq!(w.connectivity, v.connectivity);
+			assert_eq!(w.types, v.types);
+			assert_eq!(w.pdata, v.pdata);
+			assert_eq!(w.cdata, v.cdata);
+		}
+	}
+
+	#[test]
+	fn test_ply_normals()
+	{
+		// The icosahedron's point normals are its point coordinates
+		let mut v = vtk::load("./data/icosahedron-ascii.vtu");
+		v.pdata.push(DataArray::new("n", 3, Data::F32(v.points.clone())));
+		v.set_point_attribute(Attribute::Normals, Some("n"));
+
+		let mut buf = Vec::new();
+		v.write_ply(&mut buf, false).unwrap();
+		let w = read(&buf[..], "buffer");
+		assert_eq!(w.ncells, 20);
+		assert_eq!(w.point_attribute(Attribute::Normals), Some(NORMALS));
+		assert_eq!(w.point_array(NORMALS).unwrap().data, Data::F32(v.points));
+	}
+}
+
+//==============================================================================
+
@@ -0,0 +1,830 @@
+
+//==============================================================================
+
+// Fast metadata scan of VTK files.  Only headers and attributes are read:
+// array bodies are skipped and reading stops at the appended data block
+
+// Standard
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+//********
+
+// Third party
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+
+//********
+
+// This lib
+use crate::base64;
+use crate::vtk;
+
+//********
+
+// Time step collections, which have no geometry of their own
+pub const PVD_EXT: &str = ".pvd";
+
+const TIME_VALUE: &str = "TimeValue";
+// Sections of parallel files
+// Sections of parallel files, and field data, which VtkFile does not load
+const PPDATA     : &str = "PPointData";
+const PCDATA     : &str = "PCellData";
+const PPOINTS    : &str = "PPoints";
+const PDATA_ARRAY: &str = "PDataArray";
+
+const SOURCE    : &str = "Source";
+const DATASET   : &str = "DataSet";
+const FILE      : &str = "file";
+const TIMESTEP  : &str = "timestep";
+const APPENDED  : &str = "AppendedData";
+const COLLECTION: &str = "Collection";
+const RANGE_MIN : &str = "RangeMin";
+const RANGE_MAX : &str = "RangeMax";
+
+//==============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayInfo
+{
+	pub name       : String,
+	pub dtype      : String,
+	pub ncomponents: u64,
+
+	// From the RangeMin/RangeMax attributes, if the writer stored them
+	pub range: Option<(f64, f64)>,
+}
+
+//********
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata
+{
+	pub file: String,
+
+	// e.g. "UnstructuredGrid", "PUnstructuredGrid", "Collection", or a legacy
+	// DATASET type like "UNSTRUCTURED_GRID"
+	pub vtype      : String,
+	pub version    : String,
+	pub byte_order : String,
+	pub header_type: String,
+	pub compressor : String,
+
+	pub npoints: u64,
+	pub ncells : u64,
+
+	pub points: Option<ArrayInfo>,
+	pub pdata : Vec<ArrayInfo>,
+	pub cdata : Vec<ArrayInfo>,
+	pub fdata : Vec<ArrayInfo>,
+
+	pub time: Option<f64>,
+
+	// Pieces of a .pvtu, or time steps of a .pvd
+	pub children: Vec<Metadata>,
+}
+
+impl Metadata
+{
+	fn new(file: &str) -> Metadata
+	{
+		Metadata
+		{
+			file       : file.to_string(),
+			vtype      : "".to_string(),
+			version    : "".to_string(),
+			byte_order : "".to_string(),
+			header_type: "".to_string(),
+			compressor : "".to_string(),
+
+			npoints: 0,
+			ncells : 0,
+
+			points: None,
+			pdata : Vec::new(),
+			cdata : Vec::new(),
+			fdata : Vec::new(),
+
+			time: None,
+
+			children: Vec::new(),
+		}
+	}
+}
+
+//==============================================================================
+
+pub fn probe(file: &str) -> Metadata
+{
+	// Dispatch on the first non-blank byte:  XML files start with '<', legacy
+	// files with "# vtk"
+
+	let errstr = format!("Cannot probe VTK file \"{}\"", file);
+
+	let mut f = BufReader::new(File::open(file).expect(&errstr));
+	let first = f.fill_buf().expect(&errstr).iter()
+		.find(|c| !c.is_ascii_whitespace()).copied();
+
+	return match first
+	{
+		Some(b'<') => probe_xml(file),
+		Some(b'#') => probe_legacy(file, f),
+		_ => panic!("{}.  Unrecognized format", errstr),
+	};
+}
+
+//==============================================================================
+
+fn attributes(e: &BytesStart, reader: &Reader<BufReader<File>>, errstr: &str)
+	-> Vec<(String, String)>
+{
+	e.attributes().map(|a|
+	{
+		let attr = a.expect(errstr);
+		let key = reader.decode(attr.key).expect(errstr).to_string();
+		let val = attr.unescape_and_decode_value(reader).expect(errstr);
+		(key, val)
+	}).collect()
+}
+
+//********
+
+fn array_info(attrs: &[(String, String)]) -> ArrayInfo
+{
+	let mut a = ArrayInfo
+	{
+		name: "".to_string(),
+		dtype: "".to_string(),
+		ncomponents: 1,
+		range: None,
+	};
+
+	let mut lo = None;
+	let mut hi = None;
+	for (key, val) in attrs
+	{
+		match key.as_str()
+		{
+			vtk::NAME  => a.name = val.clone(),
+			vtk::TYPE  => a.dtype = val.clone(),
+			vtk::NCOMP => a.ncomponents = val.parse().unwrap_or(1),
+
+			// ParaView writes empty ranges for some arrays
+			RANGE_MIN  => lo = val.trim().parse().ok(),
+			RANGE_MAX  => hi = val.trim().parse().ok(),
+			_ => (),
+		}
+	}
+	if let (Some(lo), Some(hi)) = (lo, hi)
+	{
+		a.range = Some((lo, hi));
+	}
+	return a;
+}
+
+//********
+
+fn attr<'a>(attrs: &'a [(String, String)], key: &str) -> Option<&'a str>
+{
+	attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
+
+//********
+
+fn merge_range(a: &mut ArrayInfo, b: &ArrayInfo)
+{
+	// Widen the range of a by b, e.g. to combine pieces
+	a.range = match (a.range, b.range)
+	{
+		(Some(r), Some(s)) => Some((r.0.min(s.0), r.1.max(s.1))),
+		(r, None) | (None, r) => r,
+	};
+}
+
+//********
+
+fn merge_arrays(into: &mut Vec<ArrayInfo>, from: &[ArrayInfo])
+{
+	for b in from
+	{
+		match into.iter_mut().find(|a| a.name == b.name)
+		{
+			Some(a) => merge_range(a, b),
+			None => into.push(b.clone()),
+		}
+	}
+}
+
+//********
+
+fn sibling(file: &str, name: &str) -> String
+{
+	// Resolve a file referenced by a .pvtu or .pvd relative to it
+	let dir = Path::new(file).parent().unwrap_or_else(|| Path::new(""));
+	return dir.join(name).to_string_lossy().to_string();
+}
+
+//********
+
+fn decode_time(text: &str, info: &ArrayInfo, format: &str, m: &Metadata)
+	-> Option<f64>
+{
+	// The only array body that gets decoded.  It holds a single value
+
+	if format == vtk::ASCII
+	{
+		return text.split_whitespace().next()?.parse().ok();
+	}
+
+	let endianness = if m.byte_order == vtk::VTK_BIG {
+		base64::BIG_ENDIAN
+	} else {
+		base64::LITTLE_ENDIAN
+	};
+
+	// Files without a header_type attribute have UInt32 headers
+	let header_bytes = if m.header_type == vtk::VTK_U64 { 8 } else { 4 };
+
+	fn first<T: base64::Scalar>(text: &str, endianness: u8, header_bytes: usize,
+		zlib: bool) -> Option<T>
+	{
+		let v = if zlib
+		{
+			base64::decode_zlib::<T>(text, endianness, header_bytes)
+		}
+		else
+		{
+			base64::decode_with_header::<T>(text, endianness, header_bytes)
+		};
+		return v.first().copied();
+	}
+
+	let zlib = !m.compressor.is_empty();
+	return match info.dtype.as_str()
+	{
+		vtk::VTK_F32 => first::<f32>(text, endianness, header_bytes, zlib)
+			.map(|t| t as f64),
+		vtk::VTK_F64 => first::<f64>(text, endianness, header_bytes, zlib),
+		_ => None,
+	};
+}
+
+//==============================================================================
+
+fn probe_xml(file: &str) -> Metadata
+{
+	let errstr = format!("Cannot probe VTK file \"{}\"", file);
+
+	let mut m = Metadata::new(file);
+
+	let mut reader = Reader::from_file(file).expect(&errstr);
+	reader.trim_text(true);
+
+	let mut buf = Vec::new();
+
+	// Enclosing tag of DataArrays, and a pending TimeValue array
+	let mut section = "".to_string();
+	let mut time: Option<(ArrayInfo, String)> = None;
+
+	loop
+	{
+		let event = reader.read_event(&mut buf);
+		let (e, is_empty) = match event
+		{
+			Ok(Event::Start(ref e)) => (e.to_owned(), false),
+			Ok(Event::Empty(ref e)) => (e.to_owned(), true),
+			Ok(Event::Text(ref e)) =>
+			{
+				if let Some((info, format)) = time.take()
+				{
+					let text = e.unescape_and_decode(&reader).expect(&errstr);
+					m.time = decode_time(&text, &info, &format, &m);
+				}
+				buf.clear();
+				continue;
+			},
+			Ok(Event::End(ref e)) =>
+			{
+				if e.name() == section.as_bytes()
+				{
+					section.clear();
+				}
+				buf.clear();
+				continue;
+			},
+			Ok(Event::Eof) => break,
+			Err(e) => panic!("{}.  Error at position {}: {:?}", errstr,
+				reader.buffer_position(), e),
+			_ =>
+			{
+				buf.clear();
+				continue;
+			},
+		};
+		buf.clear();
+
+		let name = String::from_utf8(e.name().to_vec()).expect(&errstr);
+		let attrs = attributes(&e, &reader, &errstr);
+
+		match name.as_str()
+		{
+			vtk::VTK_FILE =>
+			{
+				m.vtype       = attr(&attrs, vtk::TYPE    ).unwrap_or("").to_string();
+				m.version     = attr(&attrs, vtk::VERSION ).unwrap_or("").to_string();
+				m.byte_order  = attr(&attrs, vtk::BYTEORD ).unwrap_or("").to_string();
+				m.header_type = attr(&attrs, vtk::HEADER  ).unwrap_or("").to_string();
+				m.compressor  = attr(&attrs, vtk::COMPRESS).unwrap_or("").to_string();
+			},
+
+			vtk::PIECE =>
+			{
+				if let Some(source) = attr(&attrs, SOURCE)
+				{
+					// Piece of a parallel file
+					m.children.push(probe(&sibling(file, source)));
+				}
+				else
+				{
+					// Multiple pieces in one serial file add up
+					let count = |key| attr(&attrs, key)
+						.and_then(|n| n.parse::<u64>().ok()).unwrap_or(0);
+					m.npoints += count(vtk::NPOINTS);
+					m.ncells  += count(vtk::NCELLS);
+				}
+			},
+
+			DATASET =>
+			{
+				// Time step of a .pvd collection
+				let source = attr(&attrs, FILE).expect(&errstr);
+				let mut c = probe(&sibling(file, source));
+				if let Some(t) = attr(&attrs, TIMESTEP)
+				{
+					c.time = t.parse().ok();
+				}
+				m.children.push(c);
+			},
+
+			vtk::PDATA | vtk::CDATA | vtk::FDATA | vtk::POINTS |
+			PPDATA | PCDATA | PPOINTS =>
+			{
+				section = name.clone();
+			},
+
+			vtk::DATA | PDATA_ARRAY =>
+			{
+				let info = array_info(&attrs);
+				let format = attr(&attrs, vtk::FORMAT).unwrap_or("").to_string();
+
+				match section.as_str()
+				{
+					vtk::PDATA  | PPDATA  => merge_arrays(&mut m.pdata, &[info]),
+					vtk::CDATA  | PCDATA  => merge_arrays(&mut m.cdata, &[info]),
+					vtk::POINTS | PPOINTS =>
+					{
+						match m.points
+						{
+							Some(ref mut p) => merge_range(p, &info),
+							None => m.points = Some(info),
+						}
+					},
+					vtk::FDATA =>
+					{
+						if info.name == TIME_VALUE && !is_empty
+						{
+							time = Some((info.clone(), format));
+						}
+						m.fdata.push(info);
+					},
+					_ => (),
+				}
+			},
+
+			// Everything after this is raw binary, which is not even valid XML
+			APPENDED => break,
+
+			_ => (),
+		}
+	}
+
+	// Parallel pieces add up, and contribute array ranges
+	let children = if m.vtype == COLLECTION {
+		Vec::new()
+	} else {
+		m.children.clone()
+	};
+	for c in &children
+	{
+		m.npoints += c.npoints;
+		m.ncells  += c.ncells;
+
+		if let (Some(p), Some(q)) = (m.points.as_mut(), c.points.as_ref())
+		{
+			merge_range(p, q);
+		}
+		merge_arrays(&mut m.pdata, &c.pdata);
+		merge_arrays(&mut m.cdata, &c.cdata);
+		if m.time.is_none()
+		{
+			m.time = c.time;
+		}
+	}
+
+	return m;
+}
+
+//==============================================================================
+
+// Legacy files are scanned line by line.  ASCII bodies are skipped by counting
+// tokens and binary bodies by counting bytes
+
+struct LegacyScanner
+{
+	f: BufReader<File>,
+	binary: bool,
+	errstr: String,
+}
+
+impl LegacyScanner
+{
+	fn line(&mut self) -> Option<String>
+	{
+		let mut s = String::new();
+		let n = self.f.read_line(&mut s).expect(&self.errstr);
+		if n == 0 { None } else { Some(s.trim().to_string()) }
+	}
+
+	fn keyword_line(&mut self) -> Option<Vec<String>>
+	{
+		// Next non-blank line, split into words
+		loop
+		{
+			let s = self.line()?;
+			if !s.is_empty()
+			{
+				return Some(s.split_whitespace().map(String::from).collect());
+			}
+		}
+	}
+
+	fn skip(&mut self, n: u64, dtype: &str)
+	{
+		// Skip n values of the given legacy type
+		if self.binary
+		{
+			let bytes = n * type_size(dtype);
+			std::io::copy(&mut (&mut self.f).take(bytes), &mut std::io::sink())
+				.expect(&self.errstr);
+			return;
+		}
+
+		let mut count = 0;
+		while count < n
+		{
+			let s = self.line().expect(&self.errstr);
+			count += s.split_whitespace().count() as u64;
+		}
+	}
+
+	fn value(&mut self, dtype: &str) -> Option<f64>
+	{
+		// Read a single value, e.g. the TimeValue
+		if self.binary
+		{
+			let mut b = vec![0; type_size(dtype) as usize];
+			self.f.read_exact(&mut b).ok()?;
+			return match dtype
+			{
+				"float"  => Some(f32::from_be_bytes([b[0], b[1], b[2], b[3]]) as f64),
+				"double" => Some(<f64 as base64::Scalar>::from_bytes(&b,
+					base64::BIG_ENDIAN)),
+				_ => None,
+			};
+		}
+
+		let s = self.line()?;
+		return s.split_whitespace().next()?.parse().ok();
+	}
+}
+
+//********
+
+pub fn type_size(dtype: &str) -> u64
+{
+	// Bytes per value of a legacy or XML data type
+	match dtype
+	{
+		"bit" | "char" | "unsigned_char" | vtk::VTK_I8 | vtk::VTK_U8 => 1,
+		"short" | "unsigned_short" | vtk::VTK_I16 | vtk::VTK_U16 => 2,
+		"int" | "unsigned_int" | "float" | vtk::VTK_I32 | vtk::VTK_U32
+			| vtk::VTK_F32 => 4,
+		_ => 8,
+	}
+}
+
+//********
+
+fn parse(words: &[String], i: usize, errstr: &str) -> u64
+{
+	words.get(i).and_then(|w| w.parse().ok())
+		.unwrap_or_else(|| panic!("{}.  Bad line \"{}\"", errstr, words.join(" ")))
+}
+
+//********
+
+fn probe_legacy(file: &str, f: BufReader<File>) -> Metadata
+{
+	let errstr = format!("Cannot probe legacy VTK file \"{}\"", file);
+
+	let mut m = Metadata::new(file);
+	m.byte_order = vtk::VTK_BIG.to_string();
+
+	let mut s = LegacyScanner { f, binary: false, errstr: errstr.clone() };
+
+	// "# vtk DataFile Version 4.2", title, then ASCII or BINARY
+	let header = s.line().expect(&errstr);
+	m.version = header.rsplit(' ').next().unwrap_or("").to_string();
+	s.line();
+	s.binary = s.line().expect(&errstr).eq_ignore_ascii_case("BINARY");
+
+	let version5 = m.version.starts_with('5');
+
+	// Number of tuples in the current POINT_DATA or CELL_DATA section
+	let mut ntuples = 0;
+	let mut in_points = true;
+
+	while let Some(w) = s.keyword_line()
+	{
+		let key = w[0].to_uppercase();
+		match key.as_str()
+		{
+			"DATASET" => m.vtype = w.get(1).cloned().unwrap_or_default(),
+
+			"DIMENSIONS" =>
+			{
+				let d: Vec<u64> = (1 .. 4).map(|i| parse(&w, i, &errstr)).collect();
+				m.npoints = d[0] * d[1] * d[2];
+				m.ncells = d.iter().map(|&n| std::cmp::max(n, 2) - 1).product();
+			},
+
+			"POINTS" =>
+			{
+				m.npoints = parse(&w, 1, &errstr);
+				m.points = Some(ArrayInfo
+				{
+					name: "Points".to_string(),
+					dtype: w[2].clone(),
+					ncomponents: 3,
+					range: None,
+				});
+				s.skip(3 * m.npoints, &w[2]);
+			},
+
+			"X_COORDINATES" | "Y_COORDINATES" | "Z_COORDINATES" =>
+			{
+				s.skip(parse(&w, 1, &errstr), &w[2]);
+			},
+
+			"CELLS" | "VERTICES" | "LINES" | "POLYGONS" | "TRIANGLE_STRIPS" =>
+			{
+				let n = parse(&w, 1, &errstr);
+				let size = parse(&w, 2, &errstr);
+				if version5
+				{
+					// OFFSETS and CONNECTIVITY sections follow
+					m.ncells += n.saturating_sub(1);
+					for count in [n, size].iter()
+					{
+						let sub = s.keyword_line().expect(&errstr);
+						s.skip(*count, &sub[1]);
+					}
+				}
+				else
+				{
+					m.ncells += n;
+					s.skip(size, "int");
+				}
+			},
+
+			"CELL_TYPES" => s.skip(parse(&w, 1, &errstr), "int"),
+
+			"POINT_DATA" =>
+			{
+				ntuples = parse(&w, 1, &errstr);
+				in_points = true;
+			},
+			"CELL_DATA" =>
+			{
+				ntuples = parse(&w, 1, &errstr);
+				in_points = false;
+			},
+
+			"SCALARS" | "VECTORS" | "NORMALS" | "TENSORS" | "TENSORS6" |
+			"TEXTURE_COORDINATES" | "GLOBAL_IDS" | "PEDIGREE_IDS" |
+			"COLOR_SCALARS" =>
+			{
+				let (nc, dtype) = match key.as_str()
+				{
+					"SCALARS" => (w.get(3).and_then(|n| n.parse().ok()).unwrap_or(1),
+						w[2].clone()),
+					"VECTORS" | "NORMALS" => (3, w[2].clone()),
+					"TENSORS"  => (9, w[2].clone()),
+					"TENSORS6" => (6, w[2].clone()),
+					"TEXTURE_COORDINATES" => (parse(&w, 2, &errstr), w[3].clone()),
+					"COLOR_SCALARS" => (parse(&w, 2, &errstr),
+						if s.binary { "unsigned_char" } else { "float" }.to_string()),
+					_ => (1, w[2].clone()),
+				};
+
+				if key == "SCALARS"
+				{
+					// Followed by a LOOKUP_TABLE line
+					let lut = s.keyword_line().expect(&errstr);
+					if !lut[0].eq_ignore_ascii_case("LOOKUP_TABLE")
+					{
+						panic!("{}.  Expected LOOKUP_TABLE after SCALARS", errstr);
+					}
+				}
+				s.skip(nc * ntuples, &dtype);
+
+				let info = ArrayInfo { name: w[1].replace("%20", " "), dtype,
+					ncomponents: nc, range: None };
+				if in_points { m.pdata.push(info) } else { m.cdata.push(info) }
+			},
+
+			"LOOKUP_TABLE" =>
+			{
+				// Standalone table with RGBA entries
+				let n = parse(&w, 2, &errstr);
+				s.skip(4 * n, if s.binary { "unsigned_char" } else { "float" });
+			},
+
+			"FIELD" =>
+			{
+				let narrays = parse(&w, 2, &errstr);
+				let mut i = 0;
+				while i < narrays
+				{
+					let a = s.keyword_line().expect(&errstr);
+					if a[0] == "METADATA"
+					{
+						// Information keys until a blank line
+						while !s.line().expect(&errstr).is_empty() {}
+						continue;
+					}
+
+					let nc = parse(&a, 1, &errstr);
+					let nt = parse(&a, 2, &errstr);
+					let info = ArrayInfo { name: a[0].replace("%20", " "),
+						dtype: a[3].clone(), ncomponents: nc, range: None };
+
+					if info.name == TIME_VALUE && nc * nt == 1
+					{
+						m.time = s.value(&a[3]);
+					}
+					else
+					{
+						s.skip(nc * nt, &a[3]);
+					}
+
+					// Field data before any POINT_DATA belongs to the dataset
+					if ntuples == 0
+					{
+						m.fdata.push(info);
+					}
+					else if in_points
+					{
+						m.pdata.push(info);
+					}
+					else
+					{
+						m.cdata.push(info);
+					}
+					i += 1;
+				}
+			},
+
+			// Information keys for the previous array
+			"METADATA" => while !s.line().expect(&errstr).is_empty() {},
+
+			_ => (),
+		}
+	}
+
+	return m;
+}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::vtk;
+
+	#[test]
+	fn test_probe_vtu()
+	{
+		let m = probe("./data/icosahedron-binary-pdata.vtu");
+		assert_eq!(m.vtype, "UnstructuredGrid");
+		assert_eq!(m.header_type, "UInt64");
+		assert_eq!((m.npoints, m.ncells), (12, 20));
+		assert_eq!(m.pdata.len(), 1);
+		assert_eq!(m.pdata[0].name, "Resultz");
+		assert_eq!(m.pdata[0].dtype, "Float64");
+		assert_eq!(m.pdata[0].range, Some((-1.0, 1.2472135709870287)));
+
+		// Stops before the raw appended block
+		let m = probe("./data/icosahedron-appended.vtu");
+		assert_eq!((m.npoints, m.ncells), (12, 20));
+		assert_eq!(m.points.unwrap().ncomponents, 3);
+
+		let m = probe("./data/icosahedron-zlib.vtu");
+		assert_eq!(m.compressor, "vtkZLibDataCompressor");
+	}
+
+	#[test]
+	fn test_probe_pvtu_pvd()
+	{
+		let pvtu = "./scratch/tmp.Gk2PvtuN0q.pvtu";
+		std::fs::write(pvtu, "<VTKFile type=\"PUnstructuredGrid\" version=\"1.0\">\n\
+			<PUnstructuredGrid>\n\
+			<PPointData Scalars=\"Resultz\">\n\
+			<PDataArray type=\"Float64\" Name=\"Resultz\"/>\n\
+			</PPointData>\n\
+			<PPoints><PDataArray type=\"Float32\" NumberOfComponents=\"3\"/></PPoints>\n\
+			<Piece Source=\"../data/icosahedron-binary-pdata.vtu\"/>\n\
+			<Piece Source=\"../data/icosahedron-binary.vtu\"/>\n\
+			</PUnstructuredGrid>\n</VTKFile>\n").unwrap();
+
+		let m = probe(pvtu);
+		assert_eq!(m.children.len(), 2);
+		assert_eq!((m.npoints, m.ncells), (24, 40));
+		assert_eq!(m.pdata[0].range, Some((-1.0, 1.2472135709870287)));
+
+		let pvd = "./scratch/tmp.Gk2PvdLm7r.pvd";
+		std::fs::write(pvd, "<VTKFile type=\"Collection\" version=\"0.1\">\n\
+			<Collection>\n\
+			<DataSet timestep=\"0.5\" file=\"tmp.Gk2PvtuN0q.pvtu\"/>\n\
+			<DataSet timestep=\"1.5\" file=\"../data/icosahedron-ascii.vtu\"/>\n\
+			</Collection>\n</VTKFile>\n").unwrap();
+
+		let m = probe(pvd);
+		assert_eq!(m.children.len(), 2);
+		assert_eq!(m.children[1].time, Some(1.5));
+		assert_eq!(m.children[0].ncells, 40);
+	}
+
+	#[test]
+	fn test_probe_legacy()
+	{
+		let mut v = vtk::load("./data/icosahedron-binary-pdata.vtu");
+		for format in ["ascii", "binary"].iter()
+		{
+			v.format = format.to_string();
+			let temp = "./scratch/tmp.Lq8vX0wPze.vtk";
+			v.export(temp);
+
+			let m = probe(temp);
+			assert_eq!(m.vtype, "UNSTRUCTURED_GRID");
+			assert_eq!(m.version, "4.2");
+			assert_eq!((m.npoints, m.ncells), (12, 20));
+			assert_eq!(m.pdata.len(), 1);
+			assert_eq!(m.pdata[0].name, "Resultz");
+			assert_eq!(m.pdata[0].dtype, "double");
+		}
+	}
+
+	#[test]
+	fn test_probe_time()
+	{
+		// Binary TimeValue with UInt32 and UInt64 headers, plain and zlib
+		let t = [2.5f64];
+		let cases = [
+			(vtk::VTK_U32, "", base64::encode_with_header(&t, base64::LITTLE_ENDIAN, 4)),
+			(vtk::VTK_U64, "", base64::encode_with_header(&t, base64::LITTLE_ENDIAN, 8)),
+			(vtk::VTK_U32, vtk::ZLIB, base64::encode_zlib(&t, base64::LITTLE_ENDIAN, 4)),
+			(vtk::VTK_U64, vtk::ZLIB, base64::encode_zlib(&t, base64::LITTLE_ENDIAN, 8)),
+		];
+		for (header_type, compressor, data) in cases.iter()
+		{
+			let temp = "./scratch/tmp.Tv3RqH8mWb.vtu";
+			std::fs::write(temp, format!("<VTKFile type=\"UnstructuredGrid\" \
+				version=\"1.0\" byte_order=\"LittleEndian\" header_type=\"{}\" \
+				compressor=\"{}\">\n<UnstructuredGrid>\n<FieldData>\n\
+				<DataArray type=\"Float64\" Name=\"TimeValue\" \
+				NumberOfTuples=\"1\" format=\"binary\">{}</DataArray>\n\
+				</FieldData>\n<Piece NumberOfPoints=\"0\" NumberOfCells=\"0\">\n\
+				</Piece>\n</UnstructuredGrid>\n</VTKFile>\n", header_type,
+				compressor, data)).unwrap();
+
+			let m = probe(temp);
+			assert_eq!(m.time, Some(2.5));
+		}
+	}
+}
+
+//==============================================================================
+
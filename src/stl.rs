@@ -0,0 +1,358 @@
+
+//==============================================================================
+
+// STL (stereolithography) surface IO, in ASCII or binary.  Binary STL is
+// always little endian:  an 80-byte header, a UInt32 facet count, and per
+// facet a normal, 3 vertices, and a UInt16 attribute
+
+// Standard
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+//********
+
+// Third party
+use log::{info, warn};
+
+//********
+
+// This lib
+use crate::base64::{self, Scalar};
+use crate::cells;
+use crate::vtk::{Attribute, Data, DataArray, VtkFile};
+
+//********
+
+pub const EXT: &str = ".stl";
+
+// Cell array of the facet normals on import
+pub const NORMALS: &str = "Normals";
+
+const HEADER_LEN: usize = 80;
+const FACET_LEN : usize = 50;
+
+const SOLID: &str = "vtk-io-rs";
+
+const ASCII: &str = "ascii";
+
+//==============================================================================
+
+impl VtkFile
+{
+fn stl_facets(&self) -> (VtkFile, Vec<[f32; 3]>)
+{
+	// Boundary triangles and their normals.  An active 3-component cell
+	// normals array is used if there is one, e.g. from an STL import
+
+	let s = self.surface();
+	let nother = s.types.iter().filter(|&&t| cells::dimension(t) != Some(2)).count();
+	if nother > 0
+	{
+		warn!("dropping {} vertices and lines, which STL cannot hold", nother);
+	}
+	let t = s.triangulate();
+
+	let normals = match t.active_cell_array(Attribute::Normals)
+	{
+		Some(a) if a.ncomponents == 3 =>
+		{
+			a.data.to_f64().chunks_exact(3)
+				.map(|n| [n[0] as f32, n[1] as f32, n[2] as f32]).collect()
+		},
+		_ => t.facet_normals(),
+	};
+
+	return (t, normals);
+}
+
+//********
+
+pub fn write_stl<W: Write>(&self, f: &mut W, ascii: bool) -> Result<(), io::Error>
+{
+	let (t, normals) = self.stl_facets();
+
+	let nc = t.ncomponents as usize;
+	let point = |p: i64| -> [f32; 3]
+	{
+		let mut x = [0.0; 3];
+		for d in 0 .. nc.min(3)
+		{
+			x[d] = t.points[nc * p as usize + d];
+		}
+		x
+	};
+
+	if ascii
+	{
+		writeln!(f, "solid {}", SOLID)?;
+		for (i, n) in normals.iter().enumerate()
+		{
+			writeln!(f, "  facet normal {:e} {:e} {:e}", n[0], n[1], n[2])?;
+			writeln!(f, "    outer loop")?;
+			for &p in t.cell_points(i)
+			{
+				let x = point(p);
+				writeln!(f, "      vertex {:e} {:e} {:e}", x[0], x[1], x[2])?;
+			}
+			writeln!(f, "    endloop")?;
+			writeln!(f, "  endfacet")?;
+		}
+		writeln!(f, "endsolid {}", SOLID)?;
+		return Ok(());
+	}
+
+	let mut header = format!("binary STL from {}", SOLID).into_bytes();
+	header.resize(HEADER_LEN, b' ');
+	f.write_all(&header)?;
+	f.write_all(&(t.ncells as u32).to_bytes(base64::LITTLE_ENDIAN))?;
+
+	let mut facet = Vec::with_capacity(FACET_LEN);
+	for (i, n) in normals.iter().enumerate()
+	{
+		facet.clear();
+		let xs = t.cell_points(i).iter().map(|&p| point(p));
+		for x in std::iter::once(*n).chain(xs)
+		{
+			for c in &x
+			{
+				facet.extend(c.to_bytes(base64::LITTLE_ENDIAN));
+			}
+		}
+		facet.extend(0_u16.to_bytes(base64::LITTLE_ENDIAN));
+		f.write_all(&facet)?;
+	}
+	return Ok(());
+}
+
+//********
+
+pub fn export_stl(&self, file: &str)
+{
+	info!("Exporting STL file \"{}\"", file);
+
+	let try_export = || -> Result<(), io::Error>
+	{
+		let mut f = BufWriter::new(File::create(file)?);
+		self.write_stl(&mut f, self.format == ASCII)?;
+		f.flush()
+	};
+
+	if let Err(e) = try_export()
+	{
+		panic!("Cannot export STL file \"{}\":  {}", file, e);
+	}
+}}
+
+//==============================================================================
+
+fn is_binary(bytes: &[u8]) -> bool
+{
+	// Binary files may also begin with "solid", so trust the size if it fits
+	if bytes.len() >= HEADER_LEN + 4
+	{
+		let n = u32::from_bytes(&bytes[HEADER_LEN ..], base64::LITTLE_ENDIAN);
+		if bytes.len() == HEADER_LEN + 4 + FACET_LEN * n as usize
+		{
+			return true;
+		}
+	}
+	return !bytes.trim_ascii_start().starts_with(b"solid");
+}
+
+//********
+
+fn parse_binary(bytes: &[u8], name: &str) -> Vec<[f32; 12]>
+{
+	// Normal and 3 vertices of each facet
+	let n = u32::from_bytes(&bytes[HEADER_LEN ..], base64::LITTLE_ENDIAN) as usize;
+	if bytes.len() < HEADER_LEN + 4 + FACET_LEN * n
+	{
+		panic!("Cannot read STL file \"{}\":  {} facets do not fit in {} bytes",
+			name, n, bytes.len());
+	}
+
+	return bytes[HEADER_LEN + 4 ..].chunks_exact(FACET_LEN).take(n).map(|b|
+	{
+		let mut facet = [0.0; 12];
+		for (j, x) in facet.iter_mut().enumerate()
+		{
+			*x = f32::from_bytes(&b[4*j ..], base64::LITTLE_ENDIAN);
+		}
+		facet
+	}).collect();
+}
+
+//********
+
+fn parse_ascii(text: &str, name: &str) -> Vec<[f32; 12]>
+{
+	let errstr = format!("Cannot read STL file \"{}\"", name);
+	let mut facets = Vec::new();
+	let mut facet = [0.0; 12];
+	let mut nvertices = 0;
+
+	for line in text.lines()
+	{
+		let mut words = line.split_whitespace();
+		let xyz = |words: std::str::SplitWhitespace| -> Vec<f32> { words
+			.map(|w| w.parse().unwrap_or_else(|_| panic!("{}:  bad number \
+			\"{}\"", errstr, w))).collect() };
+
+		match words.next()
+		{
+			Some("facet") =>
+			{
+				words.next(); // "normal"
+				let n = xyz(words);
+				facet = [0.0; 12];
+				facet[.. 3].copy_from_slice(&n[.. 3.min(n.len())]);
+				nvertices = 0;
+			},
+			Some("vertex") =>
+			{
+				let x = xyz(words);
+				if nvertices >= 3 || x.len() != 3
+				{
+					panic!("{}:  facets must be triangles", errstr);
+				}
+				nvertices += 1;
+				facet[3 * nvertices .. 3 * nvertices + 3].copy_from_slice(&x);
+			},
+			Some("endfacet") =>
+			{
+				if nvertices != 3
+				{
+					panic!("{}:  facets must be triangles", errstr);
+				}
+				facets.push(facet);
+			},
+			_ => (),
+		}
+	}
+	return facets;
+}
+
+//********
+
+pub fn read<R: Read>(mut reader: R, name: &str) -> VtkFile
+{
+	// Triangle grid with duplicate vertices merged, and the facet normals as
+	// the active cell normals
+
+	let errstr = format!("Cannot read STL file \"{}\"", name);
+	let mut bytes = Vec::new();
+	reader.read_to_end(&mut bytes).expect(&errstr);
+
+	let binary = is_binary(&bytes);
+	let facets = if binary
+	{
+		parse_binary(&bytes, name)
+	}
+	else
+	{
+		parse_ascii(&String::from_utf8_lossy(&bytes), name)
+	};
+
+	let mut v = VtkFile::new();
+	v.vtype = "UnstructuredGrid".to_string();
+	v.ncomponents = 3;
+	if !binary
+	{
+		v.format = ASCII.to_string();
+	}
+
+	// Vertices match by their exact bits
+	let mut ids: HashMap<[u32; 3], i64> = HashMap::new();
+	let mut normals = Vec::with_capacity(3 * facets.len());
+	for facet in &facets
+	{
+		normals.extend_from_slice(&facet[.. 3]);
+		for x in facet[3 ..].chunks_exact(3)
+		{
+			let key = [x[0].to_bits(), x[1].to_bits(), x[2].to_bits()];
+			let next = ids.len() as i64;
+			let id = *ids.entry(key).or_insert_with(||
+			{
+				v.points.extend_from_slice(x);
+				next
+			});
+			v.connectivity.push(id);
+		}
+		v.offsets.push(v.connectivity.len() as i64);
+		v.types.push(cells::VTK_TRIANGLE);
+	}
+
+	v.npoints = ids.len() as u64;
+	v.ncells  = facets.len() as u64;
+	v.cdata.push(DataArray::new(NORMALS, 3, Data::F32(normals)));
+	v.set_cell_attribute(Attribute::Normals, Some(NORMALS));
+
+	return v;
+}
+
+//********
+
+pub fn load(file: &str) -> VtkFile
+{
+	info!("Loading STL file \"{}\"", file);
+	let errstr = format!("Cannot load STL file \"{}\"", file);
+	let f = File::open(file).expect(&errstr);
+	return read(BufReader::new(f), file);
+}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::vtk;
+
+	#[test]
+	fn test_stl()
+	{
+		let v = vtk::load("./data/icosahedron-binary.vtu");
+		for &ascii in &[true, false]
+		{
+			let mut buf = Vec::new();
+			v.write_stl(&mut buf, ascii).unwrap();
+			assert_eq!(is_binary(&buf), !ascii);
+
+			let w = read(&buf[..], "buffer");
+			assert_eq!(w.npoints, 12);
+			assert_eq!(w.ncells, 20);
+			assert_eq!(w.types, vec![cells::VTK_TRIANGLE; 20]);
+			assert_eq!(w.cdata[0].data.len(), 60);
+
+			// Points are renumbered in order of appearance, but binary keeps
+			// the coordinates exactly
+			let xyz = |u: &VtkFile| -> Vec<f32> { u.connectivity.iter()
+				.flat_map(|&p| u.points[3 * p as usize ..][.. 3].to_vec())
+				.collect() };
+			if !ascii
+			{
+				assert_eq!(xyz(&w), xyz(&v));
+			}
+
+			// Normals are carried through on export
+			let mut again = Vec::new();
+			w.write_stl(&mut again, ascii).unwrap();
+			assert_eq!(again, buf);
+		}
+	}
+
+	#[test]
+	#[should_panic(expected = "facets must be triangles")]
+	fn test_stl_short_facet()
+	{
+		// A facet with only two vertices
+		let text = "solid s\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\n\
+			vertex 1 0 0\nendloop\nendfacet\nendsolid s\n";
+		read(text.as_bytes(), "short");
+	}
+}
+
+//==============================================================================
+
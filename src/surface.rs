@@ -0,0 +1,284 @@
+
+//==============================================================================
+
+// Boundary surface of a grid, for surface-only formats and viewers
+
+//********
+
+// Standard
+use std::collections::HashMap;
+
+//********
+
+// Third party
+use log::warn;
+
+//********
+
+// This lib
+use crate::cells;
+use crate::vtk::{DataArray, VtkFile};
+
+//==============================================================================
+
+impl VtkFile
+{
+pub fn surface(&self) -> VtkFile
+{
+	// Vertices, lines, and 2D cells, plus the faces of 3D cells that are not
+	// shared with another 3D cell.  Lagrange and Bézier cells are linearized
+	// first, and quadratic cells are reduced to their corners.  Points and
+	// point data are kept as-is, and cell data comes from the parent cell of
+	// each new cell
+
+	if self.types.iter().any(|&t| cells::is_higher_order(t))
+	{
+		return self.linearize().surface();
+	}
+
+	let mut v = self.clone();
+	v.connectivity.clear();
+	v.offsets.clear();
+	v.types.clear();
+
+	let mut parents = Vec::new();
+
+	// 3D faces in order of first appearance, with their parent and count.
+	// Faces are matched by their sorted point IDs
+	let mut faces: Vec<(Vec<i64>, usize, u8)> = Vec::new();
+	let mut index: HashMap<Vec<i64>, usize> = HashMap::new();
+
+	let mut nskipped = 0;
+	for i in 0 .. self.ncells as usize
+	{
+		let conn = self.cell_points(i);
+		let ctype = self.types[i];
+		let corners = &conn[.. cells::ncorners(ctype, conn.len()).min(conn.len())];
+
+		match cells::dimension(ctype)
+		{
+			Some(0) | Some(1) | Some(2) =>
+			{
+				v.connectivity.extend_from_slice(corners);
+				v.offsets.push(v.connectivity.len() as i64);
+				v.types.push(cells::corner_type(ctype));
+				parents.push(i);
+			},
+			Some(_) if !cells::faces(ctype).is_empty() =>
+			{
+				for f in cells::faces(ctype)
+				{
+					let face: Vec<i64> = f.iter().map(|&j| corners[j]).collect();
+					let mut key = face.clone();
+					key.sort_unstable();
+
+					match index.get(&key)
+					{
+						Some(&k) => faces[k].2 += 1,
+						None =>
+						{
+							index.insert(key, faces.len());
+							faces.push((face, i, 1));
+						},
+					}
+				}
+			},
+			_ => nskipped += 1,
+		}
+	}
+
+	if nskipped > 0
+	{
+		warn!("skipping {} cells without a surface, e.g. polyhedra or empty \
+			cells", nskipped);
+	}
+
+	for (face, parent, count) in faces
+	{
+		if count > 1
+		{
+			continue;
+		}
+		v.types.push(if face.len() == 3 { cells::VTK_TRIANGLE }
+			else { cells::VTK_QUAD });
+		v.connectivity.extend(face);
+		v.offsets.push(v.connectivity.len() as i64);
+		parents.push(parent);
+	}
+
+	v.ncells = v.types.len() as u64;
+	v.cdata = self.cdata.iter()
+		.filter(|a| a.name != cells::HO_DEGREES)
+		.map(|a| DataArray
+		{
+			data: a.data.select(a.ncomponents as usize, &parents),
+			..a.clone()
+		})
+		.collect();
+
+	return v;
+}
+
+//********
+
+pub fn triangulate(&self) -> VtkFile
+{
+	// Split 2D cells into triangles:  quads and polygons as fans from their
+	// first point, and strips with alternating orientation.  Other cells are
+	// dropped, so use surface() first for 3D cells.  Cell data is replicated
+	// onto the triangles
+
+	let mut v = self.clone();
+	v.connectivity.clear();
+	v.offsets.clear();
+	v.types.clear();
+
+	let mut parents = Vec::new();
+	for i in 0 .. self.ncells as usize
+	{
+		let mut conn = self.cell_points(i).to_vec();
+		let ctype = self.types[i];
+		if cells::dimension(ctype) != Some(2)
+		{
+			continue;
+		}
+		conn.truncate(cells::ncorners(ctype, conn.len()));
+
+		let mut tris = Vec::new();
+		match cells::corner_type(ctype)
+		{
+			cells::VTK_TRIANGLE_STRIP => for j in 2 .. conn.len()
+			{
+				tris.push(if j % 2 == 0 { [conn[j-2], conn[j-1], conn[j]] }
+					else { [conn[j-1], conn[j-2], conn[j]] });
+			},
+			c =>
+			{
+				// Pixels are quads with the last 2 points swapped
+				if c == cells::VTK_PIXEL && conn.len() == 4
+				{
+					conn.swap(2, 3);
+				}
+				for j in 2 .. conn.len()
+				{
+					tris.push([conn[0], conn[j-1], conn[j]]);
+				}
+			},
+		}
+
+		for t in tris
+		{
+			v.connectivity.extend_from_slice(&t);
+			v.offsets.push(v.connectivity.len() as i64);
+			v.types.push(cells::VTK_TRIANGLE);
+			parents.push(i);
+		}
+	}
+
+	v.ncells = v.types.len() as u64;
+	for a in v.cdata.iter_mut()
+	{
+		a.data = a.data.select(a.ncomponents as usize, &parents);
+	}
+
+	return v;
+}
+
+//********
+
+pub fn facet_normals(&self) -> Vec<[f32; 3]>
+{
+	// Unit normal of each triangle by the right-hand rule, or zero for
+	// degenerate triangles.  Other cells use their first 3 points
+
+	let nc = self.ncomponents as usize;
+	let point = |p: i64| -> [f64; 3]
+	{
+		let mut x = [0.0; 3];
+		for d in 0 .. nc.min(3)
+		{
+			x[d] = self.points[nc * p as usize + d] as f64;
+		}
+		x
+	};
+
+	return (0 .. self.ncells as usize).map(|i|
+	{
+		let c = self.cell_points(i);
+		if c.len() < 3
+		{
+			return [0.0; 3];
+		}
+		let (a, b, c) = (point(c[0]), point(c[1]), point(c[2]));
+		let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+		let w = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+		let n = [u[1]*w[2] - u[2]*w[1], u[2]*w[0] - u[0]*w[2], u[0]*w[1] - u[1]*w[0]];
+		let len = (n[0]*n[0] + n[1]*n[1] + n[2]*n[2]).sqrt();
+		if len == 0.0
+		{
+			return [0.0; 3];
+		}
+		[(n[0] / len) as f32, (n[1] / len) as f32, (n[2] / len) as f32]
+	}).collect();
+}}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	#[test]
+	fn test_surface()
+	{
+		// Two tets sharing the face (1, 2, 3), and a line
+		let mut v = VtkFile::new();
+		v.vtype = "UnstructuredGrid".to_string();
+		v.npoints = 5;
+		v.points = vec![0.0, 0.0, 0.0,  1.0, 0.0, 0.0,  0.0, 1.0, 0.0,
+			0.0, 0.0, 1.0,  1.0, 1.0, 1.0];
+		v.connectivity = vec![0, 1, 2, 3,  1, 2, 3, 4,  0, 4];
+		v.offsets = vec![4, 8, 10];
+		v.types = vec![cells::VTK_TETRA, cells::VTK_TETRA, cells::VTK_LINE];
+		v.ncells = 3;
+		v.cdata.push(DataArray::new("id", 1,
+			crate::vtk::Data::I32(vec![10, 11, 12])));
+
+		let s = v.surface();
+		assert_eq!(s.ncells, 7);
+		assert_eq!(s.types[0], cells::VTK_LINE);
+		assert!(s.types[1 ..].iter().all(|&t| t == cells::VTK_TRIANGLE));
+		assert_eq!(s.cdata[0].data, crate::vtk::Data::I32(vec![12, 10, 10, 10,
+			11, 11, 11]));
+
+		let t = s.triangulate();
+		assert_eq!(t.ncells, 6);
+		assert_eq!(t.cdata[0].data, crate::vtk::Data::I32(vec![10, 10, 10,
+			11, 11, 11]));
+
+		// Outward normal of the tet face on the z = 0 plane
+		assert_eq!(t.facet_normals()[2], [0.0, 0.0, -1.0]);
+	}
+
+	#[test]
+	fn test_triangulate()
+	{
+		// A quad and a pentagon
+		let mut v = VtkFile::new();
+		v.npoints = 6;
+		v.points = vec![0.0; 18];
+		v.connectivity = vec![0, 1, 2, 3,  0, 1, 2, 4, 5];
+		v.offsets = vec![4, 9];
+		v.types = vec![cells::VTK_QUAD, cells::VTK_POLYGON];
+		v.ncells = 2;
+
+		let t = v.triangulate();
+		assert_eq!(t.ncells, 5);
+		assert_eq!(t.cell_points(1), &[0, 2, 3]);
+		assert_eq!(t.cell_points(4), &[0, 4, 5]);
+	}
+}
+
+//==============================================================================
+
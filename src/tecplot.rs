@@ -0,0 +1,277 @@
+
+//==============================================================================
+
+// Tecplot ASCII (.dat) export of finite element zones.  Tecplot zones hold a
+// single element type, so mixed grids get one zone per type, and all zones
+// share the points and point data of the first one
+
+// Standard
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+
+//********
+
+// Third party
+use log::{info, warn};
+
+//********
+
+// This lib
+use crate::cells;
+use crate::vtk::{DataArray, VtkFile};
+
+//********
+
+pub const EXT: &str = ".dat";
+
+const TITLE: &str = "vtk-io-rs";
+
+// Values per line of the blocks of variables
+const NPERLINE: usize = 10;
+
+// VTK type and the VTK node of each Tecplot node
+type Nodes = (u8, &'static [usize]);
+
+// Zone types with the VTK types they hold.  Wedges and pyramids are bricks
+// with repeated nodes
+const ZONES: &[(&str, &[Nodes])] = &[
+	("FELINESEG"      , &[(cells::VTK_LINE, &[0, 1])]),
+	("FETRIANGLE"     , &[(cells::VTK_TRIANGLE, &[0, 1, 2])]),
+	("FEQUADRILATERAL", &[
+		(cells::VTK_QUAD , &[0, 1, 2, 3]),
+		(cells::VTK_PIXEL, &[0, 1, 3, 2]),
+	]),
+	("FETETRAHEDRON"  , &[(cells::VTK_TETRA, &[0, 1, 2, 3])]),
+	("FEBRICK"        , &[
+		(cells::VTK_HEXAHEDRON, &[0, 1, 2, 3, 4, 5, 6, 7]),
+		(cells::VTK_VOXEL     , &[0, 1, 3, 2, 4, 5, 7, 6]),
+		(cells::VTK_WEDGE     , &[0, 1, 2, 2, 3, 4, 5, 5]),
+		(cells::VTK_PYRAMID   , &[0, 1, 2, 3, 4, 4, 4, 4]),
+	]),
+];
+
+//==============================================================================
+
+fn variable_names(a: &DataArray) -> Vec<String>
+{
+	// One variable per component, named by the component names if there are
+	// any.  Quotes would end the name
+	let nc = a.ncomponents as usize;
+	let names: Vec<String> = if nc == 1
+	{
+		vec![a.name.clone()]
+	}
+	else
+	{
+		(0 .. nc).map(|c| match a.component_names.get(c)
+		{
+			Some(n) if !n.is_empty() => format!("{}_{}", a.name, n),
+			_ => format!("{}_{}", a.name, c),
+		}).collect()
+	};
+	return names.iter().map(|n| format!("\"{}\"", n.replace('"', "'"))).collect();
+}
+
+//********
+
+fn write_block<W: Write>(f: &mut W, values: &[String]) -> Result<(), io::Error>
+{
+	for line in values.chunks(NPERLINE)
+	{
+		writeln!(f, "{}", line.join(" "))?;
+	}
+	return Ok(());
+}
+
+//********
+
+fn range(begin: usize, end: usize) -> String
+{
+	// 1-based, inclusive variable range, e.g. "4-6"
+	if end == begin + 1
+	{
+		return (begin + 1).to_string();
+	}
+	return format!("{}-{}", begin + 1, end);
+}
+
+//==============================================================================
+
+impl VtkFile
+{
+pub fn write_tecplot<W: Write>(&self, f: &mut W) -> Result<(), io::Error>
+{
+	// Block packing:  all values of each variable in turn, then the 1-based
+	// connectivity of the zone.  Cell arrays are cell-centered variables
+
+	if self.types.iter().any(|&t| cells::is_higher_order(t))
+	{
+		return self.linearize().write_tecplot(f);
+	}
+
+	let nc = self.ncomponents as usize;
+	let npoints = self.npoints as usize;
+
+	let mut variables: Vec<String> = ["\"X\"", "\"Y\"", "\"Z\""].iter()
+		.map(|s| s.to_string()).collect();
+	variables.extend(self.pdata.iter().flat_map(variable_names));
+	let nnodal = variables.len();
+	variables.extend(self.cdata.iter().flat_map(variable_names));
+
+	writeln!(f, "TITLE = \"{}\"", TITLE)?;
+	writeln!(f, "VARIABLES = {}", variables.join(" "))?;
+
+	let mut nwritten = 0;
+	let mut first = true;
+	for (zonetype, types) in ZONES
+	{
+		let zcells: Vec<(usize, &[usize])> = (0 .. self.ncells as usize)
+			.filter_map(|i| types.iter().find(|t| t.0 == self.types[i])
+				.map(|t| (i, t.1)))
+			.collect();
+		if zcells.is_empty()
+		{
+			continue;
+		}
+		nwritten += zcells.len();
+
+		write!(f, "ZONE T=\"{}\", ZONETYPE={}, NODES={}, ELEMENTS={}, \
+			DATAPACKING=BLOCK", &zonetype[2 ..], zonetype, npoints, zcells.len())?;
+		if variables.len() > nnodal
+		{
+			write!(f, ", VARLOCATION=([{}]=CELLCENTERED)",
+				range(nnodal, variables.len()))?;
+		}
+		if !first
+		{
+			write!(f, ", VARSHARELIST=([{}]=1)", range(0, nnodal))?;
+		}
+		writeln!(f)?;
+
+		if first
+		{
+			for d in 0 .. 3
+			{
+				let x: Vec<String> = (0 .. npoints).map(|p| if d < nc
+				{
+					self.points[nc * p + d].to_string()
+				}
+				else
+				{
+					"0".to_string()
+				}).collect();
+				write_block(f, &x)?;
+			}
+			for a in &self.pdata
+			{
+				let anc = a.ncomponents as usize;
+				for c in 0 .. anc
+				{
+					let x: Vec<String> = (0 .. npoints)
+						.map(|p| a.data.value_string(anc * p + c)).collect();
+					write_block(f, &x)?;
+				}
+			}
+			first = false;
+		}
+
+		for a in &self.cdata
+		{
+			let anc = a.ncomponents as usize;
+			for c in 0 .. anc
+			{
+				let x: Vec<String> = zcells.iter()
+					.map(|&(i, _)| a.data.value_string(anc * i + c)).collect();
+				write_block(f, &x)?;
+			}
+		}
+
+		for (i, nodes) in &zcells
+		{
+			let conn = self.cell_points(*i);
+			let ids: Vec<String> = nodes.iter().map(|&j| (conn[j] + 1).to_string())
+				.collect();
+			writeln!(f, "{}", ids.join(" "))?;
+		}
+	}
+
+	let nskipped = self.ncells as usize - nwritten;
+	if nskipped > 0
+	{
+		warn!("skipping {} vertices, poly lines, strips, and polygons, which \
+			Tecplot zones cannot hold", nskipped);
+	}
+	if first
+	{
+		warn!("no cells for Tecplot zones, so the points are dropped too");
+	}
+
+	return Ok(());
+}
+
+//********
+
+pub fn export_tecplot(&self, file: &str)
+{
+	info!("Exporting Tecplot file \"{}\"", file);
+
+	let try_export = || -> Result<(), io::Error>
+	{
+		let mut f = BufWriter::new(File::create(file)?);
+		self.write_tecplot(&mut f)?;
+		f.flush()
+	};
+
+	if let Err(e) = try_export()
+	{
+		panic!("Cannot export Tecplot file \"{}\":  {}", file, e);
+	}
+}}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use crate::cells;
+	use crate::vtk::{Data, DataArray, VtkFile};
+
+	#[test]
+	fn test_tecplot()
+	{
+		// A tet and a wedge on 7 points, with a point and a cell array
+		let mut v = VtkFile::new();
+		v.vtype = "UnstructuredGrid".to_string();
+		v.ncomponents = 3;
+		v.npoints = 7;
+		v.points = vec![0.0, 0.0, 0.0,  1.0, 0.0, 0.0,  0.0, 1.0, 0.0,
+			0.0, 0.0, 1.0,  1.0, 0.0, 1.0,  0.0, 1.0, 1.0,  0.5, 0.5, 2.0];
+		v.ncells = 2;
+		v.connectivity = vec![3, 4, 5, 6,  0, 1, 2, 3, 4, 5];
+		v.offsets = vec![4, 10];
+		v.types = vec![cells::VTK_TETRA, cells::VTK_WEDGE];
+		v.pdata.push(DataArray::new("T", 1, Data::F32((0 .. 7).map(|i| i as f32)
+			.collect())));
+		v.cdata.push(DataArray::new("Id", 2, Data::I32(vec![1, 2, 3, 4])));
+
+		let mut buf = Vec::new();
+		v.write_tecplot(&mut buf).unwrap();
+		let s = String::from_utf8(buf).unwrap();
+		let lines: Vec<&str> = s.lines().collect();
+
+		assert_eq!(lines[1], "VARIABLES = \"X\" \"Y\" \"Z\" \"T\" \"Id_0\" \"Id_1\"");
+		assert_eq!(lines[2], "ZONE T=\"TETRAHEDRON\", ZONETYPE=FETETRAHEDRON, \
+			NODES=7, ELEMENTS=1, DATAPACKING=BLOCK, VARLOCATION=([5-6]=CELLCENTERED)");
+		assert_eq!(lines[4], "0 0 1 0 0 1 0.5");
+		assert_eq!(lines[7], "1");
+		assert_eq!(lines[9], "4 5 6 7");
+		assert_eq!(lines[10], "ZONE T=\"BRICK\", ZONETYPE=FEBRICK, NODES=7, \
+			ELEMENTS=1, DATAPACKING=BLOCK, VARLOCATION=([5-6]=CELLCENTERED), \
+			VARSHARELIST=([1-4]=1)");
+		assert_eq!(&lines[11 ..], &["3", "4", "1 2 3 3 4 5 6 6"]);
+	}
+}
+
+//==============================================================================
+
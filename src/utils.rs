@@ -3,8 +3,12 @@ use std::env;
 use std::path::Path;
 use std::ffi::OsStr;
 
-use clap::{Arg, App};
+use clap::{Arg, App, AppSettings, ArgMatches, Error, ErrorKind, SubCommand};
 
+use crate::batch;
+use crate::extract::Section;
+use crate::gltf;
+use crate::logger;
 use crate::vtk;
 
 // Get the filename of this executable
@@ -18,6 +22,55 @@ pub fn this() -> Option<String>
 		.map(String::from)
 }
 
+fn parse_tol(s: &str) -> f64
+{
+	let tol: f64 = s.parse().unwrap_or_else(|_|
+		panic!("Cannot parse tolerance \"{}\"", s));
+	if tol.is_nan() || tol < 0.0
+	{
+		panic!("Tolerance {} must be non-negative", s);
+	}
+	return tol;
+}
+
+// Parse NAME=ABS or NAME=ABS,REL
+fn parse_array_tol(s: &str) -> (String, f64, f64)
+{
+	let (name, tols) = match s.rfind('=')
+	{
+		Some(i) => (&s[.. i], &s[i+1 ..]),
+		None => panic!("Cannot parse array tolerance \"{}\".  Expected \
+			NAME=ABS[,REL]", s),
+	};
+	let mut t = tols.splitn(2, ',');
+	let abs = parse_tol(t.next().unwrap());
+	let rel = t.next().map_or(0.0, parse_tol);
+	return (name.to_string(), abs, rel);
+}
+
+// Parse NAME=FILE of an array to attach
+fn parse_npy_array(s: &str, section: Section) -> (String, String, Section)
+{
+	return match s.find('=')
+	{
+		Some(i) => (s[.. i].to_string(), s[i+1 ..].to_string(), section),
+		None => panic!("Cannot parse array \"{}\".  Expected NAME=FILE", s),
+	};
+}
+
+// Parse MIN,MAX of a colormap range
+fn parse_range(s: &str) -> (f64, f64)
+{
+	let bounds: Vec<f64> = s.split(',').map(|x| x.trim().parse())
+		.collect::<Result<_, _>>().unwrap_or_else(|_|
+		panic!("Cannot parse range \"{}\".  Expected MIN,MAX", s));
+	if bounds.len() != 2 || bounds.iter().any(|x| x.is_nan()) || bounds[0] >= bounds[1]
+	{
+		panic!("Range {} must be MIN,MAX with MIN < MAX", s);
+	}
+	return (bounds[0], bounds[1]);
+}
+
 // Command line arg IDs
 pub const INPUT : &str = "INPUT";
 pub const OUTPUT: &str = "OUTPUT";
@@ -25,29 +78,63 @@ pub const LEND  : &str = "little-endian";
 pub const BEND  : &str = "big-endian";
 pub const ASCII : &str = "ascii";
 pub const BINARY: &str = "binary";
+pub const ZLIB  : &str = "zlib";
+pub const NOZLIB: &str = "uncompressed";
+pub const TO    : &str = "to";
+pub const LINEAR: &str = "linearize";
+pub const STRIP : &str = "strip-ghosts";
+pub const VTKVER: &str = "vtk-version";
+pub const JSON  : &str = "json";
+pub const OTHER : &str = "OTHER";
+pub const ABSTOL: &str = "abs";
+pub const RELTOL: &str = "rel";
+pub const TOL   : &str = "tol";
+pub const OUTDIR: &str = "output-dir";
+pub const JOBS  : &str = "jobs";
+pub const FORCE : &str = "force";
+pub const QUIET : &str = "quiet";
+pub const VERBOSE: &str = "verbose";
+pub const STRICT: &str = "strict";
+pub const PNPY  : &str = "point-npy";
+pub const CNPY  : &str = "cell-npy";
+pub const FNPY  : &str = "field-npy";
+pub const ARRAY : &str = "array";
+pub const CELLS : &str = "cells";
+pub const FIELD : &str = "field";
+pub const COLOR : &str = "color";
+pub const CMAP  : &str = "colormap";
+pub const RANGE : &str = "range";
 
-pub fn get_settings(app_name: &str) -> vtk::Settings
-{
+// Subcommands
+pub const BATCH   : &str = "batch";
+pub const CONVERT : &str = "convert";
+pub const DIFF    : &str = "diff";
+pub const EXTRACT : &str = "extract";
+pub const INFO    : &str = "info";
+pub const MERGE   : &str = "merge";
+pub const VALIDATE: &str = "validate";
 
-	//return App::new(app_name)
-	let args = App::new(app_name)
+//==============================================================================
 
-		.version("0.1.0")
-		.author("https://github.com/JeffIrwin")
-		.about("VTK file input/output toy")
+fn output_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b>
+{
+	// Output options shared by the subcommands that export a file
 
-		.arg(Arg::with_name(INPUT)
-			.help("Sets the input VTK file to load")
-			.required(true)
-			.index(1))
+	let formats: Vec<&str> = vtk::FORMATS.iter().map(|f| f.0).collect();
 
-		.arg(Arg::with_name(OUTPUT)
-			.help("Sets the output VTK file to export")
-			.required(true)
-			.index(2))
+	return app
+
+		.arg(Arg::with_name(TO)
+			.long(TO)
+			.value_name("FORMAT")
+			.takes_value(true)
+			.possible_values(&formats)
+			.help("Sets the output format.  Default:  inferred from the output \
+				extension"))
 
 		.arg(Arg::with_name(LEND)
 			.long("le")
+			.conflicts_with(BEND)
 			.help("Sets little endian output format"))
 
 		.arg(Arg::with_name(BEND)
@@ -57,6 +144,7 @@ pub fn get_settings(app_name: &str) -> vtk::Settings
 		.arg(Arg::with_name(ASCII)
 			.short("a")
 			.long(ASCII)
+			.conflicts_with_all(&[BINARY, ZLIB])
 			.help("Sets ASCII output format"))
 
 		.arg(Arg::with_name(BINARY)
@@ -64,21 +152,554 @@ pub fn get_settings(app_name: &str) -> vtk::Settings
 			.long(BINARY)
 			.help("Sets binary (base64 encoded) output format"))
 
+		.arg(Arg::with_name(ZLIB)
+			.short("z")
+			.long(ZLIB)
+			.conflicts_with(NOZLIB)
+			.help("Compresses binary XML output with zlib"))
+
+		.arg(Arg::with_name(NOZLIB)
+			.long(NOZLIB)
+			.help("Writes binary XML output without compression"))
+
+		.arg(Arg::with_name(VTKVER)
+			.long(VTKVER)
+			.value_name("VERSION")
+			.takes_value(true)
+			.help("Sets the XML file version to export, e.g. 0.1 for old \
+				ParaView"));
+}
+
+//********
+
+fn report_args<'a, 'b>(app: App<'a, 'b>, what: &'a str) -> App<'a, 'b>
+{
+	return app.arg(Arg::with_name(JSON)
+		.long(JSON)
+		.help(what));
+}
+
+//********
+
+fn output_settings(settings: &mut vtk::Settings, args: &ArgMatches)
+{
+	// Fill output options and infer the format.  Option combinations that clap
+	// cannot express are rejected here
+
+	settings.le     = args.is_present(LEND);
+	settings.be     = args.is_present(BEND);
+	settings.ascii  = args.is_present(ASCII);
+	settings.binary = args.is_present(BINARY);
+	settings.zlib   = args.is_present(ZLIB);
+	settings.uncompressed = args.is_present(NOZLIB);
+	settings.vtk_version  = args.value_of(VTKVER).map(String::from);
+
+	settings.output_format = match args.value_of(TO)
+	{
+		Some(f) => f.to_string(),
+		None if settings.output == vtk::STDIO => vtk::FMT_VTU.to_string(),
+		None => match vtk::format_from_path(&settings.output)
+		{
+			Some(f) => f.to_string(),
+			None => Error::with_description(&format!("Cannot infer the output \
+				format from \"{}\".  Use --{} to set it", settings.output, TO),
+				ErrorKind::InvalidValue).exit(),
+		},
+	};
+
+	if let Some(e) = encoding_conflict(settings)
+	{
+		Error::with_description(&e, ErrorKind::ArgumentConflict).exit();
+	}
+}
+
+//********
+
+pub fn encoding_conflict(settings: &vtk::Settings) -> Option<String>
+{
+	// Encoding options that conflict with each other or that the output format
+	// cannot honor, for CLI flags and batch settings alike.  Legacy binary is
+	// always big endian and STL and EnSight binary are always little endian, and
+	// neither they nor PLY and Gmsh have versions or compression.  XDMF heavy
+	// data is always raw binary.  Other formats have no encoding options at all
+
+	let encoding = [
+		(LEND  , "--le"          , settings.le),
+		(BEND  , "--be"          , settings.be),
+		(ASCII , "--ascii"       , settings.ascii),
+		(BINARY, "--binary"      , settings.binary),
+		(ZLIB  , "--zlib"        , settings.zlib),
+		(NOZLIB, "--uncompressed", settings.uncompressed),
+		(VTKVER, "--vtk-version" , settings.vtk_version.is_some()),
+	];
+	let flag = |id: &str| encoding.iter().find(|e| e.0 == id).unwrap();
+
+	let conflicts = [(LEND, BEND), (ASCII, BINARY), (ASCII, ZLIB), (ZLIB, NOZLIB)];
+	for (a, b) in conflicts.iter().map(|(a, b)| (flag(a), flag(b)))
+	{
+		if a.2 && b.2
+		{
+			return Some(format!("The argument '{}' cannot be used with '{}'",
+				a.1, b.1));
+		}
+	}
+
+	let unsupported: &[&str] = match settings.output_format.as_str()
+	{
+		vtk::FMT_VTU => &[],
+		vtk::FMT_VTK => &[LEND, ZLIB, NOZLIB, VTKVER],
+		vtk::FMT_STL | vtk::FMT_ENSIGHT => &[LEND, BEND, ZLIB, NOZLIB, VTKVER],
+		vtk::FMT_PLY | vtk::FMT_GMSH => &[ZLIB, NOZLIB, VTKVER],
+		vtk::FMT_XDMF => &[ASCII, BINARY, ZLIB, NOZLIB, VTKVER],
+		_ => &[LEND, BEND, ASCII, BINARY, ZLIB, NOZLIB, VTKVER],
+	};
+	return encoding.iter().find(|e| e.2 && unsupported.contains(&e.0))
+		.map(|e| format!("The argument '{}' cannot be used with {} output", e.1,
+		settings.output_format));
+}
+
+//********
+
+fn override_settings(settings: &mut vtk::Settings, args: &ArgMatches)
+{
+	// CLI flags that override a batch settings file.  Flags that are absent
+	// leave the file's values
+
+	if args.is_present(LEND)
+	{
+		settings.le = true;
+		settings.be = false;
+	}
+	if args.is_present(BEND)
+	{
+		settings.le = false;
+		settings.be = true;
+	}
+	if args.is_present(ASCII)
+	{
+		settings.ascii  = true;
+		settings.binary = false;
+		settings.zlib   = false;
+	}
+	if args.is_present(BINARY)
+	{
+		settings.ascii  = false;
+		settings.binary = true;
+	}
+	if args.is_present(ZLIB)
+	{
+		settings.zlib = true;
+		settings.uncompressed = false;
+	}
+	if args.is_present(NOZLIB)
+	{
+		settings.zlib = false;
+		settings.uncompressed = true;
+	}
+	if let Some(v) = args.value_of(VTKVER)
+	{
+		settings.vtk_version = Some(v.to_string());
+	}
+	if let Some(f) = args.value_of(TO)
+	{
+		settings.output_format = f.to_string();
+	}
+	if let Some(d) = args.value_of(OUTDIR)
+	{
+		settings.output = d.to_string();
+	}
+	if let Some(n) = args.value_of(JOBS)
+	{
+		settings.threads = n.parse().unwrap_or_else(|_| Error::with_description(
+			&format!("Cannot parse --{} \"{}\" as a number", JOBS, n),
+			ErrorKind::InvalidValue).exit());
+	}
+	settings.force        |= args.is_present(FORCE);
+	settings.linearize    |= args.is_present(LINEAR);
+	settings.strip_ghosts |= args.is_present(STRIP);
+}
+
+//==============================================================================
+
+pub fn get_settings(app_name: &str) -> vtk::Settings
+{
+	let colormaps: Vec<&str> = gltf::COLORMAPS.iter().map(|m| m.0).collect();
+
+	//return App::new(app_name)
+	let args = App::new(app_name)
+
+		.version("0.1.0")
+		.author("https://github.com/JeffIrwin")
+		.about("VTK file input/output toy")
+
+		.setting(AppSettings::SubcommandRequiredElseHelp)
+		.setting(AppSettings::VersionlessSubcommands)
+
+		.arg(Arg::with_name(QUIET)
+			.short("q")
+			.long(QUIET)
+			.global(true)
+			.conflicts_with(VERBOSE)
+			.help("Prints errors only"))
+
+		.arg(Arg::with_name(VERBOSE)
+			.short("v")
+			.long(VERBOSE)
+			.global(true)
+			.help("Prints debug details"))
+
+		.arg(Arg::with_name(STRICT)
+			.long(STRICT)
+			.global(true)
+			.help("Treats warnings as errors"))
+
+		.subcommand(output_args(SubCommand::with_name(CONVERT)
+			.about("Converts a VTK file to another encoding or format")
+
+			.arg(Arg::with_name(INPUT)
+				.help("Sets the input VTK file to load, or - for stdin.  A .pvd \
+					collection is converted step by step, to EnSight or XDMF only")
+				.required(true)
+				.index(1))
+
+			.arg(Arg::with_name(OUTPUT)
+				.help("Sets the output file to export, or - for stdout")
+				.required(true)
+				.index(2))
+
+			.arg(Arg::with_name(LINEAR)
+				.short("l")
+				.long(LINEAR)
+				.help("Splits higher-order cells into linear sub-cells"))
+
+			.arg(Arg::with_name(STRIP)
+				.long(STRIP)
+				.help("Removes ghost and hidden cells, and unused points"))
+
+			.arg(Arg::with_name(PNPY)
+				.long(PNPY)
+				.value_name("NAME=FILE")
+				.takes_value(true)
+				.multiple(true)
+				.number_of_values(1)
+				.help("Attaches a NumPy .npy file as a point array"))
+
+			.arg(Arg::with_name(CNPY)
+				.long(CNPY)
+				.value_name("NAME=FILE")
+				.takes_value(true)
+				.multiple(true)
+				.number_of_values(1)
+				.help("Attaches a NumPy .npy file as a cell array"))
+
+			.arg(Arg::with_name(FNPY)
+				.long(FNPY)
+				.value_name("NAME=FILE")
+				.takes_value(true)
+				.multiple(true)
+				.number_of_values(1)
+				.help("Attaches a NumPy .npy file as a field array, e.g. \
+					TimeValue"))
+
+			.arg(Arg::with_name(COLOR)
+				.long(COLOR)
+				.value_name("NAME")
+				.takes_value(true)
+				.help("Colours glTF vertices by a point array, by magnitude for \
+					vectors"))
+
+			.arg(Arg::with_name(CMAP)
+				.long(CMAP)
+				.takes_value(true)
+				.possible_values(&colormaps)
+				.requires(COLOR)
+				.help("Sets the colormap of --color.  Default:  viridis"))
+
+			.arg(Arg::with_name(RANGE)
+				.long(RANGE)
+				.value_name("MIN,MAX")
+				.takes_value(true)
+				.allow_hyphen_values(true)
+				.requires(COLOR)
+				.help("Sets the values at the ends of the colormap.  Default:  \
+					the range of the array"))))
+
+		.subcommand(SubCommand::with_name(EXTRACT)
+			.about("Writes the point coordinates and point arrays, cell arrays, \
+				or field arrays as CSV columns or as a NumPy .npy file per array")
+
+			.arg(Arg::with_name(INPUT)
+				.help("Sets the input VTK file to load, or - for stdin")
+				.required(true)
+				.index(1))
+
+			.arg(Arg::with_name(OUTPUT)
+				.help("Sets the .csv or .npy file to write.  Several arrays go \
+					to .npy files named after it and each array")
+				.required(true)
+				.index(2))
+
+			.arg(Arg::with_name(ARRAY)
+				.long(ARRAY)
+				.value_name("NAME")
+				.takes_value(true)
+				.multiple(true)
+				.number_of_values(1)
+				.help("Selects an array to extract, or the coordinates with the \
+					name Points.  Default:  all of them"))
+
+			.arg(Arg::with_name(CELLS)
+				.long(CELLS)
+				.help("Extracts cell arrays instead of point arrays"))
+
+			.arg(Arg::with_name(FIELD)
+				.long(FIELD)
+				.conflicts_with(CELLS)
+				.help("Extracts field arrays, e.g. TimeValue, instead of point \
+					arrays")))
+
+		.subcommand(output_args(SubCommand::with_name(BATCH)
+			.about("Converts many files concurrently, as described by a JSON or \
+				TOML settings file and/or the inputs given here.  Options given \
+				here override the file")
+
+			.arg(Arg::with_name(INPUT)
+				.help("Sets a settings file (.json or .toml), or input files, \
+					directories, or glob patterns.  Subdirectories are kept in \
+					the output tree")
+				.required(true)
+				.multiple(true))
+
+			.arg(Arg::with_name(OUTDIR)
+				.short("o")
+				.long(OUTDIR)
+				.value_name("DIR")
+				.takes_value(true)
+				.help("Sets the output directory.  Required without a settings \
+					file"))
+
+			.arg(Arg::with_name(JOBS)
+				.short("j")
+				.long(JOBS)
+				.value_name("N")
+				.takes_value(true)
+				.help("Sets the number of worker threads.  Default:  one per CPU"))
+
+			.arg(Arg::with_name(FORCE)
+				.short("f")
+				.long(FORCE)
+				.help("Converts files even if their outputs are up to date"))
+
+			.arg(Arg::with_name(LINEAR)
+				.short("l")
+				.long(LINEAR)
+				.help("Splits higher-order cells into linear sub-cells"))
+
+			.arg(Arg::with_name(STRIP)
+				.long(STRIP)
+				.help("Removes ghost and hidden cells, and unused points"))))
+
+		.subcommand(output_args(SubCommand::with_name(MERGE)
+			.about("Merges partitioned pieces, or the pieces of a .pvtu, into \
+				one grid.  Ghost cells are removed, and shared points are merged \
+				by global ID")
+
+			.arg(Arg::with_name(INPUT)
+				.help("Sets the input VTK files to merge")
+				.required(true)
+				.multiple(true))
+
+			.arg(Arg::with_name(OUTPUT)
+				.short("o")
+				.long("output")
+				.value_name("FILE")
+				.takes_value(true)
+				.required(true)
+				.help("Sets the output file to export"))))
+
+		.subcommand(report_args(SubCommand::with_name(DIFF)
+			.about("Compares two VTK files.  Topology must be identical, and \
+				coordinates and arrays must be within tolerances.  Exits nonzero \
+				if they differ")
+
+			.arg(Arg::with_name(INPUT)
+				.help("Sets the VTK file to check")
+				.required(true)
+				.index(1))
+
+			.arg(Arg::with_name(OTHER)
+				.help("Sets the reference VTK file, e.g. a golden result")
+				.required(true)
+				.index(2))
+
+			.arg(Arg::with_name(ABSTOL)
+				.long(ABSTOL)
+				.value_name("TOL")
+				.takes_value(true)
+				.help("Sets the default absolute tolerance.  Default 0"))
+
+			.arg(Arg::with_name(RELTOL)
+				.long(RELTOL)
+				.value_name("TOL")
+				.takes_value(true)
+				.help("Sets the default relative tolerance.  Default 0"))
+
+			.arg(Arg::with_name(TOL)
+				.long(TOL)
+				.value_name("NAME=ABS[,REL]")
+				.takes_value(true)
+				.multiple(true)
+				.number_of_values(1)
+				.help("Sets the tolerances of one array, or of the coordinates \
+					with the name Points")), "Prints the comparison as JSON"))
+
+		.subcommand(report_args(SubCommand::with_name(INFO)
+			.about("Summarizes a VTK file:  header, counts, cell types, bounds, \
+				and arrays")
+
+			.arg(Arg::with_name(INPUT)
+				.help("Sets the input VTK file to summarize")
+				.required(true)
+				.index(1)), "Prints the summary as JSON"))
+
+		.subcommand(report_args(SubCommand::with_name(VALIDATE)
+			.about("Checks a VTK file for broken cells, points, and arrays.  \
+				Exits nonzero if there are errors")
+
+			.arg(Arg::with_name(INPUT)
+				.help("Sets the input VTK file to validate")
+				.required(true)
+				.index(1)), "Prints the report as JSON"))
+
 		.get_matches();
 
 	// Instead of returning the args struct, abstract it to a settings struct.
 	// This leaves room for extension, in case other settings are loaded later
 	// e.g. from a JSON file
-	let settings = vtk::Settings
-	{
-		input : args.value_of(INPUT ).unwrap().to_string(),
-		output: args.value_of(OUTPUT).unwrap().to_string(),
-		le    : args.is_present(LEND),
-		be    : args.is_present(BEND),
-		ascii : args.is_present(ASCII),
-		binary: args.is_present(BINARY),
-	};
+
+	let (command, sub) = args.subcommand();
+	let sub = sub.unwrap();
+
+	// Global flags may come before or after the subcommand.  The logger is
+	// needed before loading e.g. batch settings files
+	let flag = |id| args.is_present(id) || sub.is_present(id);
+	logger::init(flag(QUIET), flag(VERBOSE), flag(STRICT));
+	let mut settings = vtk::Settings::new(command);
+
+	match command
+	{
+		BATCH =>
+		{
+			let (files, inputs): (Vec<&str>, Vec<&str>) = sub.values_of(INPUT)
+				.unwrap().partition(|i| batch::is_settings_file(i));
+
+			settings = match files.len()
+			{
+				0 if !sub.is_present(OUTDIR) => Error::with_description(
+					&format!("The argument '--{}' is required without a settings \
+					file", OUTDIR), ErrorKind::MissingRequiredArgument).exit(),
+				0 => batch::new_settings(),
+				1 => batch::load(files[0]),
+				_ => Error::with_description("Only one settings file can be given",
+					ErrorKind::TooManyValues).exit(),
+			};
+			for i in inputs
+			{
+				batch::add_input(&mut settings, i);
+			}
+			override_settings(&mut settings, sub);
+
+			// The file and the CLI flags may conflict only once merged
+			if let Some(e) = encoding_conflict(&settings)
+			{
+				Error::with_description(&e, ErrorKind::ArgumentConflict).exit();
+			}
+		},
+
+		CONVERT =>
+		{
+			settings.input  = sub.value_of(INPUT ).unwrap().to_string();
+			settings.output = sub.value_of(OUTPUT).unwrap().to_string();
+			settings.linearize    = sub.is_present(LINEAR);
+			settings.strip_ghosts = sub.is_present(STRIP);
+			for (id, section) in &[(PNPY, Section::Point), (CNPY, Section::Cell),
+				(FNPY, Section::Field)]
+			{
+				if let Some(arrays) = sub.values_of(id)
+				{
+					settings.npy_arrays.extend(arrays.map(|a| parse_npy_array(a,
+						*section)));
+				}
+			}
+			output_settings(&mut settings, sub);
+			if let Some(array) = sub.value_of(COLOR)
+			{
+				if settings.output_format != vtk::FMT_GLTF
+					&& settings.output_format != vtk::FMT_GLB
+				{
+					Error::with_description(&format!("The argument '--{}' cannot \
+						be used with {} output", COLOR, settings.output_format),
+						ErrorKind::ArgumentConflict).exit();
+				}
+				settings.coloring = Some(gltf::Coloring
+				{
+					array: array.to_string(),
+					colormap: sub.value_of(CMAP).unwrap_or(gltf::VIRIDIS).to_string(),
+					range: sub.value_of(RANGE).map(parse_range),
+				});
+			}
+		},
+
+		EXTRACT =>
+		{
+			settings.input  = sub.value_of(INPUT ).unwrap().to_string();
+			settings.output = sub.value_of(OUTPUT).unwrap().to_string();
+			settings.section = if sub.is_present(CELLS)
+			{
+				Section::Cell
+			}
+			else if sub.is_present(FIELD)
+			{
+				Section::Field
+			}
+			else
+			{
+				Section::Point
+			};
+			if let Some(arrays) = sub.values_of(ARRAY)
+			{
+				settings.arrays = arrays.map(String::from).collect();
+			}
+		},
+
+		MERGE =>
+		{
+			settings.inputs = sub.values_of(INPUT).unwrap().map(String::from)
+				.collect();
+			settings.output = sub.value_of(OUTPUT).unwrap().to_string();
+			output_settings(&mut settings, sub);
+		},
+
+		DIFF =>
+		{
+			settings.input = sub.value_of(INPUT).unwrap().to_string();
+			settings.other = sub.value_of(OTHER).unwrap().to_string();
+			settings.json  = sub.is_present(JSON);
+			settings.abs_tol = sub.value_of(ABSTOL).map_or(0.0, parse_tol);
+			settings.rel_tol = sub.value_of(RELTOL).map_or(0.0, parse_tol);
+			if let Some(tols) = sub.values_of(TOL)
+			{
+				settings.array_tols = tols.map(parse_array_tol).collect();
+			}
+		},
+
+		// Subcommands that only report on an input file
+		_ =>
+		{
+			settings.input = sub.value_of(INPUT).unwrap().to_string();
+			settings.json  = sub.is_present(JSON);
+		},
+	}
 
 	return settings;
 }
-
@@ -0,0 +1,582 @@
+
+//==============================================================================
+
+// Mesh validation.  Unlike load, which panics on the first problem, validate
+// collects every problem into a report
+
+// Standard
+use std::fmt::Write;
+
+//********
+
+// Third party
+use ansi_term::Colour;
+use serde_json::{json, Value};
+
+//********
+
+// This lib
+use crate::cells;
+use crate::vtk::{DataArray, VtkFile};
+
+//********
+
+// Issue kinds
+pub const SIZE        : &str = "size";
+pub const OFFSETS     : &str = "offsets";
+pub const CONNECTIVITY: &str = "connectivity";
+pub const CELL_TYPE   : &str = "cell-type";
+pub const NODE_COUNT  : &str = "node-count";
+pub const COORDINATES : &str = "coordinates";
+pub const INVERTED    : &str = "inverted-cell";
+pub const DEGENERATE  : &str = "degenerate-cell";
+pub const UNUSED_POINT: &str = "unused-point";
+pub const NO_GRID     : &str = "no-grid";
+
+// Max issues of each kind printed in text reports.  JSON reports have them all
+const MAX_PRINT: usize = 10;
+
+//==============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity
+{
+	Error,
+	Warning,
+}
+
+impl Severity
+{
+	pub fn name(&self) -> &'static str
+	{
+		match self
+		{
+			Severity::Error   => "error",
+			Severity::Warning => "warning",
+		}
+	}
+}
+
+//********
+
+#[derive(Debug, Clone)]
+pub struct Issue
+{
+	pub severity: Severity,
+
+	// One of the kind constants above, e.g. CONNECTIVITY
+	pub kind: &'static str,
+
+	// Index of the offending point or cell, if any
+	pub index: Option<usize>,
+
+	pub message: String,
+}
+
+//********
+
+#[derive(Debug, Clone)]
+pub struct Report
+{
+	pub issues: Vec<Issue>,
+}
+
+impl Report
+{
+	pub fn new() -> Report
+	{
+		Report
+		{
+			issues: Vec::new(),
+		}
+	}
+
+	fn error(&mut self, kind: &'static str, index: Option<usize>, message: String)
+	{
+		self.issues.push(Issue {severity: Severity::Error, kind, index, message});
+	}
+
+	fn warning(&mut self, kind: &'static str, index: Option<usize>, message: String)
+	{
+		self.issues.push(Issue {severity: Severity::Warning, kind, index, message});
+	}
+
+	pub fn nerrors(&self) -> usize
+	{
+		self.issues.iter().filter(|i| i.severity == Severity::Error).count()
+	}
+
+	pub fn nwarnings(&self) -> usize
+	{
+		self.issues.iter().filter(|i| i.severity == Severity::Warning).count()
+	}
+
+	pub fn is_valid(&self) -> bool
+	{
+		// Warnings alone do not make a mesh invalid
+		self.nerrors() == 0
+	}
+
+	pub fn has(&self, kind: &str) -> bool
+	{
+		self.issues.iter().any(|i| i.kind == kind)
+	}
+
+	//****************
+
+	pub fn to_text(&self, file: &str) -> String
+	{
+		// Human-readable report, with at most MAX_PRINT issues of each kind
+
+		let mut s = String::new();
+		writeln!(s, "Validation report for \"{}\"\n", file).unwrap();
+
+		let mut kinds: Vec<&str> = Vec::new();
+		for i in &self.issues
+		{
+			if !kinds.contains(&i.kind)
+			{
+				kinds.push(i.kind);
+			}
+		}
+
+		for kind in kinds
+		{
+			let issues: Vec<&Issue> = self.issues.iter()
+				.filter(|i| i.kind == kind).collect();
+
+			for i in issues.iter().take(MAX_PRINT)
+			{
+				let label = match i.severity
+				{
+					Severity::Error   => Colour::Red.bold().paint("error"),
+					Severity::Warning => Colour::Yellow.bold().paint("warning"),
+				};
+				writeln!(s, "{} [{}]:  {}", label, kind, i.message).unwrap();
+			}
+			if issues.len() > MAX_PRINT
+			{
+				writeln!(s, "... and {} more [{}] issues", issues.len() - MAX_PRINT,
+					kind).unwrap();
+			}
+		}
+
+		if !self.issues.is_empty()
+		{
+			writeln!(s).unwrap();
+		}
+		writeln!(s, "{} errors, {} warnings", self.nerrors(), self.nwarnings())
+			.unwrap();
+
+		return s;
+	}
+
+	//****************
+
+	pub fn to_json(&self, file: &str) -> String
+	{
+		let issues: Vec<Value> = self.issues.iter().map(|i| json!(
+		{
+			"severity": i.severity.name(),
+			"kind": i.kind,
+			"index": i.index,
+			"message": i.message,
+		})).collect();
+
+		let j = json!(
+		{
+			"file": file,
+			"valid": self.is_valid(),
+			"errors": self.nerrors(),
+			"warnings": self.nwarnings(),
+			"issues": issues,
+		});
+		return serde_json::to_string_pretty(&j).unwrap() + "\n";
+	}
+}
+
+impl Default for Report
+{
+	fn default() -> Report
+	{
+		Report::new()
+	}
+}
+
+//==============================================================================
+
+fn check_data_sizes(r: &mut Report, arrays: &[DataArray], section: &str,
+	n: usize)
+{
+	for a in arrays
+	{
+		if a.ntuples() < n
+		{
+			r.error(SIZE, None, format!("{} {} has {} tuples.  Expected {}",
+				section, a.name, a.ntuples(), n));
+		}
+	}
+}
+
+//********
+
+fn tet_volume(p: &[[f64; 3]; 4]) -> f64
+{
+	// Signed volume, positive for VTK's node ordering
+	let a = [p[1][0] - p[0][0], p[1][1] - p[0][1], p[1][2] - p[0][2]];
+	let b = [p[2][0] - p[0][0], p[2][1] - p[0][1], p[2][2] - p[0][2]];
+	let c = [p[3][0] - p[0][0], p[3][1] - p[0][1], p[3][2] - p[0][2]];
+
+	return (a[0] * (b[1] * c[2] - b[2] * c[1])
+	      - a[1] * (b[0] * c[2] - b[2] * c[0])
+	      + a[2] * (b[0] * c[1] - b[1] * c[0])) / 6.0;
+}
+
+//==============================================================================
+
+impl VtkFile
+{
+pub fn validate(&self) -> Report
+{
+	// Check array sizes, coordinates, offsets, connectivity, cell types, node
+	// counts, tet orientation, and unused points.  Cells with bad offsets or
+	// connectivity are skipped by the later checks
+
+	let mut r = Report::new();
+
+	// E.g. a file that is not XML, or has no Piece
+	if self.vtype.is_empty()
+	{
+		r.error(NO_GRID, None, "No UnstructuredGrid piece was read".to_string());
+		return r;
+	}
+
+	let npoints = self.npoints as usize;
+	let ncells  = self.ncells  as usize;
+	let nc      = self.ncomponents as usize;
+
+	//****************
+	// Sizes
+
+	if self.points.len() < nc * npoints
+	{
+		r.error(SIZE, None, format!("Points have {} values.  Expected {}",
+			self.points.len(), nc * npoints));
+	}
+	if self.offsets.len() < ncells
+	{
+		r.error(SIZE, None, format!("Offsets have {} values.  Expected {}",
+			self.offsets.len(), ncells));
+	}
+	if self.types.len() < ncells
+	{
+		r.error(SIZE, None, format!("Types have {} values.  Expected {}",
+			self.types.len(), ncells));
+	}
+	check_data_sizes(&mut r, &self.pdata, "PointData", npoints);
+	check_data_sizes(&mut r, &self.cdata, "CellData" , ncells );
+
+	// Only check what is there
+	let npoints_ok = std::cmp::min(npoints, self.points.len() / std::cmp::max(nc, 1));
+	let ncells_ok  = std::cmp::min(ncells, std::cmp::min(self.offsets.len(),
+		self.types.len()));
+
+	//****************
+	// Coordinates
+
+	let mut coords = vec![[0.0_f64; 3]; npoints_ok];
+	for i in 0 .. npoints_ok
+	{
+		for d in 0 .. std::cmp::min(nc, 3)
+		{
+			coords[i][d] = self.points[nc * i + d] as f64;
+		}
+		if coords[i].iter().any(|x| !x.is_finite())
+		{
+			r.error(COORDINATES, Some(i), format!("Point {} has non-finite \
+				coordinates {:?}", i, coords[i]));
+		}
+	}
+
+	//****************
+	// Offsets and connectivity
+
+	// Whether each cell's range and point IDs are valid
+	let mut ok = vec![true; ncells_ok];
+	let mut used = vec![false; npoints];
+
+	// Cells begin at the previous offset, like cell_points
+	let mut last = 0;
+	for i in 0 .. ncells_ok
+	{
+		let begin = if i == 0 { 0 } else { self.offsets[i-1] };
+		let end = self.offsets[i];
+		last = std::cmp::max(last, end);
+
+		if begin < 0 || end < begin
+		{
+			r.error(OFFSETS, Some(i), format!("Offset {} of cell {} is less \
+				than the previous offset {}", end, i, begin));
+			ok[i] = false;
+			continue;
+		}
+		if end as usize > self.connectivity.len()
+		{
+			r.error(OFFSETS, Some(i), format!("Offset {} of cell {} is past the \
+				end of connectivity of len {}", end, i, self.connectivity.len()));
+			ok[i] = false;
+			continue;
+		}
+
+		let conn = &self.connectivity[begin as usize .. end as usize];
+		if let Some(p) = conn.iter().find(|&&p| p < 0 || p as usize >= npoints)
+		{
+			r.error(CONNECTIVITY, Some(i), format!("Cell {} uses point {}, \
+				which is out of range for {} points", i, p, npoints));
+			ok[i] = false;
+		}
+
+		for &p in conn
+		{
+			if p >= 0 && (p as usize) < npoints
+			{
+				used[p as usize] = true;
+			}
+		}
+	}
+
+	if ncells_ok == ncells && (last as usize) < self.connectivity.len()
+	{
+		r.warning(CONNECTIVITY, None, format!("Connectivity has {} values, but \
+			only {} are used by cells", self.connectivity.len(), last));
+	}
+
+	//****************
+	// Cell types and node counts
+
+	for i in 0 .. ncells_ok
+	{
+		let ctype = self.types[i];
+		if !cells::is_known_type(ctype)
+		{
+			r.error(CELL_TYPE, Some(i), format!("Cell {} has unknown type {}",
+				i, ctype));
+			ok[i] = false;
+			continue;
+		}
+		if !ok[i]
+		{
+			continue;
+		}
+
+		let n = self.cell_points(i).len();
+		let min = match ctype
+		{
+			cells::VTK_POLY_VERTEX    => 1,
+			cells::VTK_POLY_LINE      => 2,
+			cells::VTK_TRIANGLE_STRIP => 3,
+			cells::VTK_POLYGON        => 3,
+			_ => 0,
+		};
+
+		let expected = cells::npoints_fixed(ctype);
+		if expected.is_some() && expected != Some(n) || n < min
+		{
+			r.error(NODE_COUNT, Some(i), format!("Cell {} of type {} has {} \
+				points.  Expected {}", i, ctype, n,
+				expected.unwrap_or(min)));
+			ok[i] = false;
+		}
+	}
+
+	// Higher-order node counts depend on the degrees, and checking them needs
+	// consistent sizes and offsets
+	let ho_ok = match self.cell_array(cells::HO_DEGREES)
+	{
+		Some(a) if a.ncomponents != 3 =>
+		{
+			r.error(SIZE, None, format!("{} has {} components.  Expected 3",
+				cells::HO_DEGREES, a.ncomponents));
+			false
+		},
+		_ => true,
+	};
+	if ho_ok && !r.has(SIZE) && !r.has(OFFSETS)
+	{
+		for (i, e) in cells::degree_errors(self)
+		{
+			r.error(NODE_COUNT, Some(i), e);
+			ok[i] = false;
+		}
+	}
+
+	//****************
+	// Tet orientation.  Higher-order and quadratic tets have their corners
+	// first
+
+	for i in 0 .. ncells_ok
+	{
+		let is_tet = matches!(self.types[i], cells::VTK_TETRA
+			| cells::VTK_QUADRATIC_TETRA
+			| cells::VTK_LAGRANGE_TETRAHEDRON | cells::VTK_BEZIER_TETRAHEDRON);
+		if !ok[i] || !is_tet
+		{
+			continue;
+		}
+
+		// Higher-order node counts are not checked if sizes are inconsistent
+		let pts = self.cell_points(i);
+		if pts.len() < 4 || pts[.. 4].iter().any(|&p| p as usize >= npoints_ok)
+		{
+			continue;
+		}
+
+		let mut corners = [[0.0; 3]; 4];
+		for j in 0 .. 4
+		{
+			corners[j] = coords[pts[j] as usize];
+		}
+
+		let vol = tet_volume(&corners);
+		if vol < 0.0
+		{
+			r.error(INVERTED, Some(i), format!("Tet {} is inverted with volume {}",
+				i, vol));
+		}
+		else if vol == 0.0
+		{
+			r.warning(DEGENERATE, Some(i), format!("Tet {} has zero volume", i));
+		}
+	}
+
+	//****************
+	// Unused points
+
+	for (i, &u) in used.iter().enumerate()
+	{
+		if !u
+		{
+			r.warning(UNUSED_POINT, Some(i), format!("Point {} is not used by \
+				any cell", i));
+		}
+	}
+
+	return r;
+}}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::vtk;
+
+	#[test]
+	fn test_validate_ico()
+	{
+		let r = vtk::load("./data/icosahedron-binary.vtu").validate();
+		assert!(r.is_valid());
+		assert!(r.issues.is_empty());
+	}
+
+	#[test]
+	fn test_validate_broken()
+	{
+		let mut v = vtk::load("./data/icosahedron-binary.vtu");
+		v.connectivity[0] = 100;
+		v.offsets.swap(3, 4);
+		v.types[7] = 200;
+		v.points[0] = f32::NAN;
+
+		// An extra point that no cell uses
+		v.points.extend(&[0.0, 0.0, 0.0]);
+		v.npoints += 1;
+		for a in &mut v.pdata
+		{
+			let indices: Vec<usize> = (0 .. 13).map(|i| i % 12).collect();
+			a.data = a.data.select(a.ncomponents as usize, &indices);
+		}
+
+		let r = v.validate();
+		assert!(!r.is_valid());
+		for kind in &[CONNECTIVITY, OFFSETS, CELL_TYPE, COORDINATES, UNUSED_POINT]
+		{
+			assert!(r.has(kind), "missing {}", kind);
+		}
+		assert!(r.issues.iter().any(|i| i.kind == UNUSED_POINT
+			&& i.index == Some(12)));
+
+		let json: Value = serde_json::from_str(&r.to_json("broken.vtu")).unwrap();
+		assert_eq!(json["valid"], false);
+		assert!(json["issues"].as_array().unwrap().iter()
+			.any(|i| i["kind"] == CELL_TYPE && i["index"] == 7));
+	}
+
+	#[test]
+	fn test_validate_not_vtu()
+	{
+		let temp = "./scratch/tmp.Gq7nVc2LtX.vtu";
+		std::fs::write(temp, "garbage").unwrap();
+		let r = vtk::load_unchecked(temp).validate();
+		assert!(!r.is_valid());
+		assert!(r.has(NO_GRID));
+
+		let temp = "./scratch/tmp.Hw3pKd8ZsY.vtu";
+		std::fs::write(temp, "<VTKFile type=\"UnstructuredGrid\" version=\"1.0\">\
+			<UnstructuredGrid/></VTKFile>").unwrap();
+		assert!(vtk::load_unchecked(temp).validate().has(NO_GRID));
+	}
+
+	#[test]
+	#[should_panic(expected = "can be written but not read")]
+	fn test_validate_legacy()
+	{
+		let temp = "./scratch/tmp.Jm5rTb9QwE.vtk";
+		vtk::load("./data/icosahedron-binary.vtu").export_as(temp, vtk::FMT_VTK);
+		vtk::load_unchecked(temp).validate();
+	}
+
+	#[test]
+	fn test_inverted_tet()
+	{
+		let mut v = VtkFile::new();
+		v.vtype = "UnstructuredGrid".to_string();
+		v.npoints = 4;
+		v.ncells = 1;
+		v.points = vec![0.0, 0.0, 0.0,  1.0, 0.0, 0.0,  0.0, 1.0, 0.0,
+			0.0, 0.0, 1.0];
+		v.connectivity = vec![0, 1, 2, 3];
+		v.offsets = vec![4];
+		v.types = vec![cells::VTK_TETRA];
+		assert!(v.validate().is_valid());
+
+		v.connectivity = vec![0, 2, 1, 3];
+		assert!(v.validate().has(INVERTED));
+
+		v.types = vec![cells::VTK_HEXAHEDRON];
+		assert!(v.validate().has(NODE_COUNT));
+	}
+
+	#[test]
+	fn test_short_lagrange_tet()
+	{
+		// A size error skips the higher-order node counts, so the tet
+		// orientation check sees the 2-node tet
+		let mut v = vtk::load("./data/icosahedron-binary-pdata.vtu");
+		v.connectivity.truncate(v.offsets[0] as usize - 1);
+		v.offsets = vec![2];
+		v.types = vec![cells::VTK_LAGRANGE_TETRAHEDRON];
+		v.ncells = 1;
+		v.cdata.clear();
+		v.pdata[0].data = v.pdata[0].data.select(v.pdata[0].ncomponents as usize,
+			&[0]);
+
+		let r = v.validate();
+		assert!(r.has(SIZE));
+		assert!(!r.is_valid());
+	}
+}
+
+//==============================================================================
+
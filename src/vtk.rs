@@ -5,14 +5,14 @@
 use std::fmt;
 use std::fs::File;
 use std::io;
-use std::io::Write;
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::str;
 
 //********
 
 // Third party
 
-use ansi_term::Colour;
+use log::{debug, info, warn};
 
 use quick_xml::Reader;
 use quick_xml::events::Event;
@@ -20,79 +20,291 @@ use quick_xml::events::Event;
 //********
 
 // This lib
+use crate::abaqus;
 use crate::base64;
+use crate::cells;
+use crate::ensight;
+use crate::extract::Section;
+use crate::gltf;
+use crate::gmsh;
+use crate::legacy;
+use crate::nastran;
+use crate::obj;
+use crate::ply;
+use crate::stl;
+use crate::tecplot;
+use crate::vtkjs;
+use crate::xdmf;
 
 //********
 
 // VTK identifiers
-const ASCII   : &str = "ascii";
-const BINARY  : &str = "binary";
-const VTK_F32 : &str = "Float32";
-const VTK_I64 : &str = "Int64";
-const VTK_U8  : &str = "UInt8";
-//const VTK_U64 : &str = "UInt64";  // not used (yet)
-const VTK_FILE: &str = "VTKFile";
-const UGRID   : &str = "UnstructuredGrid";
-const PIECE   : &str = "Piece";
-const DATA    : &str = "DataArray";
-const POINTS  : &str = "Points";
-const CELLS   : &str = "Cells";
-const PDATA   : &str = "PointData";
-const CDATA   : &str = "CellData";
-const CONN    : &str = "connectivity";
-const OFFSETS : &str = "offsets";
-const TYPES   : &str = "types";
-const TYPE    : &str = "type";
-const VERSION : &str = "version";
-const BYTEORD : &str = "byte_order";
-const VTK_LIT : &str = "LittleEndian";
-const VTK_BIG : &str = "BigEndian";
-const NPOINTS : &str = "NumberOfPoints";
-const NCELLS  : &str = "NumberOfCells";
-const NCOMP   : &str = "NumberOfComponents";
-const NAME    : &str = "Name";
-const FORMAT  : &str = "format";
+pub const ASCII   : &str = "ascii";
+pub const BINARY  : &str = "binary";
+pub const VTK_I8  : &str = "Int8";
+pub const VTK_U8  : &str = "UInt8";
+pub const VTK_I16 : &str = "Int16";
+pub const VTK_U16 : &str = "UInt16";
+pub const VTK_I32 : &str = "Int32";
+pub const VTK_U32 : &str = "UInt32";
+pub const VTK_I64 : &str = "Int64";
+pub const VTK_U64 : &str = "UInt64";
+pub const VTK_F32 : &str = "Float32";
+pub const VTK_F64 : &str = "Float64";
+pub const VTK_FILE: &str = "VTKFile";
+
+// All DataArray types
+pub const VTK_TYPES: &[&str] = &[VTK_I8, VTK_U8, VTK_I16, VTK_U16, VTK_I32,
+	VTK_U32, VTK_I64, VTK_U64, VTK_F32, VTK_F64];
+pub const UGRID   : &str = "UnstructuredGrid";
+pub const PIECE   : &str = "Piece";
+pub const DATA    : &str = "DataArray";
+pub const POINTS  : &str = "Points";
+pub const CELLS   : &str = "Cells";
+pub const PDATA   : &str = "PointData";
+pub const CDATA   : &str = "CellData";
+pub const FDATA   : &str = "FieldData";
+pub const CONN    : &str = "connectivity";
+pub const OFFSETS : &str = "offsets";
+pub const TYPES   : &str = "types";
+pub const TYPE    : &str = "type";
+pub const VERSION : &str = "version";
+pub const BYTEORD : &str = "byte_order";
+pub const HEADER  : &str = "header_type";
+pub const COMPRESS: &str = "compressor";
+
+// The only supported compressor
+pub const ZLIB: &str = "vtkZLibDataCompressor";
+pub const VTK_LIT : &str = "LittleEndian";
+pub const VTK_BIG : &str = "BigEndian";
+pub const NPOINTS : &str = "NumberOfPoints";
+pub const NCELLS  : &str = "NumberOfCells";
+pub const NCOMP   : &str = "NumberOfComponents";
+pub const NTUPLES : &str = "NumberOfTuples";
+pub const COMPNAME: &str = "ComponentName";
+pub const NAME    : &str = "Name";
+pub const FORMAT  : &str = "format";
+pub const SCALARS : &str = "Scalars";
+pub const VECTORS : &str = "Vectors";
+pub const NORMALS : &str = "Normals";
+pub const TENSORS : &str = "Tensors";
+pub const TCOORDS : &str = "TCoords";
+pub const GLOBALID: &str = "GlobalIds";
+pub const PEDIGREE: &str = "PedigreeIds";
+
+// Array metadata such as ranges, which is skipped without a warning
+const INFO_KEY  : &str = "InformationKey";
+const INFO_VALUE: &str = "Value";
+
+// Path of stdin or stdout
+pub const STDIO: &str = "-";
+const STDIN_NAME: &str = "<stdin>";
+
+// Output formats, inferred from these file extensions unless overridden
+pub const FMT_VTU    : &str = "vtu";
+pub const FMT_VTK    : &str = "vtk";
+pub const FMT_JSON   : &str = "json";
+pub const FMT_MSGPACK: &str = "msgpack";
+pub const FMT_STL    : &str = "stl";
+pub const FMT_OBJ    : &str = "obj";
+pub const FMT_PLY    : &str = "ply";
+pub const FMT_GMSH   : &str = "msh";
+pub const FMT_TECPLOT: &str = "dat";
+pub const FMT_ENSIGHT: &str = "case";
+pub const FMT_XDMF   : &str = "xdmf";
+pub const FMT_GLTF   : &str = "gltf";
+pub const FMT_GLB    : &str = "glb";
+
+pub const FORMATS: &[(&str, &[&str])] = &[
+	(FMT_VTU    , &[".vtu"]),
+	(FMT_VTK    , &[legacy::EXT]),
+	(FMT_JSON   , &[vtkjs::JSON_EXT]),
+	(FMT_MSGPACK, &[vtkjs::MSGPACK_EXT]),
+	(FMT_STL    , &[stl::EXT]),
+	(FMT_OBJ    , &[obj::EXT]),
+	(FMT_PLY    , &[ply::EXT]),
+	(FMT_GMSH   , &[gmsh::EXT]),
+	(FMT_TECPLOT, &[tecplot::EXT]),
+	(FMT_ENSIGHT, &[ensight::EXT]),
+	(FMT_XDMF   , xdmf::EXTS),
+	(FMT_GLTF   , &[gltf::GLTF_EXT]),
+	(FMT_GLB    , &[gltf::GLB_EXT]),
+];
+
+// Formats that can only be loaded, e.g. solver input decks
+pub const FMT_ABAQUS : &str = "inp";
+pub const FMT_NASTRAN: &str = "bdf";
+
+pub const INPUT_FORMATS: &[(&str, &[&str])] = &[
+	(FMT_ABAQUS , &[abaqus::EXT]),
+	(FMT_NASTRAN, nastran::EXTS),
+];
+
+// Formats that load() can read, of FORMATS and INPUT_FORMATS
+pub const LOAD_FORMATS: &[&str] = &[FMT_VTU, FMT_JSON, FMT_MSGPACK, FMT_STL,
+	FMT_OBJ, FMT_PLY, FMT_GMSH, FMT_XDMF, FMT_ABAQUS, FMT_NASTRAN];
 
 //==============================================================================
 
+pub fn format_from_path(file: &str) -> Option<&'static str>
+{
+	let lower = file.to_lowercase();
+	return FORMATS.iter()
+		.find(|(_, exts)| exts.iter().any(|e| lower.ends_with(e)))
+		.map(|&(f, _)| f);
+}
+
+//********
+
+pub fn input_format_from_path(file: &str) -> Option<&'static str>
+{
+	// Any loadable format, including the ones that cannot be exported
+	let lower = file.to_lowercase();
+	return format_from_path(file).or_else(|| INPUT_FORMATS.iter()
+		.find(|(_, exts)| exts.iter().any(|e| lower.ends_with(e)))
+		.map(|&(f, _)| f));
+}
+
+//********
+
+pub fn is_loadable(file: &str) -> bool
+{
+	return input_format_from_path(file).is_some_and(|f| LOAD_FORMATS.contains(&f));
+}
+
+//==============================================================================
+
+#[derive(Debug, Clone)]
 pub struct Settings
 {
+	// Subcommand, e.g. convert or validate
+	pub command: String,
+
 	pub input: String,
 	pub output: String,
+
+	// All inputs of merge or batch
+	pub inputs: Vec<String>,
+
+	// Output naming pattern of batch, where output is the directory
+	pub output_name: String,
+
+	// Directory of each batch input whose subtree is kept under the output
+	// directory, or "" to write the output flat
+	pub input_roots: Vec<String>,
+
+	// Batch worker threads, or 0 for one per CPU
+	pub threads: usize,
+
+	// Convert batch inputs even if their outputs are up to date
+	pub force: bool,
+
+	// One of FORMATS, inferred from the output extension if not given
+	pub output_format: String,
+
 	pub le: bool,
 	pub be: bool,
 	pub ascii: bool,
 	pub binary: bool,
+	pub zlib: bool,
+	pub uncompressed: bool,
+	pub linearize: bool,
+	pub strip_ghosts: bool,
+	pub vtk_version: Option<String>,
+
+	// Point and cell arrays to keep (all if empty) or drop, by name
+	pub keep_arrays: Vec<String>,
+	pub drop_arrays: Vec<String>,
+
+	// Type casts (from, to), where from is an array name or a type, e.g.
+	// ("Float64", "Float32")
+	pub downcasts: Vec<(String, String)>,
+
+	// .npy files that convert attaches as arrays, as (name, file, section)
+	pub npy_arrays: Vec<(String, String, Section)>,
+
+	// Arrays of extract by name, or all if empty, and which section they are
+	// in
+	pub arrays : Vec<String>,
+	pub section: Section,
+
+	// Point array and colormap of glTF vertex colours, if any
+	pub coloring: Option<gltf::Coloring>,
+
+	// Report format of subcommands other than convert
+	pub json: bool,
+
+	// Second file and tolerances for diff.  Array tolerances are (name, abs,
+	// rel), where the name "Points" is for the coordinates
+	pub other: String,
+	pub abs_tol: f64,
+	pub rel_tol: f64,
+	pub array_tols: Vec<(String, f64, f64)>,
 }
 
-//impl Settings
-//{
-//	pub fn new() -> Settings
-//	{
-//		Settings
-//		{
-//			input: "".to_string(),
-//			output: "".to_string(),
-//			le: false,
-//			be: false,
-//		}
-//	}
-//}
-
-#[derive(Debug)]
+impl Settings
+{
+	pub fn new(command: &str) -> Settings
+	{
+		Settings
+		{
+			command: command.to_string(),
+			input: "".to_string(),
+			output: "".to_string(),
+			inputs: Vec::new(),
+			output_name: "".to_string(),
+			input_roots: Vec::new(),
+			threads: 0,
+			force: false,
+			output_format: "".to_string(),
+			le: false,
+			be: false,
+			ascii: false,
+			binary: false,
+			zlib: false,
+			uncompressed: false,
+			linearize: false,
+			strip_ghosts: false,
+			vtk_version: None,
+			keep_arrays: Vec::new(),
+			drop_arrays: Vec::new(),
+			downcasts: Vec::new(),
+			npy_arrays: Vec::new(),
+			arrays: Vec::new(),
+			section: Section::Point,
+			coloring: None,
+			json: false,
+			other: "".to_string(),
+			abs_tol: 0.0,
+			rel_tol: 0.0,
+			array_tols: Vec::new(),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VtkFile
 {
 	// File eader info
 	pub vtype     : String,
-	pub version   : String,
+	pub version   : Version,
 	pub endianness: u8,
 
+	// Type of the byte count before each binary block, UInt32 or UInt64
+	pub header_type: String,
+
+	// Compressor of binary blocks, ZLIB or empty for none
+	pub compressor: String,
+
 	pub format    : String,
 
 	// The real data
 
 	pub npoints    : u64,
 	pub ncells     : u64,
+
+	// Components of the Points array only.  Field arrays have their own
 	pub ncomponents: u64,
 
 	pub points      : Vec<f32>,
@@ -100,6 +312,18 @@ pub struct VtkFile
 	pub offsets     : Vec<i64>,
 	pub types       : Vec<u8>,
 
+	// Data arrays in <PointData> and <CellData>
+	pub pdata: Vec<DataArray>,
+	pub cdata: Vec<DataArray>,
+
+	// Arrays of the whole grid in <FieldData>, e.g. TimeValue, with any number
+	// of tuples
+	pub fdata: Vec<DataArray>,
+
+	// Active attribute designations, e.g. <PointData Scalars="...">
+	pub pattributes: Attributes,
+	pub cattributes: Attributes,
+
 }
 
 impl VtkFile
@@ -110,9 +334,13 @@ impl VtkFile
 		VtkFile
 		{
 			vtype: "".to_string(),
-			version: "".to_string(),
+			version: Version::new(1, 0),
 			endianness: base64::LITTLE_ENDIAN,
 
+			header_type: VTK_U64.to_string(),
+
+			compressor: "".to_string(),
+
 			format: BINARY.to_string(),
 
 			npoints: 0,
@@ -124,18 +352,611 @@ impl VtkFile
 			offsets     : Vec::new(),
 			types       : Vec::new(),
 
+			pdata: Vec::new(),
+			cdata: Vec::new(),
+			fdata: Vec::new(),
+
+			pattributes: Attributes::new(),
+			cattributes: Attributes::new(),
+
+		}
+	}
+
+	// Size of binary block headers in bytes
+	fn header_bytes(&self) -> usize
+	{
+		if self.header_type == VTK_U32 { 4 } else { 8 }
+	}
+
+	// Choose the file version to export, e.g. so that older ParaView builds can
+	// open it
+	pub fn set_version(&mut self, version: Version)
+	{
+		version.check();
+		self.version = version;
+
+		// Version 0.1 has no header_type attribute, which implies UInt32
+		if version < Version::new(1, 0)
+		{
+			self.header_type = VTK_U32.to_string();
+		}
+	}
+
+	// byte_order attribute value, e.g. "LittleEndian"
+	pub fn byte_order(&self) -> &'static str
+	{
+		if self.endianness == base64::BIG_ENDIAN { VTK_BIG } else { VTK_LIT }
+	}
+
+	// Find a point or cell data array by name
+	pub fn point_array(&self, name: &str) -> Option<&DataArray>
+	{
+		self.pdata.iter().find(|a| a.name == name)
+	}
+
+	pub fn cell_array(&self, name: &str) -> Option<&DataArray>
+	{
+		self.cdata.iter().find(|a| a.name == name)
+	}
+
+	//********
+
+	// Getters and setters for active attributes.  The getters return the
+	// designated array name, while active_*_array() returns the array itself
+
+	pub fn point_attribute(&self, a: Attribute) -> Option<&str>
+	{
+		self.pattributes.get(a)
+	}
+
+	pub fn cell_attribute(&self, a: Attribute) -> Option<&str>
+	{
+		self.cattributes.get(a)
+	}
+
+	pub fn active_point_array(&self, a: Attribute) -> Option<&DataArray>
+	{
+		self.point_array(self.point_attribute(a)?)
+	}
+
+	pub fn active_cell_array(&self, a: Attribute) -> Option<&DataArray>
+	{
+		self.cell_array(self.cell_attribute(a)?)
+	}
+
+	// Designate an existing array as active, or clear the designation with
+	// None
+	pub fn set_point_attribute(&mut self, a: Attribute, name: Option<&str>)
+	{
+		if let Some(n) = name
+		{
+			if self.point_array(n).is_none()
+			{
+				panic!("Cannot set {} to unknown {} array \"{}\"",
+					a.xml_name(), PDATA, n);
+			}
+		}
+		self.pattributes.set(a, name.map(String::from));
+	}
+
+	pub fn set_cell_attribute(&mut self, a: Attribute, name: Option<&str>)
+	{
+		if let Some(n) = name
+		{
+			if self.cell_array(n).is_none()
+			{
+				panic!("Cannot set {} to unknown {} array \"{}\"",
+					a.xml_name(), CDATA, n);
+			}
+		}
+		self.cattributes.set(a, name.map(String::from));
+	}
+}
+
+impl Default for VtkFile
+{
+	fn default() -> VtkFile
+	{
+		VtkFile::new()
+	}
+}
+
+//==============================================================================
+
+// Kinds of active attributes that a point or cell data array can be
+// designated as
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Attribute
+{
+	Scalars,
+	Vectors,
+	Normals,
+	Tensors,
+	TCoords,
+	GlobalIds,
+	PedigreeIds,
+}
+
+impl Attribute
+{
+	pub const ALL: [Attribute; 7] = [Attribute::Scalars, Attribute::Vectors,
+		Attribute::Normals, Attribute::Tensors, Attribute::TCoords,
+		Attribute::GlobalIds, Attribute::PedigreeIds];
+
+	// XML attribute name of the designation, e.g. "Scalars"
+	pub fn xml_name(self) -> &'static str
+	{
+		match self
+		{
+			Attribute::Scalars => SCALARS,
+			Attribute::Vectors => VECTORS,
+			Attribute::Normals => NORMALS,
+			Attribute::Tensors => TENSORS,
+			Attribute::TCoords => TCOORDS,
+			Attribute::GlobalIds   => GLOBALID,
+			Attribute::PedigreeIds => PEDIGREE,
+		}
+	}
+
+	pub fn from_xml_name(name: &str) -> Option<Attribute>
+	{
+		Attribute::ALL.iter().copied().find(|a| a.xml_name() == name)
+	}
+}
+
+//********
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Attributes
+{
+	pub scalars: Option<String>,
+	pub vectors: Option<String>,
+	pub normals: Option<String>,
+	pub tensors: Option<String>,
+	pub tcoords: Option<String>,
+	pub global_ids  : Option<String>,
+	pub pedigree_ids: Option<String>,
+}
+
+impl Attributes
+{
+	pub fn new() -> Attributes
+	{
+		Attributes
+		{
+			scalars: None,
+			vectors: None,
+			normals: None,
+			tensors: None,
+			tcoords: None,
+			global_ids  : None,
+			pedigree_ids: None,
+		}
+	}
+
+	fn slot(&mut self, a: Attribute) -> &mut Option<String>
+	{
+		match a
+		{
+			Attribute::Scalars => &mut self.scalars,
+			Attribute::Vectors => &mut self.vectors,
+			Attribute::Normals => &mut self.normals,
+			Attribute::Tensors => &mut self.tensors,
+			Attribute::TCoords => &mut self.tcoords,
+			Attribute::GlobalIds   => &mut self.global_ids,
+			Attribute::PedigreeIds => &mut self.pedigree_ids,
+		}
+	}
+
+	pub fn get(&self, a: Attribute) -> Option<&str>
+	{
+		match a
+		{
+			Attribute::Scalars => self.scalars.as_deref(),
+			Attribute::Vectors => self.vectors.as_deref(),
+			Attribute::Normals => self.normals.as_deref(),
+			Attribute::Tensors => self.tensors.as_deref(),
+			Attribute::TCoords => self.tcoords.as_deref(),
+			Attribute::GlobalIds   => self.global_ids.as_deref(),
+			Attribute::PedigreeIds => self.pedigree_ids.as_deref(),
+		}
+	}
+
+	pub fn set(&mut self, a: Attribute, name: Option<String>)
+	{
+		*self.slot(a) = name;
+	}
+
+	// Designation of the named array, if any
+	pub fn of(&self, name: &str) -> Option<Attribute>
+	{
+		Attribute::ALL.iter().copied().find(|&a| self.get(a) == Some(name))
+	}
+
+	// Format as XML attributes, e.g. ` Scalars="p" Vectors="u"`
+	fn to_xml(&self) -> String
+	{
+		let mut s = String::new();
+		for &a in Attribute::ALL.iter()
+		{
+			if let Some(name) = self.get(a)
+			{
+				s += &format!(" {}=\"{}\"", a.xml_name(), name);
+			}
+		}
+		return s;
+	}
+}
+
+impl Default for Attributes
+{
+	fn default() -> Attributes
+	{
+		Attributes::new()
+	}
+}
+
+//==============================================================================
+
+// VTK XML file version, e.g. version="1.0".  Versions compare numerically, so
+// 0.10 > 0.9
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Version
+{
+	pub major: u32,
+	pub minor: u32,
+}
+
+impl Version
+{
+	// Oldest and newest versions that this lib can read and write
+	pub const OLDEST: Version = Version { major: 0, minor: 1 };
+	pub const NEWEST: Version = Version { major: 2, minor: 2 };
+
+	pub fn new(major: u32, minor: u32) -> Version
+	{
+		Version { major, minor }
+	}
+
+	pub fn parse(s: &str) -> Version
+	{
+		let errstr = format!("Cannot parse {} \"{}\"", VERSION, s);
+
+		let mut parts = s.trim().splitn(2, '.');
+		let major = parts.next().unwrap().parse().expect(&errstr);
+		let minor = match parts.next()
+		{
+			Some(m) => m.parse().expect(&errstr),
+			None => 0,
+		};
+		return Version::new(major, minor);
+	}
+
+	// Panic on versions that cannot be read, including any version newer than
+	// the newest known one
+	pub fn check(self)
+	{
+		if self < Version::OLDEST || self > Version::NEWEST
+		{
+			panic!("VTK file {} {} is not supported.  Supported versions are {} \
+				through {}", VERSION, self, Version::OLDEST, Version::NEWEST);
 		}
 	}
 }
 
+impl fmt::Display for Version
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+	{
+		write!(f, "{}.{}", self.major, self.minor)
+	}
+}
+
+//==============================================================================
+
+// Typed values of a DataArray.  VTK arrays can be any of these scalar types,
+// so the type is kept as loaded instead of casting everything to f64
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Data
+{
+	I8 (Vec<i8 >),
+	U8 (Vec<u8 >),
+	I16(Vec<i16>),
+	U16(Vec<u16>),
+	I32(Vec<i32>),
+	U32(Vec<u32>),
+	I64(Vec<i64>),
+	U64(Vec<u64>),
+	F32(Vec<f32>),
+	F64(Vec<f64>),
+}
+
+// Apply the same expression to the Vec inside of any Data variant
+macro_rules! for_data
+{
+	($data:expr, $v:ident => $body:expr) =>
+	{
+		match $data
+		{
+			Data::I8 ($v) => $body,
+			Data::U8 ($v) => $body,
+			Data::I16($v) => $body,
+			Data::U16($v) => $body,
+			Data::I32($v) => $body,
+			Data::U32($v) => $body,
+			Data::I64($v) => $body,
+			Data::U64($v) => $body,
+			Data::F32($v) => $body,
+			Data::F64($v) => $body,
+		}
+	};
+}
+
+// Like for_data, but wrap the resulting Vec back into the same variant
+macro_rules! map_data
+{
+	($data:expr, $v:ident => $body:expr) =>
+	{
+		match $data
+		{
+			Data::I8 ($v) => Data::I8 ($body),
+			Data::U8 ($v) => Data::U8 ($body),
+			Data::I16($v) => Data::I16($body),
+			Data::U16($v) => Data::U16($body),
+			Data::I32($v) => Data::I32($body),
+			Data::U32($v) => Data::U32($body),
+			Data::I64($v) => Data::I64($body),
+			Data::U64($v) => Data::U64($body),
+			Data::F32($v) => Data::F32($body),
+			Data::F64($v) => Data::F64($body),
+		}
+	};
+}
+
+impl Data
+{
+	// Number of scalar values (not tuples)
+	pub fn len(&self) -> usize
+	{
+		for_data!(self, v => v.len())
+	}
+
+	pub fn is_empty(&self) -> bool
+	{
+		self.len() == 0
+	}
+
+	// Memory size of the values in bytes
+	pub fn nbytes(&self) -> usize
+	{
+		for_data!(self, v => std::mem::size_of_val(v.as_slice()))
+	}
+
+	// VTK type name, e.g. "Float32"
+	pub fn vtk_type(&self) -> &'static str
+	{
+		match self
+		{
+			Data::I8 (_) => VTK_I8 ,
+			Data::U8 (_) => VTK_U8 ,
+			Data::I16(_) => VTK_I16,
+			Data::U16(_) => VTK_U16,
+			Data::I32(_) => VTK_I32,
+			Data::U32(_) => VTK_U32,
+			Data::I64(_) => VTK_I64,
+			Data::U64(_) => VTK_U64,
+			Data::F32(_) => VTK_F32,
+			Data::F64(_) => VTK_F64,
+		}
+	}
+
+	// Lossy (for 64-bit ints) copy of all values as f64
+	#[allow(clippy::unnecessary_cast)]
+	pub fn to_f64(&self) -> Vec<f64>
+	{
+		for_data!(self, v => v.iter().map(|&x| x as f64).collect())
+	}
+
+	// Copy of all values as i64, e.g. for IDs stored as floats
+	#[allow(clippy::unnecessary_cast)]
+	pub fn to_i64(&self) -> Vec<i64>
+	{
+		for_data!(self, v => v.iter().map(|&x| x as i64).collect())
+	}
+
+	// Format value i for ASCII output
+	pub fn value_string(&self, i: usize) -> String
+	{
+		for_data!(self, v => v[i].to_string())
+	}
+
+	// Raw bytes of all values in the given byte order
+	pub fn to_bytes(&self, endianness: u8) -> Vec<u8>
+	{
+		for_data!(self, v => v.iter()
+			.flat_map(|&x| base64::Scalar::to_bytes(x, endianness))
+			.collect())
+	}
+
+	// Values of a VTK type, e.g. "Float32", from raw bytes in the given byte
+	// order.  Trailing bytes of a partial value are ignored
+	pub fn from_bytes(bytes: &[u8], vtk_type: &str, endianness: u8) -> Data
+	{
+		fn values<T: base64::Scalar>(bytes: &[u8], e: u8) -> Vec<T>
+		{
+			bytes.chunks_exact(T::NBYTES).map(|b| T::from_bytes(b, e)).collect()
+		}
+
+		let e = endianness;
+		return match vtk_type
+		{
+			VTK_I8  => Data::I8 (values(bytes, e)),
+			VTK_U8  => Data::U8 (values(bytes, e)),
+			VTK_I16 => Data::I16(values(bytes, e)),
+			VTK_U16 => Data::U16(values(bytes, e)),
+			VTK_I32 => Data::I32(values(bytes, e)),
+			VTK_U32 => Data::U32(values(bytes, e)),
+			VTK_I64 => Data::I64(values(bytes, e)),
+			VTK_U64 => Data::U64(values(bytes, e)),
+			VTK_F32 => Data::F32(values(bytes, e)),
+			VTK_F64 => Data::F64(values(bytes, e)),
+			_ => panic!("Cannot decode unknown type {}", vtk_type),
+		};
+	}
+
+	// Convert to another VTK type, e.g. "Float32".  Narrowing casts behave
+	// like Rust's "as"
+	pub fn cast(&self, vtk_type: &str) -> Data
+	{
+		if vtk_type == self.vtk_type()
+		{
+			return self.clone();
+		}
+
+		let f = || self.to_f64();
+		let i = || self.to_i64();
+		return match vtk_type
+		{
+			VTK_I8  => Data::I8 (i().iter().map(|&x| x as i8 ).collect()),
+			VTK_U8  => Data::U8 (i().iter().map(|&x| x as u8 ).collect()),
+			VTK_I16 => Data::I16(i().iter().map(|&x| x as i16).collect()),
+			VTK_U16 => Data::U16(i().iter().map(|&x| x as u16).collect()),
+			VTK_I32 => Data::I32(i().iter().map(|&x| x as i32).collect()),
+			VTK_U32 => Data::U32(i().iter().map(|&x| x as u32).collect()),
+			VTK_I64 => Data::I64(i()),
+			VTK_U64 => Data::U64(i().iter().map(|&x| x as u64).collect()),
+			VTK_F32 => Data::F32(f().iter().map(|&x| x as f32).collect()),
+			VTK_F64 => Data::F64(f()),
+			_ => panic!("Cannot cast to unknown type {}", vtk_type),
+		};
+	}
+
+	// Append values of the same type, e.g. when merging pieces
+	pub fn append(&mut self, other: &Data)
+	{
+		match (self, other)
+		{
+			(Data::I8 (a), Data::I8 (b)) => a.extend_from_slice(b),
+			(Data::U8 (a), Data::U8 (b)) => a.extend_from_slice(b),
+			(Data::I16(a), Data::I16(b)) => a.extend_from_slice(b),
+			(Data::U16(a), Data::U16(b)) => a.extend_from_slice(b),
+			(Data::I32(a), Data::I32(b)) => a.extend_from_slice(b),
+			(Data::U32(a), Data::U32(b)) => a.extend_from_slice(b),
+			(Data::I64(a), Data::I64(b)) => a.extend_from_slice(b),
+			(Data::U64(a), Data::U64(b)) => a.extend_from_slice(b),
+			(Data::F32(a), Data::F32(b)) => a.extend_from_slice(b),
+			(Data::F64(a), Data::F64(b)) => a.extend_from_slice(b),
+			(a, b) => panic!("Cannot append {} data to {} data", b.vtk_type(),
+				a.vtk_type()),
+		}
+	}
+
+	// Gather the tuples at the given indices, e.g. to replicate cell data
+	// onto sub-cells
+	pub fn select(&self, ncomponents: usize, indices: &[usize]) -> Data
+	{
+		map_data!(self, v => indices.iter()
+			.flat_map(|&i| v[ncomponents*i .. ncomponents*(i+1)].iter().copied())
+			.collect())
+	}
+}
+
+//********
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DataArray
+{
+	pub name       : String,
+	pub ncomponents: u64,
+	pub data       : Data,
+
+	// Optional ComponentName0..N attributes, empty if the file has none
+	pub component_names: Vec<String>,
+}
+
+impl DataArray
+{
+	pub fn new(name: &str, ncomponents: u64, data: Data) -> DataArray
+	{
+		DataArray
+		{
+			name: name.to_string(),
+			ncomponents,
+			data,
+			component_names: Vec::new(),
+		}
+	}
+
+	// Min and max like ParaView shows them:  of the values for a single
+	// component, otherwise of the tuple magnitudes.  None if empty
+	pub fn range(&self) -> Option<(f64, f64)>
+	{
+		let nc = std::cmp::max(self.ncomponents as usize, 1);
+		let values = self.data.to_f64();
+		let mut range: Option<(f64, f64)> = None;
+		for t in values.chunks_exact(nc)
+		{
+			let x = if nc == 1
+			{
+				t[0]
+			}
+			else
+			{
+				t.iter().map(|x| x * x).sum::<f64>().sqrt()
+			};
+			range = Some(match range
+			{
+				None => (x, x),
+				Some((lo, hi)) => (lo.min(x), hi.max(x)),
+			});
+		}
+		return range;
+	}
+
+	// Name of component i, which may not be set even if others are
+	pub fn component_name(&self, i: usize) -> Option<&str>
+	{
+		self.component_names.get(i).map(String::as_str).filter(|n| !n.is_empty())
+	}
+
+	pub fn set_component_name(&mut self, i: usize, name: &str)
+	{
+		if i >= self.ncomponents as usize
+		{
+			panic!("Component {} is out of range for {} {} with {} components",
+				i, DATA, self.name, self.ncomponents);
+		}
+		if self.component_names.len() <= i
+		{
+			self.component_names.resize(i + 1, "".to_string());
+		}
+		self.component_names[i] = name.to_string();
+	}
+
+	// Number of tuples, i.e. points or cells
+	pub fn ntuples(&self) -> usize
+	{
+		self.data.len() / std::cmp::max(self.ncomponents as usize, 1)
+	}
+}
+
 //==============================================================================
 
 struct DataHeader
 {
 	// Attributes of DataArray element.  Could add RangeMin, RangeMax, etc.
-	pub dtype : String,
-	pub name  : String,
-	pub format: String,
+	pub dtype      : String,
+	pub name       : String,
+	pub format     : String,
+	pub ncomponents: u64,
+	pub component_names: Vec<String>,
 }
 
 impl DataHeader
@@ -147,6 +968,8 @@ impl DataHeader
 			dtype  : "".to_string(),
 			name   : "".to_string(),
 			format : BINARY.to_string(),
+			ncomponents: 1,
+			component_names: Vec::new(),
 		}
 	}
 }
@@ -192,17 +1015,81 @@ impl<'a, T: fmt::Display + 'a> fmt::Display for SliceDisplay<'a, T>
 
 pub fn load(file: &str) -> VtkFile
 {
-	println!("Loading VTK file \"{}\"\n", file);
+	// Load and check sizes, panicking on the first inconsistency
+	let v = load_unchecked(file);
+	check_sizes(&v);
+	return v;
+}
+
+//********
+
+pub fn load_unchecked(file: &str) -> VtkFile
+{
+	// Parse without checking sizes or cells, e.g. for validate, which reports
+	// every problem instead of stopping at the first one.  The file "-" is
+	// stdin
+
+	if file == STDIO
+	{
+		return read_unchecked(io::stdin().lock(), STDIN_NAME);
+	}
+
+	match input_format_from_path(file)
+	{
+		Some(FMT_JSON   ) => return vtkjs::load(file, false),
+		Some(FMT_MSGPACK) => return vtkjs::load(file, true),
+		Some(FMT_STL    ) => return stl::load(file),
+		Some(FMT_OBJ    ) => return obj::load(file),
+		Some(FMT_PLY    ) => return ply::load(file),
+		Some(FMT_GMSH   ) => return gmsh::load(file),
+		Some(FMT_ABAQUS ) => return abaqus::load(file),
+		Some(FMT_NASTRAN) => return nastran::load(file),
+		Some(FMT_XDMF   ) => return xdmf::load(file),
+		Some(FMT_VTU) | None => (),
+		Some(f) => panic!("Cannot load \"{}\".  {} files can be written but \
+			not read", file, f),
+	}
+
+	info!("Loading VTK file \"{}\"", file);
 
+	let errstr = format!("Cannot load VTK file \"{}\"", file);
+	let f = File::open(file).expect(&errstr);
+	return read_unchecked(BufReader::new(f), file);
+}
+
+//********
+
+pub fn read<R: BufRead>(reader: R, name: &str) -> VtkFile
+{
+	// Load from a stream, e.g. a socket or an in-memory buffer.  The name is
+	// only for error messages
+	let v = read_unchecked(reader, name);
+	check_sizes(&v);
+	return v;
+}
+
+//********
+
+pub fn read_unchecked<R: BufRead>(reader: R, name: &str) -> VtkFile
+{
 	let mut v = VtkFile::new();
 
+	// Files without a header_type attribute, including all version 0.1 files,
+	// have UInt32 headers
+	v.header_type = VTK_U32.to_string();
+
 	let mut data_array = false;
 	let mut dh = DataHeader::new();
 	let mut ename = "".to_string();
 
-	let errstr = format!("Cannot load VTK file \"{}\"", file);
+	// Enclosing tag of the current DataArray, e.g. Points or PointData
+	let mut section = "".to_string();
+
+	let mut piece = false;
 
-	let mut reader = Reader::from_file(file).expect(&errstr);
+	let errstr = format!("Cannot load VTK file \"{}\"", name);
+
+	let mut reader = Reader::from_reader(reader);
 	reader.trim_text(true);
 
 	let mut buf = Vec::new();
@@ -241,12 +1128,20 @@ pub fn load(file: &str) -> VtkFile
 
 					VERSION =>
 					{
-						v.version = val;
+						v.version = Version::parse(&val);
+						v.version.check();
+					},
 
-						// TODO:  unimplemented on unsupported old versions,
-						// warn about new versions.  Need to parse version into
-						// multiple ints, compare lexicographically
+					HEADER => v.header_type = val,
 
+					COMPRESS =>
+					{
+						if val != ZLIB
+						{
+							panic!("{}.  {} {} is not supported.  Only {} is \
+								supported", errstr, COMPRESS, val, ZLIB);
+						}
+						v.compressor = val;
 					},
 
 					BYTEORD =>
@@ -263,8 +1158,6 @@ pub fn load(file: &str) -> VtkFile
 						//};
 					},
 
-					// header_type not parsed
-
 					_ => (),
 					}  // match key
 
@@ -273,6 +1166,7 @@ pub fn load(file: &str) -> VtkFile
 
 			PIECE =>
 			{
+				piece = true;
 				for a in e.attributes()
 				{
 					let attr = a.expect(&errstr);
@@ -288,20 +1182,40 @@ pub fn load(file: &str) -> VtkFile
 				}
 			},  // Piece
 
-			// Outer tags are ignored, "name" attribute is used later in
-			// Text event
+			// Outer tags are only remembered.  For Points and Cells, the
+			// "name" attribute is used later in Text event
 			UGRID  => (),
-			POINTS => (),
-			CELLS  => (),
+			POINTS | CELLS | FDATA => section = ename.clone(),
 
-			// TODO
-			PDATA => (),
-			CDATA => (),
+			PDATA | CDATA =>
+			{
+				section = ename.clone();
+
+				for a in e.attributes()
+				{
+					let attr = a.expect(&errstr);
+					let key = reader.decode(attr.key).expect(&errstr);
+					let val = attr.unescape_and_decode_value(&reader).expect(&errstr);
+
+					if let Some(at) = Attribute::from_xml_name(key)
+					{
+						if ename == PDATA
+						{
+							v.pattributes.set(at, Some(val));
+						}
+						else
+						{
+							v.cattributes.set(at, Some(val));
+						}
+					}
+				}
+			},
 
 			DATA =>
 			{
 				//println!("DataArray");
 				data_array = true;
+				dh = DataHeader::new();
 
 				for a in e.attributes()
 				{
@@ -313,24 +1227,40 @@ pub fn load(file: &str) -> VtkFile
 					{
 						TYPE   => dh.dtype = val,
 						NAME   => dh.name = val,
-						NCOMP  => v.ncomponents = val.parse().expect(&errstr),
+						NCOMP  => dh.ncomponents = val.parse().expect(&errstr),
 						FORMAT => dh.format = val,
+
+						// ComponentName0, ComponentName1, etc.
+						_ if key.starts_with(COMPNAME) =>
+						{
+							let i: usize = key[COMPNAME.len() ..].parse()
+								.expect(&errstr);
+							if dh.component_names.len() <= i
+							{
+								dh.component_names.resize(i + 1, "".to_string());
+							}
+							dh.component_names[i] = val;
+						},
+
 						_ => (),
 					}
 				}
 
 			},  // DataArray
 
+			INFO_KEY | INFO_VALUE =>
+			{
+				debug!("skipping tag \"{}\" at position {} in \"{}\"", ename,
+					reader.buffer_position(), name);
+			},
+
 			// Default case
 			_ =>
 			{
-				// Bold does not work on Windows
-				println!("{}: unknown tag \"{}\" at position {}",
-						 Colour::Yellow.bold().paint("warning"),
-						 ename, reader.buffer_position());
+				warn!("unknown tag \"{}\" at position {} in \"{}\"", ename,
+					reader.buffer_position(), name);
 				//println!("attributes values: {:?}",
 				//	e.attributes().collect::<Vec<_>>());
-				println!();
 			},
 
 			}  // Start tag match
@@ -344,32 +1274,52 @@ pub fn load(file: &str) -> VtkFile
 				let string = e.unescape_and_decode(&reader).expect(&errstr);
 				//println!("string = {}", string);
 
-				// just use the name attribute (e.g. Name="Points") and ignore
-				// the outer tag (e.g.  <Points>)
-				match dh.name.as_str()
+				// Data arrays keep any name.  Otherwise, just use the name
+				// attribute (e.g. Name="Points") and ignore the outer tag (e.g.
+				// <Points>)
+				match (section.as_str(), dh.name.as_str())
 				{
-					POINTS  => v.points       = parse_data_f32(&dh, &string, &v),
-					CONN    => v.connectivity = parse_data_i64(&dh, &string, &v),
-					OFFSETS => v.offsets      = parse_data_i64(&dh, &string, &v),
-					TYPES   => v.types        = parse_data_u8 (&dh, &string, &v),
+					(PDATA, _) => v.pdata.push(parse_data_array(&dh, &string, &v)),
+					(CDATA, _) => v.cdata.push(parse_data_array(&dh, &string, &v)),
+					(FDATA, _) => v.fdata.push(parse_data_array(&dh, &string, &v)),
+
+					// Older files have no Name on the Points array
+					(POINTS, _) | (_, POINTS) =>
+					{
+						v.points      = parse_data_f32(&dh, &string, &v);
+						v.ncomponents = dh.ncomponents;
+
+						// Keep the input format unless convert changes it
+						v.format      = dh.format.clone();
+					},
+					(_, CONN   ) => v.connectivity = parse_data_i64(&dh, &string, &v),
+					(_, OFFSETS) => v.offsets      = parse_data_i64(&dh, &string, &v),
+					(_, TYPES  ) => v.types        = parse_data_u8 (&dh, &string, &v),
 
 					_ =>
 					{
-						println!("{}: unknown {} name \"{}\" at position {}\n",
-								 Colour::Yellow.bold().paint("warning"), DATA,
-								 dh.name, reader.buffer_position());
+						warn!("unknown {} name \"{}\" at position {} in \"{}\"",
+							DATA, dh.name, reader.buffer_position(), name);
 					},
 				}
+
+				// Only the first Text event belongs to the DataArray.
+				// Nested InformationKey values come later
+				data_array = false;
 			}
-			else
+			else if ename != INFO_VALUE
 			{
-				println!("{}: not parsing text in tag \"{}\" at position {}\n",
-						 Colour::Yellow.bold().paint("warning"),
-						 ename, reader.buffer_position());
+				warn!("not parsing text in tag \"{}\" at position {} in \"{}\"",
+					ename, reader.buffer_position(), name);
 			}
 
 		},  // Text event
 
+		Ok(Event::End(ref e)) if e.name() == section.as_bytes() =>
+		{
+			section.clear();
+		},
+
 		Ok(Event::Eof) => break, // exits the loop when reaching end of file
 		Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
 		_ => (), // There are several other `Event`s we do not consider here
@@ -377,8 +1327,32 @@ pub fn load(file: &str) -> VtkFile
 	}}  // reader match and loop
 	buf.clear();
 
-	// Verify DataArray sizes are consistent w/ npoints, ncells,
-	// ncomponents, etc.
+	// A grid without a Piece was not really read, which validate reports
+	if !piece && !v.vtype.is_empty()
+	{
+		warn!("no {} in \"{}\"", PIECE, name);
+		v.vtype.clear();
+	}
+
+	// Offsets may include the leading 0 in version 2 files
+	if v.version.major >= 2 && v.offsets.len() == v.ncells as usize + 1
+		&& v.offsets.first() == Some(&0)
+	{
+		v.offsets.remove(0);
+	}
+
+	debug!("Read {} points, {} cells, {} point arrays, and {} cell arrays from \
+		\"{}\"", v.npoints, v.ncells, v.pdata.len(), v.cdata.len(), name);
+
+	return v;
+}
+
+//********
+
+pub fn check_sizes(v: &VtkFile)
+{
+	// Verify DataArray sizes are consistent w/ npoints, ncells, and the
+	// Points array's own ncomponents
 
 	if v.points.len() < (v.ncomponents * v.npoints) as usize
 	{
@@ -394,17 +1368,34 @@ pub fn load(file: &str) -> VtkFile
 		panic!("Offsets are only of len {} < {}", v.offsets.len(), v.ncells);
 	}
 
-	if v.types.len() < v.ncells as usize
+	if v.types.len() < v.ncells as usize
+	{
+		panic!("Types are only of len {} < {}", v.types.len(), v.ncells);
+	}
+
+	// Could also warn if sizes are greater than expected, but that's less
+	// severe
+
+	for a in &v.pdata
+	{
+		if a.ntuples() < v.npoints as usize
+		{
+			panic!("{} {} is only of len {} < {}", PDATA, a.name, a.ntuples(),
+				v.npoints);
+		}
+	}
+
+	for a in &v.cdata
 	{
-		panic!("Offsets are only of len {} < {}", v.types.len(), v.ncells);
+		if a.ntuples() < v.ncells as usize
+		{
+			panic!("{} {} is only of len {} < {}", CDATA, a.name, a.ntuples(),
+				v.ncells);
+		}
 	}
 
-	// Could also warn if sizes are greater than expected, but that's less
-	// severe
-
-	// TODO:  check PointData and CellData too
-
-	return v;
+	// Node counts of higher-order cells depend on their degree
+	cells::check_cells(v);
 }
 
 //==============================================================================
@@ -413,7 +1404,69 @@ impl VtkFile
 {
 pub fn export(&self, file: &str)
 {
-	println!("Exporting VTK file \"{}\"\n", file);
+	// Export in the format of the file extension, or XML if it is unknown
+	self.export_as(file, format_from_path(file).unwrap_or(FMT_VTU));
+}
+
+pub fn export_as(&self, file: &str, format: &str)
+{
+	// The file "-" is stdout
+	if file == STDIO
+	{
+		let stdout = io::stdout();
+		let mut f = stdout.lock();
+		if let Err(e) = self.write(&mut f, format)
+		{
+			panic!("Cannot export VTK file to stdout:  {}", e);
+		}
+		return;
+	}
+
+	match format
+	{
+		FMT_VTU     => self.export_xml(file),
+		FMT_VTK     => self.export_legacy(file),
+		FMT_JSON    => self.export_vtkjs(file, false),
+		FMT_MSGPACK => self.export_vtkjs(file, true),
+		FMT_STL     => self.export_stl(file),
+		FMT_OBJ     => self.export_obj(file),
+		FMT_PLY     => self.export_ply(file),
+		FMT_GMSH    => self.export_gmsh(file),
+		FMT_TECPLOT => self.export_tecplot(file),
+		FMT_ENSIGHT => self.export_ensight(file),
+		FMT_XDMF    => self.export_xdmf(file),
+		FMT_GLTF    => self.export_gltf(file, false, None),
+		FMT_GLB     => self.export_gltf(file, true, None),
+		_ => panic!("Cannot export unknown format \"{}\"", format),
+	}
+}
+
+pub fn write<W: Write>(&self, f: &mut W, format: &str) -> Result<(), io::Error>
+{
+	// Export to a stream, e.g. a socket or an in-memory buffer
+	match format
+	{
+		FMT_VTU     => self.write_xml(f),
+		FMT_VTK     => self.write_legacy(f),
+		FMT_JSON    => self.write_vtkjs(f, false),
+		FMT_MSGPACK => self.write_vtkjs(f, true),
+		FMT_STL     => self.write_stl(f, self.format == ASCII),
+		FMT_OBJ     => self.write_obj(f),
+		FMT_PLY     => self.write_ply(f, self.format == ASCII),
+		FMT_GMSH    => self.write_gmsh(f, self.format != ASCII),
+		FMT_TECPLOT => self.write_tecplot(f),
+		FMT_GLTF    => self.write_gltf(f, false, None),
+		FMT_GLB     => self.write_gltf(f, true, None),
+		FMT_ENSIGHT | FMT_XDMF => Err(io::Error::new(io::ErrorKind::Unsupported,
+			format!("{} output is several files, which cannot go to a stream",
+			format))),
+		_ => panic!("Cannot export unknown format \"{}\"", format),
+	}
+}
+
+pub fn export_xml(&self, file: &str)
+{
+	info!("Exporting VTK file \"{}\"", file);
 
 	//// Just print a whole struct?  WTF rust!
 	//println!("self = {:?}", self);
@@ -422,115 +1475,196 @@ pub fn export(&self, file: &str)
 	// Basically a try/catch block
 	let try_export = || -> Result<(), io::Error>
 	{
-		let mut f = File::create(file)?;
+		let mut f = BufWriter::new(File::create(file)?);
+		self.write_xml(&mut f)?;
+		f.flush()
+	};
 
-		let byte_order = if self.endianness == base64::BIG_ENDIAN {
-			VTK_BIG
-		} else {
-			VTK_LIT
-		};
+	if let Err(_err) = try_export()
+	{
+		panic!("Cannot export VTK file \"{}\"", file);
+	}
+}
 
-		writeln!(f, "<{} {}=\"{}\" {}=\"{}\" {}=\"{}\" \
-			header_type=\"UInt64\">", VTK_FILE, TYPE, self.vtype, VERSION,
-			self.version, BYTEORD, byte_order)?;
+pub fn write_xml<W: Write>(&self, f: &mut W) -> Result<(), io::Error>
+{
+	let byte_order = self.byte_order();
 
-		writeln!(f, "	<{}>", self.vtype)?;
-		writeln!(f, "		<{} {}=\"{}\" {}=\"{}\">", PIECE, NPOINTS, self.npoints,
-			NCELLS, self.ncells)?;
+	// The header_type attribute was added in version 1.0
+	let header = if self.version < Version::new(1, 0) {
+		"".to_string()
+	} else {
+		format!(" {}=\"{}\"", HEADER, self.header_type)
+	};
 
-		//********
+	// Compression only applies to binary blocks
+	let compressor = if self.compressor.is_empty() || self.format != BINARY {
+		"".to_string()
+	} else {
+		format!(" {}=\"{}\"", COMPRESS, self.compressor)
+	};
 
-		// TODO
-		writeln!(f, "			<{}>", PDATA)?;
-		writeln!(f, "			</{}>", PDATA)?;
-		//********
-		writeln!(f, "			<{}>", CDATA)?;
-		writeln!(f, "			</{}>", CDATA)?;
+	writeln!(f, "<{} {}=\"{}\" {}=\"{}\" {}=\"{}\"{}{}>", VTK_FILE, TYPE,
+		self.vtype, VERSION, self.version, BYTEORD, byte_order, header,
+		compressor)?;
 
-		// No RangeMin/RangeMax
+	writeln!(f, "	<{}>", self.vtype)?;
+	if !self.fdata.is_empty()
+	{
+		writeln!(f, "		<{}>", FDATA)?;
+		for a in &self.fdata
+		{
+			self.write_data_array(f, a, true)?;
+		}
+		writeln!(f, "		</{}>", FDATA)?;
+	}
+	writeln!(f, "		<{} {}=\"{}\" {}=\"{}\">", PIECE, NPOINTS, self.npoints,
+		NCELLS, self.ncells)?;
 
-		//********
+	//********
 
-		writeln!(f, "			<{}>", POINTS)?;
+	writeln!(f, "			<{}{}>", PDATA, self.pattributes.to_xml())?;
+	for a in &self.pdata
+	{
+		self.write_data_array(f, a, false)?;
+	}
+	writeln!(f, "			</{}>", PDATA)?;
+	//********
+	writeln!(f, "			<{}{}>", CDATA, self.cattributes.to_xml())?;
+	for a in &self.cdata
+	{
+		self.write_data_array(f, a, false)?;
+	}
+	writeln!(f, "			</{}>", CDATA)?;
 
-		writeln!(f, "				<{} {}=\"{}\" {}=\"{}\" {}=\"{}\" {}=\"{}\">",
-			DATA, TYPE, VTK_F32, NAME, POINTS, NCOMP, self.ncomponents, FORMAT,
-			self.format)?;
+	// No RangeMin/RangeMax
 
-		// Could refactor these as format_data_*() fns for each type
-		if self.format == BINARY
-		{
-			writeln!(f, "					{}",
-				base64::encode_f32(&self.points, self.endianness))?;
-		}
-		else
-		{
-			writeln!(f, "{}", SliceDisplay(&self.points))?;
-		}
+	//********
 
-		writeln!(f, "				</{}>", DATA)?;
+	writeln!(f, "			<{}>", POINTS)?;
 
-		writeln!(f, "			</{}>", POINTS)?;
+	writeln!(f, "				<{} {}=\"{}\" {}=\"{}\" {}=\"{}\" {}=\"{}\">",
+		DATA, TYPE, VTK_F32, NAME, POINTS, NCOMP, self.ncomponents, FORMAT,
+		self.format)?;
 
-		//********
+	// Could refactor these as format_data_*() fns for each type
+	if self.format == BINARY
+	{
+		writeln!(f, "					{}", self.encode(&self.points))?;
+	}
+	else
+	{
+		writeln!(f, "{}", SliceDisplay(&self.points))?;
+	}
 
-		writeln!(f, "			<{}>", CELLS)?;
+	writeln!(f, "				</{}>", DATA)?;
 
-		writeln!(f, "				<{} {}=\"{}\" {}=\"{}\" {}=\"{}\">",
-			DATA, TYPE, VTK_I64, NAME, CONN, FORMAT, self.format)?;
-		if self.format == BINARY
-		{
-			writeln!(f, "					{}",
-				base64::encode_i64(&self.connectivity, self.endianness))?;
-		}
-		else
-		{
-			writeln!(f, "{}", SliceDisplay(&self.connectivity))?;
-		}
-		writeln!(f, "				</{}>", DATA)?;
+	writeln!(f, "			</{}>", POINTS)?;
 
-		writeln!(f, "				<{} {}=\"{}\" {}=\"{}\" {}=\"{}\">",
-			DATA, TYPE, VTK_I64, NAME, OFFSETS, FORMAT, self.format)?;
-		if self.format == BINARY
-		{
-			writeln!(f, "					{}",
-				base64::encode_i64(&self.offsets, self.endianness))?;
-		}
-		else
-		{
-			writeln!(f, "{}", SliceDisplay(&self.offsets))?;
-		}
-		writeln!(f, "				</{}>", DATA)?;
+	//********
 
-		writeln!(f, "				<{} {}=\"{}\" {}=\"{}\" {}=\"{}\">",
-			DATA, TYPE, VTK_U8, NAME, TYPES, FORMAT, self.format)?;
-		if self.format == BINARY
-		{
-			writeln!(f, "					{}",
-				base64::encode_u8(&self.types, self.endianness))?;
-		}
-		else
-		{
-			writeln!(f, "{}", SliceDisplay(&self.types))?;
-		}
-		writeln!(f, "				</{}>", DATA)?;
+	writeln!(f, "			<{}>", CELLS)?;
+
+	writeln!(f, "				<{} {}=\"{}\" {}=\"{}\" {}=\"{}\">",
+		DATA, TYPE, VTK_I64, NAME, CONN, FORMAT, self.format)?;
+	if self.format == BINARY
+	{
+		writeln!(f, "					{}", self.encode(&self.connectivity))?;
+	}
+	else
+	{
+		writeln!(f, "{}", SliceDisplay(&self.connectivity))?;
+	}
+	writeln!(f, "				</{}>", DATA)?;
+
+	writeln!(f, "				<{} {}=\"{}\" {}=\"{}\" {}=\"{}\">",
+		DATA, TYPE, VTK_I64, NAME, OFFSETS, FORMAT, self.format)?;
+	if self.format == BINARY
+	{
+		writeln!(f, "					{}", self.encode(&self.offsets))?;
+	}
+	else
+	{
+		writeln!(f, "{}", SliceDisplay(&self.offsets))?;
+	}
+	writeln!(f, "				</{}>", DATA)?;
 
-		writeln!(f, "			</{}>", CELLS)?;
+	writeln!(f, "				<{} {}=\"{}\" {}=\"{}\" {}=\"{}\">",
+		DATA, TYPE, VTK_U8, NAME, TYPES, FORMAT, self.format)?;
+	if self.format == BINARY
+	{
+		writeln!(f, "					{}", self.encode(&self.types))?;
+	}
+	else
+	{
+		writeln!(f, "{}", SliceDisplay(&self.types))?;
+	}
+	writeln!(f, "				</{}>", DATA)?;
 
-		//********
+	writeln!(f, "			</{}>", CELLS)?;
 
-		writeln!(f, "		</{}>", PIECE)?;
-		writeln!(f, "	</{}>", self.vtype)?;
-		writeln!(f, "</{}>", VTK_FILE)?;
+	//********
 
-		Ok(())
+	writeln!(f, "		</{}>", PIECE)?;
+	writeln!(f, "	</{}>", self.vtype)?;
+	writeln!(f, "</{}>", VTK_FILE)?;
+
+	Ok(())
+}
+
+fn encode<T: base64::Scalar>(&self, v: &[T]) -> String
+{
+	// Binary block with this file's header type and compressor
+	return if self.compressor.is_empty()
+	{
+		base64::encode_with_header(v, self.endianness, self.header_bytes())
+	}
+	else
+	{
+		base64::encode_zlib(v, self.endianness, self.header_bytes())
 	};
+}
 
-	if let Err(_err) = try_export()
+fn write_data_array<W: Write>(&self, f: &mut W, a: &DataArray, field: bool)
+	-> Result<(), io::Error>
+{
+	// Write a PointData, CellData or FieldData array.  Field arrays have no
+	// point or cell count to size them
+
+	let mut names = String::new();
+	for (i, n) in a.component_names.iter().enumerate()
 	{
-		panic!("Cannot export VTK file \"{}\"", file);
+		if !n.is_empty()
+		{
+			names += &format!(" {}{}=\"{}\"", COMPNAME, i, n);
+		}
+	}
+
+	let ntuples = if field
+	{
+		format!(" {}=\"{}\"", NTUPLES, a.data.len() as u64 / a.ncomponents.max(1))
+	}
+	else
+	{
+		"".to_string()
+	};
+
+	writeln!(f, "				<{} {}=\"{}\" {}=\"{}\" {}=\"{}\"{}{} {}=\"{}\">",
+		DATA, TYPE, a.data.vtk_type(), NAME, a.name, NCOMP, a.ncomponents,
+		ntuples, names, FORMAT, self.format)?;
+
+	if self.format == BINARY
+	{
+		writeln!(f, "					{}", for_data!(&a.data, d => self.encode(d)))?;
+	}
+	else
+	{
+		writeln!(f, "{}", for_data!(&a.data, d => SliceDisplay(d).to_string()))?;
 	}
 
+	writeln!(f, "				</{}>", DATA)?;
+
+	Ok(())
 }}
 
 //==============================================================================
@@ -563,64 +1697,183 @@ pub fn convert(&mut self, settings: &Settings)
 		self.format = BINARY.to_string();
 	}
 
+	if settings.zlib
+	{
+		self.compressor = ZLIB.to_string();
+	}
+	else if settings.uncompressed
+	{
+		self.compressor.clear();
+	}
+
+	if let Some(version) = &settings.vtk_version
+	{
+		self.set_version(Version::parse(version));
+	}
+
+	for (name, file, section) in &settings.npy_arrays
+	{
+		self.attach_npy(file, name, *section);
+	}
+
+	if settings.strip_ghosts
+	{
+		*self = self.strip_ghosts();
+	}
+
+	if settings.linearize
+	{
+		*self = self.linearize();
+	}
+
+	// Array selection after the steps above, which may need e.g. ghost arrays
+	let keep = |a: &DataArray| (settings.keep_arrays.is_empty()
+		|| settings.keep_arrays.contains(&a.name))
+		&& !settings.drop_arrays.contains(&a.name);
+	self.pdata.retain(keep);
+	self.cdata.retain(keep);
+	self.fdata.retain(keep);
+	for &a in Attribute::ALL.iter()
+	{
+		if self.point_array(self.pattributes.get(a).unwrap_or("")).is_none()
+		{
+			self.pattributes.set(a, None);
+		}
+		if self.cell_array(self.cattributes.get(a).unwrap_or("")).is_none()
+		{
+			self.cattributes.set(a, None);
+		}
+	}
+
+	// Casts by array name take precedence over casts by type
+	for a in self.pdata.iter_mut().chain(self.cdata.iter_mut())
+		.chain(self.fdata.iter_mut())
+	{
+		let cast = settings.downcasts.iter().find(|(from, _)| *from == a.name)
+			.or_else(|| settings.downcasts.iter()
+				.find(|(from, _)| from == a.data.vtk_type()));
+		if let Some((_, to)) = cast
+		{
+			a.data = a.data.cast(to);
+		}
+	}
+
 }}
 
 //==============================================================================
 
-fn check_type(dh: &DataHeader, expected: &str)
+fn check_type(dh: &DataHeader, expected: &[&str])
 {
-	if dh.dtype != expected
+	if !expected.contains(&dh.dtype.as_str())
 	{
 		panic!("Expected type {} for {} {}.  Found type {}",
-			expected, DATA, dh.name, dh.dtype);
+			expected.join(" or "), DATA, dh.name, dh.dtype);
 	}
 }
 
 //==============================================================================
 
-// TODO:  generic fns that return any type Vec
-
-//fn parse_data<T: i64>(dh: &DataHeader, string: &str, v: &VtkFile) -> Vec<T>
-//{
-//	check_type(&dh, VTK_I64);
-//	return if dh.format == BINARY {
-//		base64::decode_i64(string, v.endianness)
-//		//base64::decode_i64(string, v.endianness) as Vec<T>
-//		//Vec<T>::from(base64::decode_i64(string, v.endianness))
-//	} else {
-//		unimplemented!("format {} is not implemented", dh.format)
-//	};
-//	return v;
-//}
+fn parse_ascii<T: std::str::FromStr>(dh: &DataHeader, string: &str) -> Vec<T>
+{
+	return string.split_whitespace().map(|x| x.parse().unwrap_or_else(|_|
+		panic!("Cannot parse \"{}\" as {} in {} {}", x, dh.dtype, DATA, dh.name)))
+		.collect();
+}
 
-fn parse_data_f32(dh: &DataHeader, string: &str, v: &VtkFile) -> Vec<f32>
+//********
+
+fn decode<T: base64::Scalar>(string: &str, v: &VtkFile) -> Vec<T>
 {
-	check_type(&dh, VTK_F32);
-	return if dh.format == BINARY {
-		base64::decode_f32(string, v.endianness)
-	} else {
-		// TODO ascii, appended (raw binary)
-		unimplemented!("format {} is not implemented", dh.format)
+	let (e, h) = (v.endianness, v.header_bytes());
+	return if v.compressor.is_empty()
+	{
+		base64::decode_with_header(string, e, h)
+	}
+	else
+	{
+		base64::decode_zlib(string, e, h)
 	};
 }
 
-fn parse_data_i64(dh: &DataHeader, string: &str, v: &VtkFile) -> Vec<i64>
+//********
+
+fn parse_data(dh: &DataHeader, string: &str, v: &VtkFile) -> Data
 {
-	check_type(&dh, VTK_I64);
-	return if dh.format == BINARY {
-		base64::decode_i64(string, v.endianness)
-	} else {
+	// Decode a DataArray of any type
+
+	if dh.format == ASCII
+	{
+		return match dh.dtype.as_str()
+		{
+			VTK_I8  => Data::I8 (parse_ascii(dh, string)),
+			VTK_U8  => Data::U8 (parse_ascii(dh, string)),
+			VTK_I16 => Data::I16(parse_ascii(dh, string)),
+			VTK_U16 => Data::U16(parse_ascii(dh, string)),
+			VTK_I32 => Data::I32(parse_ascii(dh, string)),
+			VTK_U32 => Data::U32(parse_ascii(dh, string)),
+			VTK_I64 => Data::I64(parse_ascii(dh, string)),
+			VTK_U64 => Data::U64(parse_ascii(dh, string)),
+			VTK_F32 => Data::F32(parse_ascii(dh, string)),
+			VTK_F64 => Data::F64(parse_ascii(dh, string)),
+			_ => panic!("Unknown type {} for {} {}", dh.dtype, DATA, dh.name),
+		};
+	}
+	else if dh.format != BINARY
+	{
+		// TODO appended (raw binary)
 		unimplemented!("format {} is not implemented", dh.format)
+	}
+
+	return match dh.dtype.as_str()
+	{
+		VTK_I8  => Data::I8 (decode(string, v)),
+		VTK_U8  => Data::U8 (decode(string, v)),
+		VTK_I16 => Data::I16(decode(string, v)),
+		VTK_U16 => Data::U16(decode(string, v)),
+		VTK_I32 => Data::I32(decode(string, v)),
+		VTK_U32 => Data::U32(decode(string, v)),
+		VTK_I64 => Data::I64(decode(string, v)),
+		VTK_U64 => Data::U64(decode(string, v)),
+		VTK_F32 => Data::F32(decode(string, v)),
+		VTK_F64 => Data::F64(decode(string, v)),
+		_ => panic!("Unknown type {} for {} {}", dh.dtype, DATA, dh.name),
 	};
 }
 
+fn parse_data_f32(dh: &DataHeader, string: &str, v: &VtkFile) -> Vec<f32>
+{
+	// Float64 points are narrowed
+	check_type(dh, &[VTK_F32, VTK_F64]);
+	return match parse_data(dh, string, v)
+	{
+		Data::F32(p) => p,
+		d => d.to_f64().iter().map(|&x| x as f32).collect(),
+	};
+}
+
+fn parse_data_i64(dh: &DataHeader, string: &str, v: &VtkFile) -> Vec<i64>
+{
+	// Older files and VTK builds with 32-bit IDs use Int32 connectivity and
+	// offsets
+	check_type(dh, &[VTK_I32, VTK_U32, VTK_I64, VTK_U64]);
+	return parse_data(dh, string, v).to_i64();
+}
+
+fn parse_data_array(dh: &DataHeader, string: &str, v: &VtkFile) -> DataArray
+{
+	// Parse a PointData or CellData array of any type
+	let mut a = DataArray::new(&dh.name, dh.ncomponents, parse_data(dh, string, v));
+	a.component_names = dh.component_names.clone();
+	return a;
+}
+
 fn parse_data_u8(dh: &DataHeader, string: &str, v: &VtkFile) -> Vec<u8>
 {
-	check_type(&dh, VTK_U8);
-	return if dh.format == BINARY {
-		base64::decode_u8(string, v.endianness)
-	} else {
-		unimplemented!("format {} is not implemented", dh.format)
+	check_type(dh, &[VTK_U8]);
+	return match parse_data(dh, string, v)
+	{
+		Data::U8(t) => t,
+		_ => unreachable!(),
 	};
 }
 
@@ -637,8 +1890,10 @@ mod tests
 		{
 
 			vtype: "UnstructuredGrid".to_string(),
-			version: "1.0".to_string(),
+			version: Version::new(1, 0),
 			endianness: base64::LITTLE_ENDIAN,
+			header_type: VTK_U64.to_string(),
+			compressor: "".to_string(),
 			format: BINARY.to_string(),
 
 			npoints: 12,
@@ -663,7 +1918,14 @@ mod tests
 				48, 51, 54, 57, 60].to_vec(),
 
 			types: [5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5,
-				5, 5].to_vec()
+				5, 5].to_vec(),
+
+			pdata: Vec::new(),
+			cdata: Vec::new(),
+			fdata: Vec::new(),
+
+			pattributes: Attributes::new(),
+			cattributes: Attributes::new(),
 
 		};
 		return ico;
@@ -682,6 +1944,233 @@ mod tests
 		assert_eq!(vs, icos);
 	}
 
+	#[test]
+	fn test_load_encodings()
+	{
+		// ASCII and zlib files load like binary ones, and zlib is kept on export
+		let b = load("./data/icosahedron-binary.vtu");
+		let a = load("./data/icosahedron-ascii.vtu");
+		let z = load("./data/icosahedron-zlib.vtu");
+		assert_eq!(a.format, ASCII);
+		assert_eq!(z.compressor, ZLIB);
+		assert_eq!((&a.points, &a.connectivity), (&b.points, &b.connectivity));
+		assert_eq!((&z.points, &z.offsets, &z.types), (&b.points, &b.offsets, &b.types));
+
+		let temp = "./scratch/tmp.Hx2kR9vLcw.vtu";
+		z.export(temp);
+		let w = load(temp);
+		assert_eq!(w.compressor, ZLIB);
+		assert_eq!(w.connectivity, b.connectivity);
+	}
+
+	#[test]
+	fn test_streams()
+	{
+		// Write to and read from memory instead of files
+		let v = load("./data/icosahedron-binary-pdata.vtu");
+		let mut buf = Vec::new();
+		v.write(&mut buf, FMT_VTU).unwrap();
+
+		let w = read(&buf[..], "buffer");
+		assert_eq!((&w.points, &w.connectivity), (&v.points, &v.connectivity));
+		assert_eq!(w.pdata[0].data, v.pdata[0].data);
+
+		let mut legacy = Vec::new();
+		v.write(&mut legacy, FMT_VTK).unwrap();
+		assert!(legacy.starts_with(b"# vtk DataFile"));
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn test_serde()
+	{
+		let v = load("./data/icosahedron-binary-pdata.vtu");
+		let json = serde_json::to_string(&v).unwrap();
+		let w: VtkFile = serde_json::from_str(&json).unwrap();
+		assert_eq!((&w.points, &w.pdata), (&v.points, &v.pdata));
+		assert_eq!(w.version, v.version);
+	}
+
+	#[test]
+	fn test_format_from_path()
+	{
+		assert_eq!(format_from_path("a/b.vtu"), Some(FMT_VTU));
+		assert_eq!(format_from_path("B.VTK"), Some(FMT_VTK));
+		assert_eq!(format_from_path("b.foo"), None);
+		assert_eq!(format_from_path("b.inp"), None);
+		assert_eq!(input_format_from_path("b.nas"), Some(FMT_NASTRAN));
+	}
+
+	#[test]
+	fn test_attributes()
+	{
+		let mut v = load("./data/icosahedron-binary-pdata.vtu");
+		assert_eq!(v.point_attribute(Attribute::Scalars), Some("Resultz"));
+		assert_eq!(v.point_attribute(Attribute::Vectors), None);
+		assert_eq!(v.active_point_array(Attribute::Scalars).unwrap().ntuples(), 12);
+
+		v.set_point_attribute(Attribute::Vectors, Some("Resultz"));
+		let temp = "./scratch/tmp.q8ZTm3bEwR.vtu";
+		v.export(temp);
+
+		let w = load(temp);
+		assert_eq!(w.pattributes, v.pattributes);
+		assert_eq!(w.point_attribute(Attribute::Vectors), Some("Resultz"));
+	}
+
+	#[test]
+	#[should_panic]
+	fn test_attribute_unknown()
+	{
+		let mut v = load("./data/icosahedron-binary-pdata.vtu");
+		v.set_cell_attribute(Attribute::Scalars, Some("Resultz"));
+	}
+
+	#[test]
+	fn test_components()
+	{
+		// A 9-component tensor with component names, placed after the Points
+		// so that its NumberOfComponents is parsed last
+		let mut v = icosahedron();
+		let mut t = DataArray::new("T", 9, Data::F64(vec![0.5; 9 * 12]));
+		t.set_component_name(0, "XX");
+		t.set_component_name(8, "ZZ");
+		v.pdata.push(t);
+
+		let temp = "./scratch/tmp.Vd9xR2cKfA.vtu";
+		v.export(temp);
+
+		let s = std::fs::read_to_string(temp).unwrap();
+		let begin = s.find("			<PointData").unwrap();
+		let end = s.find("			<CellData").unwrap();
+		let pdata = s[begin .. end].to_string();
+		let s = s.replace(&pdata, "").replace("		</Piece>",
+			&format!("{}		</Piece>", pdata));
+		std::fs::write(temp, s).unwrap();
+
+		let w = load(temp);
+		assert_eq!(w.ncomponents, 3);
+		assert_eq!(w.pdata, v.pdata);
+		assert_eq!(w.pdata[0].component_name(0), Some("XX"));
+		assert_eq!(w.pdata[0].component_name(1), None);
+		assert_eq!(w.pdata[0].component_name(8), Some("ZZ"));
+	}
+
+	#[test]
+	fn test_field_data()
+	{
+		// FieldData before the Piece, with a tuple count unrelated to the grid
+		let mut v = icosahedron();
+		v.fdata.push(DataArray::new("TimeValue", 1, Data::F64(vec![2.5])));
+		v.fdata.push(DataArray::new("steps", 2, Data::I32(vec![1, 2, 3, 4, 5, 6])));
+
+		let temp = "./scratch/tmp.Fd7kWq2zNe.vtu";
+		for format in &[BINARY, ASCII]
+		{
+			v.format = format.to_string();
+			v.export(temp);
+
+			let s = std::fs::read_to_string(temp).unwrap();
+			assert!(s.find("<FieldData>").unwrap() < s.find("<Piece").unwrap());
+			assert!(s.contains("Name=\"steps\" NumberOfComponents=\"2\" \
+				NumberOfTuples=\"3\""));
+
+			let w = load(temp);
+			assert_eq!(w.fdata, v.fdata);
+			assert_eq!(w.pdata, v.pdata);
+		}
+	}
+
+	#[test]
+	fn test_version()
+	{
+		assert!(Version::parse("0.1") < Version::parse("1.0"));
+		assert!(Version::parse("0.10") > Version::parse("0.9"));
+		assert_eq!(Version::parse("2"), Version::new(2, 0));
+		assert_eq!(Version::parse("2.1").to_string(), "2.1");
+	}
+
+	#[test]
+	fn test_version_0_1()
+	{
+		let mut ico = icosahedron();
+		ico.set_version(Version::parse("0.1"));
+
+		let temp = "./scratch/tmp.u3CkJ0yWsT.vtu";
+		ico.export(temp);
+
+		let s = std::fs::read_to_string(temp).unwrap();
+		assert!(s.contains("version=\"0.1\""));
+		assert!(!s.contains(HEADER));
+
+		let v = load(temp);
+		assert_eq!(v.header_type, VTK_U32);
+		assert_eq!(format!("{:?}", v), format!("{:?}", ico));
+	}
+
+	#[test]
+	fn test_int32_offsets()
+	{
+		// Version 0.1 file with UInt32 headers, Int32 cells, and no
+		// header_type attribute
+		let ico = icosahedron();
+		let e = base64::LITTLE_ENDIAN;
+		let conn: Vec<i32> = ico.connectivity.iter().map(|&i| i as i32).collect();
+		let offs: Vec<i32> = ico.offsets.iter().map(|&i| i as i32).collect();
+
+		let temp = "./scratch/tmp.P7tNw1eKqa.vtu";
+		std::fs::write(temp, format!("<VTKFile type=\"UnstructuredGrid\" \
+			version=\"0.1\" byte_order=\"LittleEndian\">\n\
+			<UnstructuredGrid><Piece NumberOfPoints=\"12\" NumberOfCells=\"20\">\n\
+			<Points><DataArray type=\"Float32\" NumberOfComponents=\"3\" \
+			format=\"binary\">{}</DataArray></Points>\n\
+			<Cells>\n\
+			<DataArray type=\"Int32\" Name=\"connectivity\" format=\"binary\">{}</DataArray>\n\
+			<DataArray type=\"Int32\" Name=\"offsets\" format=\"binary\">{}</DataArray>\n\
+			<DataArray type=\"UInt8\" Name=\"types\" format=\"binary\">{}</DataArray>\n\
+			</Cells></Piece></UnstructuredGrid></VTKFile>\n",
+			base64::encode_with_header(&ico.points, e, 4),
+			base64::encode_with_header(&conn, e, 4),
+			base64::encode_with_header(&offs, e, 4),
+			base64::encode_with_header(&ico.types, e, 4))).unwrap();
+
+		let v = load(temp);
+		assert_eq!(v.connectivity, ico.connectivity);
+		assert_eq!(v.offsets, ico.offsets);
+		assert_eq!(v.points, ico.points);
+	}
+
+	#[test]
+	#[should_panic(expected = "not supported")]
+	fn test_future_version()
+	{
+		let temp = "./scratch/tmp.Zr5bDqM2hc.vtu";
+		let s = std::fs::read_to_string("./data/icosahedron-binary.vtu").unwrap();
+		std::fs::write(temp, s.replace("version=\"1.0\"", "version=\"3.0\"")).unwrap();
+		load(temp);
+	}
+
+	#[test]
+	#[should_panic(expected = "compressor vtkLZ4DataCompressor is not supported")]
+	fn test_unsupported_compressor()
+	{
+		let temp = "./scratch/tmp.Lz4q8RbVe1.vtu";
+		let s = std::fs::read_to_string("./data/icosahedron-binary.vtu").unwrap();
+		std::fs::write(temp, s.replace("<VTKFile ",
+			"<VTKFile compressor=\"vtkLZ4DataCompressor\" ")).unwrap();
+		load(temp);
+	}
+
+	#[test]
+	#[should_panic(expected = "2.3 is not supported")]
+	fn test_future_minor_version()
+	{
+		let temp = "./scratch/tmp.Wm4cTy7Ln0.vtu";
+		let s = std::fs::read_to_string("./data/icosahedron-binary.vtu").unwrap();
+		std::fs::write(temp, s.replace("version=\"1.0\"", "version=\"2.3\"")).unwrap();
+		load(temp);
+	}
+
 	// TODO: ascii tests (need ascii load fn first)
 
 	#[test]
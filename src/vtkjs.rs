@@ -0,0 +1,409 @@
+
+//==============================================================================
+
+// vtk.js JSON and MessagePack IO.  Files hold a serialized vtkPolyData, as
+// made by vtk.js getState(), which the browser viewer loads with vtk(json).
+// Only the boundary surface is exported, since vtk.js renders polydata only
+
+// Standard
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+//********
+
+// Third party
+use log::info;
+use serde_json::{json, Value};
+
+//********
+
+// This lib
+use crate::cells;
+use crate::vtk::{Attribute, Attributes, Data, DataArray, VtkFile};
+
+//********
+
+pub const JSON_EXT   : &str = ".json";
+pub const MSGPACK_EXT: &str = ".msgpack";
+
+const VTK_CLASS: &str = "vtkClass";
+const POLY_DATA: &str = "vtkPolyData";
+
+// vtkPolyData cell arrays, in vtk.js cell order
+const VERTS : &str = "verts";
+const LINES : &str = "lines";
+const POLYS : &str = "polys";
+const STRIPS: &str = "strips";
+
+//==============================================================================
+
+fn array_type(data: &Data) -> &'static str
+{
+	// JS typed array of the data.  JS has no 64-bit int arrays in vtk.js, so
+	// those are written as doubles
+	match data
+	{
+		Data::I8 (_) => "Int8Array",
+		Data::U8 (_) => "Uint8Array",
+		Data::I16(_) => "Int16Array",
+		Data::U16(_) => "Uint16Array",
+		Data::I32(_) => "Int32Array",
+		Data::U32(_) => "Uint32Array",
+		Data::F32(_) => "Float32Array",
+		Data::I64(_) | Data::U64(_) | Data::F64(_) => "Float64Array",
+	}
+}
+
+//********
+
+fn vtk_type(array_type: &str) -> &'static str
+{
+	match array_type
+	{
+		"Int8Array"    => "Int8",
+		"Uint8Array"   => "UInt8",
+		"Int16Array"   => "Int16",
+		"Uint16Array"  => "UInt16",
+		"Int32Array"   => "Int32",
+		"Uint32Array"  => "UInt32",
+		"Float32Array" => "Float32",
+		"Float64Array" => "Float64",
+		_ => panic!("Cannot read vtk.js data type \"{}\"", array_type),
+	}
+}
+
+//********
+
+fn values(data: &Data) -> Vec<Value>
+{
+	// Floats go through their shortest string, so f32 values are not
+	// padded with f64 digits.  Non-finite values become null
+	let float = |x: f64| serde_json::Number::from_f64(x).map_or(Value::Null,
+		Value::Number);
+	return match data
+	{
+		Data::F32(v) => v.iter().map(|x| float(x.to_string().parse().unwrap()))
+			.collect(),
+		Data::F64(v) => v.iter().map(|&x| float(x)).collect(),
+		Data::U64(v) => v.iter().map(|&x| Value::from(x)).collect(),
+		_ => data.to_i64().into_iter().map(Value::from).collect(),
+	};
+}
+
+//********
+
+fn data_array(class: &str, name: &str, ncomponents: u64, data: &Data) -> Value
+{
+	return json!({
+		VTK_CLASS: class,
+		"name": name,
+		"numberOfComponents": ncomponents,
+		"dataType": array_type(data),
+		"size": data.len(),
+		"values": values(data),
+	});
+}
+
+//********
+
+fn attributes(arrays: &[DataArray], attrs: &Attributes) -> Value
+{
+	// vtkDataSetAttributes, with active attributes as array indices
+	let mut state = json!({
+		VTK_CLASS: "vtkDataSetAttributes",
+		"arrays": arrays.iter().map(|a| json!({"data": data_array("vtkDataArray",
+			&a.name, a.ncomponents, &a.data)})).collect::<Vec<Value>>(),
+	});
+	for &a in Attribute::ALL.iter()
+	{
+		let index = attrs.get(a)
+			.and_then(|n| arrays.iter().position(|b| b.name == n))
+			.map_or(-1, |i| i as i64);
+		state[active_key(a)] = Value::from(index);
+	}
+	return state;
+}
+
+//********
+
+fn active_key(a: Attribute) -> String
+{
+	// e.g. "activeScalars"
+	return format!("active{}", a.xml_name());
+}
+
+//==============================================================================
+
+impl VtkFile
+{
+pub fn to_vtkjs(&self) -> Value
+{
+	// Serialized vtkPolyData of the boundary surface
+
+	let v = self.surface();
+
+	// Sort cells into the 4 polydata cell arrays
+	let mut arrays = vec![Vec::new(); 4];
+	let mut parents = vec![Vec::new(); 4];
+	for i in 0 .. v.ncells as usize
+	{
+		let mut conn = v.cell_points(i).to_vec();
+		let k = match v.types[i]
+		{
+			cells::VTK_VERTEX | cells::VTK_POLY_VERTEX => 0,
+			cells::VTK_LINE   | cells::VTK_POLY_LINE   => 1,
+			cells::VTK_TRIANGLE_STRIP => 3,
+			cells::VTK_PIXEL =>
+			{
+				// Pixels are quads with the last 2 points swapped
+				conn.swap(2, 3);
+				2
+			},
+			_ => 2,
+		};
+		arrays[k].push(conn.len() as i64);
+		arrays[k].extend(conn);
+		parents[k].push(i);
+	}
+
+	let parents: Vec<usize> = parents.concat();
+	let cdata: Vec<DataArray> = v.cdata.iter().map(|a| DataArray
+	{
+		data: a.data.select(a.ncomponents as usize, &parents),
+		..a.clone()
+	}).collect();
+
+	let mut state = json!({
+		VTK_CLASS: POLY_DATA,
+		"points": data_array("vtkPoints", "_points", v.ncomponents,
+			&Data::F32(v.points.clone())),
+		"pointData": attributes(&v.pdata, &v.pattributes),
+		"cellData": attributes(&cdata, &v.cattributes),
+	});
+	for (k, name) in [VERTS, LINES, POLYS, STRIPS].iter().enumerate()
+	{
+		let conn = Data::U32(arrays[k].iter().map(|&x| x as u32).collect());
+		state[*name] = data_array("vtkCellArray", &format!("_{}", name), 1, &conn);
+	}
+
+	return state;
+}
+
+//********
+
+pub fn write_vtkjs<W: Write>(&self, f: &mut W, msgpack: bool)
+	-> Result<(), io::Error>
+{
+	let state = self.to_vtkjs();
+	if msgpack
+	{
+		let bytes = rmp_serde::to_vec_named(&state)
+			.map_err(io::Error::other)?;
+		f.write_all(&bytes)?;
+	}
+	else
+	{
+		serde_json::to_writer(&mut *f, &state)?;
+		writeln!(f)?;
+	}
+	return Ok(());
+}
+
+//********
+
+pub fn export_vtkjs(&self, file: &str, msgpack: bool)
+{
+	info!("Exporting vtk.js file \"{}\"", file);
+
+	let try_export = || -> Result<(), io::Error>
+	{
+		let mut f = BufWriter::new(File::create(file)?);
+		self.write_vtkjs(&mut f, msgpack)?;
+		f.flush()
+	};
+
+	if let Err(e) = try_export()
+	{
+		panic!("Cannot export vtk.js file \"{}\":  {}", file, e);
+	}
+}}
+
+//==============================================================================
+
+fn get<'a>(state: &'a Value, key: &str, name: &str) -> &'a Value
+{
+	let v = &state[key];
+	if v.is_null()
+	{
+		panic!("Cannot read vtk.js file \"{}\":  missing \"{}\"", name, key);
+	}
+	return v;
+}
+
+//********
+
+fn read_array(state: &Value, name: &str) -> DataArray
+{
+	// vtkDataArray state to a DataArray of the same type
+	let dtype = vtk_type(get(state, "dataType", name).as_str().unwrap_or(""));
+	let values: Vec<f64> = get(state, "values", name).as_array()
+		.unwrap_or_else(|| panic!("Cannot read vtk.js values in \"{}\"", name))
+		.iter().map(|x| x.as_f64().unwrap_or(f64::NAN)).collect();
+	let ncomponents = state["numberOfComponents"].as_u64().unwrap_or(1);
+	let aname = state["name"].as_str().unwrap_or("");
+	return DataArray::new(aname, ncomponents, Data::F64(values).cast(dtype));
+}
+
+//********
+
+fn read_attributes(state: &Value, name: &str) -> (Vec<DataArray>, Attributes)
+{
+	let mut attrs = Attributes::new();
+	if state.is_null()
+	{
+		return (Vec::new(), attrs);
+	}
+
+	let arrays: Vec<DataArray> = state["arrays"].as_array().map_or(Vec::new(),
+		|a| a.iter().map(|a| read_array(get(a, "data", name), name)).collect());
+
+	for &a in Attribute::ALL.iter()
+	{
+		if let Some(i) = state[active_key(a)].as_i64()
+		{
+			if i >= 0 && (i as usize) < arrays.len()
+			{
+				attrs.set(a, Some(arrays[i as usize].name.clone()));
+			}
+		}
+	}
+	return (arrays, attrs);
+}
+
+//********
+
+pub fn from_vtkjs(state: &Value, name: &str) -> VtkFile
+{
+	// Serialized vtkPolyData to an unstructured grid.  Cell sizes pick the
+	// types, e.g. polys of 3 points are triangles
+
+	if state[VTK_CLASS] != POLY_DATA
+	{
+		panic!("Cannot read vtk.js file \"{}\":  expected a {}", name, POLY_DATA);
+	}
+
+	let mut v = VtkFile::new();
+	v.vtype = "UnstructuredGrid".to_string();
+
+	let points = read_array(get(state, "points", name), name);
+	v.ncomponents = points.ncomponents;
+	v.points = points.data.to_f64().iter().map(|&x| x as f32).collect();
+	v.npoints = (v.points.len() / std::cmp::max(v.ncomponents as usize, 1)) as u64;
+
+	for (k, key) in [VERTS, LINES, POLYS, STRIPS].iter().enumerate()
+	{
+		if state[*key].is_null()
+		{
+			continue;
+		}
+		let conn = read_array(&state[*key], name).data.to_i64();
+
+		let mut i = 0;
+		while i < conn.len()
+		{
+			let n = conn[i] as usize;
+			if i + n >= conn.len()
+			{
+				panic!("Cannot read vtk.js file \"{}\":  truncated {}", name, key);
+			}
+			v.connectivity.extend_from_slice(&conn[i+1 .. i+n+1]);
+			v.offsets.push(v.connectivity.len() as i64);
+			v.types.push(match (k, n)
+			{
+				(0, 1) => cells::VTK_VERTEX,
+				(0, _) => cells::VTK_POLY_VERTEX,
+				(1, 2) => cells::VTK_LINE,
+				(1, _) => cells::VTK_POLY_LINE,
+				(2, 3) => cells::VTK_TRIANGLE,
+				(2, 4) => cells::VTK_QUAD,
+				(2, _) => cells::VTK_POLYGON,
+				_      => cells::VTK_TRIANGLE_STRIP,
+			});
+			i += n + 1;
+		}
+	}
+	v.ncells = v.types.len() as u64;
+
+	let (pdata, pattributes) = read_attributes(&state["pointData"], name);
+	let (cdata, cattributes) = read_attributes(&state["cellData"], name);
+	v.pdata = pdata;
+	v.cdata = cdata;
+	v.pattributes = pattributes;
+	v.cattributes = cattributes;
+
+	return v;
+}
+
+//********
+
+pub fn read<R: Read>(mut reader: R, name: &str, msgpack: bool) -> VtkFile
+{
+	let errstr = format!("Cannot read vtk.js file \"{}\"", name);
+	let state: Value = if msgpack
+	{
+		rmp_serde::from_read(&mut reader)
+			.unwrap_or_else(|e| panic!("{}:  {}", errstr, e))
+	}
+	else
+	{
+		serde_json::from_reader(&mut reader)
+			.unwrap_or_else(|e| panic!("{}:  {}", errstr, e))
+	};
+	return from_vtkjs(&state, name);
+}
+
+//********
+
+pub fn load(file: &str, msgpack: bool) -> VtkFile
+{
+	info!("Loading vtk.js file \"{}\"", file);
+	let errstr = format!("Cannot load vtk.js file \"{}\"", file);
+	let f = File::open(file).expect(&errstr);
+	return read(BufReader::new(f), file, msgpack);
+}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::vtk;
+
+	#[test]
+	fn test_vtkjs()
+	{
+		let v = vtk::load("./data/icosahedron-binary-pdata.vtu");
+		let state = v.to_vtkjs();
+		assert_eq!(state["polys"]["size"], 80);
+		assert_eq!(state["verts"]["size"], 0);
+		assert_eq!(state["pointData"]["activeScalars"], 0);
+		assert_eq!(state["points"]["values"][0], json!(0.2763932));
+
+		for &msgpack in &[false, true]
+		{
+			let mut buf = Vec::new();
+			v.write_vtkjs(&mut buf, msgpack).unwrap();
+			let w = read(&buf[..], "buffer", msgpack);
+			assert_eq!(w.points, v.points);
+			assert_eq!(w.connectivity, v.connectivity);
+			assert_eq!(w.types, v.types);
+			assert_eq!(w.pdata[0].data, v.pdata[0].data);
+			assert_eq!(w.pattributes, v.pattributes);
+		}
+	}
+}
+
+//==============================================================================
+
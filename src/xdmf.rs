@@ -0,0 +1,818 @@
+
+//==============================================================================
+
+// XDMF3 IO with raw binary heavy data.  The light .xdmf file describes the
+// grid in XML, and each DataItem points at a byte offset in a .bin file, which
+// is simple to memory-map.  Grids are always written with mixed topology, and
+// time series as temporal collections.  HDF5 heavy data is not supported
+
+// Standard
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+//********
+
+// Third party
+use log::{info, warn};
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+
+//********
+
+// This lib
+use crate::base64;
+use crate::cells;
+use crate::ghost;
+use crate::vtk::{self, Data, DataArray, VtkFile};
+
+//********
+
+pub const EXTS: &[&str] = &[".xdmf", ".xmf"];
+
+const BIN_EXT: &str = ".bin";
+
+// XDMF topology names, their mixed topology codes, the VTK types, and the VTK
+// node of each XDMF node where the orderings differ.  Codes 1 to 3 are
+// followed by a node count in mixed topologies
+const TYPES: &[(&str, i64, u8, &[usize])] = &[
+	("Polyvertex"     ,  1, cells::VTK_VERTEX                         , &[]),
+	("Polyvertex"     ,  1, cells::VTK_POLY_VERTEX                    , &[]),
+	("Polyline"       ,  2, cells::VTK_LINE                           , &[]),
+	("Polyline"       ,  2, cells::VTK_POLY_LINE                      , &[]),
+	("Polygon"        ,  3, cells::VTK_POLYGON                        , &[]),
+	("Triangle"       ,  4, cells::VTK_TRIANGLE                       , &[]),
+	("Quadrilateral"  ,  5, cells::VTK_QUAD                           , &[]),
+	("Quadrilateral"  ,  5, cells::VTK_PIXEL                          , &[0, 1, 3, 2]),
+	("Tetrahedron"    ,  6, cells::VTK_TETRA                          , &[]),
+	("Pyramid"        ,  7, cells::VTK_PYRAMID                        , &[]),
+	("Wedge"          ,  8, cells::VTK_WEDGE                          , &[]),
+	("Hexahedron"     ,  9, cells::VTK_HEXAHEDRON                     , &[]),
+	("Hexahedron"     ,  9, cells::VTK_VOXEL                          , &[0, 1, 3, 2, 4, 5, 7, 6]),
+	("Edge_3"         , 34, cells::VTK_QUADRATIC_EDGE                 , &[]),
+	("Triangle_6"     , 36, cells::VTK_QUADRATIC_TRIANGLE             , &[]),
+	("Quadrilateral_8", 37, cells::VTK_QUADRATIC_QUAD                 , &[]),
+	("Quadrilateral_9", 35, cells::VTK_BIQUADRATIC_QUAD               , &[]),
+	("Tetrahedron_10" , 38, cells::VTK_QUADRATIC_TETRA                , &[]),
+	("Pyramid_13"     , 39, cells::VTK_QUADRATIC_PYRAMID              , &[]),
+	("Wedge_15"       , 40, cells::VTK_QUADRATIC_WEDGE                , &[]),
+	("Wedge_18"       , 41, cells::VTK_BIQUADRATIC_QUADRATIC_WEDGE    , &[]),
+	("Hexahedron_20"  , 48, cells::VTK_QUADRATIC_HEXAHEDRON           , &[]),
+	("Hexahedron_24"  , 49, cells::VTK_BIQUADRATIC_QUADRATIC_HEXAHEDRON, &[]),
+	("Hexahedron_27"  , 50, cells::VTK_TRIQUADRATIC_HEXAHEDRON        , &[]),
+];
+
+const MIXED: &str = "Mixed";
+
+// XDMF number types and precisions of the VTK types
+const NUMBER_TYPES: &[(&str, &str, usize)] = &[
+	("Int8"   , "Char" , 1),
+	("UInt8"  , "UChar", 1),
+	("Int16"  , "Int"  , 2),
+	("UInt16" , "UInt" , 2),
+	("Int32"  , "Int"  , 4),
+	("UInt32" , "UInt" , 4),
+	("Int64"  , "Int"  , 8),
+	("UInt64" , "UInt" , 8),
+	("Float32", "Float", 4),
+	("Float64", "Float", 8),
+];
+
+//==============================================================================
+
+fn escape(s: &str) -> String
+{
+	return s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+		.replace('"', "&quot;");
+}
+
+//********
+
+fn attribute_type(ncomponents: u64) -> &'static str
+{
+	return match ncomponents
+	{
+		1 => "Scalar",
+		3 => "Vector",
+		6 => "Tensor6",
+		9 => "Tensor",
+		_ => "Matrix",
+	};
+}
+
+//********
+
+// Writer of the heavy data, which places each DataItem after the last one in
+// the .bin file
+struct Heavy<'a, W: Write>
+{
+	f: &'a mut W,
+	name: String,
+	offset: u64,
+	endianness: u8,
+}
+
+impl<W: Write> Heavy<'_, W>
+{
+	fn item<X: Write>(&mut self, xml: &mut X, indent: &str, dims: &str, data: &Data)
+		-> Result<(), io::Error>
+	{
+		let vtk_type = data.vtk_type();
+		let (_, ntype, precision) = NUMBER_TYPES.iter().find(|t| t.0 == vtk_type)
+			.unwrap_or(&NUMBER_TYPES[NUMBER_TYPES.len() - 1]);
+		let endian = if self.endianness == base64::BIG_ENDIAN { "Big" } else { "Little" };
+
+		writeln!(xml, "{}<DataItem Dimensions=\"{}\" NumberType=\"{}\" \
+			Precision=\"{}\" Format=\"Binary\" Endian=\"{}\" Seek=\"{}\">{}</DataItem>",
+			indent, dims, ntype, precision, endian, self.offset, escape(&self.name))?;
+
+		let bytes = data.to_bytes(self.endianness);
+		self.f.write_all(&bytes)?;
+		self.offset += bytes.len() as u64;
+		Ok(())
+	}
+}
+
+//********
+
+fn topology(v: &VtkFile) -> Result<Vec<i64>, io::Error>
+{
+	// Mixed topology stream of codes, counts, and nodes
+
+	let mut t = Vec::with_capacity(v.connectivity.len() + 2 * v.ncells as usize);
+	let mut nskipped = 0;
+	for i in 0 .. v.ncells as usize
+	{
+		let (_, code, _, order) = match TYPES.iter().find(|t| t.2 == v.types[i])
+		{
+			Some(t) => t,
+			None =>
+			{
+				nskipped += 1;
+				continue;
+			},
+		};
+		let conn = v.cell_points(i);
+		t.push(*code);
+		if *code <= 3
+		{
+			t.push(conn.len() as i64);
+		}
+		if order.is_empty()
+		{
+			t.extend(conn);
+		}
+		else
+		{
+			t.extend(order.iter().map(|&j| conn[j]));
+		}
+	}
+	if nskipped > 0
+	{
+		// Cell data would no longer line up with the cells
+		return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} strips \
+			or polyhedra have no XDMF mixed topology type", nskipped)));
+	}
+	return Ok(t);
+}
+
+//********
+
+fn write_grid<W: Write, X: Write>(v: &VtkFile, heavy: &mut Heavy<W>, xml: &mut X,
+	indent: &str, name: &str, time: Option<f64>) -> Result<(), io::Error>
+{
+	if v.types.iter().any(|&t| cells::is_higher_order(t))
+	{
+		return write_grid(&v.linearize(), heavy, xml, indent, name, time);
+	}
+
+	let i1 = format!("{}\t", indent);
+	let i2 = format!("{}\t\t", indent);
+
+	writeln!(xml, "{}<Grid Name=\"{}\" GridType=\"Uniform\">", indent, name)?;
+	if let Some(t) = time
+	{
+		writeln!(xml, "{}<Time Value=\"{}\"/>", i1, t)?;
+	}
+
+	let t = topology(v)?;
+	writeln!(xml, "{}<Topology TopologyType=\"{}\" NumberOfElements=\"{}\">", i1,
+		MIXED, v.ncells)?;
+	heavy.item(xml, &i2, &t.len().to_string(), &Data::I64(t))?;
+	writeln!(xml, "{}</Topology>", i1)?;
+
+	// Geometry is always 3D
+	let nc = v.ncomponents as usize;
+	let xyz: Vec<f32> = (0 .. 3 * v.npoints as usize)
+		.map(|j| if j % 3 < nc { v.points[nc * (j / 3) + j % 3] } else { 0.0 })
+		.collect();
+	writeln!(xml, "{}<Geometry GeometryType=\"XYZ\">", i1)?;
+	heavy.item(xml, &i2, &format!("{} 3", v.npoints), &Data::F32(xyz))?;
+	writeln!(xml, "{}</Geometry>", i1)?;
+
+	for &(arrays, n, center) in &[(&v.pdata, v.npoints, "Node"),
+		(&v.cdata, v.ncells, "Cell")]
+	{
+		for a in arrays.iter()
+		{
+			let dims = if a.ncomponents == 1 {
+				n.to_string()
+			} else {
+				format!("{} {}", n, a.ncomponents)
+			};
+			writeln!(xml, "{}<Attribute Name=\"{}\" AttributeType=\"{}\" \
+				Center=\"{}\">", i1, escape(&a.name), attribute_type(a.ncomponents),
+				center)?;
+			heavy.item(xml, &i2, &dims, &a.data)?;
+			writeln!(xml, "{}</Attribute>", i1)?;
+		}
+	}
+
+	writeln!(xml, "{}</Grid>", indent)?;
+	return Ok(());
+}
+
+//********
+
+fn write_files(steps: &[&VtkFile], times: Option<&[f64]>, file: &str)
+	-> Result<(), io::Error>
+{
+	// All steps share one .bin file next to the .xdmf file
+
+	let path = Path::new(file);
+	let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+	let bin_name = format!("{}{}", stem, BIN_EXT);
+
+	let mut bin = BufWriter::new(File::create(path.with_file_name(&bin_name))?);
+	let mut heavy = Heavy
+	{
+		f: &mut bin,
+		name: bin_name,
+		offset: 0,
+		endianness: steps[0].endianness,
+	};
+
+	let mut xml = BufWriter::new(File::create(file)?);
+	writeln!(xml, "<?xml version=\"1.0\" ?>")?;
+	writeln!(xml, "<Xdmf Version=\"3.0\">")?;
+	writeln!(xml, "\t<Domain>")?;
+	match times
+	{
+		None => write_grid(steps[0], &mut heavy, &mut xml, "\t\t", "Grid", None)?,
+		Some(times) =>
+		{
+			writeln!(xml, "\t\t<Grid Name=\"TimeSeries\" GridType=\"Collection\" \
+				CollectionType=\"Temporal\">")?;
+			for (s, (v, t)) in steps.iter().zip(times).enumerate()
+			{
+				write_grid(v, &mut heavy, &mut xml, "\t\t\t", &format!("Step{}", s),
+					Some(*t))?;
+			}
+			writeln!(xml, "\t\t</Grid>")?;
+		},
+	}
+	writeln!(xml, "\t</Domain>")?;
+	writeln!(xml, "</Xdmf>")?;
+
+	heavy.f.flush()?;
+	return xml.flush();
+}
+
+//********
+
+impl VtkFile
+{
+pub fn export_xdmf(&self, file: &str)
+{
+	info!("Exporting XDMF file \"{}\"", file);
+	if let Err(e) = write_files(&[self], None, file)
+	{
+		panic!("Cannot export XDMF file \"{}\":  {}", file, e);
+	}
+}}
+
+//********
+
+pub fn export_series(steps: &[(f64, VtkFile)], file: &str)
+{
+	// Time steps, e.g. of a .pvd collection, as one temporal collection
+
+	info!("Exporting XDMF file \"{}\" with {} time steps", file, steps.len());
+	if steps.is_empty()
+	{
+		panic!("Cannot export XDMF file \"{}\":  no time steps", file);
+	}
+
+	let times: Vec<f64> = steps.iter().map(|s| s.0).collect();
+	let vs: Vec<&VtkFile> = steps.iter().map(|s| &s.1).collect();
+	if let Err(e) = write_files(&vs, Some(&times), file)
+	{
+		panic!("Cannot export XDMF file \"{}\":  {}", file, e);
+	}
+}
+
+//==============================================================================
+
+// XML element with its attributes, text, and child elements
+#[derive(Default)]
+struct Element
+{
+	name    : String,
+	attrs   : Vec<(String, String)>,
+	text    : String,
+	children: Vec<Element>,
+}
+
+impl Element
+{
+	fn attr(&self, key: &str) -> Option<&str>
+	{
+		self.attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+	}
+
+	fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element>
+	{
+		self.children.iter().filter(move |c| c.name == name)
+	}
+
+	fn child(&self, name: &str) -> Option<&Element>
+	{
+		self.children.iter().find(|c| c.name == name)
+	}
+}
+
+//********
+
+fn element<R: BufRead>(e: &BytesStart, reader: &Reader<R>, errstr: &str) -> Element
+{
+	let attrs = e.attributes().map(|a|
+	{
+		let attr = a.expect(errstr);
+		let key = reader.decode(attr.key).expect(errstr).to_string();
+		let val = attr.unescape_and_decode_value(reader).expect(errstr);
+		(key, val)
+	}).collect();
+	let name = reader.decode(e.name()).expect(errstr).to_string();
+	return Element { name, attrs, ..Default::default() };
+}
+
+//********
+
+fn parse_tree<R: BufRead>(reader: R, errstr: &str) -> Element
+{
+	let mut reader = Reader::from_reader(reader);
+	reader.trim_text(true);
+
+	// Open elements, under a root without a name
+	let mut stack = vec![Element::default()];
+	let mut buf = Vec::new();
+	loop
+	{
+		match reader.read_event(&mut buf)
+		{
+			Ok(Event::Start(ref e)) => stack.push(element(e, &reader, errstr)),
+			Ok(Event::Empty(ref e)) =>
+			{
+				let c = element(e, &reader, errstr);
+				if let Some(parent) = stack.last_mut()
+				{
+					parent.children.push(c);
+				}
+			},
+			Ok(Event::Text(ref e)) =>
+			{
+				let text = e.unescape_and_decode(&reader).expect(errstr);
+				if let Some(top) = stack.last_mut()
+				{
+					top.text.push_str(&text);
+				}
+			},
+			Ok(Event::End(_)) if stack.len() > 1 =>
+			{
+				let c = stack.pop().unwrap_or_default();
+				if let Some(parent) = stack.last_mut()
+				{
+					parent.children.push(c);
+				}
+			},
+			Ok(Event::Eof) => break,
+			Err(e) => panic!("{}:  error at position {}:  {:?}", errstr,
+				reader.buffer_position(), e),
+			_ => (),
+		}
+		buf.clear();
+	}
+	return stack.swap_remove(0);
+}
+
+//********
+
+// DataItem reader, which resolves .bin files relative to the .xdmf file
+struct Items<'a>
+{
+	dir   : PathBuf,
+	errstr: &'a str,
+}
+
+impl Items<'_>
+{
+	fn read(&self, item: &Element) -> (Data, Vec<usize>)
+	{
+		// Values and dimensions of a uniform DataItem
+
+		let errstr = self.errstr;
+		if item.attr("Reference").is_some()
+			|| item.attr("ItemType").is_some_and(|t| t != "Uniform")
+		{
+			panic!("{}:  only uniform DataItems without references are \
+				supported", errstr);
+		}
+
+		let dims: Vec<usize> = item.attr("Dimensions").unwrap_or("").split_whitespace()
+			.map(|d| d.parse().unwrap_or_else(|_| panic!("{}:  bad Dimensions \
+				\"{}\"", errstr, d))).collect();
+		let n: usize = dims.iter().product();
+
+		let ntype = item.attr("NumberType").or_else(|| item.attr("DataType"))
+			.unwrap_or("Float");
+		let precision: usize = item.attr("Precision").and_then(|p| p.parse().ok())
+			.unwrap_or(if ntype == "Char" || ntype == "UChar" { 1 } else { 4 });
+		let vtk_type = match NUMBER_TYPES.iter()
+			.find(|t| t.1 == ntype && t.2 == precision)
+		{
+			Some(t) => t.0,
+			None => match (ntype, precision)
+			{
+				("Int" , 1) => "Int8",
+				("UInt", 1) => "UInt8",
+				_ => panic!("{}:  unknown NumberType \"{}\" of Precision {}",
+					errstr, ntype, precision),
+			},
+		};
+
+		let data = match item.attr("Format").unwrap_or("XML")
+		{
+			"XML" =>
+			{
+				let words = item.text.split_whitespace();
+				let d = if vtk_type.starts_with("Float")
+				{
+					Data::F64(words.map(|w| w.parse().unwrap_or_else(|_|
+						panic!("{}:  bad number \"{}\"", errstr, w))).collect())
+				}
+				else
+				{
+					Data::I64(words.map(|w| w.parse().unwrap_or_else(|_|
+						panic!("{}:  bad number \"{}\"", errstr, w))).collect())
+				};
+				d.cast(vtk_type)
+			},
+			"Binary" =>
+			{
+				if let Some(c) = item.attr("Compression").filter(|&c| c != "Raw")
+				{
+					panic!("{}:  {} compression is not supported", errstr, c);
+				}
+				let endianness = match item.attr("Endian")
+				{
+					Some("Big") => base64::BIG_ENDIAN,
+					Some("Native") if cfg!(target_endian = "big") => base64::BIG_ENDIAN,
+					_ => base64::LITTLE_ENDIAN,
+				};
+				let seek = item.attr("Seek").and_then(|s| s.parse().ok()).unwrap_or(0);
+
+				let path = self.dir.join(item.text.trim());
+				let errbin = format!("{}:  cannot read \"{}\"", errstr, path.display());
+				let mut f = File::open(&path).expect(&errbin);
+				f.seek(SeekFrom::Start(seek)).expect(&errbin);
+				let mut bytes = vec![0; n * precision];
+				f.read_exact(&mut bytes).expect(&errbin);
+				Data::from_bytes(&bytes, vtk_type, endianness)
+			},
+			format => panic!("{}:  {} heavy data is not supported", errstr, format),
+		};
+
+		if data.len() != n
+		{
+			panic!("{}:  DataItem has {} values instead of {}", errstr, data.len(), n);
+		}
+		return (data, dims);
+	}
+
+	fn child(&self, e: &Element) -> (Data, Vec<usize>)
+	{
+		match e.child("DataItem")
+		{
+			Some(item) => self.read(item),
+			None => panic!("{}:  {} without a DataItem", self.errstr, e.name),
+		}
+	}
+}
+
+//********
+
+fn read_topology(v: &mut VtkFile, t: &Element, items: &Items)
+{
+	let errstr = items.errstr;
+	let ttype = t.attr("TopologyType").or_else(|| t.attr("Type")).unwrap_or("");
+	let ids = items.child(t).0.to_i64();
+	let mut next = ids.iter().copied();
+	let mut take = |n: usize| -> Vec<i64>
+	{
+		let c: Vec<i64> = next.by_ref().take(n).collect();
+		if c.len() < n
+		{
+			panic!("{}:  topology ends in the middle of a cell", errstr);
+		}
+		c
+	};
+
+	let push = |v: &mut VtkFile, ctype: u8, conn: Vec<i64>|
+	{
+		// Counted types are their single-point or single-segment VTK types
+		let ctype = match ctype
+		{
+			cells::VTK_POLY_VERTEX if conn.len() == 1 => cells::VTK_VERTEX,
+			cells::VTK_POLY_LINE   if conn.len() == 2 => cells::VTK_LINE,
+			c => c,
+		};
+		v.connectivity.extend(conn);
+		v.offsets.push(v.connectivity.len() as i64);
+		v.types.push(ctype);
+	};
+
+	// First VTK type of each XDMF type, without pixels and voxels
+	let poly = |t: &(&str, i64, u8, &[usize])| t.3.is_empty()
+		&& t.2 != cells::VTK_VERTEX && t.2 != cells::VTK_LINE;
+
+	if ttype.eq_ignore_ascii_case(MIXED)
+	{
+		let n: usize = t.attr("NumberOfElements").and_then(|n| n.parse().ok())
+			.unwrap_or(usize::MAX);
+		let mut i = 0;
+		while i < n
+		{
+			let code = match take(1).first()
+			{
+				Some(&c) => c,
+				None => break,
+			};
+			let (_, _, ctype, _) = TYPES.iter().find(|t| t.1 == code && poly(t))
+				.unwrap_or_else(|| panic!("{}:  unknown mixed topology type {}",
+					errstr, code));
+			let npoints = if code <= 3
+			{
+				take(1)[0] as usize
+			}
+			else
+			{
+				cells::npoints_fixed(*ctype).unwrap_or(0)
+			};
+			let conn = take(npoints);
+			push(v, *ctype, conn);
+			i += 1;
+		}
+	}
+	else
+	{
+		let (_, _, ctype, _) = TYPES.iter()
+			.find(|t| t.0.eq_ignore_ascii_case(ttype) && poly(t))
+			.unwrap_or_else(|| panic!("{}:  unknown TopologyType \"{}\"", errstr,
+				ttype));
+		let npoints = t.attr("NodesPerElement").and_then(|n| n.parse().ok())
+			.or_else(|| cells::npoints_fixed(*ctype))
+			.unwrap_or_else(|| panic!("{}:  {} topology without NodesPerElement",
+				errstr, ttype));
+		for _ in 0 .. ids.len() / npoints.max(1)
+		{
+			let conn = take(npoints);
+			push(v, *ctype, conn);
+		}
+	}
+	v.ncells = v.types.len() as u64;
+}
+
+//********
+
+fn read_geometry(v: &mut VtkFile, g: &Element, items: &Items)
+{
+	let errstr = items.errstr;
+	let gtype = g.attr("GeometryType").or_else(|| g.attr("Type")).unwrap_or("XYZ");
+
+	// Components of each DataItem
+	let xs: Vec<Vec<f64>> = match gtype
+	{
+		"XYZ" | "XY" =>
+		{
+			let nc = if gtype == "XYZ" { 3 } else { 2 };
+			let x = items.child(g).0.to_f64();
+			(0 .. nc).map(|d| x.iter().skip(d).step_by(nc).copied().collect()).collect()
+		},
+		"X_Y_Z" | "X_Y" => g.children("DataItem").map(|i| items.read(i).0.to_f64())
+			.collect(),
+		_ => panic!("{}:  GeometryType \"{}\" is not supported", errstr, gtype),
+	};
+
+	v.npoints = xs.first().map_or(0, |x| x.len()) as u64;
+	v.ncomponents = 3;
+	v.points = (0 .. 3 * v.npoints as usize).map(|j|
+	{
+		xs.get(j % 3).map_or(0.0, |x| x[j / 3] as f32)
+	}).collect();
+}
+
+//********
+
+fn read_uniform(grid: &Element, items: &Items) -> VtkFile
+{
+	let errstr = items.errstr;
+	let mut v = VtkFile::new();
+	v.vtype = "UnstructuredGrid".to_string();
+
+	match grid.child("Topology")
+	{
+		Some(t) => read_topology(&mut v, t, items),
+		None => panic!("{}:  grid without a Topology", errstr),
+	}
+	match grid.child("Geometry")
+	{
+		Some(g) => read_geometry(&mut v, g, items),
+		None => panic!("{}:  grid without a Geometry", errstr),
+	}
+
+	for a in grid.children("Attribute")
+	{
+		let name = a.attr("Name").unwrap_or("");
+		let center = a.attr("Center").unwrap_or("Node");
+		let (data, dims) = items.child(a);
+		let nc = dims.iter().skip(1).product::<usize>() as u64;
+		let array = DataArray::new(name, nc, data);
+		match center
+		{
+			"Node" => v.pdata.push(array),
+			"Cell" => v.cdata.push(array),
+			_ => warn!("skipping attribute \"{}\" centered on {}", name, center),
+		}
+	}
+
+	vtk::check_sizes(&v);
+	return v;
+}
+
+//********
+
+fn read_grid(grid: &Element, items: &Items) -> VtkFile
+{
+	// Spatial collections, e.g. partitions, are merged
+	if grid.attr("GridType") == Some("Collection")
+	{
+		let pieces: Vec<VtkFile> = grid.children("Grid").map(|g| read_grid(g, items))
+			.collect();
+		return ghost::merge(&pieces);
+	}
+	return read_uniform(grid, items);
+}
+
+//********
+
+fn time(grid: &Element) -> Option<f64>
+{
+	return grid.child("Time")?.attr("Value")?.trim().parse().ok();
+}
+
+//********
+
+pub fn load_series(file: &str) -> Vec<(f64, VtkFile)>
+{
+	// Time steps of a temporal collection, or a single step at time 0
+
+	info!("Loading XDMF file \"{}\"", file);
+	let errstr = format!("Cannot load XDMF file \"{}\"", file);
+	let f = File::open(file).expect(&errstr);
+	let root = parse_tree(BufReader::new(f), &errstr);
+
+	let grid = root.child("Xdmf").and_then(|x| x.child("Domain"))
+		.and_then(|d| d.child("Grid"))
+		.unwrap_or_else(|| panic!("{}:  no Xdmf Domain Grid", errstr));
+
+	let items = Items
+	{
+		dir: Path::new(file).parent().unwrap_or_else(|| Path::new("")).to_path_buf(),
+		errstr: &errstr,
+	};
+
+	if grid.attr("CollectionType") == Some("Temporal")
+	{
+		return grid.children("Grid").enumerate()
+			.map(|(i, g)| (time(g).unwrap_or(i as f64), read_grid(g, &items)))
+			.collect();
+	}
+	return vec![(time(grid).unwrap_or(0.0), read_grid(grid, &items))];
+}
+
+//********
+
+pub fn load(file: &str) -> VtkFile
+{
+	let mut steps = load_series(file);
+	if steps.len() > 1
+	{
+		warn!("loading only the first of {} time steps", steps.len());
+	}
+	if steps.is_empty()
+	{
+		panic!("Cannot load XDMF file \"{}\":  no time steps", file);
+	}
+	return steps.swap_remove(0).1;
+}
+
+//==============================================================================
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	fn grid() -> VtkFile
+	{
+		// A voxel, a triangle on its bottom face, and a 2-point poly line
+		let mut v = VtkFile::new();
+		v.vtype = "UnstructuredGrid".to_string();
+		v.ncomponents = 3;
+		v.npoints = 8;
+		v.points = (0 .. 8).flat_map(|i| vec![(i & 1) as f32, ((i >> 1) & 1) as f32,
+			(i >> 2) as f32]).collect();
+		v.ncells = 3;
+		v.connectivity = (0 .. 8).chain(vec![0, 1, 2,  3, 7]).collect();
+		v.offsets = vec![8, 11, 13];
+		v.types = vec![cells::VTK_VOXEL, cells::VTK_TRIANGLE, cells::VTK_POLY_LINE];
+		v.pdata.push(DataArray::new("u", 3, Data::F64(v.points.iter()
+			.map(|&x| 2.0 * x as f64).collect())));
+		v.cdata.push(DataArray::new("id", 1, Data::U8(vec![4, 5, 6])));
+		return v;
+	}
+
+	#[test]
+	fn test_xdmf()
+	{
+		let mut v = grid();
+		v.endianness = base64::BIG_ENDIAN;
+		let file = "./scratch/tmp.Xd3mfQ8r.xdmf";
+		v.export_xdmf(file);
+
+		let xml = std::fs::read_to_string(file).unwrap();
+		assert!(xml.contains("Endian=\"Big\""));
+		assert!(xml.contains("<Attribute Name=\"u\" AttributeType=\"Vector\" \
+			Center=\"Node\">"));
+
+		// Voxels come back as hexahedra, and 2-point poly lines as lines
+		let w = load(file);
+		assert_eq!(w.points, v.points);
+		assert_eq!(w.types, vec![cells::VTK_HEXAHEDRON, cells::VTK_TRIANGLE,
+			cells::VTK_LINE]);
+		assert_eq!(w.cell_points(0), &[0, 1, 3, 2, 4, 5, 7, 6]);
+		assert_eq!(w.cell_points(2), &[3, 7]);
+		assert_eq!(w.pdata, v.pdata);
+		assert_eq!(w.cdata, v.cdata);
+	}
+
+	#[test]
+	fn test_xdmf_series()
+	{
+		let v = grid();
+		let mut w = v.clone();
+		w.points[0] = -1.0;
+		let file = "./scratch/tmp.Xd7TmpK2.xmf";
+		export_series(&[(0.25, v), (0.75, w)], file);
+
+		let steps = load_series(file);
+		assert_eq!(steps.len(), 2);
+		assert_eq!(steps[1].0, 0.75);
+		assert_eq!(steps[1].1.points[0], -1.0);
+		assert_eq!(steps[0].1.points[0], 0.0);
+	}
+
+	#[test]
+	fn test_xdmf_inline()
+	{
+		// Other writers often put small arrays inline as XML
+		let file = "./scratch/tmp.Xd5InlQ1.xmf";
+		std::fs::write(file, "<Xdmf Version=\"3.0\"><Domain>\n\
+			<Grid Name=\"g\"><Topology TopologyType=\"Triangle\" NumberOfElements=\"1\">\n\
+			<DataItem Dimensions=\"1 3\" NumberType=\"Int\">0 1 2</DataItem></Topology>\n\
+			<Geometry GeometryType=\"XY\"><DataItem Dimensions=\"3 2\">0 0 1 0 0 1\
+			</DataItem></Geometry>\n\
+			<Attribute Name=\"p\" Center=\"Cell\"><DataItem Dimensions=\"1\" \
+			Precision=\"8\">3.5</DataItem></Attribute>\n\
+			</Grid></Domain></Xdmf>\n").unwrap();
+
+		let v = load(file);
+		assert_eq!(v.points, vec![0.0, 0.0, 0.0,  1.0, 0.0, 0.0,  0.0, 1.0, 0.0]);
+		assert_eq!(v.types, vec![cells::VTK_TRIANGLE]);
+		assert_eq!(v.cell_array("p").unwrap().data, Data::F64(vec![3.5]));
+	}
+}
+
+//==============================================================================
+